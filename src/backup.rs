@@ -0,0 +1,125 @@
+use crate::config::BackupConfig;
+use crate::file::list_files;
+use chrono::{Local, NaiveDateTime};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Rotating per-list backups, stored as .backups/<list>/<timestamp>.<ext>
+// in the todo directory
+pub const BACKUP_DIR: &str = ".backups";
+pub const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+
+pub struct Backup {
+    pub path: PathBuf,
+    pub taken: NaiveDateTime,
+}
+
+pub fn backup_dir(list_path: &Path) -> PathBuf {
+    let parent = list_path.parent().unwrap_or(Path::new("."));
+    let stem = list_path.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown");
+    parent.join(BACKUP_DIR).join(stem)
+}
+
+// Backups of a list, oldest first
+pub fn list_backups(list_path: &Path) -> Vec<Backup> {
+    let mut backups: Vec<Backup> = match fs::read_dir(backup_dir(list_path)) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                let stem = path.file_stem()?.to_str()?;
+                let taken = NaiveDateTime::parse_from_str(stem, BACKUP_TIMESTAMP_FORMAT).ok()?;
+                Some(Backup { path, taken })
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    backups.sort_by_key(|b| b.taken);
+    backups
+}
+
+pub fn snapshot_list(list_path: &Path, taken: NaiveDateTime, config: &BackupConfig) -> io::Result<()> {
+    let dir = backup_dir(list_path);
+    fs::create_dir_all(&dir)?;
+
+    let extension = list_path.extension().and_then(|e| e.to_str()).unwrap_or("adoc");
+    let file_name = format!("{}.{}", taken.format(BACKUP_TIMESTAMP_FORMAT), extension);
+    fs::copy(list_path, dir.join(file_name))?;
+
+    prune_backups(list_path, config)
+}
+
+pub fn prune_backups(list_path: &Path, config: &BackupConfig) -> io::Result<()> {
+    let mut backups = list_backups(list_path);
+
+    // Always keep the newest backup, regardless of age
+    let newest = backups.pop();
+
+    if config.max_age_days > 0 {
+        let cutoff = Local::now().naive_local() - chrono::Duration::days(config.max_age_days as i64);
+        for backup in backups.iter().filter(|b| b.taken < cutoff) {
+            fs::remove_file(&backup.path)?;
+        }
+        backups.retain(|b| b.taken >= cutoff);
+    }
+
+    let keep = config.keep.saturating_sub(usize::from(newest.is_some()));
+    let excess = backups.len().saturating_sub(keep);
+    for backup in &backups[..excess] {
+        fs::remove_file(&backup.path)?;
+    }
+
+    Ok(())
+}
+
+// Snapshots of the whole todo directory, taken on request with `todo
+// backup` and stored as .snapshots/<timestamp>/ holding a copy of every file
+// directly in the directory
+pub const SNAPSHOT_DIR: &str = ".snapshots";
+
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub taken: NaiveDateTime,
+}
+
+impl Snapshot {
+    pub fn files(&self) -> io::Result<Vec<PathBuf>> {
+        list_files(&self.path)
+    }
+}
+
+// Snapshots of a todo directory, oldest first
+pub fn list_snapshots(todo_path: &Path) -> Vec<Snapshot> {
+    let mut snapshots: Vec<Snapshot> = match fs::read_dir(todo_path.join(SNAPSHOT_DIR)) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                let taken = NaiveDateTime::parse_from_str(path.file_name()?.to_str()?, BACKUP_TIMESTAMP_FORMAT).ok()?;
+                path.is_dir().then_some(Snapshot { path, taken })
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    snapshots.sort_by_key(|s| s.taken);
+    snapshots
+}
+
+pub fn snapshot_directory(todo_path: &Path, taken: NaiveDateTime, config: &BackupConfig) -> io::Result<Snapshot> {
+    let dir = todo_path.join(SNAPSHOT_DIR).join(taken.format(BACKUP_TIMESTAMP_FORMAT).to_string());
+    fs::create_dir_all(&dir)?;
+    for file in list_files(todo_path)? {
+        if let Some(name) = file.file_name() {
+            fs::copy(&file, dir.join(name))?;
+        }
+    }
+
+    // The newest snapshot is the one just taken, so it always survives
+    let snapshots = list_snapshots(todo_path);
+    let excess = snapshots.len().saturating_sub(config.keep_snapshots.max(1));
+    for snapshot in &snapshots[..excess] {
+        fs::remove_dir_all(&snapshot.path)?;
+    }
+    Ok(Snapshot { path: dir, taken })
+}
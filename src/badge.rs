@@ -0,0 +1,48 @@
+use crate::item::{LineType, TodoItem};
+
+// Shields.io-style "flat" badges, so a list's progress can be embedded in a
+// README or dashboard as a plain SVG file
+
+// Progress of a list: "done/total", colored from red to bright green
+pub fn progress_badge(label: &str, items: &[TodoItem]) -> String {
+    let todos = items.iter().filter(|item| matches!(item.line_type, LineType::Todo));
+    let (done, total) = todos.fold((0, 0), |(done, total), item| (done + item.completed as usize, total + 1));
+
+    let color = match (done, total) {
+        (_, 0) => "#9f9f9f",
+        (done, total) if done == total => "#4c1",
+        (done, total) if done * 4 >= total * 3 => "#a4a61d",
+        (done, total) if done * 2 >= total => "#dfb317",
+        (done, total) if done * 4 >= total => "#fe7d37",
+        _ => "#e05d44",
+    };
+    render_badge(label, &format!("{}/{}", done, total), color)
+}
+
+pub fn render_badge(label: &str, message: &str, color: &str) -> String {
+    // Verdana 11px averages about 7px a character; shields pads by 5px a side
+    let width = |text: &str| text.chars().count() as u32 * 7 + 10;
+    let (label_width, message_width) = (width(label), width(message));
+    let total = label_width + message_width;
+    let (label, message) = (escape(label), escape(message));
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{total}" height="20" role="img" aria-label="{label}: {message}">
+<title>{label}: {message}</title>
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="{total}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{total}" height="20" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+<text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{label}</text><text x="{label_x}" y="14">{label}</text>
+<text x="{message_x}" y="15" fill="#010101" fill-opacity=".3">{message}</text><text x="{message_x}" y="14">{message}</text>
+</g>
+</svg>
+"##,
+        label_x = label_width as f32 / 2.0,
+        message_x = label_width as f32 + message_width as f32 / 2.0,
+    )
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,141 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+// Everything todo keeps, in one archive for `todo backup create`: the
+// config directory (settings, state, the journal) under `config/`, the todo
+// directory with its backups and snapshots under `todos/`, and each of the
+// workspace's other directories under `workspace/<name>/`. The archive is a
+// tar file, compressed with zstd or gzip when its name says so.
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    // Relative, with '/' between the parts
+    pub path: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Zstd,
+    Gzip,
+}
+
+impl Compression {
+    // From the archive's extension; anything but .zst or .gz is plain tar
+    pub fn for_name(name: &str) -> Result<Compression, String> {
+        if name.ends_with(".zst") || name.ends_with(".tzst") {
+            Ok(Compression::Zstd)
+        } else if name.ends_with(".gz") || name.ends_with(".tgz") {
+            Ok(Compression::Gzip)
+        } else if [".xz", ".bz2", ".lz4"].iter().any(|extension| name.ends_with(extension)) {
+            Err(format!("Bundles can be .tar, .tar.zst or .tar.gz, not {}", name))
+        } else {
+            Ok(Compression::None)
+        }
+    }
+}
+
+// Every file under `root`, with paths starting `prefix/`. A directory that
+// doesn't exist has no files.
+pub fn collect_files(root: &Path, prefix: &str, entries: &mut Vec<Entry>) -> io::Result<()> {
+    if !root.is_dir() {
+        return Ok(());
+    }
+    let mut children: Vec<_> = fs::read_dir(root)?.collect::<Result<_, _>>()?;
+    children.sort_by_key(|entry| entry.file_name());
+    for child in children {
+        let name = child.file_name().to_string_lossy().into_owned();
+        let path = format!("{}/{}", prefix, name);
+        let kind = child.file_type()?;
+        if kind.is_dir() {
+            collect_files(&child.path(), &path, entries)?;
+        } else if kind.is_file() {
+            entries.push(Entry { path, data: fs::read(child.path())? });
+        }
+    }
+    Ok(())
+}
+
+pub fn write_bundle(out: impl Write, entries: &[Entry], mtime: u64, compression: Compression) -> io::Result<()> {
+    match compression {
+        Compression::None => write_tar(out, entries, mtime)?.flush(),
+        Compression::Zstd => write_tar(zstd::Encoder::new(out, 0)?, entries, mtime)?.finish()?.flush(),
+        Compression::Gzip => write_tar(GzEncoder::new(out, Default::default()), entries, mtime)?.finish()?.flush(),
+    }
+}
+
+fn write_tar<W: Write>(out: W, entries: &[Entry], mtime: u64) -> io::Result<W> {
+    let mut builder = tar::Builder::new(out);
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_size(entry.data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        builder.append_data(&mut header, &entry.path, entry.data.as_slice())?;
+    }
+    builder.into_inner()
+}
+
+// A path that stays inside the directory it's restored to
+fn is_safe(path: &str) -> bool {
+    !path.is_empty() && Path::new(path).components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+// Where a bundle's file goes under `root`, if it stays there: no `..` or
+// absolute parts, and no link on the way that could lead out of it
+pub fn restore_path(root: &Path, path: &str) -> Option<PathBuf> {
+    if !is_safe(path) {
+        return None;
+    }
+    let mut target = root.to_path_buf();
+    for part in Path::new(path).components() {
+        target.push(part);
+        if fs::symlink_metadata(&target).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            return None;
+        }
+    }
+    Some(target)
+}
+
+// The files in a bundle, whichever way it's compressed. Directories and
+// links are skipped, and so is anything that would land outside where it's
+// restored to.
+pub fn read_bundle(input: impl Read) -> io::Result<Vec<Entry>> {
+    let mut input = BufReader::new(input);
+    let start = input.fill_buf()?;
+    if start.starts_with(&ZSTD_MAGIC) {
+        read_tar(zstd::Decoder::with_buffer(input)?)
+    } else if start.starts_with(&GZIP_MAGIC) {
+        read_tar(GzDecoder::new(input))
+    } else if start.is_empty() {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "not a todo bundle: empty"))
+    } else {
+        read_tar(input)
+    }
+}
+
+fn read_tar(input: impl Read) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for entry in tar::Archive::new(input).entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.to_string_lossy().into_owned();
+        if !is_safe(&path) {
+            continue;
+        }
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        entries.push(Entry { path, data });
+    }
+    Ok(entries)
+}
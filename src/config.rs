@@ -0,0 +1,654 @@
+use crate::emoji::expand_shortcodes;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Config {
+    // Accessibility switches that everything respects: no animations, and
+    // colors picked for legibility over looks
+    #[serde(default)]
+    pub reduced_motion: bool,
+    #[serde(default)]
+    pub high_contrast: bool,
+    // Never create a config, todo directory or list that's missing, as
+    // `--strict` does
+    #[serde(default)]
+    pub strict: bool,
+    // Who you are on shared lists: todos you add and complete are stamped
+    // with it as `by:` and `done-by:`. Unset, todos aren't attributed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub todo: TodoConfig,
+    pub editor: EditorConfig,
+    #[serde(default)]
+    pub backup: BackupConfig,
+    #[serde(default)]
+    pub git: GitConfig,
+    #[serde(default)]
+    pub sync: SyncConfig,
+    #[serde(default)]
+    pub tui: TuiConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default)]
+    pub spell: SpellConfig,
+    #[serde(default)]
+    pub emoji: EmojiConfig,
+    #[serde(default)]
+    pub remind: RemindConfig,
+    #[serde(default)]
+    pub plan: PlanConfig,
+    #[serde(default)]
+    pub subtasks: SubtaskConfig,
+    // Per-list display settings, keyed by list name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lists: BTreeMap<String, ListStyle>,
+    // Colors tags are shown in, keyed by tag without the '#': a color name
+    // or "#rrggbb", as for lists
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tag_colors: BTreeMap<String, String>,
+    // Filters saved by name, such as `urgent`, that the dashboard toggles
+    // with their keys
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub filters: BTreeMap<String, SavedFilter>,
+    // Progress badges kept up to date, keyed by list name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub badges: BTreeMap<String, BadgeConfig>,
+    // More todo directories making one workspace with the main one, keyed
+    // by the name their lists go by: with `work = "~/work/todos"`, the
+    // standup list there is `work/standup`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub workspace: BTreeMap<String, String>,
+    // Metadata filled in on todos as they're added, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+    // The project-local list found for the current directory, which takes
+    // the place of the active list
+    #[serde(skip)]
+    pub project_list: Option<PathBuf>,
+}
+
+impl Config {
+    // The name todos are attributed to, as one word so it can be stamped
+    pub fn user_name(&self) -> Option<String> {
+        let user = self.user.as_deref()?.split_whitespace().collect::<Vec<_>>().join("-");
+        (!user.is_empty()).then_some(user)
+    }
+
+    // A todo's text as typed, with shortcodes expanded if they're enabled
+    pub fn expand_emoji(&self, text: &str) -> String {
+        if self.emoji.shortcodes {
+            expand_shortcodes(text, &self.emoji.custom)
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn list_style(&self, list_name: &str) -> ListStyle {
+        self.lists.get(&normalize_list_name(list_name)).cloned().unwrap_or_default()
+    }
+
+    // A tag's color, ignoring case, as a style of its own
+    pub fn tag_style(&self, tag: &str) -> Option<ListStyle> {
+        let (_, color) = self.tag_colors.iter().find(|(name, _)| name.eq_ignore_ascii_case(tag.trim_start_matches('#')))?;
+        Some(ListStyle { color: Some(color.clone()), emoji: None })
+    }
+}
+
+// How a list is told apart from the others: a color name (e.g. "blue",
+// "bright magenta") or "#rrggbb", and an emoji shown before its name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListStyle {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+}
+
+impl ListStyle {
+    // The list name with its emoji, if it has one
+    pub fn label(&self, name: &str) -> String {
+        match &self.emoji {
+            Some(emoji) => format!("{} {}", emoji, name),
+            None => name.to_string(),
+        }
+    }
+
+    pub fn is_color(color: &str) -> bool {
+        color.parse::<colored::Color>().is_ok() || parse_hex(color).is_some()
+    }
+
+    // The color as RGB, if it is given in hex
+    pub fn hex_color(&self) -> Option<(u8, u8, u8)> {
+        parse_hex(self.color.as_deref()?)
+    }
+}
+
+fn parse_hex(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+// The conditions a todo has to meet to pass a saved filter, written as for
+// `todo reschedule --where`, like "due<=today" or "tag=home". On the
+// dashboard, pressing `key` (1 to 9) shows only the todos that pass.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<char>,
+    #[serde(rename = "where")]
+    pub conditions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BadgeConfig {
+    pub svg: PathBuf,
+    pub label: String,
+}
+
+// What to add to a new todo whose text matches the `match` regex: tags, a
+// priority letter if it has none, and text to append, where `$0`, `$1` and
+// `${name}` stand for the match and its groups
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<char>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub append: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TodoConfig {
+    pub active_list: String,
+    pub list_extension: String,
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EditorConfig {
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    pub enabled: bool,
+    // Maximum number of backups kept per list
+    pub keep: usize,
+    // Backups older than this are removed (0 keeps them forever)
+    pub max_age_days: u64,
+    // Maximum number of whole-directory snapshots kept by `todo backup`
+    pub keep_snapshots: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig {
+            enabled: true,
+            keep: 20,
+            max_age_days: 30,
+            keep_snapshots: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitConfig {
+    // Commit the todo directory after changes, if it is a git repository
+    pub auto_commit: bool,
+    // Changes within this many seconds of each other go into one commit
+    pub debounce_secs: u64,
+    // Also pull and push after each auto-commit (implies auto_commit)
+    pub auto_sync: bool,
+    // Commit and sync from a background process once changes settle, so
+    // commands don't wait on git or the network; failures are reported by
+    // the next command. Off, each command commits and syncs its own changes
+    // before it exits.
+    pub background: bool,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        GitConfig {
+            auto_commit: false,
+            debounce_secs: 30,
+            auto_sync: false,
+            background: true,
+        }
+    }
+}
+
+// For todo directories synced by Dropbox, Syncthing and the like
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    // Log changes per item and merge the other devices' logs into lists
+    pub merge: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuiConfig {
+    pub density: Density,
+    // Lay the list out in up to this many columns, newspaper-style, as long
+    // as each gets at least `column_width` characters
+    pub columns: usize,
+    pub column_width: u16,
+    // Start with completed todos out of sight, as `h` toggles
+    pub hide_completed: bool,
+    // Start with long todos wrapped onto more rows, as `z` toggles
+    pub wrap: bool,
+}
+
+impl Default for TuiConfig {
+    fn default() -> Self {
+        TuiConfig {
+            density: Density::default(),
+            columns: 1,
+            column_width: 40,
+            hide_completed: false,
+            wrap: false,
+        }
+    }
+}
+
+// How `todo list` prints a list, and what `add` and `toggle` print after
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    // Show open todos that are overdue or due today in a block at the top
+    pub due_first: bool,
+    // Make URLs and paths in todos clickable, here and in the TUI
+    pub hyperlinks: Hyperlinks,
+    // Lines to finish `todo add` and `todo toggle` with, in this order
+    pub nudges: Vec<Nudge>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Nudge {
+    // Open todos left on the list
+    Remaining,
+    // The open todo added longest ago
+    Oldest,
+    // Days in a row with something completed
+    Streak,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hyperlinks {
+    // When the terminal is known to support them
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemindConfig {
+    // How often `todo remind --daemon` looks at the lists
+    pub interval_mins: u64,
+    // Todos due within this many days count as due soon (0 for only today)
+    pub days_ahead: u64,
+    // Shows notifications instead of the desktop's own, given the title and
+    // message as arguments; "osascript" uses the macOS notification center
+    pub command: String,
+}
+
+impl Default for RemindConfig {
+    fn default() -> Self {
+        RemindConfig {
+            interval_mins: 15,
+            days_ahead: 1,
+            command: String::new(),
+        }
+    }
+}
+
+// For `todo plan`, which weighs todos' estimates against the time there is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlanConfig {
+    // How much work fits in a day
+    pub capacity_mins: u32,
+    // What a todo without an `est:` is taken to need
+    pub default_estimate_mins: u32,
+}
+
+impl Default for PlanConfig {
+    fn default() -> Self {
+        PlanConfig { capacity_mins: 6 * 60, default_estimate_mins: 30 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubtaskConfig {
+    // Complete a todo once all of its subtasks are done
+    pub complete_parent: bool,
+}
+
+impl Default for SubtaskConfig {
+    fn default() -> Self {
+        SubtaskConfig { complete_parent: true }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmojiConfig {
+    // Turn `:rocket:`-style shortcodes into emoji as todos are added and edited
+    pub shortcodes: bool,
+    // Extra shortcodes, or replacements for built-in ones, without the colons
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom: BTreeMap<String, String>,
+}
+
+impl Default for EmojiConfig {
+    fn default() -> Self {
+        EmojiConfig {
+            shortcodes: true,
+            custom: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpellConfig {
+    // Check todos as they're added and edited, and flag misspellings in the TUI
+    pub enabled: bool,
+    // Any checker speaking the ispell pipe protocol, e.g. hunspell or aspell
+    pub command: String,
+    // Passed to the checker as `-d`, e.g. "en_US"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<String>,
+    // Words never to flag, such as names and jargon
+    pub ignore: Vec<String>,
+}
+
+impl Default for SpellConfig {
+    fn default() -> Self {
+        SpellConfig {
+            enabled: false,
+            command: "hunspell".to_string(),
+            dictionary: None,
+            ignore: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Density {
+    #[default]
+    Comfortable,
+    // No borders, padding or blank lines, to fit more todos on screen
+    Compact,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            reduced_motion: false,
+            strict: false,
+            high_contrast: false,
+            user: None,
+            todo: TodoConfig {
+                active_list: "default".to_string(),
+                list_extension: "adoc".to_string(),
+                path: "~/todos".to_string(),
+            },
+            editor: EditorConfig {
+                command: "nvim".to_string(),
+            },
+            backup: BackupConfig::default(),
+            git: GitConfig::default(),
+            sync: SyncConfig::default(),
+            tui: TuiConfig::default(),
+            output: OutputConfig::default(),
+            spell: SpellConfig::default(),
+            emoji: EmojiConfig::default(),
+            remind: RemindConfig::default(),
+            plan: PlanConfig::default(),
+            subtasks: SubtaskConfig::default(),
+            lists: BTreeMap::new(),
+            tag_colors: BTreeMap::new(),
+            filters: BTreeMap::new(),
+            badges: BTreeMap::new(),
+            workspace: BTreeMap::new(),
+            rules: Vec::new(),
+            project_list: None,
+        }
+    }
+}
+
+pub fn get_config_path() -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    home.join(".config").join("todo").join("config.toml")
+}
+
+pub fn expand_tilde(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        let home = dirs::home_dir().expect("Could not find home directory");
+        home.join(rest)
+    } else {
+        PathBuf::from(path)
+    }
+}
+
+// Errors describe what's wrong with an existing config file, including the
+// offending line for parse errors
+pub fn ensure_config_exists() -> Result<Config, String> {
+    let config_path = get_config_path();
+
+    if !config_path.exists() {
+        // Create the directory if it doesn't exist
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent).expect("Failed to create config directory");
+        }
+
+        // Create default config
+        let config = Config::default();
+        let toml_string = toml::to_string(&config).expect("Failed to serialize config");
+        fs::write(&config_path, toml_string).expect("Failed to write config file");
+
+        Ok(config)
+    } else {
+        // Load existing config
+        let config_str = fs::read_to_string(&config_path).map_err(|e| e.to_string())?;
+        toml::from_str(&config_str).map_err(|e| e.to_string())
+    }
+}
+
+// Look up a dotted key such as `todo.path` in the config
+pub fn get_config_value(config: &Config, key: &str) -> Result<toml::Value, String> {
+    let root = toml::Value::try_from(config).map_err(|e| e.to_string())?;
+    key.split('.')
+        .try_fold(&root, |value, part| value.get(part))
+        .cloned()
+        .ok_or_else(|| format!("Unknown config key '{}'", key))
+}
+
+// A copy of the config with a dotted key set. The value is read as the type
+// the key already has; keys the config doesn't know, and values it wouldn't
+// load, are errors.
+pub fn set_config_value(config: &Config, key: &str, value: &str) -> Result<Config, String> {
+    let unknown = || format!("Unknown config key '{}'", key);
+    let mut root = toml::Value::try_from(config).map_err(|e| e.to_string())?;
+    let parts: Vec<&str> = key.split('.').collect();
+    let (last, tables) = parts.split_last().ok_or_else(unknown)?;
+
+    let mut table = root.as_table_mut().ok_or_else(unknown)?;
+    for part in tables {
+        // Tables that are only written once they have something in them,
+        // such as a list's style
+        table = table
+            .entry(part.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(unknown)?;
+    }
+
+    let invalid = |expected: &str| format!("Invalid value '{}' for {}: expected {}", value, key, expected);
+    let parsed = match table.get(*last) {
+        Some(toml::Value::Boolean(_)) => toml::Value::Boolean(value.parse().map_err(|_| invalid("true or false"))?),
+        Some(toml::Value::Integer(_)) => toml::Value::Integer(value.parse().map_err(|_| invalid("a whole number"))?),
+        Some(toml::Value::String(_)) | None => toml::Value::String(value.to_string()),
+        Some(_) => return Err(format!("{} is a section; set one of its keys instead", key)),
+    };
+    let is_color_key = (tables.first() == Some(&"lists") && *last == "color") || *tables == ["tag_colors"];
+    if is_color_key && !ListStyle::is_color(value) {
+        return Err(invalid("a color name such as \"blue\" or \"bright red\", or #rrggbb"));
+    }
+    table.insert(last.to_string(), parsed);
+
+    let updated: Config = root
+        .try_into()
+        .map_err(|e: toml::de::Error| format!("Invalid value '{}' for {}: {}", value, key, e.message()))?;
+    // Keys serde doesn't know are dropped on the way in
+    if get_config_value(&updated, key).is_err() {
+        return Err(unknown());
+    }
+    Ok(updated)
+}
+
+pub fn save_config(config: &Config) {
+    let config_path = get_config_path();
+    let toml_string = toml::to_string(config).expect("Failed to serialize config");
+    fs::write(&config_path, toml_string).expect("Failed to write config file");
+}
+
+pub fn ensure_todo_directory_exists(config: &Config) -> PathBuf {
+    let todo_path = expand_tilde(&config.todo.path);
+
+    if !todo_path.exists() {
+        fs::create_dir_all(&todo_path).expect("Failed to create todo directory");
+    }
+
+    todo_path
+}
+
+pub fn get_active_list_path(config: &Config, todo_path: &Path) -> PathBuf {
+    match &config.project_list {
+        Some(path) => path.clone(),
+        None => get_list_path(config, todo_path, &config.todo.active_list),
+    }
+}
+
+// Every todo directory of the workspace, with the prefix of its lists'
+// names: "" for the main one, then "work/" and so on
+pub fn workspace_roots(config: &Config) -> Vec<(String, PathBuf)> {
+    let mut roots = vec![(String::new(), expand_tilde(&config.todo.path))];
+    roots.extend(config.workspace.iter().map(|(name, path)| (format!("{}/", name), expand_tilde(path))));
+    roots
+}
+
+// The configured directory a list file sits in: the todo directory, one of
+// the workspace's, or the project list's. None for a file anywhere else,
+// such as what a name with `..` in it points at.
+pub fn list_root(config: &Config, list_path: &Path) -> Option<PathBuf> {
+    let dir = list_path.parent()?;
+    let mut roots: Vec<PathBuf> = workspace_roots(config).into_iter().map(|(_, root)| root).collect();
+    roots.extend(config.project_list.iter().filter(|path| path.as_path() == list_path).filter_map(|path| path.parent()).map(Path::to_path_buf));
+    roots.into_iter().find(|root| root == dir)
+}
+
+// The workspace directory a `root/list` name points into, if it names one
+pub fn workspace_root<'a>(config: &Config, list_name: &'a str) -> Option<(PathBuf, &'a str)> {
+    let (root, name) = list_name.split_once('/')?;
+    Some((expand_tilde(config.workspace.get(root)?), name))
+}
+
+// Lists are named `root/list` only for the workspace's other directories,
+// and never by a path of their own
+pub fn check_list_name(config: &Config, list_name: &str) -> Result<(), String> {
+    if Path::new(list_name).is_absolute() || list_name.split(['/', '\\']).any(|part| part == "..") {
+        return Err(format!("Invalid list name '{}': lists are named, not given as paths", list_name));
+    }
+    match list_name.split_once('/') {
+        Some((root, _)) if !config.workspace.contains_key(root) => Err(format!(
+            "Unknown workspace directory '{}' in '{}'; add it under [workspace] in the config",
+            root, list_name
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Name of a project-local list, `.todo.adoc` for the default extension.
+// Commands reach it by this name, or by naming no list at all.
+pub const PROJECT_LIST: &str = ".todo";
+
+// The project list in `start` or the nearest directory above it, looking no
+// further than the root of the git repository it's in
+pub fn find_project_list(config: &Config, start: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let path = dir.join(format!("{}.{}", PROJECT_LIST, config.todo.list_extension));
+        if path.is_file() {
+            return Some(path);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+// Strip the extension from a list name if one was provided
+pub fn normalize_list_name(list_name: &str) -> String {
+    // A leading dot is part of the name, as in `.todo`
+    let (dot, name) = match list_name.strip_prefix('.') {
+        Some(name) => (".", name),
+        None => ("", list_name),
+    };
+    format!("{}{}", dot, name.split('.').next().unwrap_or(name))
+}
+
+pub fn get_list_path(config: &Config, todo_path: &Path, list_name: &str) -> PathBuf {
+    let list_name = normalize_list_name(list_name);
+    if let Some((root, name)) = workspace_root(config, &list_name) {
+        return root.join(format!("{}.{}", name, config.todo.list_extension));
+    }
+    match &config.project_list {
+        Some(path) if list_name == PROJECT_LIST => path.clone(),
+        _ => todo_path.join(format!("{}.{}", list_name, config.todo.list_extension)),
+    }
+}
+
+// Name of the list an optional list argument refers to
+pub fn target_list_name(config: &Config, target_list: Option<String>) -> String {
+    match target_list {
+        Some(name) => normalize_list_name(&name),
+        None if config.project_list.is_some() => PROJECT_LIST.to_string(),
+        None => config.todo.active_list.clone(),
+    }
+}
+
+// Resolve an optional list argument to its path and file name,
+// falling back to the active list
+pub fn resolve_list(config: &Config, target_list: Option<String>) -> (PathBuf, String) {
+    let todo_path = expand_tilde(&config.todo.path);
+    let list_name = target_list_name(config, target_list);
+    let file_name = format!("{}.{}", list_name, config.todo.list_extension);
+    (get_list_path(config, &todo_path, &list_name), file_name)
+}
+
+pub fn ensure_active_list_exists(list_path: &Path) {
+    if !list_path.exists() {
+        fs::write(list_path, "").expect("Failed to create todo list file");
+    }
+}
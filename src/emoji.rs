@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use unicode_width::UnicodeWidthStr;
+
+// GitHub/Slack-style `:shortcode:` emoji, sorted by code for lookup. Codes that aren't known stay as
+// they are, so times like 10:30:00 and other colons are left alone.
+const SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("airplane", "✈️"),
+    ("alarm_clock", "⏰"),
+    ("apple", "🍎"),
+    ("art", "🎨"),
+    ("baby", "👶"),
+    ("balloon", "🎈"),
+    ("bank", "🏦"),
+    ("beer", "🍺"),
+    ("bell", "🔔"),
+    ("bike", "🚲"),
+    ("birthday", "🎂"),
+    ("book", "📖"),
+    ("books", "📚"),
+    ("boom", "💥"),
+    ("brain", "🧠"),
+    ("broom", "🧹"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("calendar", "📅"),
+    ("camera", "📷"),
+    ("car", "🚗"),
+    ("cat", "🐱"),
+    ("chart_with_upwards_trend", "📈"),
+    ("check", "✔️"),
+    ("clapper", "🎬"),
+    ("clipboard", "📋"),
+    ("closed_lock_with_key", "🔐"),
+    ("coffee", "☕"),
+    ("computer", "💻"),
+    ("construction", "🚧"),
+    ("credit_card", "💳"),
+    ("dart", "🎯"),
+    ("dog", "🐶"),
+    ("dollar", "💵"),
+    ("email", "📧"),
+    ("eyes", "👀"),
+    ("fire", "🔥"),
+    ("gift", "🎁"),
+    ("globe_with_meridians", "🌐"),
+    ("hammer", "🔨"),
+    ("hammer_and_wrench", "🛠️"),
+    ("heart", "❤️"),
+    ("heavy_check_mark", "✔️"),
+    ("hospital", "🏥"),
+    ("hourglass", "⌛"),
+    ("house", "🏠"),
+    ("key", "🔑"),
+    ("laptop", "💻"),
+    ("link", "🔗"),
+    ("lipstick", "💄"),
+    ("lock", "🔒"),
+    ("mag", "🔍"),
+    ("mailbox", "📫"),
+    ("memo", "📝"),
+    ("money_with_wings", "💸"),
+    ("moneybag", "💰"),
+    ("muscle", "💪"),
+    ("musical_note", "🎵"),
+    ("package", "📦"),
+    ("paperclip", "📎"),
+    ("partying_face", "🥳"),
+    ("pencil", "📝"),
+    ("pencil2", "✏️"),
+    ("phone", "📞"),
+    ("pill", "💊"),
+    ("pizza", "🍕"),
+    ("pray", "🙏"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("recycle", "♻️"),
+    ("red_circle", "🔴"),
+    ("rocket", "🚀"),
+    ("rotating_light", "🚨"),
+    ("runner", "🏃"),
+    ("seedling", "🌱"),
+    ("shield", "🛡️"),
+    ("shopping_cart", "🛒"),
+    ("smile", "😄"),
+    ("sparkles", "✨"),
+    ("speech_balloon", "💬"),
+    ("star", "⭐"),
+    ("stopwatch", "⏱️"),
+    ("tada", "🎉"),
+    ("telephone", "☎️"),
+    ("thinking", "🤔"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("tooth", "🦷"),
+    ("tractor", "🚜"),
+    ("trophy", "🏆"),
+    ("truck", "🚚"),
+    ("warning", "⚠️"),
+    ("wave", "👋"),
+    ("white_check_mark", "✅"),
+    ("wrench", "🔧"),
+    ("x", "❌"),
+    ("zap", "⚡"),
+    ("zzz", "💤"),
+];
+
+fn lookup<'a>(code: &str, custom: &'a BTreeMap<String, String>) -> Option<&'a str> {
+    if let Some(emoji) = custom.get(code) {
+        return Some(emoji);
+    }
+    SHORTCODES.binary_search_by(|(name, _)| name.cmp(&code)).ok().map(|i| SHORTCODES[i].1)
+}
+
+// Replace known shortcodes with their emoji; `custom` codes come first
+pub fn expand_shortcodes(text: &str, custom: &BTreeMap<String, String>) -> String {
+    let is_code_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-');
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(':') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find(|c: char| !is_code_char(c)).unwrap_or(after.len());
+        let closed = end > 0 && after[end..].starts_with(':');
+        let emoji = if closed { lookup(&after[..end], custom) } else { None };
+        match emoji {
+            Some(emoji) => {
+                result.push_str(emoji);
+                rest = &after[end + 1..];
+            }
+            None => {
+                // The closing colon may open the next code
+                result.push(':');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// Columns the text takes up in a terminal, with wide emoji counting as two
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+// The text padded with spaces to `width` columns
+pub fn pad(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(display_width(text))))
+}
@@ -0,0 +1,108 @@
+use crate::item::{LineType, TodoItem};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+
+// Something that happened to a list. The CLI and TUI emit these once a
+// change is on disk, and everything that reacts to changes (the journal,
+// celebrations, hooks) consumes them instead of working out for itself what
+// a command did.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum TodoEvent {
+    ItemAdded { list: String, text: String },
+    ItemsAdded { list: String, items: Vec<String> },
+    ItemCompleted { list: String, text: String },
+    ItemReopened { list: String, text: String },
+    ItemEdited { list: String, old: String, new: String },
+    // The todo's text after the change
+    ItemStarred { list: String, text: String, starred: bool },
+    ItemPrioritized { list: String, text: String, priority: Option<char> },
+    ItemDueSet { list: String, text: String, due: Option<NaiveDate> },
+    ItemMoved { from: String, to: String, text: String },
+    ItemsArchived { list: String, items: Vec<String> },
+    ListsMerged { from: String, to: String, items: Vec<String> },
+    // The last open todo of a list was completed
+    ListCompleted { list: String },
+    ListSwitched { from: String, to: String },
+    ListRenamed { old: String, new: String },
+    ListDeleted { list: String },
+    ListEdited { list: String },
+    ListSorted { list: String },
+}
+
+impl TodoEvent {
+    // The list the event is about; for moves and renames, the original one
+    pub fn list(&self) -> &str {
+        match self {
+            TodoEvent::ItemAdded { list, .. }
+            | TodoEvent::ItemsAdded { list, .. }
+            | TodoEvent::ItemCompleted { list, .. }
+            | TodoEvent::ItemReopened { list, .. }
+            | TodoEvent::ItemEdited { list, .. }
+            | TodoEvent::ItemStarred { list, .. }
+            | TodoEvent::ItemPrioritized { list, .. }
+            | TodoEvent::ItemDueSet { list, .. }
+            | TodoEvent::ItemsArchived { list, .. }
+            | TodoEvent::ListCompleted { list }
+            | TodoEvent::ListDeleted { list }
+            | TodoEvent::ListEdited { list }
+            | TodoEvent::ListSorted { list } => list,
+            TodoEvent::ItemMoved { from, .. }
+            | TodoEvent::ListsMerged { from, .. }
+            | TodoEvent::ListSwitched { from, .. } => from,
+            TodoEvent::ListRenamed { old, .. } => old,
+        }
+    }
+}
+
+// Events for toggling `item` (already in its new state) in a list that now
+// holds `items`
+pub fn toggle_events(list: &str, item: &TodoItem, items: &[TodoItem]) -> Vec<TodoEvent> {
+    let (list, text) = (list.to_string(), item.text.clone());
+    if !item.completed {
+        return vec![TodoEvent::ItemReopened { list, text }];
+    }
+
+    let all_done = items
+        .iter()
+        .filter(|item| matches!(item.line_type, LineType::Todo))
+        .all(|item| item.completed);
+    let mut events = vec![TodoEvent::ItemCompleted { list: list.clone(), text }];
+    if all_done {
+        events.push(TodoEvent::ListCompleted { list });
+    }
+    events
+}
+
+type Handler = Box<dyn Fn(&TodoEvent) + Send + Sync>;
+
+// Fans events out to every subscriber, in the order they subscribed
+#[derive(Default)]
+pub struct EventBus {
+    handlers: Mutex<Vec<Handler>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        EventBus::default()
+    }
+
+    // The bus shared by the whole process
+    pub fn global() -> &'static EventBus {
+        static BUS: OnceLock<EventBus> = OnceLock::new();
+        BUS.get_or_init(EventBus::new)
+    }
+
+    pub fn subscribe(&self, handler: impl Fn(&TodoEvent) + Send + Sync + 'static) {
+        let mut handlers = self.handlers.lock().unwrap_or_else(|e| e.into_inner());
+        handlers.push(Box::new(handler));
+    }
+
+    pub fn emit(&self, event: &TodoEvent) {
+        let handlers = self.handlers.lock().unwrap_or_else(|e| e.into_inner());
+        for handler in handlers.iter() {
+            handler(event);
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use crate::backup::{list_backups, snapshot_list};
+use crate::config::BackupConfig;
+use crate::format::{detect_format, AsciiDoc, ListFormat};
+use crate::item::{block_end, parse_line, LineType, TodoItem};
+use chrono::{DateTime, Local};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// List file encoding, line endings and trailing newline, detected on read
+// so that rewriting a file doesn't produce a whole-file diff
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FileFormat {
+    pub encoding: Encoding,
+    pub crlf: bool,
+    pub trailing_newline: bool,
+    // List syntax, translated to and from AsciiDoc-style lines
+    pub syntax: &'static dyn ListFormat,
+}
+
+impl Default for FileFormat {
+    fn default() -> Self {
+        FileFormat {
+            encoding: Encoding::Utf8,
+            crlf: false,
+            trailing_newline: true,
+            syntax: &AsciiDoc,
+        }
+    }
+}
+
+impl FileFormat {
+    pub fn line_ending(&self) -> &'static str {
+        if self.crlf {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    pub fn decode(bytes: &[u8]) -> io::Result<(String, FileFormat)> {
+        // An odd byte at the end is half a character, which would be lost
+        // on the next save
+        let utf16 = |rest: &[u8], from: fn([u8; 2]) -> u16| {
+            if !rest.len().is_multiple_of(2) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "UTF-16 file ends partway through a character"));
+            }
+            let units: Vec<u16> = rest.chunks_exact(2).map(|c| from([c[0], c[1]])).collect();
+            String::from_utf16(&units).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        };
+
+        let (content, encoding) = if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+            (String::from_utf8(rest.to_vec()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?, Encoding::Utf8Bom)
+        } else if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+            (utf16(rest, u16::from_le_bytes)?, Encoding::Utf16Le)
+        } else if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+            (utf16(rest, u16::from_be_bytes)?, Encoding::Utf16Be)
+        } else {
+            (String::from_utf8(bytes.to_vec()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?, Encoding::Utf8)
+        };
+
+        let format = FileFormat {
+            encoding,
+            crlf: content.contains("\r\n"),
+            trailing_newline: content.is_empty() || content.ends_with('\n'),
+            ..FileFormat::default()
+        };
+
+        Ok((content, format))
+    }
+
+    pub fn encode(&self, lines: &[String]) -> Vec<u8> {
+        let lines: Vec<String> = lines.iter().map(|line| self.syntax.encode_line(line)).collect();
+        let mut content = lines.join(self.line_ending());
+        if self.trailing_newline && !lines.is_empty() {
+            content.push_str(self.line_ending());
+        }
+
+        match self.encoding {
+            Encoding::Utf8 => content.into_bytes(),
+            Encoding::Utf8Bom => {
+                let mut bytes = vec![0xEF, 0xBB, 0xBF];
+                bytes.extend_from_slice(content.as_bytes());
+                bytes
+            }
+            Encoding::Utf16Le => {
+                let mut bytes = vec![0xFF, 0xFE];
+                bytes.extend(content.encode_utf16().flat_map(|u| u.to_le_bytes()));
+                bytes
+            }
+            Encoding::Utf16Be => {
+                let mut bytes = vec![0xFE, 0xFF];
+                bytes.extend(content.encode_utf16().flat_map(|u| u.to_be_bytes()));
+                bytes
+            }
+        }
+    }
+}
+
+// Raw decoded content of a list file, in its own syntax
+pub fn read_list_file(path: &Path) -> io::Result<(String, FileFormat)> {
+    let (content, mut format) = FileFormat::decode(&fs::read(path)?)?;
+    format.syntax = detect_format(path, &content);
+    Ok((content, format))
+}
+
+// Lines of a list file translated to AsciiDoc-style lines
+pub fn read_list_lines(path: &Path) -> io::Result<(Vec<String>, FileFormat)> {
+    let (content, format) = read_list_file(path)?;
+    Ok((content.lines().map(|line| format.syntax.decode_line(line)).collect(), format))
+}
+
+// Where deleted lists go, in the todo directory
+pub const TRASH_DIR: &str = ".trash";
+
+// Why a list file can't be decoded, and the first line where it goes wrong
+#[derive(Debug)]
+pub struct DecodeProblem {
+    pub message: String,
+    // 1-based line number
+    pub line: usize,
+    // The line with undecodable bytes replaced by U+FFFD
+    pub context: String,
+}
+
+// Content of a list file with undecodable bytes replaced by U+FFFD, for
+// looking at a file that read_list_lines rejects
+pub fn read_list_lossy(path: &Path) -> io::Result<Vec<String>> {
+    let bytes = fs::read(path)?;
+    let utf16 = |rest: &[u8], from: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = rest.chunks_exact(2).map(|c| from([c[0], c[1]])).collect();
+        let mut content = String::from_utf16_lossy(&units);
+        // An odd byte at the end, half a character
+        if !rest.len().is_multiple_of(2) {
+            content.push('\u{FFFD}');
+        }
+        content
+    };
+    let content = if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        String::from_utf8_lossy(rest).into_owned()
+    } else if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        utf16(rest, u16::from_le_bytes)
+    } else if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        utf16(rest, u16::from_be_bytes)
+    } else {
+        String::from_utf8_lossy(&bytes).into_owned()
+    };
+    Ok(content.lines().map(str::to_string).collect())
+}
+
+// None if the file reads fine (or doesn't exist)
+pub fn diagnose_list(path: &Path) -> io::Result<Option<DecodeProblem>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let Err(e) = read_list_file(path) else {
+        return Ok(None);
+    };
+    if e.kind() != io::ErrorKind::InvalidData {
+        return Err(e);
+    }
+
+    let lines = read_list_lossy(path)?;
+    let (line, context) = lines
+        .iter()
+        .enumerate()
+        .find(|(_, line)| line.contains('\u{FFFD}'))
+        .map(|(i, line)| (i + 1, line.clone()))
+        .unwrap_or((1, String::new()));
+    Ok(Some(DecodeProblem { message: e.to_string(), line, context }))
+}
+
+// Line number of the todo with the given 1-based index, counting only todo lines
+pub fn todo_line_index(lines: &[String], index: usize) -> Option<usize> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| matches!(parse_line(line).line_type, LineType::Todo))
+        .nth(index.checked_sub(1)?)
+        .map(|(pos, _)| pos)
+}
+
+// A todo as commands take it: its number in the list, as shown by `todo
+// list`, or its id, with or without the '#'. Ids always have a letter, so
+// a bare number is the todo's number.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TodoRef {
+    Number(usize),
+    Id(String),
+}
+
+impl FromStr for TodoRef {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if let Ok(number) = input.parse() {
+            return Ok(TodoRef::Number(number));
+        }
+        let id = input.strip_prefix('#').unwrap_or(input);
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("'{}' is neither a todo's number nor an id", input));
+        }
+        Ok(TodoRef::Id(id.to_lowercase()))
+    }
+}
+
+// As in "todo #3" or "todo #k3f9"
+impl fmt::Display for TodoRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TodoRef::Number(number) => write!(f, "{}", number),
+            TodoRef::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+// Line number of the todo a command refers to
+pub fn find_todo(lines: &[String], todo: &TodoRef) -> Option<usize> {
+    match todo {
+        TodoRef::Number(number) => todo_line_index(lines, *number),
+        TodoRef::Id(id) => lines.iter().position(|line| {
+            let item = parse_line(line);
+            matches!(item.line_type, LineType::Todo) && item.id() == Some(id.as_str())
+        }),
+    }
+}
+
+// The number `todo list` shows for the todo at line `pos`
+pub fn todo_number(lines: &[String], pos: usize) -> usize {
+    lines[..=pos].iter().filter(|line| matches!(parse_line(line).line_type, LineType::Todo)).count()
+}
+
+// Where the todo at `pos` ends: past the lines of its note
+pub fn note_end(lines: &[String], pos: usize) -> usize {
+    (pos + 1..lines.len()).find(|&i| !matches!(parse_line(&lines[i]).line_type, LineType::Note)).unwrap_or(lines.len())
+}
+
+// Where the todo at `pos` ends along with its subtasks
+pub fn todo_end(lines: &[String], pos: usize) -> usize {
+    let items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+    block_end(&items, pos)
+}
+
+fn is_header(line: &str) -> bool {
+    matches!(parse_line(line).line_type, LineType::Header1 | LineType::Header2 | LineType::Header3)
+}
+
+// Line number of the header with the given text, ignoring case and any
+// defaults it gives its section
+pub fn find_header(lines: &[String], header: &str) -> Option<usize> {
+    lines.iter().position(|line| {
+        let item = parse_line(line);
+        is_header(line) && (item.text.eq_ignore_ascii_case(header.trim()) || item.section_name().eq_ignore_ascii_case(header.trim()))
+    })
+}
+
+// Where to add a line at the end of the section under the header at
+// `header`: before the next header and any blank lines separating it
+pub fn section_end(lines: &[String], header: usize) -> usize {
+    let end = (header + 1..lines.len()).find(|&i| is_header(&lines[i])).unwrap_or(lines.len());
+    (header + 1..end).rev().find(|&i| !lines[i].trim().is_empty()).map_or(header + 1, |i| i + 1)
+}
+
+// Take an exclusive advisory lock on a list file for a read-modify-write
+// cycle; the lock is released when the returned handle is dropped
+pub fn lock_list(path: &Path) -> io::Result<fs::File> {
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    file.lock()?;
+    Ok(file)
+}
+
+// Shared counterpart of lock_list, for readers that must not observe a
+// half-written file
+pub fn lock_list_shared(path: &Path) -> io::Result<fs::File> {
+    let file = fs::File::open(path)?;
+    file.lock_shared()?;
+    Ok(file)
+}
+
+pub fn write_list_file(path: &Path, lines: &[String], format: &FileFormat, backup: &BackupConfig) -> io::Result<()> {
+    // Keep the state from before the tool first touched this list
+    if backup.enabled && path.exists() && list_backups(path).is_empty() {
+        let modified = fs::metadata(path)?.modified()?;
+        snapshot_list(path, DateTime::<Local>::from(modified).naive_local(), backup)?;
+    }
+
+    fs::write(path, format.encode(lines))?;
+
+    if backup.enabled {
+        snapshot_list(path, Local::now().naive_local(), backup)?;
+    }
+    Ok(())
+}
+
+// All list files in the todo directory, sorted by name
+pub fn list_files(todo_path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(todo_path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+pub fn read_todos(path: &Path) -> io::Result<Vec<TodoItem>> {
+    let (lines, _) = read_list_lines(path)?;
+    Ok(lines
+        .iter()
+        .map(|line| parse_line(line))
+        .filter(|item| matches!(item.line_type, LineType::Todo))
+        .collect())
+}
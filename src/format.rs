@@ -0,0 +1,186 @@
+use crate::item::{format_line, is_indented, parse_line, LineType, TodoItem};
+use std::fmt::Debug;
+use std::path::Path;
+
+// Syntax of a list file. The rest of the crate works on AsciiDoc-style lines;
+// other formats translate to and from those lines when a file is read or
+// written, so adding a format only means implementing this trait and
+// registering it in FORMATS.
+pub trait ListFormat: Send + Sync + Debug {
+    fn name(&self) -> &'static str;
+
+    // File extensions (lowercase, without the dot) this format claims
+    fn extensions(&self) -> &'static [&'static str];
+
+    fn parse(&self, line: &str) -> TodoItem;
+
+    fn serialize(&self, item: &TodoItem) -> String;
+
+    // How many lines of the content are distinctive for this format; used
+    // to pick a format when the extension doesn't settle it
+    fn detect(&self, content: &str) -> usize;
+
+    fn decode_line(&self, line: &str) -> String {
+        format_line(&self.parse(line))
+    }
+
+    fn encode_line(&self, line: &str) -> String {
+        self.serialize(&parse_line(line))
+    }
+}
+
+#[derive(Debug)]
+pub struct AsciiDoc;
+
+impl ListFormat for AsciiDoc {
+    fn name(&self) -> &'static str {
+        "asciidoc"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["adoc", "asciidoc"]
+    }
+
+    fn parse(&self, line: &str) -> TodoItem {
+        parse_line(line)
+    }
+
+    fn serialize(&self, item: &TodoItem) -> String {
+        format_line(item)
+    }
+
+    fn detect(&self, content: &str) -> usize {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("* [") || line.starts_with("= ") || line.starts_with("== "))
+            .count()
+    }
+
+    // Native lines pass through untouched
+    fn decode_line(&self, line: &str) -> String {
+        line.to_string()
+    }
+
+    fn encode_line(&self, line: &str) -> String {
+        line.to_string()
+    }
+}
+
+// GitHub/Obsidian style: `- [ ] todo`, `# Header`, `- bullet`
+#[derive(Debug)]
+pub struct Markdown;
+
+impl ListFormat for Markdown {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["md", "markdown"]
+    }
+
+    fn parse(&self, line: &str) -> TodoItem {
+        let trimmed = line.trim();
+        let item = |text: &str, completed: bool, line_type: LineType| TodoItem {
+            text: text.trim().to_string(),
+            completed,
+            line_type,
+            depth: 0,
+        };
+
+        let todo = ["- ", "* ", "+ "].iter().find_map(|bullet| trimmed.strip_prefix(bullet));
+        if let Some(rest) = todo {
+            // Subtasks are indented under their todo, a level per two spaces
+            let indent: usize = line.chars().take_while(|c| c.is_whitespace()).map(|c| if c == '\t' { 4 } else { 1 }).sum();
+            let subtask = |text: &str, completed: bool| TodoItem { depth: indent / 2, ..item(text, completed, LineType::Todo) };
+            if let Some(text) = rest.strip_prefix("[ ]") {
+                return subtask(text, false);
+            }
+            if let Some(text) = rest.strip_prefix("[x]").or_else(|| rest.strip_prefix("[X]")) {
+                return subtask(text, true);
+            }
+            return item(rest, false, LineType::Bullet);
+        }
+
+        if let Some(text) = trimmed.strip_prefix("### ") {
+            item(text, false, LineType::Header3)
+        } else if let Some(text) = trimmed.strip_prefix("## ") {
+            item(text, false, LineType::Header2)
+        } else if let Some(text) = trimmed.strip_prefix("# ") {
+            item(text, false, LineType::Header1)
+        } else if trimmed.is_empty() {
+            item("", false, LineType::Empty)
+        } else if is_indented(line) {
+            item(trimmed, false, LineType::Note)
+        } else {
+            item(trimmed, false, LineType::Text)
+        }
+    }
+
+    fn serialize(&self, item: &TodoItem) -> String {
+        match item.line_type {
+            LineType::Todo => {
+                let indent = "  ".repeat(item.depth);
+                if item.completed {
+                    format!("{}- [x] {}", indent, item.text)
+                } else {
+                    format!("{}- [ ] {}", indent, item.text)
+                }
+            }
+            LineType::Header1 => format!("# {}", item.text),
+            LineType::Header2 => format!("## {}", item.text),
+            LineType::Header3 => format!("### {}", item.text),
+            LineType::Bullet => format!("- {}", item.text),
+            LineType::Text => item.text.clone(),
+            LineType::Note => format!("  {}", item.text),
+            LineType::Empty => String::new(),
+        }
+    }
+
+    fn detect(&self, content: &str) -> usize {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("- [") || line.starts_with("# ") || line.starts_with("## "))
+            .count()
+    }
+}
+
+// The first format is the fallback when nothing else matches
+pub static FORMATS: &[&dyn ListFormat] = &[&AsciiDoc, &Markdown];
+
+pub fn format_by_name(name: &str) -> Option<&'static dyn ListFormat> {
+    FORMATS.iter().copied().find(|format| format.name() == name)
+}
+
+// Pick the format claimed by the file's extension; if no format or several
+// claim it, go by which one the content looks most like
+pub fn detect_format(path: &Path, content: &str) -> &'static dyn ListFormat {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    let claimed: Vec<&'static dyn ListFormat> = FORMATS
+        .iter()
+        .copied()
+        .filter(|format| format.extensions().contains(&extension.as_str()))
+        .collect();
+    if let [format] = claimed.as_slice() {
+        return *format;
+    }
+
+    let candidates = if claimed.is_empty() { FORMATS.to_vec() } else { claimed };
+    let mut best = candidates[0];
+    let mut best_score = best.detect(content);
+    for format in &candidates[1..] {
+        let score = format.detect(content);
+        if score > best_score {
+            best = *format;
+            best_score = score;
+        }
+    }
+    best
+}
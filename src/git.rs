@@ -0,0 +1,214 @@
+use crate::config::get_config_path;
+use crate::event::TodoEvent;
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Changes waiting to be auto-committed, kept next to the config file so
+// every todo process adds to the same batch
+const PENDING_FILE: &str = "autocommit.json";
+
+// Verbs in the order they appear in commit messages
+const VERBS: &[&str] = &[
+    "added", "checked", "unchecked", "edited", "moved", "merged", "sorted", "archived", "renamed", "deleted",
+];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PendingCommit {
+    pub last_change: Option<NaiveDateTime>,
+    // List name -> verb -> count
+    pub counts: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl PendingCommit {
+    // e.g. "checked 4, added 2 in work.adoc"
+    pub fn summary(&self) -> String {
+        let lists: Vec<String> = self
+            .counts
+            .iter()
+            .filter(|(_, verbs)| !verbs.is_empty())
+            .map(|(list, verbs)| {
+                let parts: Vec<String> = VERBS
+                    .iter()
+                    .filter_map(|verb| Some(format!("{} {}", verb, verbs.get(*verb)?)))
+                    .collect();
+                format!("{} in {}", parts.join(", "), list)
+            })
+            .collect();
+        if lists.is_empty() {
+            "Update todo lists".to_string()
+        } else {
+            lists.join("; ")
+        }
+    }
+}
+
+pub fn pending_path() -> PathBuf {
+    get_config_path().with_file_name(PENDING_FILE)
+}
+
+pub fn is_repo(dir: &Path) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+// Stage everything in the directory and commit it; false if there was
+// nothing to commit
+pub fn commit_all(dir: &Path, message: &str) -> io::Result<bool> {
+    let git = |args: &[&str]| Command::new("git").arg("-C").arg(dir).args(args).output();
+
+    let add = git(&["add", "-A", "."])?;
+    if !add.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&add.stderr).trim().to_string()));
+    }
+    if git(&["diff", "--cached", "--quiet"])?.status.success() {
+        return Ok(false);
+    }
+    let commit = git(&["commit", "-q", "-m", message])?;
+    if !commit.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&commit.stderr).trim().to_string()));
+    }
+    Ok(true)
+}
+
+// Top directory of the work tree `dir` is in
+pub fn repo_root(dir: &Path) -> Option<PathBuf> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--show-toplevel"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+// When a revision (e.g. "@{push}" or "HEAD~3") was committed, in local time
+pub fn commit_time(dir: &Path, revision: &str) -> Option<NaiveDateTime> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["log", "-1", "--format=%ct", revision, "--"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let seconds: i64 = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    let time = chrono::DateTime::from_timestamp(seconds, 0)?;
+    Some(time.with_timezone(&Local).naive_local())
+}
+
+// The branch the current one pulls from and pushes to, e.g. "origin/main"
+pub fn upstream(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// Rebase local commits onto the upstream's and push them. A pull that
+// conflicts is rolled back, so the directory is never left mid-rebase for
+// the next todo command to write into.
+pub fn pull_and_push(dir: &Path) -> io::Result<()> {
+    let git = |args: &[&str]| Command::new("git").arg("-C").arg(dir).args(args).output();
+    let failed = |what: &str, output: &std::process::Output| {
+        io::Error::other(format!("{} failed: {}", what, String::from_utf8_lossy(&output.stderr).trim()))
+    };
+
+    let pull = git(&["pull", "--rebase", "-q"])?;
+    if !pull.status.success() {
+        if git(&["rebase", "--abort"])?.status.success() {
+            return Err(io::Error::other(
+                "local changes conflict with the upstream's; the pull was rolled back, so merge them with git",
+            ));
+        }
+        return Err(failed("git pull", &pull));
+    }
+    let push = git(&["push", "-q"])?;
+    if !push.status.success() {
+        return Err(failed("git push", &push));
+    }
+    Ok(())
+}
+
+// What an event adds to the batch: the list and verb to count, if any
+fn event_counts(event: &TodoEvent) -> Vec<(&str, &'static str, usize)> {
+    match event {
+        TodoEvent::ItemAdded { list, .. } => vec![(list, "added", 1)],
+        TodoEvent::ItemsAdded { list, items } => vec![(list, "added", items.len())],
+        TodoEvent::ItemCompleted { list, .. } => vec![(list, "checked", 1)],
+        TodoEvent::ItemReopened { list, .. } => vec![(list, "unchecked", 1)],
+        TodoEvent::ItemEdited { list, .. } => vec![(list, "edited", 1)],
+        TodoEvent::ItemStarred { list, starred: true, .. } => vec![(list, "starred", 1)],
+        TodoEvent::ItemStarred { list, starred: false, .. } => vec![(list, "unstarred", 1)],
+        TodoEvent::ItemPrioritized { list, .. } => vec![(list, "prioritized", 1)],
+        TodoEvent::ItemDueSet { list, .. } => vec![(list, "rescheduled", 1)],
+        TodoEvent::ItemMoved { from, .. } => vec![(from, "moved", 1)],
+        TodoEvent::ItemsArchived { list, items } => vec![(list, "archived", items.len())],
+        TodoEvent::ListsMerged { from, items, .. } => vec![(from, "merged", items.len())],
+        TodoEvent::ListRenamed { old, .. } => vec![(old, "renamed", 1)],
+        TodoEvent::ListDeleted { list } => vec![(list, "deleted", 1)],
+        TodoEvent::ListSorted { list } => vec![(list, "sorted", 1)],
+        TodoEvent::ListEdited { .. } | TodoEvent::ListCompleted { .. } | TodoEvent::ListSwitched { .. } => Vec::new(),
+    }
+}
+
+// Whether the event changes list files at all
+pub fn changes_files(event: &TodoEvent) -> bool {
+    !matches!(event, TodoEvent::ListCompleted { .. } | TodoEvent::ListSwitched { .. })
+}
+
+// Run `f` on the pending batch under an exclusive lock
+fn with_pending<T>(f: impl FnOnce(&mut PendingCommit) -> T) -> io::Result<T> {
+    let mut file = OpenOptions::new().create(true).truncate(false).read(true).write(true).open(pending_path())?;
+    file.lock()?;
+
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let mut pending: PendingCommit = serde_json::from_str(&content).unwrap_or_default();
+    let result = f(&mut pending);
+
+    file.set_len(0)?;
+    file.rewind()?;
+    file.write_all(serde_json::to_string(&pending).map_err(io::Error::other)?.as_bytes())?;
+    Ok(result)
+}
+
+// Add an event to the batch. Returns the time of the previous change, so
+// the caller can tell whether a batch was already under way.
+pub fn add_to_batch(event: &TodoEvent) -> io::Result<Option<NaiveDateTime>> {
+    with_pending(|pending| {
+        for (list, verb, count) in event_counts(event) {
+            *pending.counts.entry(list.to_string()).or_default().entry(verb.to_string()).or_default() += count;
+        }
+        pending.last_change.replace(Local::now().naive_local())
+    })
+}
+
+// Take the batch if nothing has been added for `debounce_secs`; otherwise
+// return how many seconds are left to wait
+pub fn take_batch(debounce_secs: u64) -> io::Result<Result<PendingCommit, u64>> {
+    with_pending(|pending| {
+        let Some(last_change) = pending.last_change else {
+            return Ok(std::mem::take(pending));
+        };
+        let elapsed = (Local::now().naive_local() - last_change).num_seconds().max(0) as u64;
+        if elapsed < debounce_secs {
+            return Err(debounce_secs - elapsed);
+        }
+        Ok(std::mem::take(pending))
+    })
+}
@@ -0,0 +1,82 @@
+use crate::item::{LineType, TodoItem};
+use crate::journal::{Action, JournalEntry};
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+use std::collections::BTreeMap;
+
+// Patterns in how todos get added and done, for `todo insights`. They're
+// worked out here from the journal and the todos' own stamps, and go nowhere
+// but the terminal.
+
+// Buckets of days from a todo being added to it being done, each up to and
+// including its number of days
+pub const LATENCY_BUCKETS: [(&str, i64); 6] =
+    [("same day", 0), ("next day", 1), ("2-3 days", 3), ("4-7 days", 7), ("8-30 days", 30), ("longer", i64::MAX)];
+
+// Tags need this many stamped todos to say anything about
+const MIN_TAG_TODOS: usize = 2;
+
+#[derive(Debug, Default)]
+pub struct Insights {
+    // Todos added in each hour of the day, as far back as the journal goes
+    pub added_by_hour: [usize; 24],
+    pub journal_since: Option<NaiveDateTime>,
+    // Completed todos with both stamps in each of LATENCY_BUCKETS
+    pub latency: [usize; LATENCY_BUCKETS.len()],
+    pub median_latency: Option<i64>,
+    // Slowest first
+    pub tags: Vec<TagDelay>,
+}
+
+#[derive(Debug)]
+pub struct TagDelay {
+    pub tag: String,
+    pub todos: usize,
+    pub open: usize,
+    // Days from added to done or, for open todos, to today
+    pub average_days: f64,
+}
+
+pub fn insights(lists: &[(String, Vec<TodoItem>)], journal: &[JournalEntry], today: NaiveDate) -> Insights {
+    let mut insights = Insights::default();
+    for entry in journal.iter().filter(|entry| entry.action == Action::Add && !entry.undone) {
+        insights.added_by_hour[entry.time.hour() as usize] += entry.items.len();
+    }
+    insights.journal_since = journal.iter().map(|entry| entry.time).min();
+
+    let todos = lists.iter().flat_map(|(_, items)| items).filter(|item| matches!(item.line_type, LineType::Todo));
+    let mut latencies = Vec::new();
+    let mut by_tag: BTreeMap<String, (usize, usize, i64)> = BTreeMap::new();
+    for item in todos {
+        let Some(added) = item.added() else {
+            continue;
+        };
+        let end = if item.completed { item.done() } else { Some(today) };
+        let Some(days) = end.map(|end| (end - added).num_days().max(0)) else {
+            continue;
+        };
+        if item.completed {
+            latencies.push(days);
+        }
+        for tag in item.tags() {
+            let (todos, open, total) = by_tag.entry(tag.to_lowercase()).or_default();
+            *todos += 1;
+            *open += !item.completed as usize;
+            *total += days;
+        }
+    }
+
+    for &days in &latencies {
+        let bucket = LATENCY_BUCKETS.iter().position(|&(_, most)| days <= most).unwrap_or(LATENCY_BUCKETS.len() - 1);
+        insights.latency[bucket] += 1;
+    }
+    latencies.sort_unstable();
+    insights.median_latency = latencies.get(latencies.len() / 2).copied();
+
+    insights.tags = by_tag
+        .into_iter()
+        .filter(|(_, (todos, _, _))| *todos >= MIN_TAG_TODOS)
+        .map(|(tag, (todos, open, total))| TagDelay { tag, todos, open, average_days: total as f64 / todos as f64 })
+        .collect();
+    insights.tags.sort_by(|a, b| b.average_days.total_cmp(&a.average_days));
+    insights
+}
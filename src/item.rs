@@ -0,0 +1,630 @@
+use crate::time::parse_date;
+use chrono::NaiveDate;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::ops::Range;
+
+pub const STAR: &str = "★";
+const ADDED: &str = "added";
+const ADDED_BY: &str = "by";
+const DONE: &str = "done";
+const DONE_BY: &str = "done-by";
+const ID: &str = "id";
+const STAMPS: [&str; 5] = [ADDED, ADDED_BY, DONE, DONE_BY, ID];
+const AFTER: &str = "after";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TodoItem {
+    pub text: String,
+    pub completed: bool,
+    pub line_type: LineType,
+    // How deep a todo is nested under others: 0 for `* [ ]`, 1 for a
+    // `** [ ]` subtask, and so on
+    pub depth: usize,
+}
+
+// The tag a word of a todo is, if it's one
+pub fn tag_of(word: &str) -> Option<&str> {
+    word.strip_prefix('#').filter(|tag| !tag.is_empty())
+}
+
+// A word that says something about a todo rather than being part of its
+// wording: the star, a tag or a `name:value` attribute (not a URL's scheme)
+fn is_metadata(word: &str) -> bool {
+    let attribute = word.split_once(':').is_some_and(|(name, value)| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') && !value.is_empty() && !value.starts_with('/')
+    });
+    word == STAR || tag_of(word).is_some() || attribute
+}
+
+// A todo's text without the stamps, for one known only by its text
+pub fn without_stamps(text: &str) -> String {
+    TodoItem { text: text.to_string(), completed: false, line_type: LineType::Todo, depth: 0 }.text_without_stamps()
+}
+
+impl TodoItem {
+    // Words starting with '#', without the '#'
+    pub fn tags(&self) -> Vec<&str> {
+        self.text.split_whitespace().filter_map(tag_of).collect()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.strip_prefix('#').unwrap_or(tag);
+        self.tags().iter().any(|other| other.eq_ignore_ascii_case(tag))
+    }
+
+    // Letter of a leading `(A)`..`(Z)` marker; A is the most important.
+    // A leading `!!!`, `!!` or `!` is shorthand for A, B or C.
+    pub fn priority(&self) -> Option<char> {
+        let marker = self.text.split_whitespace().next()?.as_bytes();
+        match marker {
+            [b'(', letter, b')'] if letter.is_ascii_uppercase() => Some(*letter as char),
+            b"!!!" => Some('A'),
+            b"!!" => Some('B'),
+            b"!" => Some('C'),
+            _ => None,
+        }
+    }
+
+    // The text after the priority marker, if there is one
+    pub fn text_without_priority(&self) -> &str {
+        match self.priority() {
+            Some(_) => {
+                let text = self.text.trim_start();
+                text[text.find(char::is_whitespace).unwrap_or(text.len())..].trim_start()
+            }
+            None => &self.text,
+        }
+    }
+
+    // Replace the priority marker with `(X)`, or drop it with `None`
+    pub fn set_priority(&mut self, priority: Option<char>) {
+        let rest = self.text_without_priority().to_string();
+        self.text = match priority {
+            Some(priority) if rest.is_empty() => format!("({})", priority),
+            Some(priority) => format!("({}) {}", priority, rest),
+            None => rest,
+        };
+    }
+
+    // Value of a `name:value` attribute word
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.text
+            .split_whitespace()
+            .find_map(|word| word.strip_prefix(name)?.strip_prefix(':'))
+            .filter(|value| !value.is_empty())
+    }
+
+    // Replace an attribute, which then goes at the end, or drop it with `None`
+    fn set_attribute(&mut self, name: &str, value: Option<String>) {
+        let mut words: Vec<String> = self
+            .text
+            .split_whitespace()
+            .filter(|word| word.strip_prefix(name).is_none_or(|rest| !rest.starts_with(':')))
+            .map(str::to_string)
+            .collect();
+        if let Some(value) = value {
+            words.push(format!("{}:{}", name, value));
+        }
+        self.text = words.join(" ");
+    }
+
+    // Value of a trailing `due:` attribute
+    pub fn due(&self) -> Option<&str> {
+        self.attribute("due")
+    }
+
+    // Value of an `est:` attribute: how long the todo should take, like
+    // `est:45m` or `est:2h`
+    pub fn estimate(&self) -> Option<&str> {
+        self.attribute("est")
+    }
+
+    // Value of an `every:` attribute, for todos that come back once done
+    pub fn every(&self) -> Option<&str> {
+        self.attribute("every")
+    }
+
+    // The days the todo was added and completed, stamped on it as `added:`
+    // and `done:` attributes
+    pub fn added(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self.attribute(ADDED)?, "%Y-%m-%d").ok()
+    }
+
+    pub fn done(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self.attribute(DONE)?, "%Y-%m-%d").ok()
+    }
+
+    // Who added and completed the todo, on lists shared between people:
+    // the `by:` and `done-by:` stamps
+    pub fn added_by(&self) -> Option<&str> {
+        self.attribute(ADDED_BY)
+    }
+
+    pub fn done_by(&self) -> Option<&str> {
+        self.attribute(DONE_BY)
+    }
+
+    // The todo's id, which other todos refer to it by: its `id:` stamp
+    pub fn id(&self) -> Option<&str> {
+        self.attribute(ID)
+    }
+
+    pub fn set_id(&mut self, id: &str) {
+        self.set_attribute(ID, Some(id.to_string()));
+    }
+
+    // Ids of the todos this one has to wait for, from `after:#id` words; one
+    // word can list several, as in `after:#k3f9,#x2a1`
+    pub fn blockers(&self) -> Vec<&str> {
+        self.text
+            .split_whitespace()
+            .filter_map(|word| word.strip_prefix(AFTER)?.strip_prefix(':'))
+            .flat_map(|ids| ids.split(','))
+            .map(|id| id.trim_start_matches('#'))
+            .filter(|id| !id.is_empty())
+            .collect()
+    }
+
+    pub fn add_blocker(&mut self, id: &str) {
+        let mut ids: Vec<String> = self.blockers().iter().map(|id| format!("#{}", id)).collect();
+        if !ids.iter().any(|other| other[1..] == *id) {
+            ids.push(format!("#{}", id));
+            self.set_attribute(AFTER, Some(ids.join(",")));
+        }
+    }
+
+    pub fn clear_blockers(&mut self) {
+        self.set_attribute(AFTER, None);
+    }
+
+    // Give the todo an id, unless it has one, that none of `items` has
+    pub fn stamp_id(&mut self, items: &[TodoItem]) {
+        if self.id().is_none() {
+            self.set_id(&new_id(items));
+        }
+    }
+
+    // Stamp a new todo with the day it was added and who by, unless it
+    // already says
+    pub fn stamp_added(&mut self, today: NaiveDate, by: Option<&str>) {
+        if self.attribute(ADDED).is_none() {
+            self.set_attribute(ADDED, Some(today.format("%Y-%m-%d").to_string()));
+        }
+        if let Some(by) = by.filter(|_| self.attribute(ADDED_BY).is_none()) {
+            self.set_attribute(ADDED_BY, Some(by.to_string()));
+        }
+    }
+
+    // Complete or reopen the todo, stamping the day it was completed and
+    // who by. An already completed todo keeps its stamps.
+    pub fn set_completed(&mut self, completed: bool, today: NaiveDate, by: Option<&str>) {
+        if completed && self.completed && self.attribute(DONE).is_some() {
+            return;
+        }
+        self.completed = completed;
+        self.set_attribute(DONE, completed.then(|| today.format("%Y-%m-%d").to_string()));
+        self.set_attribute(DONE_BY, by.filter(|_| completed).map(str::to_string));
+    }
+
+    // Stamps from another version of the todo that this one lacks, so
+    // rewriting a todo's text doesn't lose when it was added or completed
+    pub fn keep_stamps(&mut self, old: &TodoItem) {
+        for name in STAMPS {
+            if self.attribute(name).is_none() {
+                if let Some(value) = old.attribute(name) {
+                    self.set_attribute(name, Some(value.to_string()));
+                }
+            }
+        }
+    }
+
+    // Everything but the wording that another version of the todo has and
+    // this one doesn't say for itself: its priority, star, tags and
+    // `name:value` attributes, stamps included. Rewording a todo from
+    // scratch then leaves it due, repeating, waiting and tagged as it was.
+    pub fn keep_metadata(&mut self, old: &TodoItem) {
+        let words: Vec<&str> = old.text_without_priority().split_whitespace().filter(|word| is_metadata(word)).collect();
+        if self.priority().is_none() {
+            self.set_priority(old.priority());
+        }
+        self.apply_defaults(&words);
+    }
+
+    // The text without the stamps, for editing
+    pub fn text_without_stamps(&self) -> String {
+        let mut item = self.clone();
+        for name in STAMPS {
+            item.set_attribute(name, None);
+        }
+        item.text
+    }
+
+    // A header's text without the defaults it gives its section, so
+    // `== Errands [#errands @out]` is the Errands section
+    pub fn section_name(&self) -> &str {
+        match self.defaults_start() {
+            Some(start) => self.text[..start].trim_end(),
+            None => &self.text,
+        }
+    }
+
+    // Words a header gives every todo added under it, in a trailing
+    // `[...]`: tags, contexts, `due:` and other attributes, or a priority
+    pub fn section_defaults(&self) -> Vec<&str> {
+        match self.defaults_start() {
+            Some(start) => self.text[start + 1..self.text.len() - 1].split_whitespace().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // How high a header is: 1 for `=`, 2 for `==` and 3 for `===`
+    pub fn header_level(&self) -> Option<usize> {
+        match self.line_type {
+            LineType::Header1 => Some(1),
+            LineType::Header2 => Some(2),
+            LineType::Header3 => Some(3),
+            _ => None,
+        }
+    }
+
+    fn defaults_start(&self) -> Option<usize> {
+        if self.header_level().is_none() || !self.text.ends_with(']') {
+            return None;
+        }
+        // An asciidoc anchor, `[[name]]`, isn't defaults
+        let start = self.text.rfind('[').filter(|&start| start > 0 && !self.text[..start].ends_with('['))?;
+        self.text[..start].ends_with(' ').then_some(start)
+    }
+
+    // Add a section's defaults the todo doesn't already have. An attribute
+    // or priority the todo gives itself wins over the section's.
+    pub fn apply_defaults(&mut self, defaults: &[&str]) {
+        for &word in defaults {
+            let default = TodoItem { text: word.to_string(), completed: false, line_type: LineType::Todo, depth: 0 };
+            if let Some(priority) = default.priority().filter(|_| default.text_without_priority().is_empty()) {
+                if self.priority().is_none() {
+                    self.set_priority(Some(priority));
+                }
+                continue;
+            }
+            let attribute = word.split_once(':').filter(|(name, value)| !name.is_empty() && !value.is_empty());
+            let present = match attribute {
+                Some((name, _)) => self.attribute(name).is_some(),
+                None => self.text.split_whitespace().any(|other| other.eq_ignore_ascii_case(word)),
+            };
+            if !present {
+                self.text = format!("{} {}", self.text, word);
+            }
+        }
+    }
+
+    // What tells todos apart. Completing a todo stamps it with `done:`,
+    // which doesn't make it a different todo.
+    pub fn key(&self) -> String {
+        let mut item = self.clone();
+        item.set_attribute(DONE, None);
+        item.set_attribute(DONE_BY, None);
+        item.text
+    }
+
+    // Marked with a `★` word, by `todo star`
+    pub fn starred(&self) -> bool {
+        self.text.split_whitespace().any(|word| word == STAR)
+    }
+
+    pub fn set_starred(&mut self, starred: bool) {
+        let mut words: Vec<&str> = self.text.split_whitespace().filter(|word| *word != STAR).collect();
+        if starred {
+            words.push(STAR);
+        }
+        self.text = words.join(" ");
+    }
+
+    // The `due:` attribute as a date, when it is one (YYYY-MM-DD)
+    pub fn due_date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self.due()?, "%Y-%m-%d").ok()
+    }
+
+    // Rewrite a `due:` attribute given in words, like `due:tomorrow` or
+    // `due:next-friday`, as the date it means. Returns the value when it
+    // can't be read, leaving the text as it was.
+    pub fn resolve_due(&mut self, today: NaiveDate) -> Result<(), String> {
+        let Some(due) = self.due().filter(|_| self.due_date().is_none()) else {
+            return Ok(());
+        };
+        match parse_date(due, today) {
+            Some(date) => {
+                self.set_due(Some(date));
+                Ok(())
+            }
+            None => Err(due.to_string()),
+        }
+    }
+
+    // Replace the `due:` attribute, or drop it with `None`
+    pub fn set_due(&mut self, due: Option<NaiveDate>) {
+        self.set_attribute("due", due.map(|due| due.format("%Y-%m-%d").to_string()));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineType {
+    Todo,
+    Header1,
+    Header2,
+    Header3,
+    Bullet,
+    Text,
+    // An indented line continuing the todo above it, as part of its note
+    Note,
+    Empty,
+}
+
+// Whether a line that is neither a list item nor a header is indented,
+// which makes it part of a note
+pub fn is_indented(line: &str) -> bool {
+    line.starts_with([' ', '\t']) && !line.trim().is_empty()
+}
+
+// The lines of the note under the todo at `todo`: the indented lines
+// right after it
+pub fn note_range(items: &[TodoItem], todo: usize) -> Range<usize> {
+    let start = (todo + 1).min(items.len());
+    let len = items[start..].iter().take_while(|item| matches!(item.line_type, LineType::Note)).count();
+    start..start + len
+}
+
+// The todo the line at `line` is part of the note of, if it's a note line
+// under one
+pub fn note_owner(items: &[TodoItem], line: usize) -> Option<usize> {
+    if !matches!(items.get(line)?.line_type, LineType::Note) {
+        return None;
+    }
+    let owner = (0..line).rev().find(|&i| !matches!(items[i].line_type, LineType::Note))?;
+    matches!(items[owner].line_type, LineType::Todo).then_some(owner)
+}
+
+// The line after the todo at `todo` and everything that goes with it: its
+// note, and its subtasks with theirs
+pub fn block_end(items: &[TodoItem], todo: usize) -> usize {
+    let depth = items[todo].depth;
+    (todo + 1..items.len())
+        .find(|&i| match items[i].line_type {
+            LineType::Note => false,
+            LineType::Todo => items[i].depth <= depth,
+            _ => true,
+        })
+        .unwrap_or(items.len())
+}
+
+// The items under the header at `header`, up to the next one as high or
+// higher: a `==` section takes in its `===` subsections
+pub fn section_range(items: &[TodoItem], header: usize) -> Range<usize> {
+    let level = items[header].header_level().unwrap_or(0);
+    let end = (header + 1..items.len()).find(|&i| items[i].header_level().is_some_and(|other| other <= level));
+    header + 1..end.unwrap_or(items.len())
+}
+
+// The defaults of the section a line put at `pos` would be in: those of the
+// nearest header above it
+pub fn defaults_at(items: &[TodoItem], pos: usize) -> Vec<&str> {
+    items[..pos.min(items.len())]
+        .iter()
+        .rev()
+        .find(|item| matches!(item.line_type, LineType::Header1 | LineType::Header2 | LineType::Header3))
+        .map_or_else(Vec::new, TodoItem::section_defaults)
+}
+
+// The todo a line belongs to: the one above a note, or the one a subtask is
+// under
+pub fn parent_of(items: &[TodoItem], line: usize) -> Option<usize> {
+    let item = items.get(line)?;
+    let depth = match item.line_type {
+        LineType::Note => return note_owner(items, line),
+        LineType::Todo if item.depth > 0 => item.depth,
+        _ => return None,
+    };
+    for i in (0..line).rev() {
+        match items[i].line_type {
+            LineType::Note => {}
+            LineType::Todo if items[i].depth >= depth => {}
+            LineType::Todo => return Some(i),
+            _ => return None,
+        }
+    }
+    None
+}
+
+// The subtasks right under the todo at `todo`
+pub fn subtasks(items: &[TodoItem], todo: usize) -> Vec<usize> {
+    (todo + 1..block_end(items, todo))
+        .filter(|&i| matches!(items[i].line_type, LineType::Todo) && parent_of(items, i) == Some(todo))
+        .collect()
+}
+
+// Complete the todos above the one at `todo` that have all their subtasks
+// done now, nearest first, returning where they are
+pub fn complete_parents(items: &mut [TodoItem], todo: usize, today: NaiveDate, by: Option<&str>) -> Vec<usize> {
+    let mut completed = Vec::new();
+    let mut child = todo;
+    while let Some(parent) = parent_of(items, child).filter(|&parent| !items[parent].completed) {
+        if !subtasks(items, parent).iter().all(|&i| items[i].completed) {
+            break;
+        }
+        items[parent].set_completed(true, today, by);
+        completed.push(parent);
+        child = parent;
+    }
+    completed
+}
+
+// The note under the todo at `todo`, one line per line, or None if it has none
+pub fn note_of(items: &[TodoItem], todo: usize) -> Option<String> {
+    let range = note_range(items, todo);
+    (!range.is_empty()).then(|| items[range].iter().map(|item| item.text.as_str()).collect::<Vec<_>>().join("\n"))
+}
+
+// Whether the todo waits for a todo in `items` that's still open. Ids that
+// aren't in the list, such as those of archived todos, don't hold it up.
+pub fn is_blocked(item: &TodoItem, items: &[TodoItem]) -> bool {
+    let blockers = item.blockers();
+    !blockers.is_empty()
+        && items.iter().any(|other| {
+            matches!(other.line_type, LineType::Todo) && !other.completed && other.id().is_some_and(|id| blockers.contains(&id))
+        })
+}
+
+// Four base-36 digits from `n`. Ids with a letter can't be taken for a
+// todo's number, so all-digit ones are None.
+fn short_id(mut n: u64) -> Option<String> {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let id: String = (0..4)
+        .map(|_| {
+            let digit = DIGITS[(n % 36) as usize] as char;
+            n /= 36;
+            digit
+        })
+        .collect();
+    id.bytes().any(|b| b.is_ascii_alphabetic()).then_some(id)
+}
+
+fn id_taken(items: &[TodoItem], id: &str) -> bool {
+    items.iter().any(|item| item.id() == Some(id))
+}
+
+// A short id that none of the todos in `items` has
+pub fn new_id(items: &[TodoItem]) -> String {
+    loop {
+        // Every RandomState hashes differently, so an empty hash is random
+        let n = RandomState::new().build_hasher().finish();
+        if let Some(id) = short_id(n).filter(|id| !id_taken(items, id)) {
+            return id;
+        }
+    }
+}
+
+// 64-bit FNV-1a, which unlike std's hasher is the same on every build
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+// An id none of `items` has that depends only on `seed` and `items`, so
+// devices working it out separately come up with the same one
+pub fn derived_id(items: &[TodoItem], seed: &str) -> String {
+    (0u64..)
+        .find_map(|attempt| {
+            let hash = fnv1a(seed.bytes().chain(attempt.to_le_bytes()));
+            short_id(hash).filter(|id| !id_taken(items, id))
+        })
+        .expect("ran out of ids")
+}
+
+pub fn parse_line(line: &str) -> TodoItem {
+    let trimmed = line.trim();
+    let stars = trimmed.bytes().take_while(|&b| b == b'*').count();
+    let checkbox = |mark: &str| trimmed[stars..].strip_prefix(' ')?.strip_prefix(mark).filter(|_| stars > 0);
+
+    if let Some(text) = checkbox("[ ]") {
+        TodoItem {
+            text: text.trim().to_string(),
+            completed: false,
+            line_type: LineType::Todo,
+            depth: stars - 1,
+        }
+    } else if let Some(text) = checkbox("[x]").or_else(|| checkbox("[X]")) {
+        TodoItem {
+            text: text.trim().to_string(),
+            completed: true,
+            line_type: LineType::Todo,
+            depth: stars - 1,
+        }
+    } else if let Some(text) = trimmed.strip_prefix("= ") {
+        TodoItem {
+            text: text.to_string(),
+            completed: false,
+            line_type: LineType::Header1,
+            depth: 0,
+        }
+    } else if let Some(text) = trimmed.strip_prefix("== ") {
+        TodoItem {
+            text: text.to_string(),
+            completed: false,
+            line_type: LineType::Header2,
+            depth: 0,
+        }
+    } else if let Some(text) = trimmed.strip_prefix("=== ") {
+        TodoItem {
+            text: text.to_string(),
+            completed: false,
+            line_type: LineType::Header3,
+            depth: 0,
+        }
+    } else if let Some(text) = trimmed.strip_prefix("* ").filter(|_| !trimmed.starts_with("* [")) {
+        TodoItem {
+            text: text.to_string(),
+            completed: false,
+            line_type: LineType::Bullet,
+            depth: 0,
+        }
+    } else if trimmed.is_empty() {
+        TodoItem {
+            text: String::new(),
+            completed: false,
+            line_type: LineType::Empty,
+            depth: 0,
+        }
+    } else {
+        TodoItem {
+            text: trimmed.to_string(),
+            completed: false,
+            line_type: if is_indented(line) { LineType::Note } else { LineType::Text },
+            depth: 0,
+        }
+    }
+}
+
+pub fn format_line(item: &TodoItem) -> String {
+    match item.line_type {
+        LineType::Todo => {
+            let stars = "*".repeat(item.depth + 1);
+            if item.completed {
+                format!("{} [x] {}", stars, item.text)
+            } else {
+                format!("{} [ ] {}", stars, item.text)
+            }
+        }
+        LineType::Header1 => format!("= {}", item.text),
+        LineType::Header2 => format!("== {}", item.text),
+        LineType::Header3 => format!("=== {}", item.text),
+        LineType::Bullet => format!("* {}", item.text),
+        LineType::Text => item.text.clone(),
+        LineType::Note => format!("  {}", item.text),
+        LineType::Empty => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo(text: &str) -> TodoItem {
+        TodoItem { text: text.to_string(), completed: false, line_type: LineType::Todo, depth: 0 }
+    }
+
+    #[test]
+    fn derived_ids_stay_the_same_from_build_to_build() {
+        assert_eq!(fnv1a(*b"a"), 0xaf63_dc4c_8601_ec8c);
+        let taken = todo(&format!("x id:{}", derived_id(&[], "seed")));
+        assert_eq!(derived_id(&[], "seed"), "gjjr");
+        assert_ne!(derived_id(&[taken], "seed"), derived_id(&[], "seed"));
+    }
+
+    #[test]
+    fn rewording_keeps_what_the_new_text_leaves_out() {
+        let old = todo("(A) Fix tpyo #work due:2026-10-20 every:1w after:#k3f9 see https://x.org added:2026-10-14 id:9j30 ★");
+        let mut item = todo("Fix typo due:2026-11-01");
+        item.keep_metadata(&old);
+        assert_eq!(item.text, "(A) Fix typo due:2026-11-01 #work every:1w after:#k3f9 added:2026-10-14 id:9j30 ★");
+    }
+}
@@ -0,0 +1,289 @@
+use crate::config::get_config_path;
+use crate::event::TodoEvent;
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Journal of recent mutations, one JSON entry per line next to the config
+// file. Each entry keeps the affected files' contents from before and after
+// the change, which is all `todo undo` needs to revert it.
+const JOURNAL_FILE: &str = "journal.jsonl";
+const JOURNAL_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Add,
+    Toggle,
+    Move,
+    Archive,
+    Rename,
+    DeleteList,
+    Edit,
+    EditItem,
+    Star,
+    Unstar,
+    Prioritize,
+    Due,
+    Sort,
+    Merge,
+}
+
+impl Action {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Add => "add",
+            Action::Toggle => "toggle",
+            Action::Move => "move",
+            Action::Archive => "archive",
+            Action::Rename => "rename",
+            Action::DeleteList => "delete-list",
+            Action::Edit => "edit",
+            Action::EditItem => "edit-item",
+            Action::Star => "star",
+            Action::Unstar => "unstar",
+            Action::Prioritize => "prioritize",
+            Action::Due => "due",
+            Action::Sort => "sort",
+            Action::Merge => "merge",
+        }
+    }
+}
+
+// Contents of a file before and after a change; `None` means the file
+// didn't exist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub time: NaiveDateTime,
+    pub action: Action,
+    pub list: String,
+    // Texts of the todos the change was about
+    #[serde(default)]
+    pub items: Vec<String>,
+    // New completion state, for toggles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed: Option<bool>,
+    // The priority or due date a todo was given, for those; empty when it
+    // was taken away
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set: Option<String>,
+    #[serde(default)]
+    pub undone: bool,
+    pub changes: Vec<FileChange>,
+}
+
+impl JournalEntry {
+    pub fn describe(&self) -> String {
+        match (self.action, self.items.as_slice()) {
+            (Action::Rename, [new_name]) => format!("rename {} to {}", self.list, new_name),
+            (Action::Toggle, [text]) => {
+                let state = if self.completed == Some(true) { "completed" } else { "reopened" };
+                format!("{} in {}: {}", state, self.list, text)
+            }
+            (Action::EditItem, [old, new]) => format!("reworded in {}: {} -> {}", self.list, old, new),
+            (Action::Star, [text]) => format!("starred in {}: {}", self.list, text),
+            (Action::Unstar, [text]) => format!("unstarred in {}: {}", self.list, text),
+            (Action::Prioritize | Action::Due, [text]) => {
+                let what = if self.action == Action::Due { "due date" } else { "priority" };
+                match self.set.as_deref() {
+                    Some(set) if !set.is_empty() => format!("{} {} in {}: {}", what, set, self.list, text),
+                    _ => format!("{} removed in {}: {}", what, self.list, text),
+                }
+            }
+            (_, []) => format!("{} {}", self.action.label(), self.list),
+            (_, [text]) => format!("{} in {}: {}", self.action.label(), self.list, text),
+            (_, items) => format!("{} in {}: {} todos", self.action.label(), self.list, items.len()),
+        }
+    }
+}
+
+pub fn journal_path() -> PathBuf {
+    get_config_path().with_file_name(JOURNAL_FILE)
+}
+
+// Contents of a file, or None if it doesn't exist. Files that aren't valid
+// UTF-8 are an error, as the journal can't hold them.
+fn read_file(path: &Path) -> io::Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    fs::read_to_string(path).map(Some)
+}
+
+// Captures the state of some files before a mutation so it can be journaled
+// once the mutation is done
+pub struct Recorder {
+    before: Vec<(PathBuf, io::Result<Option<String>>)>,
+}
+
+impl Recorder {
+    pub fn new(paths: &[&Path]) -> Self {
+        Recorder {
+            before: paths.iter().map(|path| (path.to_path_buf(), read_file(path))).collect(),
+        }
+    }
+
+    // Journal the files' changes as the given event, returning them; events
+    // that aren't a mutation of their own (e.g. a list becoming complete)
+    // are skipped
+    pub fn finish(self, event: &TodoEvent) -> io::Result<Vec<FileChange>> {
+        let Some((action, items, completed, set)) = journal_fields(event) else {
+            return Ok(Vec::new());
+        };
+
+        let mut changes = Vec::new();
+        for (path, before) in self.before {
+            // Changes to files the journal can't represent just aren't undoable
+            let (Ok(before), Ok(after)) = (before, read_file(&path)) else {
+                return Ok(Vec::new());
+            };
+            if before != after {
+                changes.push(FileChange { path, before, after });
+            }
+        }
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+
+        let entry = JournalEntry {
+            time: Local::now().naive_local(),
+            action,
+            list: event.list().to_string(),
+            items,
+            completed,
+            set,
+            undone: false,
+            changes,
+        };
+        append(&entry)?;
+        Ok(entry.changes)
+    }
+}
+
+// How an event is recorded: the action, the todo texts it concerns, for
+// toggles the new completion state, and for priorities and due dates what
+// was set
+type Fields = (Action, Vec<String>, Option<bool>, Option<String>);
+
+fn journal_fields(event: &TodoEvent) -> Option<Fields> {
+    Some(match event {
+        TodoEvent::ItemAdded { text, .. } => (Action::Add, vec![text.clone()], None, None),
+        TodoEvent::ItemsAdded { items, .. } => (Action::Add, items.clone(), None, None),
+        TodoEvent::ItemCompleted { text, .. } => (Action::Toggle, vec![text.clone()], Some(true), None),
+        TodoEvent::ItemReopened { text, .. } => (Action::Toggle, vec![text.clone()], Some(false), None),
+        TodoEvent::ItemEdited { old, new, .. } => (Action::EditItem, vec![old.clone(), new.clone()], None, None),
+        TodoEvent::ItemStarred { text, starred: true, .. } => (Action::Star, vec![text.clone()], None, None),
+        TodoEvent::ItemStarred { text, starred: false, .. } => (Action::Unstar, vec![text.clone()], None, None),
+        TodoEvent::ItemPrioritized { text, priority, .. } => {
+            (Action::Prioritize, vec![text.clone()], None, Some(priority.map(String::from).unwrap_or_default()))
+        }
+        TodoEvent::ItemDueSet { text, due, .. } => {
+            (Action::Due, vec![text.clone()], None, Some(due.map(|due| due.to_string()).unwrap_or_default()))
+        }
+        TodoEvent::ItemMoved { text, .. } => (Action::Move, vec![text.clone()], None, None),
+        TodoEvent::ItemsArchived { items, .. } => (Action::Archive, items.clone(), None, None),
+        TodoEvent::ListsMerged { items, .. } => (Action::Merge, items.clone(), None, None),
+        TodoEvent::ListRenamed { new, .. } => (Action::Rename, vec![new.clone()], None, None),
+        TodoEvent::ListDeleted { .. } => (Action::DeleteList, Vec::new(), None, None),
+        TodoEvent::ListEdited { .. } => (Action::Edit, Vec::new(), None, None),
+        TodoEvent::ListSorted { .. } => (Action::Sort, Vec::new(), None, None),
+        TodoEvent::ListCompleted { .. } | TodoEvent::ListSwitched { .. } => return None,
+    })
+}
+
+pub fn read_journal() -> io::Result<Vec<JournalEntry>> {
+    let path = journal_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    // Skip lines that don't parse rather than losing the whole history
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn write_journal(entries: &[JournalEntry]) -> io::Result<()> {
+    let mut content = String::new();
+    for entry in entries {
+        content.push_str(&serde_json::to_string(entry).map_err(io::Error::other)?);
+        content.push('\n');
+    }
+    fs::write(journal_path(), content)
+}
+
+fn append(entry: &JournalEntry) -> io::Result<()> {
+    let mut entries = read_journal()?;
+    if entries.len() >= JOURNAL_LIMIT {
+        // Rewrite the journal to drop the oldest entries
+        entries.push(entry.clone());
+        let excess = entries.len() - JOURNAL_LIMIT;
+        return write_journal(&entries[excess..]);
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(journal_path())?;
+    writeln!(file, "{}", serde_json::to_string(entry).map_err(io::Error::other)?)
+}
+
+// Revert the most recent change that hasn't been undone yet. Refuses if any
+// affected file has been modified since, so undo never clobbers later edits.
+pub fn undo_last() -> io::Result<Option<JournalEntry>> {
+    let mut entries = read_journal()?;
+    let Some(pos) = entries.iter().rposition(|entry| !entry.undone) else {
+        return Ok(None);
+    };
+
+    for change in &entries[pos].changes {
+        if read_file(&change.path)? != change.after {
+            return Err(io::Error::other(format!(
+                "{} has changed since; restore it from a backup instead",
+                change.path.display()
+            )));
+        }
+    }
+
+    for change in &entries[pos].changes {
+        match &change.before {
+            Some(content) => fs::write(&change.path, content)?,
+            None => fs::remove_file(&change.path)?,
+        }
+    }
+
+    entries[pos].undone = true;
+    write_journal(&entries)?;
+    Ok(Some(entries[pos].clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn described(event: TodoEvent) -> String {
+        let (action, items, completed, set) = journal_fields(&event).unwrap();
+        let time = Local::now().naive_local();
+        let list = event.list().to_string();
+        JournalEntry { time, action, list, items, completed, set, undone: false, changes: Vec::new() }.describe()
+    }
+
+    #[test]
+    fn stars_priorities_and_due_dates_are_their_own_kinds() {
+        let (list, text) = ("work.adoc".to_string(), "call bob".to_string());
+        let starred = TodoEvent::ItemStarred { list: list.clone(), text: text.clone(), starred: false };
+        assert_eq!(described(starred), "unstarred in work.adoc: call bob");
+        let prioritized = TodoEvent::ItemPrioritized { list: list.clone(), text: text.clone(), priority: Some('A') };
+        assert_eq!(described(prioritized), "priority A in work.adoc: call bob");
+        let due = NaiveDate::from_ymd_opt(2026, 10, 20);
+        assert_eq!(described(TodoEvent::ItemDueSet { list: list.clone(), text: text.clone(), due }), "due date 2026-10-20 in work.adoc: call bob");
+        assert_eq!(described(TodoEvent::ItemDueSet { list, text, due: None }), "due date removed in work.adoc: call bob");
+    }
+}
@@ -0,0 +1,32 @@
+//! Core of the todo list manager: configuration, list parsing and
+//! serialization, backups and a thread-safe storage handle shared by the
+//! CLI and TUI.
+
+pub mod backup;
+pub mod badge;
+pub mod bundle;
+pub mod config;
+pub mod emoji;
+pub mod event;
+pub mod file;
+pub mod format;
+pub mod git;
+pub mod insights;
+pub mod item;
+pub mod journal;
+pub mod link;
+pub mod nudge;
+pub mod plan;
+pub mod plugin;
+pub mod query;
+pub mod recur;
+pub mod retro;
+pub mod rules;
+pub mod session;
+pub mod share;
+pub mod spell;
+pub mod state;
+pub mod store;
+pub mod sync;
+pub mod time;
+pub mod todotxt;
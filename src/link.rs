@@ -0,0 +1,149 @@
+use crate::config::Hyperlinks;
+use regex::Regex;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// Clickable links in terminal output, using the OSC 8 escape sequence.
+// Terminals that don't understand it are meant to ignore it, but enough of
+// them print it as garbage that links are only written when the terminal is
+// known to support them.
+
+// Something in a todo that can be opened: a URL, or a path to a file or
+// directory that exists
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    // Byte range of the link in the text
+    pub start: usize,
+    pub end: usize,
+    pub target: String,
+}
+
+fn url_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r#"\b(?:https?|ftp|file)://[^\s<>"']+"#).unwrap())
+}
+
+fn path_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r#"(?:^|\s)((?:~|\.{1,2})?/[^\s<>"']+)"#).unwrap())
+}
+
+// Sentence punctuation after a link isn't part of it, nor is a closing
+// bracket that wasn't opened inside it
+fn trim_trailing(text: &str) -> &str {
+    let mut text = text;
+    loop {
+        let trimmed = text.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']);
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(rest) if trimmed.matches('(').count() < trimmed.matches(')').count() => rest,
+            _ => trimmed,
+        };
+        if trimmed == text {
+            return text;
+        }
+        text = trimmed;
+    }
+}
+
+fn resolve_path(path: &str) -> Option<PathBuf> {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()?.join(rest),
+        None => PathBuf::from(path),
+    };
+    let path = if path.is_absolute() { path } else { env::current_dir().ok()?.join(path) };
+    path.canonicalize().ok()
+}
+
+fn file_url(path: &Path) -> String {
+    let mut url = String::from("file://");
+    for c in path.to_string_lossy().chars() {
+        match c {
+            ' ' => url.push_str("%20"),
+            '%' => url.push_str("%25"),
+            '#' => url.push_str("%23"),
+            '?' => url.push_str("%3F"),
+            c => url.push(c),
+        }
+    }
+    url
+}
+
+pub fn find_links(text: &str) -> Vec<Link> {
+    let mut links: Vec<Link> = url_pattern()
+        .find_iter(text)
+        .map(|m| {
+            let url = trim_trailing(m.as_str());
+            Link { start: m.start(), end: m.start() + url.len(), target: url.to_string() }
+        })
+        .collect();
+
+    for m in path_pattern().captures_iter(text).filter_map(|c| c.get(1)) {
+        if links.iter().any(|link| m.start() < link.end && link.start < m.end()) {
+            continue;
+        }
+        let path = trim_trailing(m.as_str());
+        // `src/main.rs:42`-style locations link to the file
+        let file = path.rsplit_once(':').filter(|(_, line)| line.parse::<u32>().is_ok()).map_or(path, |(file, _)| file);
+        if let Some(resolved) = resolve_path(file) {
+            links.push(Link { start: m.start(), end: m.start() + path.len(), target: file_url(&resolved) });
+        }
+    }
+    links.sort_by_key(|link| link.start);
+    links
+}
+
+pub fn hyperlink(target: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", target, text)
+}
+
+// The text with its links made clickable
+pub fn linkify(text: &str) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+    for link in find_links(text) {
+        out.push_str(&text[last..link.start]);
+        out.push_str(&hyperlink(&link.target, &text[link.start..link.end]));
+        last = link.end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+// Whether the terminal is known to render OSC 8 links. FORCE_HYPERLINK=1 or
+// 0 overrides the guess, as for other tools that print links.
+pub fn terminal_supports_hyperlinks() -> bool {
+    if let Ok(force) = env::var("FORCE_HYPERLINK") {
+        return force != "0";
+    }
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    if var("TERM") == "dumb" {
+        return false;
+    }
+    if env::var_os("DOMTERM").is_some() || env::var_os("WT_SESSION").is_some() || env::var_os("KONSOLE_VERSION").is_some() {
+        return true;
+    }
+    if var("VTE_VERSION").parse::<u32>().is_ok_and(|version| version >= 5000) {
+        return true;
+    }
+    if matches!(
+        var("TERM_PROGRAM").as_str(),
+        "iTerm.app" | "WezTerm" | "vscode" | "Hyper" | "ghostty" | "terminology"
+    ) {
+        return true;
+    }
+    let term = var("TERM");
+    term == "xterm-kitty" || term == "alacritty" || term.starts_with("foot")
+}
+
+impl Hyperlinks {
+    // Whether to write links to stdout
+    pub fn enabled(self) -> bool {
+        match self {
+            Hyperlinks::Always => true,
+            Hyperlinks::Never => false,
+            Hyperlinks::Auto => io::stdout().is_terminal() && terminal_supports_hyperlinks(),
+        }
+    }
+}
@@ -1,26 +1,81 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::env::{self, EnvCompleter};
+use clap_complete::CompleteEnv;
 use colored::Colorize;
 use crossterm::{
     cursor,
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, Clear, ClearType},
 };
 use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    backend::{CrosstermBackend, TestBackend},
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Position, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{self, Block, Borders, List, ListItem, Paragraph, Wrap},
     Frame, Terminal,
 };
-use serde::{Deserialize, Serialize};
-use std::fs::{self, OpenOptions};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use unicode_width::UnicodeWidthChar;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
 use std::io::{self, BufRead, BufReader, IsTerminal, Write};
-use std::path::PathBuf;
-use std::process::Command;
+use std::net::{TcpListener, TcpStream};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use todo::backup::{backup_dir, list_backups, list_snapshots, snapshot_directory, snapshot_list, Snapshot};
+use todo::badge::progress_badge;
+use todo::bundle::{collect_files, read_bundle, restore_path, write_bundle, Compression};
+use todo::config::{
+    check_list_name, ensure_active_list_exists, ensure_config_exists, ensure_todo_directory_exists, expand_tilde,
+    find_project_list, get_active_list_path, get_config_path, get_config_value, get_list_path, list_root, normalize_list_name, resolve_list,
+    save_config, set_config_value, target_list_name, workspace_roots, BadgeConfig, Config, Density, EmojiConfig, ListStyle, RemindConfig, SpellConfig,
+    PROJECT_LIST,
+};
+use todo::file::{
+    diagnose_list, find_header, list_files, note_end, read_list_lossy, read_todos, section_end, todo_end,
+    find_todo, todo_line_index, todo_number, FileFormat, TodoRef, TRASH_DIR,
+};
+use todo::emoji::{display_width, expand_shortcodes, pad};
+use todo::event::{toggle_events, EventBus, TodoEvent};
+use todo::journal::{read_journal, undo_last, FileChange, Recorder};
+use todo::link::{find_links, hyperlink, linkify};
+use todo::nudge::nudge;
+use todo::query::{parse_condition, Condition};
+use todo::plan::{self, format_minutes, suggest_moves, Day, Move};
+use todo::plugin::{self, Plugins};
+use todo::recur::{next_copy, next_occurrence};
+use todo::insights::{insights, LATENCY_BUCKETS};
+use todo::retro::{list_retro, render_markdown, ListRetro};
+use todo::rules::apply_rules;
+use todo::git::{self, PendingCommit};
+use todo::format::{ListFormat, Markdown};
+use todo::item::{
+    block_end, complete_parents, defaults_at, format_line, is_blocked, new_id, note_of, note_owner, parent_of, parse_line,
+    section_range, subtasks, tag_of, without_stamps, LineType, TodoItem, STAR,
+};
+use todo::session::{RecordedKey, Session};
+use todo::share::{self, encode, token_matches};
+use todo::spell::{self, Misspelling};
+use todo::state::{device_id, load_state, save_state, SyncFailure};
+use todo::store::{begin_batch, open_storage, MemoryStorage, Storage};
+use todo::sync;
+use todo::time::{parse_backup_time, parse_date, parse_time, shift_date};
+use todo::todotxt;
 
 #[derive(Parser)]
 #[command(name = "todo")]
@@ -29,6 +84,12 @@ use std::time::Duration;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Print JSON instead of formatted text (lists, list, search and count)
+    #[arg(long, global = true)]
+    json: bool,
+    /// Never create a missing config, todo directory or list; exit with 3, 4 or 5 instead
+    #[arg(long, global = true)]
+    strict: bool,
 }
 
 #[derive(Subcommand)]
@@ -38,224 +99,828 @@ enum Commands {
     /// List todos from the active list or a specified list
     List {
         /// Optional list to display (defaults to active list)
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+        /// Only show incomplete todos
+        #[arg(long, conflicts_with = "done")]
+        pending: bool,
+        /// Only show completed todos
+        #[arg(long)]
+        done: bool,
+        /// Only show the section under this header (and its subsections)
+        #[arg(long)]
+        section: Option<String>,
+        /// Only show todos containing this text (ignoring case)
+        #[arg(long)]
+        contains: Option<String>,
+        /// Only show todos with this tag, e.g. `work` or `#work`
+        #[arg(long, add = ArgValueCandidates::new(tag_names))]
+        tag: Option<String>,
+        /// Only show todos added or completed by this person, on shared lists
+        #[arg(long)]
+        by: Option<String>,
+        /// Only show open todos that aren't waiting for another todo
+        #[arg(long, conflicts_with = "done")]
+        actionable: bool,
+        /// Show the todos of each section in this order, without changing the file
+        #[arg(long, value_enum)]
+        sort: Option<SortKey>,
+        /// Only show todos a plugin's filter of this name keeps
+        #[arg(long)]
+        filter: Option<String>,
+    },
+    /// Notify about open todos that are overdue or due soon, once a day each
+    Remind {
+        /// Keep checking every remind.interval_mins until stopped
+        #[arg(long, conflicts_with = "once")]
+        daemon: bool,
+        /// Check once and exit, e.g. from cron (the default)
+        #[arg(long)]
+        once: bool,
+    },
+    /// Show a list and redraw it whenever its file changes, until Ctrl-C
+    Watch {
+        /// Optional list to watch (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
         list: Option<String>,
     },
     /// Show interactive TUI to manage todos
     Show {
         /// Optional list to display (defaults to active list)
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+        /// Record the session's key presses, along with the list's contents,
+        /// to this file
+        #[arg(long, conflicts_with = "replay")]
+        record: Option<PathBuf>,
+        /// Replay a recorded session off-screen and print the final screen
+        #[arg(long)]
+        replay: Option<PathBuf>,
+    },
+    /// Show every list side by side; Enter opens one in the TUI
+    Dashboard,
+    /// Share one list over HTTP, for viewing (and with the token, checking off) in a browser
+    Serve {
+        /// Listen on every network interface, for anyone who can reach this machine;
+        /// without it, only this machine can open the page
+        #[arg(long)]
+        public: bool,
+        /// List to share (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
         list: Option<String>,
+        /// Let those who open the page with ?token=<token> check todos off and open them again
+        #[arg(long)]
+        token: Option<String>,
+        /// Address to listen on (defaults to 127.0.0.1:8080, or 0.0.0.0:8080 with --public)
+        #[arg(long)]
+        bind: Option<String>,
+    },
+    /// Weigh the todos due today against the time there is (see `est:` and [plan] in the config)
+    Plan {
+        /// Plan the next seven days instead of just today, suggesting todos to move off overloaded days
+        #[arg(long)]
+        week: bool,
+        /// Open the week in the TUI, to move todos between days
+        #[arg(short, long)]
+        interactive: bool,
     },
     /// Switch to a different todo list
-    Use { list_name: String },
+    Use {
+        #[arg(add = ArgValueCandidates::new(list_names))]
+        list_name: String,
+    },
     /// Add a todo to the active list or specified list
     Add {
-        /// The todo text to add
-        todo: String,
-        /// Optional list to add the todo to (defaults to active list)
+        /// The todo text to add; each argument becomes a separate todo
+        #[arg(required_unless_present = "message")]
+        todo: Vec<String>,
+        /// Another todo to add (repeatable)
         #[arg(short, long)]
+        message: Vec<String>,
+        /// Add before the first todo instead of at the end
+        #[arg(long, group = "position")]
+        top: bool,
+        /// Add right after the todo with this number or id
+        #[arg(long, group = "position")]
+        after: Option<TodoRef>,
+        /// Add as a subtask of the todo with this number or id
+        #[arg(long, group = "position")]
+        under: Option<TodoRef>,
+        /// Add at the end of the section under this header, creating it if needed
+        #[arg(long, group = "position")]
+        section: Option<String>,
+        /// Due date for the todos: YYYY-MM-DD, or words like "tomorrow" or "in 3 days"
+        #[arg(long)]
+        due: Option<String>,
+        /// Optional list to add the todo to (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
         list: Option<String>,
     },
     /// Open the active list in the configured editor
     Edit,
+    /// Rename a todo list
+    Rename {
+        /// Current name of the list
+        #[arg(add = ArgValueCandidates::new(list_names))]
+        old: String,
+        /// New name for the list
+        new: String,
+    },
+    /// Delete a todo list
+    DeleteList {
+        /// Name of the list to delete
+        #[arg(add = ArgValueCandidates::new(list_names))]
+        name: String,
+        /// Delete without asking for confirmation
+        #[arg(short, long)]
+        force: bool,
+        /// Move the list to the trash directory instead of deleting it
+        #[arg(short, long)]
+        trash: bool,
+    },
+    /// Show what changed in a list since a point in time
+    Diff {
+        /// List to compare (defaults to active list)
+        #[arg(add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+        /// Starting point, e.g. "yesterday", "3d", "12h" or "2024-05-01"
+        #[arg(long, default_value = "yesterday")]
+        since: String,
+    },
+    /// Show the first few open todos
+    Next {
+        /// How many todos to show
+        #[arg(default_value_t = 3)]
+        count: usize,
+        /// List to show (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Replace the text of a todo, keeping its state, position, priority, star, tags, attributes and stamps
+    EditItem {
+        /// Number (as shown by `todo list`) or id of the todo to edit
+        index: TodoRef,
+        /// New text for the todo
+        text: String,
+        /// List containing the todo (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Edit the note under a todo in the configured editor
+    Note {
+        /// Number (as shown by `todo list`) or id of the todo
+        index: TodoRef,
+        /// List containing the todo (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Make a todo wait for others to be done first
+    Depend {
+        /// Number (as shown by `todo list`) or id of the todo that has to wait
+        index: TodoRef,
+        /// Numbers or ids of the todos it waits for
+        #[arg(long, num_args = 1.., required_unless_present = "clear")]
+        on: Vec<TodoRef>,
+        /// Forget what the todo waits for
+        #[arg(long, conflicts_with = "on")]
+        clear: bool,
+        /// List containing the todos (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Give every todo of a list that has no id one, so commands can refer to it by id
+    Ids {
+        /// List to give ids to (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Reorder the todos within each section of a list
+    Sort {
+        /// Order to sort todos in
+        #[arg(short, long, value_enum, default_value_t = SortKey::Status)]
+        by: SortKey,
+        /// Put completed todos after open ones
+        #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+        completed_last: bool,
+        /// List to sort (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Star a todo, marking it for today's focus
+    Star {
+        /// Number (as shown by `todo list`) or id of the todo
+        index: TodoRef,
+        /// Remove the star instead
+        #[arg(long)]
+        remove: bool,
+        /// List containing the todo (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Set or clear a todo's priority
+    Prioritize {
+        /// Number (as shown by `todo list`) or id of the todo
+        index: TodoRef,
+        /// A letter from A (most important) to Z, high/medium/low for A/B/C, or none
+        level: String,
+        /// List containing the todo (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Set or clear a todo's due date
+    Due {
+        /// Number (as shown by `todo list`) or id of the todo
+        index: TodoRef,
+        /// Due date: YYYY-MM-DD, or words like "tomorrow", "next friday" or "in 3 days"
+        #[arg(required_unless_present = "clear")]
+        date: Vec<String>,
+        /// Remove the due date instead
+        #[arg(long, conflicts_with = "date")]
+        clear: bool,
+        /// List containing the todo (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Change the due date of every open todo matching conditions, e.g. --where "due<today" --to today
+    Reschedule {
+        /// Condition on due, added, tag or priority, like "due<today", "tag=work" or "due=none"; repeat to require several
+        #[arg(long = "where", value_name = "CONDITION", required = true)]
+        conditions: Vec<String>,
+        /// New due date: YYYY-MM-DD or words like "tomorrow", or +1d/-1w/+1m to move it from the current one
+        #[arg(long, allow_hyphen_values = true)]
+        to: String,
+        /// Show what would change without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// List containing the todos (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Show the open starred todos of every list
+    Starred,
+    /// Move the open todos of one list into another
+    Merge {
+        /// List to take the todos from
+        #[arg(add = ArgValueCandidates::new(list_names))]
+        source: String,
+        /// List to add them to
+        #[arg(add = ArgValueCandidates::new(list_names))]
+        target: String,
+        /// Put the todos under a `== <source>` header in the target
+        #[arg(short, long)]
+        section: bool,
+        /// Move the source list to the trash afterwards
+        #[arg(long)]
+        delete_source: bool,
+    },
+    /// Move a todo to another list
+    Move {
+        /// Number (as shown by `todo list`) or id of the todo to move
+        index: TodoRef,
+        /// List to move the todo to
+        #[arg(long, add = ArgValueCandidates::new(list_names))]
+        to: String,
+        /// List to move the todo from (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Print a single field of a todo without decoration, for scripts
+    Get {
+        /// Number (as shown by `todo list`) or id of the todo
+        index: TodoRef,
+        /// Field to print
+        #[arg(short, long, value_enum, default_value_t = GetField::Text)]
+        field: GetField,
+        /// List containing the todo (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Search every list for matching lines
+    Search {
+        /// Text to search for (case-insensitive)
+        #[arg(required_unless_present = "tag")]
+        query: Option<String>,
+        /// Treat the query as a regular expression
+        #[arg(short, long)]
+        regex: bool,
+        /// Only match todos with this tag
+        #[arg(long, add = ArgValueCandidates::new(tag_names))]
+        tag: Option<String>,
+    },
+    /// List the tags used in todos, with how many todos have each
+    Tags {
+        /// Only count the todos of this list
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Move completed todos into <list>-archive
+    Archive {
+        /// List to archive (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Toggle the completion state of a todo, printing the new state as JSON
+    Toggle {
+        /// Number (as shown by `todo list`) or id of the todo to toggle
+        index: TodoRef,
+        /// Mark the todo done instead of flipping it (no-op if it already is)
+        #[arg(long, conflicts_with = "open")]
+        done: bool,
+        /// Mark the todo open instead of flipping it (no-op if it already is)
+        #[arg(long)]
+        open: bool,
+        /// List containing the todo (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Show completion statistics for every list
+    Stats {
+        /// Only show statistics for this list
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Show what was completed, by list and day, and what's still open
+    Report {
+        /// Starting point, e.g. "7d", "2w", "yesterday" or "2024-05-01"
+        #[arg(long, default_value = "7d")]
+        since: String,
+        /// Only this list
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Summarize what got done recently as Markdown, for a retro or journal
+    Retro {
+        /// Cover the last seven days (the default)
+        #[arg(long, conflicts_with = "days")]
+        week: bool,
+        /// Cover this many days, ending today
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        days: Option<u32>,
+        /// Only this list
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Show when you add todos, how long they take to get done and which
+    /// tags get put off, worked out on this machine and never sent anywhere
+    Insights,
+    /// Print the number of open todos, for prompts and status lines
+    Count {
+        /// Count completed todos instead
+        #[arg(short, long)]
+        complete: bool,
+        /// Count across every list
+        #[arg(short, long, conflicts_with = "list")]
+        all_lists: bool,
+        /// Output template using {incomplete}, {complete}, {total}, {list}
+        /// and {emoji}, e.g. "{emoji} {incomplete}/{total}"
+        #[arg(short, long, conflicts_with = "complete")]
+        format: Option<String>,
+        /// List to count (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Drive the TUI through a scripted session and save it as an
+    /// asciinema recording (convert it to a GIF with e.g. agg)
+    Demo {
+        /// Where to write the .cast file
+        #[arg(long)]
+        cast: PathBuf,
+        /// Use a session recorded with `todo show --record` instead of the
+        /// built-in tour
+        #[arg(long)]
+        session: Option<PathBuf>,
+    },
+    /// Walk through the basics on a sandbox list
+    Tutorial,
+    /// Commit the todo directory, pull --rebase and push, if it is a git
+    /// repository
+    Sync,
+    /// Draft a commit message from the todos completed for the current git
+    /// repository: those in its project list, or tagged with its name
+    CommitMsg {
+        /// Starting point: a git revision such as HEAD-push (the last pushed
+        /// commit), origin/main or HEAD~3, or a time such as 2d
+        #[arg(long, default_value = "HEAD-push")]
+        since: String,
+    },
+    /// Commit batched changes once the debounce window has passed (started
+    /// automatically when git.auto_commit is on)
+    #[command(hide = true)]
+    AutoCommit,
+    /// Revert the last change made with todo
+    Undo {
+        /// Show recent changes instead of undoing
+        #[arg(long = "list")]
+        history: bool,
+    },
+    /// Export todos as JSON, CSV or Markdown
+    Export {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        /// List to export (defaults to active list)
+        #[arg(short, long, conflicts_with = "all", add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+        /// Export every list
+        #[arg(short, long)]
+        all: bool,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Import todos from a todo.txt or Markdown checkbox file
+    Import {
+        /// File to import
+        file: PathBuf,
+        /// Format of the file (detected from its name and content if omitted)
+        #[arg(short, long, value_enum)]
+        format: Option<ImportFormat>,
+        /// List to append the todos to (defaults to active list)
+        #[arg(short, long, add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Run many commands in one go, locking and saving each list only once
+    Batch {
+        /// File of commands, one per line without the `todo`, or a JSON array of them ("-" reads stdin)
+        input: PathBuf,
+    },
+    /// Snapshot the whole todo directory, to go back to with `todo restore`
+    Backup {
+        #[command(subcommand)]
+        command: Option<BackupCommand>,
+    },
+    /// List the snapshots taken with `todo backup`, or restore one
+    Restore {
+        /// Snapshot to restore: its number in the listing, or a time (e.g.
+        /// "2024-05-01 14:30") to take the latest one at or before it
+        snapshot: Option<String>,
+    },
+    /// List or restore automatic backups of a list
+    Backups {
+        #[command(subcommand)]
+        command: BackupsCommand,
+    },
+    /// Write a progress badge (e.g. "todos: 7/12") as an SVG file
+    Badge {
+        /// List to report on (defaults to active list)
+        #[arg(add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+        /// Where to write the badge
+        #[arg(long)]
+        svg: PathBuf,
+        /// Text on the left of the badge
+        #[arg(long, default_value = "todos")]
+        label: String,
+        /// Regenerate the badge whenever the list changes
+        #[arg(long)]
+        keep_updated: bool,
+    },
+    /// Create a project-local list (.todo.adoc) in the current directory,
+    /// used instead of the active list here and in the directories below
+    Init,
+    /// Print a shell completion script, e.g. `source <(todo completions bash)`
+    Completions {
+        shell: CompletionShell,
+    },
+    /// Read or change settings without editing the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    // Any other command runs the plugin command or script of that name, see
+    // run_script
+    #[command(external_subcommand)]
+    Script(Vec<String>),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Config {
-    todo: TodoConfig,
-    editor: EditorConfig,
+#[derive(Clone, Copy, ValueEnum)]
+enum GetField {
+    Text,
+    Due,
+    Tags,
+    Status,
+    Note,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TodoConfig {
-    active_list: String,
-    list_extension: String,
-    path: String,
+#[derive(Clone, Copy, ValueEnum)]
+enum SortKey {
+    Status,
+    Alpha,
+    Priority,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct EditorConfig {
-    command: String,
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Json,
+    Csv,
+    Markdown,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            todo: TodoConfig {
-                active_list: "default".to_string(),
-                list_extension: "adoc".to_string(),
-                path: "~/todos".to_string(),
-            },
-            editor: EditorConfig {
-                command: "nvim".to_string(),
-            },
-        }
-    }
+#[derive(Clone, Copy, ValueEnum)]
+enum ImportFormat {
+    Todotxt,
+    Markdown,
 }
 
-// TUI structures
-#[derive(Debug, Clone)]
-struct TodoItem {
-    text: String,
-    completed: bool,
-    line_type: LineType,
+#[derive(Subcommand)]
+enum BackupCommand {
+    /// Write the config, every list, state and the journal to one archive,
+    /// e.g. to move to another machine
+    Create {
+        /// Archive to write: a .tar, .tar.zst or .tar.gz file, or - for an
+        /// uncompressed tar on stdout
+        file: PathBuf,
+    },
+    /// Put everything back from an archive written by `todo backup create`
+    Restore {
+        /// Archive to read, or - for stdin
+        file: PathBuf,
+    },
 }
 
-#[derive(Debug, Clone)]
-enum LineType {
-    Todo,
-    Header1,
-    Header2,
-    Header3,
-    Bullet,
-    Text,
-    Empty,
+#[derive(Subcommand)]
+enum BackupsCommand {
+    /// List available backups of a list
+    List {
+        /// List to show backups for (defaults to active list)
+        #[arg(add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+    },
+    /// Restore a list from a backup
+    Restore {
+        /// List to restore (defaults to active list)
+        #[arg(add = ArgValueCandidates::new(list_names))]
+        list: Option<String>,
+        /// Restore the latest backup taken at or before this time
+        /// (e.g. "2024-05-01" or "2024-05-01 14:30")
+        #[arg(long)]
+        at: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+    Elvish,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print a setting, or a whole section
+    Get {
+        /// Dotted key, e.g. todo.path
+        key: String,
+    },
+    /// Change a setting
+    Set {
+        /// Dotted key, e.g. editor.command
+        key: String,
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+    },
+    /// Print where the config file is
+    Path,
+}
+
+// How many changes `u` can take back
+const UNDO_LIMIT: usize = 100;
+
+// The list in the TUI as it was before a change, with the deletes waiting
+// for the save and which todo was selected
+struct UndoState {
+    items: Vec<TodoItem>,
+    pending_deletes: Vec<usize>,
+    selected: usize,
 }
 
+// A column of the list as drawn: its area, and the item on each of its rows
+// with whether the row is the item's first
+type DrawnColumn = (Rect, Vec<(usize, bool)>);
+
+// TUI structures
 struct App {
     items: Vec<TodoItem>,
     selected: usize,
-    list_path: PathBuf,
+    // Where the list is read from and saved to
+    storage: Box<dyn Storage>,
     list_name: String,
     pending_deletes: Vec<usize>,
-    undo_stack: Vec<usize>,
+    // The list as it was before each change made here, most recent last,
+    // and the changes undone since the last one
+    undo_stack: Vec<UndoState>,
+    redo_stack: Vec<UndoState>,
+    // The todo last deleted, with its note and subtasks, for `p` to paste
+    register: Vec<TodoItem>,
+    // The first key of a two-key command like `dd`, once it's been pressed
+    pending_key: Option<char>,
+    // Why the last plugin key failed, for the status bar
+    plugin_error: Option<String>,
+    // Where `v` was pressed: everything from there to the selected item is
+    // selected, for toggling, deleting or moving together
+    visual_anchor: Option<usize>,
     edit_mode: bool,
     edit_text: String,
+    // Byte offset of the cursor in `edit_text`
+    edit_cursor: usize,
     edit_insert_position: Option<usize>,
+    // New todos go in as siblings of the one they're added next to
+    edit_insert_depth: usize,
     edit_original_text: Option<String>,
     edit_existing_index: Option<usize>,
+    // The input is a due date for the selected todo rather than todo text,
+    // and whether what was last entered couldn't be read as one
+    edit_due: bool,
+    edit_due_invalid: bool,
+    // Adding with `a`, where a digit first picks the section the todo goes
+    // in (the header of which is `edit_section`) without moving the cursor
+    edit_append: bool,
+    edit_section: Option<usize>,
+    // The input names a list to create and switch to, followed by the list
+    // to copy, if any; `new_list_error` is why the last one couldn't be
+    edit_new_list: bool,
+    new_list_error: Option<String>,
+    // The input is a search, started from the todo at `search_origin`.
+    // What's searched for is kept after Enter for `n` and `N`, and
+    // highlighted until Esc.
+    edit_search: bool,
+    search_origin: usize,
+    search: Option<Regex>,
     show_help: bool,
+    // Set when the file couldn't be decoded; nothing is written back
+    read_only: bool,
+    style: ListStyle,
+    density: Density,
+    columns: usize,
+    column_width: u16,
+    // Completed todos, and their notes, are left out of the view
+    hide_completed: bool,
+    // Columns shown and rows in each, as of the last frame
+    layout: Cell<(usize, usize)>,
+    // Position in visible_rows of the first row shown, as of the last frame
+    scroll: Cell<usize>,
+    // Each column of the list as drawn in the last frame
+    drawn_rows: RefCell<Vec<DrawnColumn>>,
+    // Long todos wrap onto more rows, rather than being cut off at the edge
+    // with the view scrolled `hscroll` columns sideways
+    wrap: bool,
+    hscroll: usize,
+    emoji: EmojiConfig,
+    spell: SpellConfig,
+    // What the spell checker found in each todo text checked so far
+    misspellings: HashMap<String, Vec<Misspelling>>,
+    // Write OSC 8 links over URLs and paths once a frame is drawn
+    hyperlinks: bool,
+    reduced_motion: bool,
+    high_contrast: bool,
+    // Who todos added and completed here are attributed to
+    user: Option<String>,
+    // The pane beside the list with the selected todo's details
+    show_details: bool,
+    // Complete a todo when the last of its subtasks is
+    complete_parents: bool,
+    // Whether to show hints, and the ones dismissed for good
+    hints: bool,
+    dismissed_hints: Vec<String>,
+}
+
+// One-line hints about newer features, shown in the TUI's status bar where
+// they'd be useful until dismissed with Esc or the feature is used. The
+// first that applies is shown.
+struct Hint {
+    id: &'static str,
+    text: &'static str,
+    applies: fn(&App) -> bool,
 }
 
+const HINTS: &[Hint] = &[
+    Hint {
+        id: "notes",
+        text: "This todo has a note: [i] shows it, `todo note` edits it",
+        applies: |app| !app.show_details && note_of(&app.items, app.selected).is_some(),
+    },
+    Hint {
+        id: "details",
+        text: "New: [i] shows when the todo was added and done, and by whom",
+        applies: |app| !app.show_details && app.items.get(app.selected).is_some_and(|item| item.added().is_some()),
+    },
+    Hint {
+        id: "tags",
+        text: "`todo list --tag <tag>` shows only the todos with a tag",
+        applies: |app| app.items.get(app.selected).is_some_and(|item| !item.tags().is_empty()),
+    },
+];
+
 impl App {
-    fn new(list_path: PathBuf, list_name: String) -> io::Result<Self> {
-        let items = Self::load_todos(&list_path)?;
+    // A list that isn't there yet starts empty
+    fn new(storage: Box<dyn Storage>, list_name: String) -> io::Result<Self> {
+        let items = if storage.exists(&list_name) { storage.load(&list_name)? } else { Vec::new() };
+        Ok(Self::with_items(items, storage, list_name))
+    }
+
+    // View of a list file that can't be decoded, with the bad bytes replaced
+    fn new_read_only(storage: Box<dyn Storage>, list_path: &Path, list_name: String) -> io::Result<Self> {
+        let items = read_list_lossy(list_path)?.iter().map(|line| parse_line(line)).collect();
+        let mut app = Self::with_items(items, storage, list_name);
+        app.read_only = true;
+        Ok(app)
+    }
+
+    fn with_items(items: Vec<TodoItem>, storage: Box<dyn Storage>, list_name: String) -> Self {
         let selected = items.iter().position(|item| matches!(item.line_type, LineType::Todo)).unwrap_or(0);
-        Ok(App {
+        App {
             items,
             selected,
-            list_path,
+            storage,
             list_name,
             pending_deletes: Vec::new(),
             undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            register: Vec::new(),
+            pending_key: None,
+            plugin_error: None,
+            visual_anchor: None,
             edit_mode: false,
             edit_text: String::new(),
+            edit_cursor: 0,
             edit_insert_position: None,
+            edit_insert_depth: 0,
             edit_original_text: None,
             edit_existing_index: None,
+            edit_due: false,
+            edit_due_invalid: false,
+            edit_append: false,
+            edit_section: None,
+            edit_new_list: false,
+            new_list_error: None,
+            edit_search: false,
+            search_origin: 0,
+            search: None,
             show_help: false,
-        })
+            read_only: false,
+            style: ListStyle::default(),
+            density: Density::default(),
+            columns: 1,
+            column_width: 40,
+            hide_completed: false,
+            layout: Cell::new((1, 0)),
+            scroll: Cell::new(0),
+            drawn_rows: RefCell::new(Vec::new()),
+            wrap: false,
+            hscroll: 0,
+            emoji: EmojiConfig::default(),
+            spell: SpellConfig::default(),
+            misspellings: HashMap::new(),
+            hyperlinks: false,
+            reduced_motion: false,
+            high_contrast: false,
+            user: None,
+            show_details: false,
+            complete_parents: true,
+            hints: false,
+            dismissed_hints: Vec::new(),
+        }
+    }
+
+    fn save_todos(&self) -> io::Result<()> {
+        self.write_items(true)
     }
 
-    fn load_todos(path: &PathBuf) -> io::Result<Vec<TodoItem>> {
-        let mut items = Vec::new();
+    // Write the list without carrying out the deletes, which wait for quit
+    fn save_keeping_deletes(&self) -> io::Result<()> {
+        self.write_items(false)
+    }
 
-        if !path.exists() {
-            return Ok(items);
-        }
-
-        let file = fs::File::open(path)?;
-        let reader = BufReader::new(file);
-
-        for line in reader.lines() {
-            let line = line?;
-            let trimmed = line.trim();
-
-            if trimmed.starts_with("* [ ]") {
-                let text = trimmed.strip_prefix("* [ ]").unwrap_or("").trim().to_string();
-                items.push(TodoItem {
-                    text,
-                    completed: false,
-                    line_type: LineType::Todo,
-                });
-            } else if trimmed.starts_with("* [x]") || trimmed.starts_with("* [X]") {
-                let text = trimmed
-                    .strip_prefix("* [x]")
-                    .or_else(|| trimmed.strip_prefix("* [X]"))
-                    .unwrap_or("")
-                    .trim()
-                    .to_string();
-                items.push(TodoItem {
-                    text,
-                    completed: true,
-                    line_type: LineType::Todo,
-                });
-            } else if trimmed.starts_with("= ") {
-                let text = trimmed.strip_prefix("= ").unwrap_or(trimmed).to_string();
-                items.push(TodoItem {
-                    text,
-                    completed: false,
-                    line_type: LineType::Header1,
-                });
-            } else if trimmed.starts_with("== ") {
-                let text = trimmed.strip_prefix("== ").unwrap_or(trimmed).to_string();
-                items.push(TodoItem {
-                    text,
-                    completed: false,
-                    line_type: LineType::Header2,
-                });
-            } else if trimmed.starts_with("=== ") {
-                let text = trimmed.strip_prefix("=== ").unwrap_or(trimmed).to_string();
-                items.push(TodoItem {
-                    text,
-                    completed: false,
-                    line_type: LineType::Header3,
-                });
-            } else if trimmed.starts_with("* ") && !trimmed.starts_with("* [") {
-                let text = trimmed.strip_prefix("* ").unwrap_or(trimmed).to_string();
-                items.push(TodoItem {
-                    text,
-                    completed: false,
-                    line_type: LineType::Bullet,
-                });
-            } else if trimmed.is_empty() {
-                items.push(TodoItem {
-                    text: String::new(),
-                    completed: false,
-                    line_type: LineType::Empty,
-                });
-            } else {
-                items.push(TodoItem {
-                    text: trimmed.to_string(),
-                    completed: false,
-                    line_type: LineType::Text,
-                });
-            }
+    fn write_items(&self, drop_deleted: bool) -> io::Result<()> {
+        if self.read_only {
+            return Ok(());
         }
+        let lines: Vec<String> = self
+            .items
+            .iter()
+            .enumerate()
+            // Skip items that are marked for deletion, and their notes and
+            // subtasks
+            .filter(|(idx, _)| !drop_deleted || !self.is_deleted(*idx))
+            .map(|(_, item)| format_line(item))
+            .collect();
 
-        Ok(items)
+        self.storage.save_list(&self.list_name, &lines)
     }
 
-    fn save_todos(&self) -> io::Result<()> {
-        let mut content = String::new();
-
-        for (idx, item) in self.items.iter().enumerate() {
-            // Skip items that are marked for deletion
-            if self.pending_deletes.contains(&idx) {
-                continue;
+    // Whether the item at `index` is marked for deletion, or belongs to a
+    // todo that is
+    fn is_deleted(&self, index: usize) -> bool {
+        let mut line = Some(index);
+        while let Some(i) = line {
+            if self.pending_deletes.contains(&i) {
+                return true;
             }
-
-            let line = match item.line_type {
-                LineType::Todo => {
-                    if item.completed {
-                        format!("* [x] {}\n", item.text)
-                    } else {
-                        format!("* [ ] {}\n", item.text)
-                    }
-                }
-                LineType::Header1 => format!("= {}\n", item.text),
-                LineType::Header2 => format!("== {}\n", item.text),
-                LineType::Header3 => format!("=== {}\n", item.text),
-                LineType::Bullet => format!("* {}\n", item.text),
-                LineType::Text => format!("{}\n", item.text),
-                LineType::Empty => "\n".to_string(),
-            };
-            content.push_str(&line);
+            line = parent_of(&self.items, i);
         }
-
-        fs::write(&self.list_path, content)?;
-        Ok(())
+        false
     }
 
     fn next(&mut self) {
@@ -266,14 +931,170 @@ impl App {
         let start = self.selected;
         loop {
             self.selected = (self.selected + 1) % self.items.len();
-            if (matches!(self.items[self.selected].line_type, LineType::Todo)
-                && !self.pending_deletes.contains(&self.selected))
-                || self.selected == start {
+            if self.navigable(self.selected) || self.selected == start {
                 break;
             }
         }
     }
 
+    // Whether the item at `index` is a completed todo, or its note, while
+    // those are hidden
+    fn is_hidden(&self, index: usize) -> bool {
+        if !self.hide_completed {
+            return false;
+        }
+        let owner = match self.items[index].line_type {
+            LineType::Note => note_owner(&self.items, index),
+            _ => Some(index),
+        };
+        owner.is_some_and(|i| matches!(self.items[i].line_type, LineType::Todo) && self.items[i].completed)
+    }
+
+    // Whether the item at `index` is a todo that can be moved to
+    fn selectable(&self, index: usize) -> bool {
+        matches!(self.items[index].line_type, LineType::Todo) && !self.is_deleted(index) && !self.is_hidden(index)
+    }
+
+    // What `j`, `k` and clicks stop at: todos, and headers, where Space
+    // toggles the whole section
+    fn navigable(&self, index: usize) -> bool {
+        self.selectable(index) || self.items[index].header_level().is_some()
+    }
+
+    fn set_hide_completed(&mut self, hide: bool) {
+        self.hide_completed = hide;
+        if self.selected < self.items.len() && !self.selectable(self.selected) {
+            self.next();
+        }
+    }
+
+    // Indices of the items drawn, in order: the view's rows map to these
+    fn visible_rows(&self) -> Vec<usize> {
+        let compact = self.density == Density::Compact;
+        (0..self.items.len())
+            .filter(|&i| !(compact && matches!(self.items[i].line_type, LineType::Empty)))
+            .filter(|&i| !self.is_hidden(i))
+            .collect()
+    }
+
+    // How many columns fit in `width`
+    fn columns_for(&self, width: u16) -> usize {
+        let fit = (width / self.column_width.max(1)) as usize;
+        self.columns.min(fit).max(1)
+    }
+
+    // Move to the next column's todo on the same row, or its first todo
+    fn next_column(&mut self) {
+        let drawn = self.drawn_rows.borrow();
+        let Some((column, row)) = drawn
+            .iter()
+            .enumerate()
+            .find_map(|(c, (_, rows))| Some((c, rows.iter().position(|&(i, first)| first && i == self.selected)?)))
+        else {
+            return;
+        };
+        let is_todo = |&(i, first): &(usize, bool)| first && self.selectable(i);
+        for step in 1..drawn.len() {
+            let (_, rows) = &drawn[(column + step) % drawn.len()];
+            let same_row = rows.get(row).copied().filter(is_todo);
+            if let Some((i, _)) = same_row.or_else(|| rows.iter().copied().find(is_todo)) {
+                drop(drawn);
+                self.selected = i;
+                return;
+            }
+        }
+    }
+
+    // Where the view starts so that it shows the selection, moving it as
+    // little as it takes; `heights` are the rows each visible item takes
+    fn scroll_to_selection(&self, visible: &[usize], heights: &[usize], columns: usize, rows: usize) -> usize {
+        // Whether the items in `range` fit in the columns
+        let fits = |range: std::ops::Range<usize>| {
+            let (mut column, mut used) = (1, 0);
+            for &height in &heights[range] {
+                if used > 0 && used + height > rows {
+                    column += 1;
+                    used = 0;
+                }
+                used += height;
+            }
+            column <= columns
+        };
+        let mut offset = self.scroll.get().min(visible.len().saturating_sub(1));
+        while offset > 0 && fits(offset - 1..visible.len()) {
+            offset -= 1;
+        }
+        if let Some(pos) = visible.iter().position(|&i| i == self.selected) {
+            offset = offset.min(pos);
+            while offset < pos && !fits(offset..pos + 1) {
+                offset += 1;
+            }
+        }
+        self.scroll.set(offset);
+        offset
+    }
+
+    // Scroll the view and move the selection half a page, to the todo
+    // nearest that far
+    fn scroll_half_page(&mut self, down: bool) {
+        let (columns, rows) = self.layout.get();
+        let half = (columns * rows / 2).max(1);
+        let visible = self.visible_rows();
+        let Some(pos) = visible.iter().position(|&i| i == self.selected) else {
+            return;
+        };
+        let target = if down { (pos + half).min(visible.len() - 1) } else { pos.saturating_sub(half) };
+        if target == pos {
+            return;
+        }
+        let found = if down {
+            visible[target..].iter().chain(visible[pos + 1..target].iter().rev()).copied().find(|&i| self.selectable(i))
+        } else {
+            visible[..=target].iter().rev().chain(&visible[target + 1..pos]).copied().find(|&i| self.selectable(i))
+        };
+        if let Some(i) = found {
+            self.selected = i;
+        }
+        let offset = self.scroll.get();
+        self.scroll.set(if down { offset + half } else { offset.saturating_sub(half) });
+    }
+
+    // The wheel scrolls the view, taking the selection along only when it
+    // would go out of sight
+    fn scroll_by(&mut self, rows: isize) {
+        let (columns, column_rows) = self.layout.get();
+        let page = (columns * column_rows).max(1);
+        let visible = self.visible_rows();
+        let offset = self.scroll.get().saturating_add_signed(rows).min(visible.len().saturating_sub(page));
+        self.scroll.set(offset);
+        let Some(pos) = visible.iter().position(|&i| i == self.selected) else {
+            return;
+        };
+        let mut shown = visible[offset..(offset + page).min(visible.len())].iter().copied();
+        let found = if pos < offset {
+            shown.find(|&i| self.selectable(i))
+        } else if pos >= offset + page {
+            shown.rfind(|&i| self.selectable(i))
+        } else {
+            None
+        };
+        if let Some(i) = found {
+            self.selected = i;
+        }
+    }
+
+    // The item drawn at a screen position in the last frame, and whether the
+    // position is on its checkbox
+    fn item_at(&self, column: u16, row: u16) -> Option<(usize, bool)> {
+        let drawn = self.drawn_rows.borrow();
+        let (area, rows) = drawn.iter().find(|(area, _)| area.contains(Position::new(column, row)))?;
+        let &(i, first) = rows.get((row - area.y) as usize)?;
+        let item = &self.items[i];
+        // After the indent of two spaces a level, the box and a space
+        let checkbox = first && matches!(item.line_type, LineType::Todo) && (column - area.x) as usize / 2 == item.depth;
+        Some((i, checkbox))
+    }
+
     fn previous(&mut self) {
         if self.items.is_empty() {
             return;
@@ -286,9 +1107,7 @@ impl App {
             } else {
                 self.selected - 1
             };
-            if (matches!(self.items[self.selected].line_type, LineType::Todo)
-                && !self.pending_deletes.contains(&self.selected))
-                || self.selected == start {
+            if self.navigable(self.selected) || self.selected == start {
                 break;
             }
         }
@@ -298,7 +1117,7 @@ impl App {
         self.selected = self.items
             .iter()
             .enumerate()
-            .position(|(idx, item)| matches!(item.line_type, LineType::Todo) && !self.pending_deletes.contains(&idx))
+            .position(|(idx, _)| self.selectable(idx))
             .unwrap_or(0);
     }
 
@@ -306,90 +1125,573 @@ impl App {
         self.selected = self.items
             .iter()
             .enumerate()
-            .rposition(|(idx, item)| matches!(item.line_type, LineType::Todo) && !self.pending_deletes.contains(&idx))
+            .rposition(|(idx, _)| self.selectable(idx))
             .unwrap_or(self.items.len().saturating_sub(1));
     }
 
     fn toggle_current(&mut self) {
-        if self.selected < self.items.len() {
-            if matches!(self.items[self.selected].line_type, LineType::Todo) {
-                self.items[self.selected].completed = !self.items[self.selected].completed;
+        if self.selected < self.items.len() && matches!(self.items[self.selected].line_type, LineType::Todo) {
+            let today = Local::now().date_naive();
+            let completed = !self.items[self.selected].completed;
+            // A recurring todo needs an id for its next copy to wait on
+            if self.items[self.selected].every().is_some() {
+                let items = self.items.clone();
+                self.items[self.selected].stamp_id(&items);
+            }
+            self.items[self.selected].set_completed(completed, today, self.user.as_deref());
+            let parents = if completed && self.complete_parents {
+                complete_parents(&mut self.items, self.selected, today, self.user.as_deref())
+            } else {
+                Vec::new()
+            };
+            let item = &self.items[self.selected];
+            let copy = next_copy(&self.items, item);
+            // Reopened, it takes back the copy completing it left
+            if let Some(copy) = copy.filter(|_| !completed) {
+                self.remove_items(copy..block_end(&self.items, copy));
+            }
+            let item = &self.items[self.selected];
+            let mut next = next_occurrence(item, today).filter(|_| item.completed && copy.is_none());
+            if let Some(next) = &mut next {
+                next.stamp_id(&self.items);
+                // After the todo's note and subtasks
+                let at = block_end(&self.items, self.selected);
+                self.items.insert(at, next.clone());
+                // Deletes waiting for the save refer to items by position
+                for index in &mut self.pending_deletes {
+                    if *index >= at {
+                        *index += 1;
+                    }
+                }
+            }
+            for event in toggle_events(&self.list_name, &self.items[self.selected], &self.items) {
+                EventBus::global().emit(&event);
+            }
+            for parent in parents {
+                EventBus::global().emit(&TodoEvent::ItemCompleted { list: self.list_name.clone(), text: self.items[parent].text.clone() });
+            }
+            if let Some(next) = next {
+                EventBus::global().emit(&TodoEvent::ItemAdded { list: self.list_name.clone(), text: next.text });
+            }
+            // A todo just completed goes out of sight with the others
+            if self.is_hidden(self.selected) {
+                self.next();
             }
         }
     }
 
-    fn delete_current(&mut self) {
-        if self.selected < self.items.len() {
-            if matches!(self.items[self.selected].line_type, LineType::Todo) {
-                // Add to pending deletes if not already there
-                if !self.pending_deletes.contains(&self.selected) {
-                    self.pending_deletes.push(self.selected);
-                    self.undo_stack.push(self.selected);
+    // Take items out of the list for good, keeping the selection and deletes
+    // waiting for the save on the same items
+    fn remove_items(&mut self, range: std::ops::Range<usize>) {
+        let count = range.len();
+        self.items.drain(range.clone());
+        self.pending_deletes.retain(|index| !range.contains(index));
+        for index in self.pending_deletes.iter_mut().chain([&mut self.selected]) {
+            if *index >= range.end {
+                *index -= count;
+            }
+        }
+    }
 
-                    // Move to next non-deleted todo
-                    self.next();
-                }
+    // Make the selected todo a subtask of the todo above it, or (not
+    // `deeper`) take it a level out; its own subtasks move with it
+    fn nest_current(&mut self, deeper: bool) {
+        let Some(item) = self.items.get(self.selected).filter(|item| matches!(item.line_type, LineType::Todo)) else {
+            return;
+        };
+        let depth = item.depth;
+        if deeper {
+            let above = (0..self.selected).rev().find(|&i| !matches!(self.items[i].line_type, LineType::Note));
+            if !above.is_some_and(|i| matches!(self.items[i].line_type, LineType::Todo) && self.items[i].depth >= depth) {
+                return;
+            }
+        } else if depth == 0 {
+            return;
+        }
+        for i in self.selected..block_end(&self.items, self.selected) {
+            if matches!(self.items[i].line_type, LineType::Todo) {
+                let item = &mut self.items[i];
+                item.depth = if deeper { item.depth + 1 } else { item.depth - 1 };
             }
         }
     }
 
-    fn undo_delete(&mut self) {
-        if let Some(idx) = self.undo_stack.pop() {
-            // Remove from pending deletes
-            if let Some(pos) = self.pending_deletes.iter().position(|&x| x == idx) {
-                self.pending_deletes.remove(pos);
-                // Move selection to the restored item
-                self.selected = idx;
+    // Move the selected todo, with its note and subtasks, past what's below
+    // it (`down`) or above; in visual mode, all the todos selected. A todo at
+    // the top level passes blank lines and headers too, so it can go to
+    // another section; a subtask only trades places with the subtasks next
+    // to it. Todos waiting to be deleted are passed over along with the one
+    // next to them.
+    fn move_current(&mut self, down: bool) {
+        loop {
+            let Some((start, end)) = self.moved_block() else {
+                return;
+            };
+            let depth = self.items[start].depth;
+            let is_sibling = |i: usize| matches!(self.items[i].line_type, LineType::Todo) && self.items[i].depth == depth;
+            let passed = if down {
+                if end == self.items.len() || (depth > 0 && !is_sibling(end)) {
+                    return;
+                }
+                let next = if matches!(self.items[end].line_type, LineType::Todo) { block_end(&self.items, end) } else { end + 1 };
+                self.swap_adjacent(start, end, next);
+                start
+            } else {
+                let Some(mut above) = start.checked_sub(1) else {
+                    return;
+                };
+                // Notes and subtasks above belong to a todo further up
+                while above > 0
+                    && (matches!(self.items[above].line_type, LineType::Note)
+                        || (matches!(self.items[above].line_type, LineType::Todo) && self.items[above].depth > depth))
+                {
+                    above -= 1;
+                }
+                if !is_sibling(above) {
+                    // A subtask stays under its parent
+                    if depth > 0 {
+                        return;
+                    }
+                    if above + 1 < start {
+                        above += 1;
+                    }
+                }
+                self.swap_adjacent(above, start, end);
+                above + (end - start)
+            };
+            if !self.is_deleted(passed) && !self.is_hidden(passed) {
+                return;
             }
         }
     }
 
-    fn start_insert_above(&mut self) {
-        self.edit_mode = true;
-        self.edit_text = String::new();
-        self.edit_insert_position = Some(self.selected);
-        self.edit_original_text = None;
-        self.edit_existing_index = None;
+    // Trade the places of the items in `first..middle` and `middle..last`,
+    // keeping the selection and deletes waiting for the save on the same items
+    fn swap_adjacent(&mut self, first: usize, middle: usize, last: usize) {
+        self.items[first..last].rotate_left(middle - first);
+        let moved = |index: &mut usize| {
+            if (first..middle).contains(index) {
+                *index += last - middle;
+            } else if (middle..last).contains(index) {
+                *index -= middle - first;
+            }
+        };
+        for index in &mut self.pending_deletes {
+            moved(index);
+        }
+        moved(&mut self.selected);
+        if let Some(anchor) = &mut self.visual_anchor {
+            moved(anchor);
+        }
     }
 
-    fn start_insert_below(&mut self) {
-        self.edit_mode = true;
-        self.edit_text = String::new();
-        self.edit_insert_position = Some(self.selected + 1);
-        self.edit_original_text = None;
-        self.edit_existing_index = None;
+    fn delete_current(&mut self) {
+        if self.selected < self.items.len() && matches!(self.items[self.selected].line_type, LineType::Todo) {
+            // Add to pending deletes if not already there
+            if !self.pending_deletes.contains(&self.selected) {
+                self.pending_deletes.push(self.selected);
+                self.register = self.items[self.selected..block_end(&self.items, self.selected)].to_vec();
+
+                // Move to next non-deleted todo
+                self.next();
+            }
+        }
     }
 
-    fn start_edit_current(&mut self) {
-        if self.selected < self.items.len() {
-            if matches!(self.items[self.selected].line_type, LineType::Todo) {
-                self.edit_mode = true;
-                self.edit_text = self.items[self.selected].text.clone();
-                self.edit_original_text = Some(self.items[self.selected].text.clone());
-                self.edit_existing_index = Some(self.selected);
-                self.edit_insert_position = None;
+    // Run the plugin's key for the selected todo, which gets the text it
+    // gives back and keeps its stamps
+    fn run_plugin_key(&mut self, key: char) {
+        let (Some(registered), Some(item)) = (plugins().key(key), self.items.get(self.selected)) else {
+            return;
+        };
+        if !matches!(item.line_type, LineType::Todo) {
+            return;
+        }
+        match plugins().run_key(registered, item) {
+            Ok(Some(text)) if !text.trim().is_empty() => {
+                let old = item.clone();
+                let item = &mut self.items[self.selected];
+                item.text = text.trim().to_string();
+                item.keep_stamps(&old);
+                self.check_spelling();
             }
+            Ok(_) => {}
+            Err(e) => self.plugin_error = Some(e),
         }
     }
 
-    fn finish_edit(&mut self) {
-        if self.edit_mode {
-            self.edit_mode = false;
+    // Positions from the first to the last item selected in visual mode
+    fn visual_range(&self) -> Option<RangeInclusive<usize>> {
+        let anchor = self.visual_anchor?;
+        Some(anchor.min(self.selected)..=anchor.max(self.selected))
+    }
 
-            // Check if we're editing an existing todo
-            if let Some(idx) = self.edit_existing_index {
-                // Update existing todo
-                if !self.edit_text.trim().is_empty() && idx < self.items.len() {
+    fn toggle_visual(&mut self) {
+        self.visual_anchor = match self.visual_anchor {
+            Some(_) => None,
+            None => self.items.get(self.selected).filter(|item| matches!(item.line_type, LineType::Todo)).map(|_| self.selected),
+        };
+    }
+
+    // Complete every todo selected in visual mode or, when they all are,
+    // open them again
+    fn toggle_selection(&mut self) {
+        let Some(range) = self.visual_range() else {
+            return;
+        };
+        let todos: Vec<usize> = range.clone().filter(|&i| self.selectable(i)).collect();
+        self.toggle_todos(&todos);
+        self.visual_anchor = None;
+        self.selected = *range.start();
+        if !self.selectable(self.selected) {
+            self.next();
+        }
+    }
+
+    // Complete every todo in the section under the selected header or,
+    // when they're all done already, open them again
+    fn toggle_section(&mut self) {
+        let section = section_range(&self.items, self.selected);
+        let todos: Vec<usize> = section.filter(|&i| matches!(self.items[i].line_type, LineType::Todo) && !self.is_deleted(i)).collect();
+        self.toggle_todos(&todos);
+    }
+
+    // Complete the todos at `todos`, in order, or open them all again when
+    // none is open. Going from the bottom up, todos that come back and
+    // parents completed along the way don't move the ones still to do. The
+    // selection ends up where it was, which is above them all.
+    fn toggle_todos(&mut self, todos: &[usize]) {
+        let selected = self.selected;
+        let completed = todos.iter().any(|&i| !self.items[i].completed);
+        for &i in todos.iter().rev() {
+            if self.items[i].completed != completed {
+                self.selected = i;
+                self.toggle_current();
+            }
+        }
+        self.selected = selected;
+    }
+
+    // Delete every todo selected in visual mode, with their notes and
+    // subtasks, which then go in the register together
+    fn delete_selection(&mut self) {
+        let Some(range) = self.visual_range() else {
+            return;
+        };
+        let mut register = Vec::new();
+        for i in range.clone() {
+            if self.selectable(i) {
+                self.pending_deletes.push(i);
+                register.extend_from_slice(&self.items[i..block_end(&self.items, i)]);
+            }
+        }
+        if !register.is_empty() {
+            self.register = register;
+        }
+        self.visual_anchor = None;
+        self.selected = *range.start();
+        if !self.selectable(self.selected) {
+            self.next();
+        }
+    }
+
+    // What `J` and `K` move: the selected todo's block or, in visual mode,
+    // the blocks of all the todos selected. A selection that goes from a
+    // subtask out to a todo less deep, or takes in a header, doesn't move.
+    fn moved_block(&self) -> Option<(usize, usize)> {
+        let Some(range) = self.visual_range() else {
+            self.items.get(self.selected).filter(|item| matches!(item.line_type, LineType::Todo))?;
+            return Some((self.selected, block_end(&self.items, self.selected)));
+        };
+        let start = range.clone().find(|&i| matches!(self.items[i].line_type, LineType::Todo))?;
+        let depth = self.items[start].depth;
+        let mut end = start;
+        for i in start..=*range.end() {
+            match self.items[i].line_type {
+                LineType::Todo if self.items[i].depth < depth => return None,
+                LineType::Todo => end = end.max(block_end(&self.items, i)),
+                LineType::Note => {}
+                _ => return None,
+            }
+        }
+        Some((start, end))
+    }
+
+    fn undo_state(&self) -> UndoState {
+        UndoState { items: self.items.clone(), pending_deletes: self.pending_deletes.clone(), selected: self.selected }
+    }
+
+    // Remember `before` for `u` if the list has changed since
+    fn record_change(&mut self, before: UndoState) {
+        if before.items == self.items && before.pending_deletes == self.pending_deletes {
+            return;
+        }
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(before);
+        self.redo_stack.clear();
+    }
+
+    fn restore_state(&mut self, state: UndoState) {
+        self.items = state.items;
+        self.pending_deletes = state.pending_deletes;
+        self.selected = state.selected.min(self.items.len().saturating_sub(1));
+        self.check_spelling();
+    }
+
+    fn undo(&mut self) {
+        if let Some(state) = self.undo_stack.pop() {
+            self.redo_stack.push(self.undo_state());
+            self.restore_state(state);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(state) = self.redo_stack.pop() {
+            self.undo_stack.push(self.undo_state());
+            self.restore_state(state);
+        }
+    }
+
+    // Put the last deleted todo back below the selected one, at its depth.
+    // It keeps its id unless a todo that's still there has it, as when the
+    // delete was undone or the same todo is pasted twice.
+    fn paste_register(&mut self) {
+        let Some(first) = self.register.first() else {
+            return;
+        };
+        let live = |app: &App, id: &str| (0..app.items.len()).any(|i| app.items[i].id() == Some(id) && !app.is_deleted(i));
+        let (at, depth) = match self.items.get(self.selected).filter(|item| matches!(item.line_type, LineType::Todo)) {
+            Some(item) => (block_end(&self.items, self.selected), item.depth),
+            None => (self.items.len(), 0),
+        };
+        // Todos deleted together in visual mode can start deeper than they go
+        let base = self.register.iter().filter(|item| matches!(item.line_type, LineType::Todo)).map(|item| item.depth).min().unwrap_or(first.depth);
+        let mut pasted = Vec::new();
+        for item in &self.register {
+            let mut item = item.clone();
+            if matches!(item.line_type, LineType::Todo) {
+                item.depth = item.depth - base + depth;
+                if item.id().is_some_and(|id| live(self, id) || pasted.iter().any(|p: &TodoItem| p.id() == Some(id))) {
+                    let taken: Vec<TodoItem> = self.items.iter().chain(&pasted).cloned().collect();
+                    item.set_id(&new_id(&taken));
+                }
+            }
+            pasted.push(item);
+        }
+        let count = pasted.len();
+        let text = pasted[0].text.clone();
+        self.items.splice(at..at, pasted);
+        // Deletes waiting for the save refer to items by position
+        for index in &mut self.pending_deletes {
+            if *index >= at {
+                *index += count;
+            }
+        }
+        self.selected = at;
+        EventBus::global().emit(&TodoEvent::ItemAdded { list: self.list_name.clone(), text });
+    }
+
+    // Depth of the selected todo, for a new one next to it
+    fn selected_depth(&self) -> usize {
+        self.items.get(self.selected).filter(|item| matches!(item.line_type, LineType::Todo)).map_or(0, |item| item.depth)
+    }
+
+    fn start_insert_above(&mut self) {
+        self.edit_mode = true;
+        self.edit_text = String::new();
+        self.edit_cursor = 0;
+        self.edit_insert_position = Some(self.selected);
+        self.edit_insert_depth = self.selected_depth();
+        self.edit_original_text = None;
+        self.edit_existing_index = None;
+    }
+
+    // A new todo at the end of the list, or of a section picked next
+    fn start_append(&mut self) {
+        self.edit_mode = true;
+        self.edit_append = true;
+        self.edit_section = None;
+        self.edit_text = String::new();
+        self.edit_cursor = 0;
+        self.edit_insert_position = Some(self.items.len());
+        self.edit_insert_depth = 0;
+        self.edit_original_text = None;
+        self.edit_existing_index = None;
+    }
+
+    fn start_insert_below(&mut self) {
+        self.edit_mode = true;
+        self.edit_text = String::new();
+        self.edit_cursor = 0;
+        // Below the todo's note and subtasks, which would otherwise become the
+        // new todo's
+        let below = if self.selected < self.items.len() { block_end(&self.items, self.selected) } else { self.items.len() };
+        self.edit_insert_position = Some(below);
+        self.edit_insert_depth = self.selected_depth();
+        self.edit_original_text = None;
+        self.edit_existing_index = None;
+    }
+
+    fn start_edit_current(&mut self) {
+        if self.selected < self.items.len() && matches!(self.items[self.selected].line_type, LineType::Todo) {
+            self.edit_mode = true;
+            // The added/done stamps aren't edited, and are put back after
+            self.edit_text = self.items[self.selected].text_without_stamps();
+            self.edit_cursor = self.edit_text.len();
+            self.edit_original_text = Some(self.items[self.selected].text.clone());
+            self.edit_existing_index = Some(self.selected);
+            self.edit_insert_position = None;
+        }
+    }
+
+    // Give the selected todo a new due date, or none
+    fn reschedule_current(&mut self, due: Option<NaiveDate>) {
+        if let Some(item) = self.items.get_mut(self.selected).filter(|item| matches!(item.line_type, LineType::Todo)) {
+            item.set_due(due);
+        }
+    }
+
+    // Prompt for a due date in words, starting from the current one
+    fn start_due_prompt(&mut self) {
+        if let Some(item) = self.items.get(self.selected).filter(|item| matches!(item.line_type, LineType::Todo)) {
+            self.edit_mode = true;
+            self.edit_due = true;
+            self.edit_due_invalid = false;
+            self.edit_text = item.due().unwrap_or_default().to_string();
+            self.edit_cursor = self.edit_text.len();
+        }
+    }
+
+    // An empty date clears the due date; one that can't be read leaves the
+    // prompt open to fix it
+    fn finish_due_prompt(&mut self) {
+        let input = self.edit_text.trim();
+        let due = if input.is_empty() {
+            None
+        } else {
+            match parse_date(input, Local::now().date_naive()) {
+                Some(due) => Some(due),
+                None => {
+                    self.edit_due_invalid = true;
+                    return;
+                }
+            }
+        };
+        self.reschedule_current(due);
+        self.edit_mode = false;
+        self.edit_due = false;
+        self.edit_due_invalid = false;
+        self.edit_text.clear();
+        self.edit_cursor = 0;
+    }
+
+    fn start_new_list(&mut self) {
+        self.edit_mode = true;
+        self.edit_new_list = true;
+        self.new_list_error = None;
+        self.edit_text.clear();
+        self.edit_cursor = 0;
+    }
+
+    fn start_search(&mut self) {
+        self.edit_mode = true;
+        self.edit_search = true;
+        self.search_origin = self.selected;
+        self.edit_text.clear();
+        self.edit_cursor = 0;
+        self.search = None;
+    }
+
+    // Jump to the first match from where the search started, as it's typed
+    fn update_search(&mut self) {
+        self.search = Some(&self.edit_text)
+            .filter(|query| !query.is_empty())
+            .and_then(|query| RegexBuilder::new(&regex::escape(query)).case_insensitive(true).build().ok());
+        self.selected = self.search_origin;
+        if !self.matches_search(self.selected) {
+            self.next_match(true);
+        }
+    }
+
+    // Enter keeps the search, unless nothing was typed
+    fn finish_search(&mut self) {
+        self.edit_mode = false;
+        self.edit_search = false;
+        self.edit_text.clear();
+        self.edit_cursor = 0;
+    }
+
+    fn matches_search(&self, index: usize) -> bool {
+        let Some(pattern) = &self.search else {
+            return false;
+        };
+        // Only what's shown of it, without the stamps
+        self.selectable(index) && pattern.is_match(&self.items[index].text_without_stamps())
+    }
+
+    // Select the next todo that matches the search (`forward`) or the one
+    // before, going round the end of the list
+    fn next_match(&mut self, forward: bool) {
+        let len = self.items.len();
+        let found = (1..=len)
+            .map(|step| if forward { (self.selected + step) % len } else { (self.selected + len - step) % len })
+            .find(|&i| self.matches_search(i));
+        if let Some(i) = found {
+            self.selected = i;
+        }
+    }
+
+    fn search_matches(&self) -> usize {
+        (0..self.items.len()).filter(|&i| self.matches_search(i)).count()
+    }
+
+    fn finish_edit(&mut self) {
+        if self.edit_due {
+            self.finish_due_prompt();
+            return;
+        }
+        if self.edit_search {
+            self.finish_search();
+            return;
+        }
+        if self.edit_mode {
+            self.edit_mode = false;
+            if self.emoji.shortcodes {
+                self.edit_text = expand_shortcodes(&self.edit_text, &self.emoji.custom);
+            }
+            // A due date the TUI can't read is kept as typed
+            let mut edited = TodoItem { text: self.edit_text.clone(), completed: false, line_type: LineType::Todo, depth: 0 };
+            if edited.resolve_due(Local::now().date_naive()).is_ok() {
+                self.edit_text = edited.text;
+            }
+
+            // Check if we're editing an existing todo
+            if let Some(idx) = self.edit_existing_index {
+                // Update existing todo
+                if !self.edit_text.trim().is_empty() && idx < self.items.len() {
+                    let old = self.items[idx].clone();
                     self.items[idx].text = self.edit_text.trim().to_string();
+                    self.items[idx].keep_stamps(&old);
                 }
             } else if let Some(pos) = self.edit_insert_position {
                 // Insert new todo (only if there's text)
                 if !self.edit_text.trim().is_empty() {
-                    let new_item = TodoItem {
+                    let mut new_item = TodoItem {
                         text: self.edit_text.trim().to_string(),
                         completed: false,
                         line_type: LineType::Todo,
+                        depth: self.edit_insert_depth,
                     };
+                    // Added under a header, the todo takes its section's
+                    // defaults; at the end of the list it doesn't
+                    if !self.edit_append || self.edit_section.is_some() {
+                        new_item.apply_defaults(&defaults_at(&self.items, pos));
+                        let _ = new_item.resolve_due(Local::now().date_naive());
+                    }
+                    new_item.stamp_added(Local::now().date_naive(), self.user.as_deref());
+                    new_item.stamp_id(&self.items);
 
                     // Insert at the specified position
                     let insert_pos = pos.min(self.items.len());
@@ -402,28 +1704,44 @@ impl App {
                         }
                     }
 
-                    // Update undo_stack indices
-                    for idx in &mut self.undo_stack {
-                        if *idx >= insert_pos {
-                            *idx += 1;
-                        }
+                    // Move selection to the new item, unless it went in a
+                    // section picked by number
+                    if self.edit_section.is_none() {
+                        self.selected = insert_pos;
+                    } else if insert_pos <= self.selected {
+                        self.selected += 1;
                     }
-
-                    // Move selection to the new item
-                    self.selected = insert_pos;
+                    EventBus::global().emit(&TodoEvent::ItemAdded {
+                        list: self.list_name.clone(),
+                        text: self.items[insert_pos].text.clone(),
+                    });
                 }
             }
 
             self.edit_text.clear();
+
+            self.edit_cursor = 0;
             self.edit_insert_position = None;
             self.edit_original_text = None;
             self.edit_existing_index = None;
+            self.edit_append = false;
+            self.edit_section = None;
+            self.check_spelling();
         }
     }
 
     fn cancel_edit(&mut self) {
         if self.edit_mode {
             self.edit_mode = false;
+            self.edit_due = false;
+            self.edit_due_invalid = false;
+            self.edit_new_list = false;
+            self.new_list_error = None;
+            if self.edit_search {
+                self.edit_search = false;
+                self.search = None;
+                self.selected = self.search_origin;
+            }
 
             // If editing an existing todo, restore original text
             if let Some(idx) = self.edit_existing_index {
@@ -436,21 +1754,98 @@ impl App {
             // If inserting a new todo, just discard it (do nothing)
 
             self.edit_text.clear();
+
+            self.edit_cursor = 0;
             self.edit_insert_position = None;
             self.edit_original_text = None;
             self.edit_existing_index = None;
+            self.edit_append = false;
+            self.edit_section = None;
         }
     }
 
+    // Positions of the list's headers, which `a1`, `a2`... add to
+    fn section_headers(&self) -> Vec<usize> {
+        (0..self.items.len())
+            .filter(|&i| matches!(self.items[i].line_type, LineType::Header1 | LineType::Header2 | LineType::Header3))
+            .collect()
+    }
+
     fn handle_char_input(&mut self, c: char) {
-        if self.edit_mode {
-            self.edit_text.push(c);
+        if !self.edit_mode {
+            return;
+        }
+        let header = c.to_digit(10).and_then(|n| self.section_headers().get((n as usize).checked_sub(1)?).copied());
+        if let Some(header) = header.filter(|_| self.edit_append && self.edit_text.is_empty()) {
+            let lines: Vec<String> = self.items.iter().map(format_line).collect();
+            self.edit_section = Some(header);
+            self.edit_insert_position = Some(section_end(&lines, header));
+            return;
+        }
+        self.edit_text.insert(self.edit_cursor, c);
+        self.edit_cursor += c.len_utf8();
+    }
+
+    // Where the cursor goes moving a character back or forward
+    fn cursor_before(&self) -> Option<usize> {
+        self.edit_text[..self.edit_cursor].char_indices().next_back().map(|(i, _)| i)
+    }
+
+    fn cursor_after(&self) -> Option<usize> {
+        self.edit_text[self.edit_cursor..].chars().next().map(|c| self.edit_cursor + c.len_utf8())
+    }
+
+    fn move_cursor(&mut self, key: KeyCode) {
+        self.edit_cursor = match key {
+            KeyCode::Left => self.cursor_before().unwrap_or(self.edit_cursor),
+            KeyCode::Right => self.cursor_after().unwrap_or(self.edit_cursor),
+            KeyCode::Home => 0,
+            KeyCode::End => self.edit_text.len(),
+            _ => self.edit_cursor,
+        };
+    }
+
+    // Delete the character under the cursor
+    fn handle_delete(&mut self) {
+        if let Some(after) = self.cursor_after() {
+            self.edit_text.replace_range(self.edit_cursor..after, "");
         }
     }
 
     fn handle_backspace(&mut self) {
-        if self.edit_mode && !self.edit_text.is_empty() {
-            self.edit_text.pop();
+        if let Some(before) = self.cursor_before() {
+            self.edit_text.replace_range(before..self.edit_cursor, "");
+            self.edit_cursor = before;
+        } else if self.edit_text.is_empty() && self.edit_section.take().is_some() {
+            // Back to the end of the list
+            self.edit_insert_position = Some(self.items.len());
+        }
+    }
+
+    // Check the todos the spell checker hasn't seen yet, all in one go. A
+    // checker that can't be run is given up on for the session.
+    fn check_spelling(&mut self) {
+        if !self.spell.enabled {
+            return;
+        }
+        let mut unchecked: Vec<&str> = self
+            .items
+            .iter()
+            .filter(|item| matches!(item.line_type, LineType::Todo) && !self.misspellings.contains_key(&item.text))
+            .map(|item| item.text.as_str())
+            .collect();
+        unchecked.sort_unstable();
+        unchecked.dedup();
+        if unchecked.is_empty() {
+            return;
+        }
+
+        match spell::check(&self.spell, &unchecked) {
+            Ok(results) => {
+                let checked: Vec<String> = unchecked.iter().map(|text| text.to_string()).collect();
+                self.misspellings.extend(checked.into_iter().zip(results));
+            }
+            Err(_) => self.spell.enabled = false,
         }
     }
 
@@ -458,109 +1853,145 @@ impl App {
         self.show_help = !self.show_help;
     }
 
+    fn current_hint(&self) -> Option<&'static Hint> {
+        if !self.hints || self.edit_mode {
+            return None;
+        }
+        HINTS.iter().find(|hint| !self.dismissed_hints.iter().any(|id| id == hint.id) && (hint.applies)(self))
+    }
+
+    // Stop showing a hint, in this and later sessions
+    fn dismiss_hint(&mut self, id: &str) {
+        if !self.hints || self.dismissed_hints.iter().any(|dismissed| dismissed == id) {
+            return;
+        }
+        self.dismissed_hints.push(id.to_string());
+        let mut state = load_state();
+        state.dismissed_hints = self.dismissed_hints.clone();
+        let _ = save_state(&state);
+    }
+
+    fn toggle_details(&mut self) {
+        self.show_details = !self.show_details;
+        self.dismiss_hint("details");
+        self.dismiss_hint("notes");
+    }
+
     fn count_todos(&self) -> (usize, usize) {
         let incomplete = self.items.iter()
             .enumerate()
-            .filter(|(idx, item)| matches!(item.line_type, LineType::Todo) && !item.completed && !self.pending_deletes.contains(idx))
+            .filter(|(idx, item)| matches!(item.line_type, LineType::Todo) && !item.completed && !self.is_deleted(*idx))
             .count();
         let complete = self.items.iter()
             .enumerate()
-            .filter(|(idx, item)| matches!(item.line_type, LineType::Todo) && item.completed && !self.pending_deletes.contains(idx))
+            .filter(|(idx, item)| matches!(item.line_type, LineType::Todo) && item.completed && !self.is_deleted(*idx))
             .count();
         (incomplete, complete)
     }
 }
 
-fn get_config_path() -> PathBuf {
-    let home = dirs::home_dir().expect("Could not find home directory");
-    home.join(".config").join("todo").join("config.toml")
+#[derive(Serialize)]
+struct ListSummary {
+    name: String,
+    path: PathBuf,
+    active: bool,
+    // None when the list can't be read
+    incomplete: Option<usize>,
+    complete: Option<usize>,
 }
 
-fn expand_tilde(path: &str) -> PathBuf {
-    if path.starts_with("~/") {
-        let home = dirs::home_dir().expect("Could not find home directory");
-        home.join(&path[2..])
-    } else {
-        PathBuf::from(path)
-    }
+fn print_json(value: &impl Serialize) {
+    println!("{}", serde_json::to_string_pretty(value).expect("Failed to serialize JSON"));
 }
 
-fn ensure_config_exists() -> Config {
-    let config_path = get_config_path();
-
-    if !config_path.exists() {
-        // Create the directory if it doesn't exist
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent).expect("Failed to create config directory");
-        }
-
-        // Create default config
-        let config = Config::default();
-        let toml_string = toml::to_string(&config).expect("Failed to serialize config");
-        fs::write(&config_path, toml_string).expect("Failed to write config file");
-
-        config
-    } else {
-        // Load existing config
-        let config_str = fs::read_to_string(&config_path).expect("Failed to read config file");
-        toml::from_str(&config_str).expect("Failed to parse config file")
-    }
+// Files in every directory of the workspace, named as commands refer to
+// them (`work/standup.adoc`). Other directories that can't be read are
+// skipped.
+fn workspace_files(config: &Config) -> io::Result<Vec<(String, PathBuf)>> {
+    let todo_path = expand_tilde(&config.todo.path);
+    let lists = open_storage(config).list_lists()?;
+    Ok(lists
+        .into_iter()
+        .map(|name| {
+            let path = get_list_path(config, &todo_path, &name);
+            (format!("{}.{}", name, config.todo.list_extension), path)
+        })
+        .collect())
 }
 
-fn save_config(config: &Config) {
-    let config_path = get_config_path();
-    let toml_string = toml::to_string(config).expect("Failed to serialize config");
-    fs::write(&config_path, toml_string).expect("Failed to write config file");
+// A list's items, or for one that can't be decoded, its file's lines with the
+// bad bytes replaced
+fn load_leniently(store: &dyn Storage, list_name: &str, list_path: &Path) -> io::Result<Vec<TodoItem>> {
+    match store.load(list_name) {
+        Ok(items) => Ok(items),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => Ok(read_list_lossy(list_path)?.iter().map(|line| parse_line(line)).collect()),
+        Err(e) => Err(e),
+    }
 }
 
-fn ensure_todo_directory_exists(config: &Config) -> PathBuf {
-    let todo_path = expand_tilde(&config.todo.path);
-
-    if !todo_path.exists() {
-        fs::create_dir_all(&todo_path).expect("Failed to create todo directory");
+fn list_summaries(config: &Config, todo_path: &Path) -> io::Result<Vec<ListSummary>> {
+    let active = get_active_list_path(config, todo_path);
+    let mut files = workspace_files(config)?;
+    if let Some(project) = &config.project_list {
+        files.insert(0, (format!("{}.{}", PROJECT_LIST, config.todo.list_extension), project.clone()));
     }
 
-    todo_path
+    let store = open_storage(config);
+    Ok(files
+        .into_iter()
+        .map(|(file, path)| {
+            let counts = store.load_todos(&file).ok().map(|items| {
+                let todos = items.iter().filter(|item| matches!(item.line_type, LineType::Todo));
+                todos.fold((0, 0), |(open, done), item| {
+                    if item.completed { (open, done + 1) } else { (open + 1, done) }
+                })
+            });
+            ListSummary {
+                name: normalize_list_name(&file),
+                active: path == active,
+                incomplete: counts.map(|(open, _)| open),
+                complete: counts.map(|(_, done)| done),
+                path,
+            }
+        })
+        .collect())
 }
 
-fn get_active_list_path(config: &Config, todo_path: &PathBuf) -> PathBuf {
-    let file_name = format!("{}.{}", config.todo.active_list, config.todo.list_extension);
-    todo_path.join(file_name)
-}
+fn list_todos(config: &Config, json: bool) {
+    let todo_path = expand_tilde(&config.todo.path);
 
-fn ensure_active_list_exists(list_path: &PathBuf) {
-    if !list_path.exists() {
-        fs::write(list_path, "").expect("Failed to create todo list file");
+    if json {
+        match list_summaries(config, &todo_path) {
+            Ok(lists) => print_json(&lists),
+            Err(e) => eprintln!("Error reading todo directory: {}", e),
+        }
+        return;
     }
-}
-
-fn list_todos(config: &Config) {
-    let todo_path = expand_tilde(&config.todo.path);
 
     if !todo_path.exists() {
         println!("No todo lists found.");
         return;
     }
 
-    match fs::read_dir(&todo_path) {
-        Ok(entries) => {
-            let mut files: Vec<String> = entries
-                .filter_map(|entry| entry.ok())
-                .filter(|entry| entry.path().is_file())
-                .filter_map(|entry| {
-                    entry.file_name().to_str().map(|s| s.to_string())
-                })
-                .collect();
-
+    match workspace_files(config) {
+        Ok(files) => {
+            if let Some(project) = &config.project_list {
+                println!("* {} (project, active)", project.display());
+            }
             if files.is_empty() {
                 println!("No todo lists found.");
             } else {
-                files.sort();
-                for file in files {
-                    if file == format!("{}.{}", config.todo.active_list, config.todo.list_extension) {
-                        println!("* {} (active)", file);
+                let active = match config.project_list {
+                    Some(_) => String::new(),
+                    None => format!("{}.{}", config.todo.active_list, config.todo.list_extension),
+                };
+                for file in files.iter().map(|(file, _)| file.as_str()) {
+                    let style = config.list_style(file);
+                    let label = paint(&style.label(file), &style);
+                    if file == active {
+                        println!("* {} (active)", label);
                     } else {
-                        println!("  {}", file);
+                        println!("  {}", label);
                     }
                 }
             }
@@ -571,639 +2002,6249 @@ fn list_todos(config: &Config) {
     }
 }
 
-fn use_list(config: &mut Config, list_name: String) {
-    // Extract just the name without extension if provided
-    let list_name = if list_name.contains('.') {
-        list_name.split('.').next().unwrap().to_string()
-    } else {
-        list_name
-    };
+// Set from `high_contrast` before any output
+static HIGH_CONTRAST: AtomicBool = AtomicBool::new(false);
 
-    config.todo.active_list = list_name.clone();
-    save_config(config);
+fn high_contrast() -> bool {
+    HIGH_CONTRAST.load(Ordering::Relaxed)
+}
 
-    println!("Switched to list: {}.{}", list_name, config.todo.list_extension);
+// Set from `tag_colors` before any output, keyed by lowercase tag
+static TAG_STYLES: OnceLock<BTreeMap<String, ListStyle>> = OnceLock::new();
 
-    // Ensure the new list file exists
-    let todo_path = expand_tilde(&config.todo.path);
-    let list_path = get_active_list_path(config, &todo_path);
-    ensure_active_list_exists(&list_path);
+fn tag_style(tag: &str) -> Option<&'static ListStyle> {
+    TAG_STYLES.get()?.get(&tag.trim_start_matches('#').to_lowercase())
 }
 
-fn add_todo(config: &Config, todo_text: String, target_list: Option<String>) {
-    let todo_path = expand_tilde(&config.todo.path);
+trait Faint {
+    // De-emphasized text, unless high contrast is on
+    fn faint(self) -> colored::ColoredString;
+}
 
-    // Determine which list to add to
-    let list_path = if let Some(list_name) = target_list {
-        // Extract just the name without extension if provided
-        let list_name = if list_name.contains('.') {
-            list_name.split('.').next().unwrap().to_string()
+impl<T: Colorize> Faint for T {
+    fn faint(self) -> colored::ColoredString {
+        if high_contrast() {
+            self.normal()
         } else {
-            list_name
-        };
-        let file_name = format!("{}.{}", list_name, config.todo.list_extension);
-        let path = todo_path.join(file_name);
+            self.dimmed()
+        }
+    }
+}
 
-        // Ensure the target list exists
-        ensure_active_list_exists(&path);
-        path
-    } else {
-        // Use active list
-        get_active_list_path(config, &todo_path)
-    };
+// A priority marker in its level's color: A red, B yellow, C cyan
+fn paint_priority(marker: &str, priority: char) -> colored::ColoredString {
+    match priority {
+        'A' => marker.bright_red().bold(),
+        'B' => marker.bright_yellow().bold(),
+        'C' => marker.bright_cyan().bold(),
+        _ => marker.bold(),
+    }
+}
 
-    // Format the todo item
-    let todo_line = format!("* [ ] {}\n", todo_text);
+// Tags with a color of their own are chips, dark text on the color; the
+// others are magenta
+fn paint_tag(tag: &str) -> colored::ColoredString {
+    if high_contrast() {
+        return tag.bright_magenta();
+    }
+    let Some(style) = tag_style(tag) else {
+        return tag.magenta();
+    };
+    if let Some((r, g, b)) = style.hex_color() {
+        return tag.black().on_truecolor(r, g, b);
+    }
+    match style.color.as_deref().map(str::parse::<colored::Color>) {
+        Some(Ok(color)) => tag.black().on_color(color),
+        _ => tag.magenta(),
+    }
+}
 
-    // Append to the file
-    match OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&list_path)
-    {
-        Ok(mut file) => {
-            if let Err(e) = file.write_all(todo_line.as_bytes()) {
-                eprintln!("Error writing to todo list: {}", e);
+// The text with its tags colored
+fn paint_words(text: &str) -> String {
+    text.split_inclusive(char::is_whitespace)
+        .map(|word| {
+            let bare = word.trim_end();
+            if tag_of(bare).is_some() {
+                format!("{}{}", paint_tag(bare), &word[bare.len()..])
             } else {
-                let list_name = list_path.file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("unknown");
-                println!("Added todo to {}: {}", list_name, todo_text);
+                word.to_string()
             }
-        }
-        Err(e) => {
-            eprintln!("Error opening todo list: {}", e);
-        }
-    }
+        })
+        .collect()
 }
 
-fn edit_list(config: &Config) {
-    let todo_path = expand_tilde(&config.todo.path);
-    let list_path = get_active_list_path(config, &todo_path);
+// The TUI's colors for priority markers, as for `todo list`
+fn priority_color(priority: char) -> Color {
+    match priority {
+        'A' => Color::LightRed,
+        'B' => Color::LightYellow,
+        'C' => Color::LightCyan,
+        _ => Color::White,
+    }
+}
 
-    // Ensure the list exists
-    ensure_active_list_exists(&list_path);
+// Text in a list's color, if it has one. High contrast leaves it in the
+// terminal's own color, which some list colors would be hard to read against.
+fn paint(text: &str, style: &ListStyle) -> colored::ColoredString {
+    if high_contrast() {
+        return text.normal();
+    }
+    if let Some((r, g, b)) = style.hex_color() {
+        return text.truecolor(r, g, b);
+    }
+    match style.color.as_deref().map(str::parse::<colored::Color>) {
+        Some(Ok(color)) => text.color(color),
+        _ => text.normal(),
+    }
+}
 
-    // Open in editor
-    let editor = &config.editor.command;
+// A list's color for the TUI, or `default` if it has none
+fn tui_color(style: &ListStyle, default: Color) -> Color {
+    if let Some((r, g, b)) = style.hex_color() {
+        return Color::Rgb(r, g, b);
+    }
+    let name = style.color.as_deref().map(|name| name.replace("bright ", "light").replace(' ', ""));
+    name.and_then(|name| name.parse().ok()).unwrap_or(default)
+}
 
-    match Command::new(editor)
-        .arg(&list_path)
-        .status()
-    {
-        Ok(status) => {
-            if !status.success() {
-                eprintln!("Editor exited with status: {}", status);
-            }
-        }
+fn init_project_list(config: &Config) {
+    let dir = match std::env::current_dir() {
+        Ok(dir) => dir,
         Err(e) => {
-            eprintln!("Failed to open editor '{}': {}", editor, e);
-            eprintln!("Make sure the editor command is correct in your config.");
+            eprintln!("Error finding the current directory: {}", e);
+            return;
         }
+    };
+    let path = dir.join(format!("{}.{}", PROJECT_LIST, config.todo.list_extension));
+    if path.exists() {
+        eprintln!("{} already exists", path.display());
+        return;
     }
-}
 
-fn display_todo_list(config: &Config, target_list: Option<String>) {
-    let todo_path = expand_tilde(&config.todo.path);
+    if let Err(e) = fs::write(&path, "") {
+        eprintln!("Error creating project list: {}", e);
+        return;
+    }
+    println!("Created {}", path.display());
+    if let Some(outer) = config.project_list.as_ref().filter(|outer| **outer != path) {
+        println!("It takes the place of {} in this directory", outer.display());
+    }
+}
 
-    // Determine which list to display
-    let (list_path, list_name) = if let Some(list_name) = target_list {
-        // Extract just the name without extension if provided
-        let list_name = if list_name.contains('.') {
-            list_name.split('.').next().unwrap().to_string()
-        } else {
-            list_name
-        };
-        let file_name = format!("{}.{}", list_name, config.todo.list_extension);
-        let path = todo_path.join(&file_name);
-        (path, file_name)
-    } else {
-        // Use active list
-        let path = get_active_list_path(config, &todo_path);
-        let file_name = format!("{}.{}", config.todo.active_list, config.todo.list_extension);
-        (path, file_name)
-    };
+fn write_badge(store: &dyn Storage, list_name: &str, badge: &BadgeConfig) -> io::Result<()> {
+    let items = store.load_todos(list_name)?;
+    fs::write(&badge.svg, progress_badge(&badge.label, &items))
+}
 
-    // Check if the list exists
-    if !list_path.exists() {
-        eprintln!("List '{}' does not exist", list_name);
+fn write_badge_command(config: &mut Config, target_list: Option<String>, svg: &Path, label: &str, keep_updated: bool) {
+    let (_, list_name) = resolve_list(config, target_list);
+    let store = open_storage(config);
+    if !store.exists(&list_name) {
+        eprintln!("Todo list '{}' does not exist", list_name);
         return;
     }
 
-    // Display header
-    println!("{}", format!("=== {} ===", list_name).bold().cyan());
-    println!();
+    // Absolute, so later changes made from any directory update the same file
+    let svg = std::env::current_dir().map(|dir| dir.join(svg)).unwrap_or_else(|_| svg.to_path_buf());
+    let badge = BadgeConfig { svg, label: label.to_string() };
+    if let Err(e) = write_badge(store.as_ref(), &list_name, &badge) {
+        eprintln!("Error writing badge: {}", e);
+        return;
+    }
+    println!("Wrote {}", badge.svg.display());
 
-    // Read and parse the file
-    match fs::File::open(&list_path) {
-        Ok(file) => {
-            let reader = BufReader::new(file);
-            let mut incomplete_count = 0;
-            let mut complete_count = 0;
-            let mut has_todos = false;
+    if keep_updated {
+        println!("It will be regenerated whenever {} changes", list_name);
+        config.badges.insert(normalize_list_name(&list_name), badge);
+        save_config(config);
+    }
+}
 
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    let trimmed = line.trim();
+// Regenerate kept-up-to-date badges of the lists an event touched
+fn enable_badges(config: &Config) {
+    if config.badges.is_empty() {
+        return;
+    }
 
-                    // Parse incomplete todos: * [ ] text
-                    if trimmed.starts_with("* [ ]") {
-                        has_todos = true;
-                        incomplete_count += 1;
-                        let todo_text = trimmed.strip_prefix("* [ ]").unwrap_or("").trim();
-                        println!("{} {}", "☐".bright_yellow(), todo_text);
-                    }
-                    // Parse complete todos: * [x] text
-                    else if trimmed.starts_with("* [x]") || trimmed.starts_with("* [X]") {
-                        has_todos = true;
-                        complete_count += 1;
-                        let todo_text = trimmed.strip_prefix("* [x]")
-                            .or_else(|| trimmed.strip_prefix("* [X]"))
-                            .unwrap_or("")
-                            .trim();
-                        println!("{} {}", "☑".green(), todo_text.strikethrough().dimmed());
-                    }
-                    // Handle other adoc formatting
-                    else if trimmed.starts_with("= ") {
-                        // Level 1 header
-                        let header = trimmed.strip_prefix("= ").unwrap_or(trimmed);
-                        println!("{}", header.bold().bright_cyan());
-                    }
-                    else if trimmed.starts_with("== ") {
-                        // Level 2 header
-                        let header = trimmed.strip_prefix("== ").unwrap_or(trimmed);
-                        println!("{}", header.bold().cyan());
-                    }
-                    else if trimmed.starts_with("=== ") {
-                        // Level 3 header
-                        let header = trimmed.strip_prefix("=== ").unwrap_or(trimmed);
-                        println!("{}", header.bold().blue());
-                    }
-                    else if trimmed.starts_with("* ") && !trimmed.starts_with("* [") {
-                        // Regular bullet point
-                        let text = trimmed.strip_prefix("* ").unwrap_or(trimmed);
-                        println!("  {} {}", "•".bright_white(), text);
-                    }
-                    else if !trimmed.is_empty() {
-                        // Regular text
-                        println!("{}", trimmed);
-                    }
-                    else {
-                        // Empty line
-                        println!();
-                    }
+    let store = open_storage(config);
+    let badges = config.badges.clone();
+    EventBus::global().subscribe(move |event| {
+        let lists = match event {
+            TodoEvent::ItemMoved { from, to, .. } | TodoEvent::ListsMerged { from, to, .. } => vec![from.as_str(), to],
+            TodoEvent::ListRenamed { new, .. } => vec![new.as_str()],
+            TodoEvent::ListDeleted { .. } | TodoEvent::ListSwitched { .. } => Vec::new(),
+            _ => vec![event.list()],
+        };
+        for list in lists {
+            let name = normalize_list_name(list);
+            if let Some(badge) = badges.get(&name) {
+                if let Err(e) = write_badge(store.as_ref(), &name, badge) {
+                    eprintln!("Warning: could not update badge {}: {}", badge.svg.display(), e);
                 }
             }
+        }
+    });
+}
 
-            if !has_todos {
-                println!("{}", "No todos found.".dimmed());
-            } else {
-                println!();
-                println!(
-                    "{} {} incomplete, {} complete",
-                    "Summary:".bold(),
-                    incomplete_count.to_string().bright_yellow(),
-                    complete_count.to_string().green()
-                );
-            }
+// The Lua plugins in the scripts directory, loaded once at startup
+static PLUGINS: OnceLock<Plugins> = OnceLock::new();
+
+fn scripts_dir() -> PathBuf {
+    get_config_path().with_file_name("scripts")
+}
+
+fn plugins() -> &'static Plugins {
+    PLUGINS.get_or_init(|| {
+        let (plugins, errors) = plugin::load(&scripts_dir());
+        for error in errors {
+            eprintln!("{} {}", "Warning:".yellow(), error);
+        }
+        plugins
+    })
+}
+
+// Nudges for after a todo is added or completed from the command line, for
+// whoever's at the terminal; scripts reading the output don't get them. A
+// command that completes several todos at once nudges once, about the first.
+static NUDGE: OnceLock<String> = OnceLock::new();
+
+fn enable_nudges(config: &Config, command: &Option<Commands>) {
+    let cli_change = matches!(command, Some(Commands::Add { .. } | Commands::Toggle { .. }));
+    if config.output.nudges.is_empty() || !cli_change || !io::stdout().is_terminal() {
+        return;
+    }
+
+    let store = open_storage(config);
+    let nudges = config.output.nudges.clone();
+    EventBus::global().subscribe(move |event| {
+        let changed = matches!(event, TodoEvent::ItemAdded { .. } | TodoEvent::ItemsAdded { .. } | TodoEvent::ItemCompleted { .. });
+        if !changed || NUDGE.get().is_some() {
+            return;
+        }
+        let Ok(items) = store.load(&normalize_list_name(event.list())) else {
+            return;
+        };
+        let done_days: BTreeSet<NaiveDate> = store
+            .list_lists()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|name| store.load(name).ok())
+            .flatten()
+            .filter(|item| item.completed)
+            .filter_map(|item| item.done())
+            .collect();
+        let today = Local::now().date_naive();
+        let lines: Vec<String> =
+            nudges.iter().filter_map(|&kind| nudge(kind, event.list(), &items, &done_days, today)).collect();
+        let _ = NUDGE.set(lines.join("  ·  "));
+    });
+}
+
+// The script registers todo itself as the completer, so list names are
+// completed from whatever lists exist when Tab is pressed
+fn print_completions(shell: CompletionShell) {
+    let completer: &dyn EnvCompleter = match shell {
+        CompletionShell::Bash => &env::Bash,
+        CompletionShell::Zsh => &env::Zsh,
+        CompletionShell::Fish => &env::Fish,
+        CompletionShell::Powershell => &env::Powershell,
+        CompletionShell::Elvish => &env::Elvish,
+    };
+    let bin = Cli::command().get_name().to_string();
+    if let Err(e) = completer.write_registration("COMPLETE", &bin, &bin, &bin, &mut io::stdout()) {
+        eprintln!("Error writing completions: {}", e);
+    }
+}
+
+// Completion candidates for arguments that name a list. Runs on every Tab,
+// so it stays quiet and never creates or repairs anything.
+fn list_names() -> Vec<CompletionCandidate> {
+    let config = if get_config_path().exists() {
+        match ensure_config_exists() {
+            Ok(config) => config,
+            Err(_) => return Vec::new(),
         }
+    } else {
+        Config::default()
+    };
+    open_storage(&config)
+        .list_lists()
+        .unwrap_or_default()
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+// Plain output, for scripts; unknown keys exit non-zero
+fn show_config_value(config: &Config, key: &str) {
+    match get_config_value(config, key) {
+        Ok(toml::Value::String(value)) => println!("{}", value),
+        Ok(toml::Value::Table(table)) => print!("{}", toml::to_string(&table).unwrap_or_default()),
+        Ok(value) => println!("{}", value),
         Err(e) => {
-            eprintln!("Error reading todo list: {}", e);
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
     }
 }
 
-fn show_fireworks() -> io::Result<()> {
-    let mut stdout = io::stdout();
+fn set_config(config: &Config, key: &str, value: &str) {
+    match set_config_value(config, key, value) {
+        Ok(updated) => {
+            save_config(&updated);
+            println!("Set {} to {}", key, value);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
 
-    // Clear screen
-    execute!(stdout, Clear(ClearType::All), cursor::Hide)?;
+fn use_list(config: &mut Config, list_name: String) {
+    // Extract just the name without extension if provided
+    let list_name = normalize_list_name(&list_name);
+    if let Err(e) = check_list_name(config, &list_name) {
+        eprintln!("{}", e);
+        return;
+    }
 
-    // Fireworks explosion patterns
-    let explosion_frames = vec![
-        vec!["        *        ", "       ***       ", "      *****      ", "     *******     ", "    *********    "],
-        vec!["    *       *    ", "   **       **   ", "  ***       ***  ", " ****       **** ", "*****       *****"],
-        vec!["  *           *  ", " * *         * * ", "*   *       *   *", " *   *     *   * ", "  *   *   *   *  "],
-        vec![" *             * ", "*               *", "                 ", "*               *", " *             * "],
-    ];
+    let previous = std::mem::replace(&mut config.todo.active_list, list_name.clone());
+    save_config(config);
+    let extension = &config.todo.list_extension;
+    EventBus::global().emit(&TodoEvent::ListSwitched {
+        from: format!("{}.{}", previous, extension),
+        to: format!("{}.{}", list_name, extension),
+    });
 
-    let colors = vec![
-        "\x1b[91m", // Bright red
-        "\x1b[93m", // Bright yellow
-        "\x1b[92m", // Bright green
-        "\x1b[96m", // Bright cyan
-        "\x1b[95m", // Bright magenta
-    ];
-    let reset = "\x1b[0m";
+    println!("Switched to list: {}.{}", list_name, config.todo.list_extension);
+    if let Some(project) = &config.project_list {
+        println!("In this directory, {} is still used by default", project.display());
+    }
 
-    // Show celebration message
-    execute!(stdout, cursor::MoveTo(0, 0))?;
-    let msg = "🎉  ALL TODOS COMPLETE!  🎉";
-    let padding = (80_u16.saturating_sub(msg.len() as u16)) / 2;
-    execute!(stdout, cursor::MoveTo(padding, 2))?;
-    write!(stdout, "{}", msg.bright_green().bold())?;
-    stdout.flush()?;
+    // Ensure the new list file exists
+    let todo_path = expand_tilde(&config.todo.path);
+    let list_path = get_active_list_path(config, &todo_path);
+    ensure_active_list_exists(&list_path);
+}
 
-    // Animate fireworks at different positions
-    let positions = vec![(10, 8), (50, 6), (30, 10), (60, 12), (20, 14)];
+fn rename_list(config: &mut Config, old: String, new: String) {
+    let todo_path = expand_tilde(&config.todo.path);
+    let old_name = normalize_list_name(&old);
+    let new_name = normalize_list_name(&new);
+    let old_path = get_list_path(config, &todo_path, &old_name);
+    let new_path = get_list_path(config, &todo_path, &new_name);
 
-    for round in 0..3 {
-        for (frame_idx, frame) in explosion_frames.iter().enumerate() {
-            execute!(stdout, cursor::MoveTo(0, 4))?;
+    if new_name.is_empty() {
+        eprintln!("Invalid list name: '{}'", new);
+        return;
+    }
 
-            // Draw all fireworks
-            for (pos_idx, &(x, y)) in positions.iter().enumerate() {
-                let color = colors[pos_idx % colors.len()];
+    if !old_path.exists() {
+        eprintln!("List '{}.{}' does not exist", old_name, config.todo.list_extension);
+        return;
+    }
 
-                // Only show this firework if it's time
-                if (round * positions.len() + pos_idx) / 2 <= frame_idx {
-                    for (i, line) in frame.iter().enumerate() {
-                        execute!(stdout, cursor::MoveTo(x, y + i as u16))?;
-                        write!(stdout, "{}{}{}", color, line, reset)?;
-                    }
-                }
-            }
+    if new_path.exists() {
+        eprintln!("List '{}.{}' already exists", new_name, config.todo.list_extension);
+        return;
+    }
 
-            stdout.flush()?;
-            thread::sleep(Duration::from_millis(150));
+    let config_path = get_config_path();
+    let recorder = Recorder::new(&[&old_path, &new_path, &config_path]);
+    if let Err(e) = fs::rename(&old_path, &new_path) {
+        eprintln!("Error renaming list: {}", e);
+        return;
+    }
 
-            // Clear fireworks for next frame
-            for &(x, y) in &positions {
-                for i in 0..5 {
-                    execute!(stdout, cursor::MoveTo(x, y + i))?;
-                    write!(stdout, "                 ")?;
-                }
-            }
+    // Carry the list's backups over to the new name
+    let old_backups = backup_dir(&old_path);
+    let new_backups = backup_dir(&new_path);
+    if old_backups.exists() && !new_backups.exists() {
+        if let Err(e) = fs::rename(&old_backups, &new_backups) {
+            eprintln!("Error moving backups: {}", e);
         }
     }
 
-    // Final message
-    execute!(stdout, cursor::MoveTo(0, 20))?;
-    let final_msg = "Press any key to continue...";
-    let padding = (80_u16.saturating_sub(final_msg.len() as u16)) / 2;
-    execute!(stdout, cursor::MoveTo(padding, 20))?;
-    write!(stdout, "{}", final_msg.dimmed())?;
-    stdout.flush()?;
+    println!(
+        "Renamed list: {}.{} -> {}.{}",
+        old_name, config.todo.list_extension, new_name, config.todo.list_extension
+    );
 
-    // Wait for key press
-    loop {
-        if let Event::Key(_) = event::read()? {
-            break;
-        }
+    // Keep the active list pointing at the renamed file
+    if config.todo.active_list == old_name {
+        config.todo.active_list = new_name.clone();
+        save_config(config);
     }
 
-    execute!(stdout, Clear(ClearType::All), cursor::Show)?;
-    Ok(())
+    let old_file = format!("{}.{}", old_name, config.todo.list_extension);
+    let new_file = format!("{}.{}", new_name, config.todo.list_extension);
+    record(recorder, TodoEvent::ListRenamed { old: old_file, new: new_file });
 }
 
-fn ui(f: &mut Frame, app: &App) {
-    let chunks = if app.edit_mode {
-        Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(3),  // Title
-                Constraint::Min(0),     // Content
-                Constraint::Length(3),  // Input field
-                Constraint::Length(3),  // Status bar
-            ])
-            .split(f.area())
+// Ask a yes/no question on the terminal, defaulting to no
+fn confirm(prompt: &str) -> bool {
+    print!("{} [y/N] ", prompt);
+    if io::stdout().flush().is_err() {
+        return false;
+    }
+
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+// Where a list goes in the trash, without clobbering an earlier trashed list
+// with the same name
+fn trash_path(trash_dir: &Path, list_name: &str, extension: &str) -> PathBuf {
+    let path = trash_dir.join(format!("{}.{}", list_name, extension));
+    if !path.exists() {
+        return path;
+    }
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    trash_dir.join(format!("{}-{}.{}", list_name, stamp, extension))
+}
+
+// The trash of the directory a list is in, workspace lists' in their own
+fn list_trash_dir(config: &Config, list_path: &Path) -> PathBuf {
+    list_root(config, list_path).unwrap_or_else(|| expand_tilde(&config.todo.path)).join(TRASH_DIR)
+}
+
+fn list_stem(list_path: &Path) -> String {
+    list_path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn delete_list(config: &Config, name: String, force: bool, trash: bool) {
+    let list_name = normalize_list_name(&name);
+    let file_name = format!("{}.{}", list_name, config.todo.list_extension);
+    if let Err(e) = check_list_name(config, &name) {
+        eprintln!("{}", e);
+        return;
+    }
+    let list_path = store_path(config, &list_name);
+    if list_root(config, &list_path).is_none() {
+        eprintln!("'{}' isn't a list in the todo directory or the workspace", name);
+        return;
+    }
+
+    if !open_storage(config).exists(&list_name) {
+        eprintln!("List '{}' does not exist", file_name);
+        return;
+    }
+
+    if list_name == config.todo.active_list {
+        eprintln!("Cannot delete the active list '{}'", file_name);
+        eprintln!("Switch to another list first with 'todo use <list>'.");
+        return;
+    }
+
+    if !force {
+        if !io::stdin().is_terminal() {
+            eprintln!("Refusing to delete '{}' without confirmation. Use --force.", file_name);
+            return;
+        }
+        if !confirm(&format!("Delete list '{}'?", file_name)) {
+            println!("Aborted.");
+            return;
+        }
+    }
+
+    if trash {
+        let trash_dir = list_trash_dir(config, &list_path);
+        if let Err(e) = fs::create_dir_all(&trash_dir) {
+            eprintln!("Error creating trash directory: {}", e);
+            return;
+        }
+
+        let trash_path = trash_path(&trash_dir, &list_stem(&list_path), &config.todo.list_extension);
+        let recorder = Recorder::new(&[&list_path, &trash_path]);
+        match fs::rename(&list_path, &trash_path) {
+            Ok(_) => {
+                println!("Moved {} to {}", file_name, trash_path.display());
+                record(recorder, TodoEvent::ListDeleted { list: file_name.clone() });
+            }
+            Err(e) => eprintln!("Error moving list to trash: {}", e),
+        }
     } else {
-        Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(3),  // Title
-                Constraint::Min(0),     // Content
-                Constraint::Length(3),  // Status bar
-            ])
-            .split(f.area())
-    };
+        let recorder = Recorder::new(&[&list_path]);
+        match fs::remove_file(&list_path) {
+            Ok(_) => {
+                println!("Deleted list: {}", file_name);
+                record(recorder, TodoEvent::ListDeleted { list: file_name.clone() });
+            }
+            Err(e) => eprintln!("Error deleting list: {}", e),
+        }
+    }
+}
 
-    // Title
-    let title = Paragraph::new(format!("  {} ", app.list_name))
-        .style(
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD)
-        )
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan))
-        );
-    f.render_widget(title, chunks[0]);
+// Journal a finished mutation and announce it; failing to journal shouldn't
+// fail the command
+fn record(recorder: Recorder, event: TodoEvent) {
+    match recorder.finish(&event) {
+        Ok(changes) => log_sync(&changes),
+        Err(e) => eprintln!("Warning: could not write journal: {}", e),
+    }
+    emit(event);
+}
 
-    // Todo list
-    let items: Vec<ListItem> = app
-        .items
-        .iter()
-        .enumerate()
-        .map(|(i, todo_item)| {
-            let is_pending_delete = app.pending_deletes.contains(&i);
+// Events of a running batch, held back until its lists are saved
+static BATCH_EVENTS: Mutex<Option<Vec<TodoEvent>>> = Mutex::new(None);
 
-            let content = match todo_item.line_type {
-                LineType::Todo => {
-                    if is_pending_delete {
-                        Line::from(vec![
-                            Span::styled("✗ ", Style::default().fg(Color::Red)),
-                            Span::styled(
-                                &todo_item.text,
-                                Style::default()
-                                    .fg(Color::DarkGray)
-                                    .add_modifier(Modifier::CROSSED_OUT | Modifier::DIM),
-                            ),
-                        ])
-                    } else if todo_item.completed {
-                        Line::from(vec![
-                            Span::styled("☑ ", Style::default().fg(Color::Green)),
-                            Span::styled(
-                                &todo_item.text,
-                                Style::default()
-                                    .fg(Color::DarkGray)
-                                    .add_modifier(Modifier::CROSSED_OUT),
+fn emit(event: TodoEvent) {
+    let mut batch = BATCH_EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    match batch.as_mut() {
+        Some(events) => events.push(event),
+        None => EventBus::global().emit(&event),
+    }
+}
+
+// What a batch's commands print, held back the same way, so that none says
+// it changed a list that then wasn't saved
+static BATCH_OUTPUT: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+fn say(line: String) {
+    let mut batch = BATCH_OUTPUT.lock().unwrap_or_else(|e| e.into_inner());
+    match batch.as_mut() {
+        Some(lines) => lines.push(line),
+        None => println!("{}", line),
+    }
+}
+
+// println! for the output of commands a batch can hold
+macro_rules! say {
+    ($($arg:tt)*) => {
+        say(format!($($arg)*))
+    };
+}
+
+// Add changes to this device's sync log, when sync.merge is on
+fn log_sync(changes: &[FileChange]) {
+    if let Err(e) = sync::log_changes(changes) {
+        eprintln!("Warning: could not write sync log: {}", e);
+    }
+}
+
+// With sync.merge on, log this device's changes and merge in the other
+// devices' before running any command
+fn enable_sync(config: &Config) {
+    if !config.sync.merge {
+        return;
+    }
+    let device = match device_id() {
+        Ok(device) => device,
+        Err(e) => {
+            eprintln!("Warning: sync disabled, could not save a device id: {}", e);
+            return;
+        }
+    };
+
+    let todo_path = expand_tilde(&config.todo.path);
+    let extension = Some(config.todo.list_extension.as_str());
+    let lists = list_files(&todo_path).unwrap_or_default();
+    for list_path in lists.iter().filter(|path| path.extension().and_then(|e| e.to_str()) == extension) {
+        let name = list_path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown");
+        match sync::merge_list(list_path, &device, &config.backup) {
+            Ok(merged) => {
+                if merged.applied > 0 {
+                    eprintln!("Merged {} changes from other devices into {}", merged.applied, name);
+                }
+                for copy in &merged.conflicts {
+                    eprintln!(
+                        "Warning: {} is a conflict copy of {}; changes in it made without todo aren't merged, so compare the two and delete it",
+                        copy.display(),
+                        name
+                    );
+                }
+            }
+            Err(e) => eprintln!("Warning: could not merge {}: {}", list_path.display(), e),
+        }
+    }
+    sync::enable(todo_path, device);
+}
+
+// Where `todo add` puts new todos
+enum AddPosition {
+    End,
+    Top,
+    After(TodoRef),
+    // As the last subtask of a todo
+    Under(TodoRef),
+    Section(String),
+}
+
+// A todo's text with a `due:` given in words turned into a date
+fn resolve_due(text: String) -> String {
+    let mut item = TodoItem { text, completed: false, line_type: LineType::Todo, depth: 0 };
+    if let Err(due) = item.resolve_due(Local::now().date_naive()) {
+        eprintln!("Warning: couldn't read due date '{}'; kept as written", due);
+    }
+    item.text
+}
+
+// All the todos go in with a single write
+fn add_todos(config: &Config, todos: Vec<String>, target_list: Option<String>, position: AddPosition) {
+    let todos: Vec<String> = match todos
+        .iter()
+        .map(|text| apply_rules(&config.rules, &config.expand_emoji(text)).map(resolve_due))
+        .collect()
+    {
+        Ok(todos) => todos,
+        Err(e) => {
+            eprintln!("Error in config rules: {}", e);
+            return;
+        }
+    };
+    let todo_path = expand_tilde(&config.todo.path);
+
+    // Determine which list to add to
+    let list_path = if let Some(list_name) = &target_list {
+        if let Err(e) = check_list_name(config, list_name) {
+            eprintln!("{}", e);
+            return;
+        }
+        let path = get_list_path(config, &todo_path, list_name);
+
+        // Ensure the target list exists
+        ensure_active_list_exists(&path);
+        path
+    } else {
+        // Use active list
+        get_active_list_path(config, &todo_path)
+    };
+
+    // Stamped with today and who added them, which the messages leave out
+    let today = Local::now().date_naive();
+    let user = config.user_name();
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let recorder = Recorder::new(&[&list_path]);
+    let added = store.update(&list, |lines| {
+        // How deep the new todos are nested: beside the todo they go after,
+        // or under the one they're subtasks of
+        let mut depth = 0;
+        let insert_at = match &position {
+            AddPosition::End => lines.len(),
+            AddPosition::Top => lines
+                .iter()
+                .position(|line| matches!(parse_line(line).line_type, LineType::Todo))
+                .unwrap_or(lines.len()),
+            AddPosition::After(index) | AddPosition::Under(index) => match find_todo(lines, index) {
+                Some(pos) => {
+                    depth = parse_line(&lines[pos]).depth + matches!(position, AddPosition::Under(_)) as usize;
+                    todo_end(lines, pos)
+                }
+                None => return Err(format!("Todo #{} does not exist", index)),
+            },
+            AddPosition::Section(header) => match find_header(lines, header) {
+                Some(pos) => section_end(lines, pos),
+                None => {
+                    if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                        lines.push(String::new());
+                    }
+                    lines.push(format!("== {}", header.trim()));
+                    lines.len()
+                }
+            },
+        };
+        let mut items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+        // Todos put in a section by name or next to another todo take its
+        // header's defaults; ones just put at the top or end don't
+        let defaults: Vec<String> = match position {
+            AddPosition::End | AddPosition::Top => Vec::new(),
+            _ => defaults_at(&items, insert_at).into_iter().map(str::to_string).collect(),
+        };
+        let defaults: Vec<&str> = defaults.iter().map(String::as_str).collect();
+        let mut shown = Vec::new();
+        let stamped: Vec<String> = todos
+            .iter()
+            .map(|text| {
+                let mut item = TodoItem { text: text.clone(), completed: false, line_type: LineType::Todo, depth };
+                if !defaults.is_empty() {
+                    item.apply_defaults(&defaults);
+                    let _ = item.resolve_due(today);
+                }
+                shown.push(item.text.clone());
+                item.stamp_added(today, user.as_deref());
+                item.stamp_id(&items);
+                items.push(item.clone());
+                item.text
+            })
+            .collect();
+        let new_lines = stamped.iter().map(|text| format_line(&TodoItem { text: text.clone(), completed: false, line_type: LineType::Todo, depth }));
+        lines.splice(insert_at..insert_at, new_lines);
+        Ok((shown, stamped))
+    });
+
+    match added {
+        Ok(Ok((shown, stamped))) => {
+            let list_name = format!("{}.{}", list, config.todo.list_extension);
+            for text in &shown {
+                say!("Added todo to {}: {}", list_name, text);
+            }
+            warn_misspellings(config, &todos.iter().map(String::as_str).collect::<Vec<_>>());
+            let event = match <[String; 1]>::try_from(stamped) {
+                Ok([text]) => TodoEvent::ItemAdded { list: list_name, text },
+                Err(items) => TodoEvent::ItemsAdded { list: list_name, items },
+            };
+            record(recorder, event);
+        }
+        Ok(Err(e)) => eprintln!("{}", e),
+        Err(e) => eprintln!("Error writing to todo list: {}", e),
+    }
+}
+
+// Todos are saved whatever the spell checker says, so both misspellings and
+// a checker that can't be run are only warnings
+fn warn_misspellings(config: &Config, texts: &[&str]) {
+    if !config.spell.enabled {
+        return;
+    }
+    let results = match spell::check(&config.spell, texts) {
+        Ok(results) => results,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            eprintln!("Warning: spell checker '{}' not found; install it or set spell.command", config.spell.command);
+            return;
+        }
+        Err(e) => {
+            eprintln!("Warning: could not spell check with {}: {}", config.spell.command, e);
+            return;
+        }
+    };
+    for (text, misspellings) in texts.iter().zip(results) {
+        for misspelling in misspellings {
+            let suggestions = match misspelling.suggestions.as_slice() {
+                [] => String::new(),
+                suggestions => format!(" (did you mean {}?)", suggestions.iter().take(3).cloned().collect::<Vec<_>>().join(", ")),
+            };
+            eprintln!("{} {} in \"{}\"{}", "Possible misspelling:".yellow(), misspelling.word, text, suggestions);
+        }
+    }
+}
+
+fn move_todo(config: &Config, index: &TodoRef, target_list: String, source_list: Option<String>) {
+    let (source_path, source_name) = resolve_list(config, source_list);
+    let (target_path, target_name) = resolve_list(config, Some(target_list));
+
+    if source_path == target_path {
+        eprintln!("Source and target list are the same");
+        return;
+    }
+
+    let store = open_storage(config);
+    if !store.exists(&source_name) {
+        eprintln!("List '{}' does not exist", source_name);
+        return;
+    }
+
+    let recorder = Recorder::new(&[&source_path, &target_path]);
+    let moved = store.update(&source_name, |source_lines| {
+        let Some(pos) = find_todo(source_lines, index) else {
+            return Ok(None);
+        };
+        // The note and subtasks move along, and a subtask becomes a todo of its own
+        let end = todo_end(source_lines, pos);
+        let moved: Vec<TodoItem> = source_lines[pos..end].iter().map(|line| parse_line(line)).collect();
+        let item = moved[0].clone();
+        // The target is written first, as its update ends, so a failure
+        // can't lose the todo
+        store.update(&target_name, |target_lines| {
+            target_lines.extend(moved.into_iter().map(|moved| format_line(&TodoItem { depth: moved.depth.saturating_sub(item.depth), ..moved })));
+        })?;
+        source_lines.drain(pos..end);
+        Ok(Some(item))
+    });
+
+    match moved.and_then(|moved| moved) {
+        Ok(Some(item)) => {
+            say!("Moved todo from {} to {}: {}", source_name, target_name, item.text_without_stamps());
+            record(recorder, TodoEvent::ItemMoved { from: source_name, to: target_name, text: item.text });
+        }
+        Ok(None) => eprintln!("No todo #{} in {}", index, source_name),
+        Err(e) => eprintln!("Error writing to todo list: {}", e),
+    }
+}
+
+// Split a list's lines into the todos `take` picks, with their notes and
+// subtasks, and the rest. Subtasks go where their todo does.
+fn take_todos(lines: Vec<String>, take: impl Fn(&TodoItem) -> bool) -> (Vec<String>, Vec<String>) {
+    let items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+    let mut taken = vec![false; items.len()];
+    for (i, item) in items.iter().enumerate() {
+        taken[i] = match parent_of(&items, i) {
+            Some(parent) => taken[parent],
+            None => matches!(item.line_type, LineType::Todo) && take(item),
+        };
+    }
+    let (taken, rest): (Vec<_>, Vec<_>) = lines.into_iter().zip(taken).partition(|(_, taken)| *taken);
+    (taken.into_iter().map(|(line, _)| line).collect(), rest.into_iter().map(|(line, _)| line).collect())
+}
+
+fn todo_texts(lines: &[String]) -> Vec<String> {
+    lines.iter().map(|line| parse_line(line)).filter(|item| matches!(item.line_type, LineType::Todo)).map(|item| item.text).collect()
+}
+
+fn merge_lists(config: &Config, source: String, target: String, section: bool, delete_source: bool) {
+    let (source_path, source_name) = resolve_list(config, Some(source.clone()));
+    let (target_path, target_name) = resolve_list(config, Some(target));
+    let source_stem = normalize_list_name(&source);
+
+    if source_path == target_path {
+        eprintln!("Source and target list are the same");
+        return;
+    }
+    let store = open_storage(config);
+    if !store.exists(&source_name) {
+        eprintln!("List '{}' does not exist", source_name);
+        return;
+    }
+    if delete_source && source_stem == config.todo.active_list {
+        eprintln!("Cannot delete the active list '{}'", source_name);
+        eprintln!("Switch to another list first with 'todo use <list>'.");
+        return;
+    }
+
+    let trash_dir = list_trash_dir(config, &source_path);
+    let trashed = trash_path(&trash_dir, &list_stem(&source_path), &config.todo.list_extension);
+    let recorder = Recorder::new(&[&source_path, &target_path, &trashed]);
+
+    let result = store.update(&source_name, |source_lines| -> io::Result<Vec<String>> {
+        let (open, rest) = take_todos(source_lines.clone(), |item| !item.completed);
+        if open.is_empty() && !delete_source {
+            return Ok(Vec::new());
+        }
+
+        // The target is written first, as its update ends, so a failure
+        // can't lose todos
+        store.update(&target_name, |target_lines| {
+            if section && !open.is_empty() {
+                if target_lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                    target_lines.push(String::new());
+                }
+                target_lines.push(format!("== {}", source_stem));
+            }
+            target_lines.extend(open.iter().map(|line| format_line(&parse_line(line))));
+        })?;
+        if delete_source {
+            fs::create_dir_all(&trash_dir)?;
+            fs::rename(&source_path, &trashed)?;
+        } else {
+            *source_lines = rest;
+        }
+        Ok(todo_texts(&open))
+    });
+    let result = result.and_then(|merged| merged);
+
+    match result {
+        Ok(merged) if merged.is_empty() && !delete_source => println!("No open todos to merge in {}", source_name),
+        Ok(merged) => {
+            println!("Merged {} open todos from {} into {}", merged.len(), source_name, target_name);
+            if delete_source {
+                println!("Moved {} to {}", source_name, trashed.display());
+            }
+            record(recorder, TodoEvent::ListsMerged { from: source_name, to: target_name, items: merged });
+        }
+        Err(e) => eprintln!("Error merging lists: {}", e),
+    }
+}
+
+// Color every match of the pattern within a line of text
+fn highlight_matches(text: &str, pattern: &Regex) -> String {
+    let mut result = String::new();
+    let mut last = 0;
+    for found in pattern.find_iter(text) {
+        result.push_str(&text[last..found.start()]);
+        result.push_str(&found.as_str().bold().bright_red().to_string());
+        last = found.end();
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+// In JSON, only matching todos are reported
+fn search_lists(config: &Config, query: Option<String>, use_regex: bool, tag: Option<&str>, json: bool) {
+    let source = match &query {
+        Some(query) if use_regex => query.clone(),
+        Some(query) => regex::escape(query),
+        None => String::new(),
+    };
+    let pattern = match RegexBuilder::new(&source).case_insensitive(true).build() {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            eprintln!("Invalid regex '{}': {}", source, e);
+            return;
+        }
+    };
+    // With only a tag, every todo that has it matches
+    let matches = |item: &TodoItem| match tag {
+        Some(tag) => matches!(item.line_type, LineType::Todo) && item.has_tag(tag) && pattern.is_match(&item.text),
+        None => pattern.is_match(&item.text),
+    };
+
+    let files = match workspace_files(config) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Error reading todo directory: {}", e);
+            return;
+        }
+    };
+
+    let store = open_storage(config);
+    let mut total = 0;
+    let mut found = Vec::new();
+    for (file_name, _) in files {
+        // Skip a list that can't be read
+        let Ok(lines) = store.load_list(&file_name) else {
+            continue;
+        };
+        if json {
+            let name = normalize_list_name(&file_name);
+            let items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+            let todos = items.iter().filter(|item| matches!(item.line_type, LineType::Todo));
+            let exported = export_items(&name, &items).into_iter().zip(todos);
+            found.extend(exported.filter(|(_, item)| matches(item)).map(|(exported, _)| exported));
+            continue;
+        }
+        let matches: Vec<(usize, TodoItem)> = lines
+            .iter()
+            .map(|line| parse_line(line))
+            .enumerate()
+            .filter(|(_, item)| matches(item))
+            .collect();
+        if matches.is_empty() {
+            continue;
+        }
+
+        println!("{}", file_name.bold().cyan());
+        for (line_idx, item) in &matches {
+            let line_number = format!("{:>4}:", line_idx + 1).faint();
+            let shown = item.text_without_stamps();
+            let text = if query.is_some() { highlight_matches(&shown, &pattern) } else { paint_words(&shown) };
+            match item.line_type {
+                LineType::Todo if item.completed => println!("{} {} {}", line_number, "☑".green(), text),
+                LineType::Todo => println!("{} {} {}", line_number, "☐".bright_yellow(), text),
+                LineType::Bullet => println!("{} {} {}", line_number, "•".bright_white(), text),
+                _ => println!("{} {}", line_number, text),
+            }
+        }
+        println!();
+        total += matches.len();
+    }
+
+    if json {
+        print_json(&found);
+    } else if total == 0 {
+        let what = match (&query, tag) {
+            (Some(query), _) => format!("'{}'", query),
+            (None, Some(tag)) => format!("#{}", tag.trim_start_matches('#')),
+            (None, None) => String::new(),
+        };
+        println!("{}", format!("No matches for {}.", what).faint());
+    }
+}
+
+#[derive(Serialize)]
+struct TagCount {
+    tag: String,
+    todos: usize,
+    open: usize,
+}
+
+// Tags of the todos in a list, or in all of them, most used first. Tags
+// differing only in case count as one, spelled as first seen.
+fn tag_counts(config: &Config, target_list: Option<String>) -> io::Result<Vec<TagCount>> {
+    let files = match target_list {
+        Some(list) => vec![resolve_list(config, Some(list)).1],
+        None => workspace_files(config)?.into_iter().map(|(file, _)| file).collect(),
+    };
+    let store = open_storage(config);
+    let mut counts: BTreeMap<String, TagCount> = BTreeMap::new();
+    for file in files {
+        let Ok(items) = store.load_todos(&file) else {
+            continue;
+        };
+        for item in items.iter().filter(|item| matches!(item.line_type, LineType::Todo)) {
+            let mut seen = Vec::new();
+            for tag in item.tags() {
+                let key = tag.to_lowercase();
+                if seen.contains(&key) {
+                    continue;
+                }
+                let count = counts.entry(key.clone()).or_insert_with(|| TagCount { tag: tag.to_string(), todos: 0, open: 0 });
+                count.todos += 1;
+                count.open += !item.completed as usize;
+                seen.push(key);
+            }
+        }
+    }
+    let mut counts: Vec<TagCount> = counts.into_values().collect();
+    counts.sort_by(|a, b| b.todos.cmp(&a.todos).then_with(|| a.tag.to_lowercase().cmp(&b.tag.to_lowercase())));
+    Ok(counts)
+}
+
+fn list_tags(config: &Config, target_list: Option<String>, json: bool) {
+    if let Some(list) = &target_list {
+        let (list_path, list_name) = resolve_list(config, Some(list.clone()));
+        if !list_path.exists() {
+            eprintln!("List '{}' does not exist", list_name);
+            return;
+        }
+    }
+    let counts = match tag_counts(config, target_list) {
+        Ok(counts) => counts,
+        Err(e) => {
+            eprintln!("Error reading todo directory: {}", e);
+            return;
+        }
+    };
+    if json {
+        print_json(&counts);
+        return;
+    }
+    if counts.is_empty() {
+        println!("{}", "No tags yet. Add one to a todo with #name.".faint());
+        return;
+    }
+
+    let width = counts.iter().map(|count| count.tag.chars().count() + 1).max().unwrap_or(0);
+    for count in &counts {
+        let tag = format!("#{}", count.tag);
+        let padding = " ".repeat(width - tag.chars().count());
+        let open = format!("({} open)", count.open);
+        println!("{}{}  {:>3}  {}", paint_tag(&tag), padding, count.todos, open.faint());
+    }
+}
+
+fn tag_names() -> Vec<CompletionCandidate> {
+    let config = if get_config_path().exists() {
+        match ensure_config_exists() {
+            Ok(config) => config,
+            Err(_) => return Vec::new(),
+        }
+    } else {
+        Config::default()
+    };
+    tag_counts(&config, None)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|count| CompletionCandidate::new(count.tag))
+        .collect()
+}
+
+fn get_todo(config: &Config, index: &TodoRef, field: GetField, target_list: Option<String>) {
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+
+    let lines = match store.load_list(&list) {
+        Ok(lines) => lines,
+        Err(e) => {
+            eprintln!("Error reading todo list '{}': {}", list_name, e);
+            std::process::exit(1);
+        }
+    };
+
+    let Some(pos) = find_todo(&lines, index) else {
+        eprintln!("No todo #{} in {}", index, list_name);
+        std::process::exit(1);
+    };
+
+    let item = parse_line(&lines[pos]);
+    match field {
+        GetField::Text => say!("{}", item.text),
+        GetField::Due => {
+            if let Some(due) = item.due() {
+                say!("{}", due);
+            }
+        }
+        GetField::Tags => {
+            for tag in item.tags() {
+                say!("{}", tag);
+            }
+        }
+        GetField::Status => say!("{}", if item.completed { "done" } else { "pending" }),
+        GetField::Note => {
+            let items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+            if let Some(note) = note_of(&items, pos) {
+                say!("{}", note);
+            }
+        }
+    }
+}
+
+fn archive_list(config: &Config, target_list: Option<String>) {
+    let (list_path, list_name) = resolve_list(config, target_list);
+
+    let store = open_storage(config);
+    if !store.exists(&list_name) {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    let stem = list_path.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown");
+    let archive_path = list_path.with_file_name(format!("{}-archive.{}", stem, config.todo.list_extension));
+    let archive_list = format!("{}-archive", normalize_list_name(&list_name));
+
+    let recorder = Recorder::new(&[&list_path, &archive_path]);
+    let result = store.update(&list_name, |lines| -> io::Result<Vec<String>> {
+        let (done, remaining) = take_todos(lines.clone(), |item| item.completed);
+        if done.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // The archive is written first, as its update ends, so a failure
+        // can't lose completed todos
+        store.update(&archive_list, |archive| {
+            if archive.last().is_some_and(|line| !line.trim().is_empty()) {
+                archive.push(String::new());
+            }
+            archive.push(format!("== Archived {}", Local::now().format("%Y-%m-%d")));
+            archive.extend(done.iter().map(|line| format_line(&parse_line(line))));
+        })?;
+        *lines = remaining;
+        Ok(todo_texts(&done))
+    });
+    let result = result.and_then(|archived| archived);
+
+    match result {
+        Ok(archived) if archived.is_empty() => say!("No completed todos to archive in {}", list_name),
+        Ok(archived) => {
+            let archive_name = archive_path.file_name().and_then(|n| n.to_str()).unwrap_or("archive");
+            say!("Archived {} completed todos from {} to {}", archived.len(), list_name, archive_name);
+            record(recorder, TodoEvent::ItemsArchived { list: list_name, items: archived });
+        }
+        Err(e) => eprintln!("Error archiving todo list: {}", e),
+    }
+}
+
+#[derive(Serialize)]
+struct ToggleResult {
+    list: String,
+    index: usize,
+    text: String,
+    completed: bool,
+    // The copy a recurring todo left behind
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next: Option<String>,
+    // Todos completed along with it, the last of their subtasks
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    parents: Vec<String>,
+}
+
+// Complete or reopen (with `state` None, flip) todo number `index` of a list,
+// under the list's lock. None when there's no such todo, or when it's no
+// longer the one with `expected` text.
+fn set_done(
+    config: &Config,
+    list: &str,
+    index: &TodoRef,
+    state: Option<bool>,
+    expected: Option<&str>,
+    by: Option<&str>,
+) -> io::Result<Option<ToggleResult>> {
+    let store = open_storage(config);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+    let (list_path, _) = resolve_list(config, Some(list.to_string()));
+    let recorder = Recorder::new(&[&list_path]);
+    let result = store.update(list, |lines| {
+        let pos = find_todo(lines, index)?;
+        if expected.is_some_and(|text| parse_line(&lines[pos]).text != text) {
+            return None;
+        }
+        let mut item = parse_line(&lines[pos]);
+        let completed = state.unwrap_or(!item.completed);
+        let changed = item.completed != completed;
+        let today = Local::now().date_naive();
+        // A recurring todo needs an id for its next copy to wait on
+        if item.every().is_some() {
+            let items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+            item.stamp_id(&items);
+        }
+        item.set_completed(completed, today, by);
+        lines[pos] = format_line(&item);
+        let mut items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+        let copy = next_copy(&items, &item);
+        // Reopened, it takes back the copy completing it left
+        if let Some(copy) = copy.filter(|_| changed && !completed) {
+            let end = todo_end(lines, copy);
+            lines.drain(copy..end);
+            items.drain(copy..end);
+        }
+        let parents = if changed && completed && config.subtasks.complete_parent {
+            complete_parents(&mut items, pos, today, by)
+        } else {
+            Vec::new()
+        };
+        for &parent in &parents {
+            lines[parent] = format_line(&items[parent]);
+        }
+        let mut next = next_occurrence(&item, today).filter(|_| changed && completed && copy.is_none());
+        if let Some(next) = &mut next {
+            next.stamp_id(&items);
+            let at = todo_end(lines, pos);
+            lines.insert(at, format_line(next));
+            items.insert(at, next.clone());
+        }
+
+        let mut events = if changed { toggle_events(&list_name, &item, &items) } else { Vec::new() };
+        for &parent in &parents {
+            events.push(TodoEvent::ItemCompleted { list: list_name.clone(), text: items[parent].text.clone() });
+        }
+        if let Some(next) = &next {
+            events.push(TodoEvent::ItemAdded { list: list_name.clone(), text: next.text.clone() });
+        }
+        let result = ToggleResult {
+            list: list_name.clone(),
+            index: todo_number(lines, pos),
+            text: item.text,
+            completed: item.completed,
+            next: next.map(|next| next.text),
+            parents: parents.iter().map(|&parent| items[parent].text.clone()).collect(),
+        };
+        Some((result, events))
+    })?;
+
+    let Some((result, events)) = result else {
+        return Ok(None);
+    };
+    let mut events = events.into_iter();
+    if let Some(toggled) = events.next() {
+        record(recorder, toggled);
+    }
+    for event in events {
+        emit(event);
+    }
+    Ok(Some(result))
+}
+
+// Meant for scripts and keybindings: the read-modify-write happens under a
+// lock, and failures exit non-zero
+fn toggle_todo(config: &Config, index: &TodoRef, state: Option<bool>, target_list: Option<String>) {
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+
+    if !open_storage(config).exists(&list) {
+        eprintln!("List '{}' does not exist", list_name);
+        std::process::exit(1);
+    }
+
+    match set_done(config, &list, index, state, None, config.user_name().as_deref()) {
+        Ok(Some(result)) => {
+            say!("{}", serde_json::to_string(&result).expect("Failed to serialize result"));
+        }
+        Ok(None) => {
+            eprintln!("No todo #{} in {}", index, list_name);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Error updating todo list: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn edit_item(config: &Config, index: &TodoRef, text: String, target_list: Option<String>) {
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+
+    let text = resolve_due(config.expand_emoji(text.trim()));
+    if text.is_empty() {
+        eprintln!("The new text can't be empty");
+        return;
+    }
+
+    if !store.exists(&list) {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    let (list_path, _) = resolve_list(config, Some(list.clone()));
+    let recorder = Recorder::new(&[&list_path]);
+    let result = store.update(&list, |lines| {
+        let pos = find_todo(lines, index)?;
+        let old = parse_line(&lines[pos]);
+        let mut item = TodoItem { text: text.clone(), ..old.clone() };
+        item.keep_metadata(&old);
+        lines[pos] = format_line(&item);
+        Some((old.text, item.text_without_stamps(), item.text))
+    });
+
+    match result {
+        Ok(Some((old, shown, new))) => {
+            say!("Edited todo #{} in {}: {}", index, list_name, shown);
+            warn_misspellings(config, &[&text]);
+            record(recorder, TodoEvent::ItemEdited { list: list_name, old, new });
+        }
+        Ok(None) => eprintln!("No todo #{} in {}", index, list_name),
+        Err(e) => eprintln!("Error updating todo list: {}", e),
+    }
+}
+
+// Open a todo's note in the editor, and put what's saved back under the
+// todo as its note
+fn edit_note(config: &Config, index: &TodoRef, target_list: Option<String>) {
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+
+    let lines = match store.load_list(&list) {
+        Ok(lines) => lines,
+        Err(e) => {
+            eprintln!("Error reading todo list '{}': {}", list_name, e);
+            return;
+        }
+    };
+    let Some(pos) = find_todo(&lines, index) else {
+        eprintln!("No todo #{} in {}", index, list_name);
+        return;
+    };
+    let items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+    let todo = items[pos].text.clone();
+    let before = note_of(&items, pos).unwrap_or_default();
+
+    let scratch = std::env::temp_dir().join(format!("todo-note-{}.txt", std::process::id()));
+    if let Err(e) = fs::write(&scratch, if before.is_empty() { before.clone() } else { format!("{}\n", before) }) {
+        eprintln!("Error writing {}: {}", scratch.display(), e);
+        return;
+    }
+    open_in_editor(&config.editor.command, &scratch);
+    let after = fs::read_to_string(&scratch);
+    let _ = fs::remove_file(&scratch);
+    let after = match after {
+        Ok(after) => after,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", scratch.display(), e);
+            return;
+        }
+    };
+    // A blank line would end the note, so blank lines are left out
+    let note: Vec<String> = after.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect();
+    if note.join("\n") == before {
+        println!("Note of todo #{} unchanged", index);
+        return;
+    }
+
+    let (list_path, _) = resolve_list(config, Some(list.clone()));
+    let recorder = Recorder::new(&[&list_path]);
+    let result = store.update(&list, |lines| {
+        // The list may have changed while the editor was open
+        let pos = find_todo(lines, index).filter(|&pos| parse_line(&lines[pos]).text == todo)?;
+        let note = note.iter().map(|line| format_line(&TodoItem { text: line.clone(), completed: false, line_type: LineType::Note, depth: 0 }));
+        let end = note_end(lines, pos);
+        lines.splice(pos + 1..end, note);
+        Some(())
+    });
+
+    match result {
+        Ok(Some(())) if note.is_empty() => {
+            println!("Removed the note of todo #{} in {}", index, list_name);
+            record(recorder, TodoEvent::ListEdited { list: list_name });
+        }
+        Ok(Some(())) => {
+            println!("Updated the note of todo #{} in {}", index, list_name);
+            record(recorder, TodoEvent::ListEdited { list: list_name });
+        }
+        Ok(None) => eprintln!("Todo #{} in {} changed while the note was open; the note wasn't saved", index, list_name),
+        Err(e) => eprintln!("Error updating todo list: {}", e),
+    }
+}
+
+// Blockers are referred to by id, so an `after:#id` keeps pointing at the
+// same todo when the list is reordered; todos get an id when first needed.
+fn depend_todo(config: &Config, index: &TodoRef, on: &[TodoRef], clear: bool, target_list: Option<String>) {
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+
+    if !store.exists(&list) {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    let (list_path, _) = resolve_list(config, Some(list.clone()));
+    let recorder = Recorder::new(&[&list_path]);
+    let result = store.update(&list, |lines| {
+        let find = |todo: &TodoRef| find_todo(lines, todo).ok_or_else(|| format!("No todo #{} in {}", todo, list_name));
+        let pos = find(index)?;
+        let blockers = on.iter().map(find).collect::<Result<Vec<_>, _>>()?;
+        if blockers.contains(&pos) {
+            return Err(format!("Todo #{} can't wait for itself", index));
+        }
+        let mut items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+        if clear {
+            items[pos].clear_blockers();
+        }
+        for blocker in blockers {
+            let id = match items[blocker].id() {
+                Some(id) => id.to_string(),
+                None => {
+                    let id = new_id(&items);
+                    items[blocker].set_id(&id);
+                    lines[blocker] = format_line(&items[blocker]);
+                    id
+                }
+            };
+            items[pos].add_blocker(&id);
+        }
+        lines[pos] = format_line(&items[pos]);
+        Ok(items[pos].text.clone())
+    });
+
+    match result {
+        Ok(Ok(_)) if clear => {
+            say!("Todo #{} in {} no longer waits for other todos", index, list_name);
+            record(recorder, TodoEvent::ListEdited { list: list_name });
+        }
+        Ok(Ok(text)) => {
+            let on: Vec<String> = on.iter().map(|i| format!("#{}", i)).collect();
+            say!("Todo #{} in {} waits for {}: {}", index, list_name, on.join(", "), text);
+            record(recorder, TodoEvent::ListEdited { list: list_name });
+        }
+        Ok(Err(message)) => eprintln!("{}", message),
+        Err(e) => eprintln!("Error updating todo list: {}", e),
+    }
+}
+
+// New todos get an id as they're added; this is for those from before ids,
+// or added to the file by hand
+fn assign_ids(config: &Config, target_list: Option<String>) {
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+
+    if !store.exists(&list) {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    let (list_path, _) = resolve_list(config, Some(list.clone()));
+    let recorder = Recorder::new(&[&list_path]);
+    let result = store.update(&list, |lines| {
+        let mut items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+        let mut assigned = 0;
+        for pos in 0..items.len() {
+            if matches!(items[pos].line_type, LineType::Todo) && items[pos].id().is_none() {
+                let id = new_id(&items);
+                items[pos].set_id(&id);
+                lines[pos] = format_line(&items[pos]);
+                assigned += 1;
+            }
+        }
+        assigned
+    });
+
+    match result {
+        Ok(0) => say!("Every todo in {} already has an id", list_name),
+        Ok(assigned) => {
+            say!("Gave {} todos in {} an id", assigned, list_name);
+            record(recorder, TodoEvent::ListEdited { list: list_name });
+        }
+        Err(e) => eprintln!("Error updating todo list: {}", e),
+    }
+}
+
+fn star_todo(config: &Config, index: &TodoRef, starred: bool, target_list: Option<String>) {
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+
+    if !store.exists(&list) {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    let (list_path, _) = resolve_list(config, Some(list.clone()));
+    let recorder = Recorder::new(&[&list_path]);
+    let result = store.update(&list, |lines| {
+        let pos = find_todo(lines, index)?;
+        let mut item = parse_line(&lines[pos]);
+        let old = item.text.clone();
+        item.set_starred(starred);
+        lines[pos] = format_line(&item);
+        Some((old, item.text))
+    });
+
+    match result {
+        Ok(Some((old, new))) if old == new => {
+            let state = if starred { "already starred" } else { "not starred" };
+            say!("Todo #{} in {} is {}", index, list_name, state);
+        }
+        Ok(Some((_, new))) => {
+            let action = if starred { "Starred" } else { "Unstarred" };
+            say!("{} todo #{} in {}: {}", action, index, list_name, new);
+            record(recorder, TodoEvent::ItemStarred { list: list_name, text: new, starred });
+        }
+        Ok(None) => eprintln!("No todo #{} in {}", index, list_name),
+        Err(e) => eprintln!("Error updating todo list: {}", e),
+    }
+}
+
+fn prioritize_todo(config: &Config, index: &TodoRef, level: &str, target_list: Option<String>) {
+    let priority = match level.to_lowercase().as_str() {
+        "none" | "-" => None,
+        "high" => Some('A'),
+        "medium" => Some('B'),
+        "low" => Some('C'),
+        letter if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_alphabetic()) => letter.to_uppercase().chars().next(),
+        _ => {
+            eprintln!("Invalid priority '{}': expected a letter from A to Z, high, medium, low or none", level);
+            return;
+        }
+    };
+
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+
+    if !store.exists(&list) {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    let (list_path, _) = resolve_list(config, Some(list.clone()));
+    let recorder = Recorder::new(&[&list_path]);
+    let result = store.update(&list, |lines| {
+        let pos = find_todo(lines, index)?;
+        let mut item = parse_line(&lines[pos]);
+        let old = item.text.clone();
+        item.set_priority(priority);
+        lines[pos] = format_line(&item);
+        Some((old, item.text))
+    });
+
+    match result {
+        Ok(Some((old, new))) if old == new => match priority {
+            Some(priority) => say!("Todo #{} in {} already has priority {}", index, list_name, priority),
+            None => say!("Todo #{} in {} has no priority", index, list_name),
+        },
+        Ok(Some((_, new))) => {
+            say!("Updated todo #{} in {}: {}", index, list_name, new);
+            record(recorder, TodoEvent::ItemPrioritized { list: list_name, text: new, priority });
+        }
+        Ok(None) => eprintln!("No todo #{} in {}", index, list_name),
+        Err(e) => eprintln!("Error updating todo list: {}", e),
+    }
+}
+
+// Set a todo's due date, or clear it when `date` is None
+fn set_due(config: &Config, index: &TodoRef, date: Option<&str>, target_list: Option<String>) {
+    let due = match date.map(|date| parse_date(date, Local::now().date_naive())) {
+        Some(Some(due)) => Some(due),
+        Some(None) => {
+            eprintln!("Invalid date '{}': expected YYYY-MM-DD or words like \"next friday\"", date.unwrap_or_default());
+            return;
+        }
+        None => None,
+    };
+
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+
+    if !store.exists(&list) {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    let (list_path, _) = resolve_list(config, Some(list.clone()));
+    let recorder = Recorder::new(&[&list_path]);
+    let result = store.update(&list, |lines| {
+        let pos = find_todo(lines, index)?;
+        let mut item = parse_line(&lines[pos]);
+        let old = item.text.clone();
+        item.set_due(due);
+        lines[pos] = format_line(&item);
+        Some((old, item.text))
+    });
+
+    match result {
+        Ok(Some((old, new))) if old == new => match due {
+            Some(due) => say!("Todo #{} in {} is already due {}", index, list_name, due.format("%Y-%m-%d")),
+            None => say!("Todo #{} in {} has no due date", index, list_name),
+        },
+        Ok(Some((_, new))) => {
+            say!("Updated todo #{} in {}: {}", index, list_name, new);
+            record(recorder, TodoEvent::ItemDueSet { list: list_name, text: new, due });
+        }
+        Ok(None) => eprintln!("No todo #{} in {}", index, list_name),
+        Err(e) => eprintln!("Error updating todo list: {}", e),
+    }
+}
+
+// A todo `todo reschedule` changed: its number, text, and old and new due date
+struct Rescheduled {
+    index: usize,
+    text: String,
+    from: Option<NaiveDate>,
+    to: NaiveDate,
+}
+
+fn reschedule_todos(config: &Config, conditions: &[String], to: &str, dry_run: bool, target_list: Option<String>) {
+    let today = Local::now().date_naive();
+    let conditions = match conditions.iter().map(|condition| parse_condition(condition, today)).collect::<Result<Vec<_>, _>>() {
+        Ok(conditions) => conditions,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    // An offset moves each todo from its own due date, or from today
+    let new_due = |due: Option<NaiveDate>| {
+        if to.trim_start().starts_with(['+', '-']) {
+            shift_date(to, due.unwrap_or(today))
+        } else {
+            parse_date(to, today)
+        }
+    };
+    if new_due(None).is_none() {
+        eprintln!("Invalid date '{}': expected YYYY-MM-DD, words like \"next friday\", or an offset like +1d", to);
+        return;
+    }
+
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+    if !store.exists(&list) {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    let reschedule = |lines: &mut Vec<String>| {
+        let mut changed = Vec::new();
+        let mut index = 0;
+        for line in lines.iter_mut() {
+            let mut item = parse_line(line);
+            if !matches!(item.line_type, LineType::Todo) {
+                continue;
+            }
+            index += 1;
+            if item.completed || !conditions.iter().all(|condition| condition.matches(&item)) {
+                continue;
+            }
+            let from = item.due_date();
+            let Some(to) = new_due(from).filter(|&to| Some(to) != from) else {
+                continue;
+            };
+            item.set_due(Some(to));
+            *line = format_line(&item);
+            item.set_due(None);
+            changed.push(Rescheduled { index, text: item.text_without_stamps(), from, to });
+        }
+        changed
+    };
+    let (list_path, _) = resolve_list(config, Some(list.clone()));
+    let recorder = Recorder::new(&[&list_path]);
+    let result = if dry_run { store.load_list(&list).map(|mut lines| reschedule(&mut lines)) } else { store.update(&list, reschedule) };
+
+    let changed = match result {
+        Ok(changed) => changed,
+        Err(e) => {
+            eprintln!("Error updating todo list: {}", e);
+            return;
+        }
+    };
+    if changed.is_empty() {
+        say!("No open todos in {} to reschedule", list_name);
+        return;
+    }
+    let verb = if dry_run { "Would reschedule" } else { "Rescheduled" };
+    say!("{} {} todos in {}:", verb, changed.len(), list_name);
+    for todo in &changed {
+        let from = todo.from.map_or("no date".to_string(), |from| from.format("%Y-%m-%d").to_string());
+        say!("{} {}: {} → {}", format!("{:>3}", todo.index).faint(), todo.text, from.faint(), todo.to.format("%Y-%m-%d"));
+    }
+    if !dry_run {
+        record(recorder, TodoEvent::ListEdited { list: list_name });
+    }
+}
+
+// Starred open todos across all lists, numbered as in their own list
+fn show_starred(config: &Config) {
+    let store = open_storage(config);
+    let mut lists = match store.list_lists() {
+        Ok(lists) => lists,
+        Err(e) => {
+            eprintln!("Error reading todo directory: {}", e);
+            return;
+        }
+    };
+    if config.project_list.is_some() {
+        lists.insert(0, PROJECT_LIST.to_string());
+    }
+
+    let mut total = 0;
+    for list in lists {
+        let Ok(items) = store.load(&list) else {
+            continue;
+        };
+        let starred: Vec<(usize, &TodoItem)> = items
+            .iter()
+            .filter(|item| matches!(item.line_type, LineType::Todo))
+            .enumerate()
+            .filter(|(_, item)| item.starred() && !item.completed)
+            .collect();
+        if starred.is_empty() {
+            continue;
+        }
+
+        let style = config.list_style(&list);
+        println!("{}", paint(&style.label(&list), &style).bold());
+        for (i, item) in &starred {
+            println!("{} {} {}", format!("{:>3}", i + 1).faint(), "★".yellow(), item.text_without_stamps().replace(STAR, "").trim_end());
+        }
+        println!();
+        total += starred.len();
+    }
+
+    if total == 0 {
+        println!("{}", "No starred todos. Star one with `todo star <number>`.".faint());
+    }
+}
+
+// Positions of `items` in sorted order: todos are reordered within each
+// section, and headers and other lines stay where they are
+fn sorted_order(items: &[TodoItem], by: SortKey, completed_last: bool) -> Vec<usize> {
+    let mut order = Vec::with_capacity(items.len());
+    let mut start = 0;
+    while start < items.len() {
+        let end = (start + 1..items.len())
+            .find(|&i| header_level(&items[i]).is_some())
+            .unwrap_or(items.len());
+
+        // Subtasks are sorted along with their todo, not among the others
+        let slots: Vec<usize> = (start..end)
+            .filter(|&i| matches!(items[i].line_type, LineType::Todo) && parent_of(items, i).is_none())
+            .collect();
+        let mut todos = slots.clone();
+        todos.sort_by(|&a, &b| {
+            let (a, b) = (&items[a], &items[b]);
+            let status = if completed_last { a.completed.cmp(&b.completed) } else { std::cmp::Ordering::Equal };
+            status.then_with(|| match by {
+                SortKey::Status => std::cmp::Ordering::Equal,
+                SortKey::Alpha => alpha_key(a).cmp(&alpha_key(b)),
+                // Unprioritized todos go after prioritized ones
+                SortKey::Priority => a.priority().unwrap_or('~').cmp(&b.priority().unwrap_or('~')),
+            })
+        });
+        // Each todo takes its note and subtasks along
+        let mut todos = todos.into_iter();
+        for i in start..end {
+            match items[i].line_type {
+                _ if parent_of(items, i).is_some() => {}
+                LineType::Todo => {
+                    let todo = todos.next().unwrap_or(i);
+                    order.extend(todo..block_end(items, todo));
+                }
+                _ => order.push(i),
+            }
+        }
+
+        start = end;
+    }
+    order
+}
+
+fn sort_lines(lines: &mut [String], by: SortKey, completed_last: bool) {
+    let items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+    let sorted: Vec<String> = sorted_order(&items, by, completed_last).into_iter().map(|i| lines[i].clone()).collect();
+    lines.clone_from_slice(&sorted);
+}
+
+// Text to sort alphabetically by, ignoring case and any priority marker
+fn alpha_key(item: &TodoItem) -> String {
+    item.text_without_priority().to_lowercase()
+}
+
+fn sort_list(config: &Config, by: SortKey, completed_last: bool, target_list: Option<String>) {
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+
+    if !store.exists(&list) {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    let (list_path, _) = resolve_list(config, Some(list.clone()));
+    let recorder = Recorder::new(&[&list_path]);
+    let result = store.update(&list, |lines| {
+        let before = lines.clone();
+        sort_lines(lines, by, completed_last);
+        *lines != before
+    });
+
+    match result {
+        Ok(true) => {
+            say!("Sorted {}", list_name);
+            record(recorder, TodoEvent::ListSorted { list: list_name });
+        }
+        Ok(false) => say!("{} is already sorted", list_name),
+        Err(e) => eprintln!("Error updating todo list: {}", e),
+    }
+}
+
+fn edit_list(config: &Config) {
+    let todo_path = expand_tilde(&config.todo.path);
+    let list_path = get_active_list_path(config, &todo_path);
+
+    // Ensure the list exists
+    ensure_active_list_exists(&list_path);
+    let recorder = Recorder::new(&[&list_path]);
+
+    // Open in editor
+    let editor = &config.editor.command;
+
+    match Command::new(editor)
+        .arg(&list_path)
+        .status()
+    {
+        Ok(status) => {
+            if !status.success() {
+                eprintln!("Editor exited with status: {}", status);
+            }
+            let list_name = list_path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown").to_string();
+            record(recorder, TodoEvent::ListEdited { list: list_name });
+        }
+        Err(e) => {
+            eprintln!("Failed to open editor '{}': {}", editor, e);
+            eprintln!("Make sure the editor command is correct in your config.");
+        }
+    }
+}
+
+struct ListStats {
+    name: String,
+    complete: usize,
+    incomplete: usize,
+    // Incomplete todo that has gone unchanged the longest, and since when
+    oldest: Option<(String, NaiveDateTime)>,
+    velocity: Option<Velocity>,
+    // Average days since open todos were added, and from added to done for
+    // completed ones, over the todos with the stamps
+    open_age: Option<f64>,
+    time_to_done: Option<f64>,
+}
+
+// How a list has moved since the oldest backup in the trailing window
+struct Velocity {
+    since: NaiveDateTime,
+    days: f64,
+    // Todos open back then and completed now
+    completed: usize,
+    open_change: i64,
+}
+
+const VELOCITY_WINDOW_DAYS: i64 = 14;
+
+fn collect_stats(list_path: &Path, name: String, items: &[TodoItem]) -> ListStats {
+    let todos: Vec<&TodoItem> = items.iter().filter(|item| matches!(item.line_type, LineType::Todo)).collect();
+    let complete = todos.iter().filter(|item| item.completed).count();
+
+    // An open todo has been untouched since the earliest backup that already
+    // had it, unchanged and open
+    let mut first_seen: Vec<Option<NaiveDateTime>> = vec![None; todos.len()];
+    for backup in list_backups(list_path) {
+        let Ok(old) = read_todos(&backup.path) else {
+            continue;
+        };
+        for (seen, item) in first_seen.iter_mut().zip(&todos) {
+            if seen.is_none() && !item.completed && old.iter().any(|o| !o.completed && o.text == item.text) {
+                *seen = Some(backup.taken);
+            }
+        }
+    }
+    let oldest = todos
+        .iter()
+        .zip(first_seen)
+        .filter_map(|(item, seen)| Some((item.text.clone(), seen?)))
+        .min_by_key(|(_, seen)| *seen);
+
+    let today = Local::now().date_naive();
+    let mean = |days: Vec<i64>| (!days.is_empty()).then(|| days.iter().sum::<i64>() as f64 / days.len() as f64);
+    let (done, open): (Vec<&TodoItem>, Vec<&TodoItem>) = todos.iter().partition(|item| item.completed);
+    let open_age = mean(open.iter().filter_map(|item| Some((today - item.added()?).num_days())).collect());
+    let time_to_done = mean(done.iter().filter_map(|item| Some((item.done()? - item.added()?).num_days())).collect());
+
+    let incomplete = todos.len() - complete;
+    ListStats {
+        name,
+        complete,
+        incomplete,
+        oldest,
+        velocity: velocity(list_path, &todos, incomplete),
+        open_age,
+        time_to_done,
+    }
+}
+
+// Measured against the oldest backup from the last two weeks; less than a
+// day of history says too little to go on
+fn velocity(list_path: &Path, todos: &[&TodoItem], incomplete: usize) -> Option<Velocity> {
+    let now = Local::now().naive_local();
+    let window_start = now - chrono::Duration::days(VELOCITY_WINDOW_DAYS);
+    let baseline = list_backups(list_path).into_iter().find(|backup| backup.taken >= window_start)?;
+    let days = (now - baseline.taken).num_minutes() as f64 / (24.0 * 60.0);
+    if days < 1.0 {
+        return None;
+    }
+
+    let old = read_todos(&baseline.path).ok()?;
+    let old_open: Vec<String> = old
+        .iter()
+        .filter(|item| matches!(item.line_type, LineType::Todo) && !item.completed)
+        .map(TodoItem::key)
+        .collect();
+    let completed = todos.iter().filter(|item| item.completed && old_open.contains(&item.key())).count();
+
+    Some(Velocity {
+        since: baseline.taken,
+        days,
+        completed,
+        open_change: incomplete as i64 - old_open.len() as i64,
+    })
+}
+
+fn print_forecast(velocity: &Velocity, incomplete: usize, width: usize) {
+    let rate = velocity.completed as f64 / velocity.days;
+    let forecast = if incomplete == 0 {
+        "all done".to_string()
+    } else if velocity.completed == 0 {
+        format!("nothing completed in {:.0} days", velocity.days)
+    } else {
+        format!("at this pace: ~{:.0} days", (incomplete as f64 / rate).ceil())
+    };
+    println!(
+        "{:<width$}  {} {:.1}/day {}",
+        "",
+        "pace:".faint(),
+        rate,
+        format!("({})", forecast).faint(),
+        width = width
+    );
+
+    if velocity.open_change > 0 {
+        println!(
+            "{:<width$}  {}",
+            "",
+            format!("open count trending up (+{} since {})", velocity.open_change, velocity.since.format("%Y-%m-%d"))
+                .yellow(),
+            width = width
+        );
+    }
+}
+
+// Every list (or just the one asked for) with what happened in it since
+// `since`, for retros and reports
+fn lists_since(
+    config: &Config,
+    target_list: Option<String>,
+    since: NaiveDateTime,
+) -> Option<Vec<(String, Vec<TodoItem>, ListRetro)>> {
+    let store = open_storage(config);
+    let names = match target_list {
+        Some(list) => vec![normalize_list_name(&list)],
+        None => match store.list_lists() {
+            Ok(mut names) => {
+                if config.project_list.is_some() {
+                    names.insert(0, PROJECT_LIST.to_string());
+                }
+                names
+            }
+            Err(e) => {
+                eprintln!("Error reading todo directory: {}", e);
+                return None;
+            }
+        },
+    };
+    let journal = match read_journal() {
+        Ok(journal) => journal,
+        Err(e) => {
+            eprintln!("Error reading journal: {}", e);
+            return None;
+        }
+    };
+
+    let mut lists = Vec::new();
+    for name in names {
+        let items = match store.load(&name) {
+            Ok(items) => items,
+            Err(e) => {
+                eprintln!("Error reading todo list '{}.{}': {}", name, config.todo.list_extension, e);
+                continue;
+            }
+        };
+        let (list_path, _) = resolve_list(config, Some(name.clone()));
+        let retro = list_retro(&list_path, &items, &journal, since);
+        lists.push((name, items, retro));
+    }
+    Some(lists)
+}
+
+fn show_retro(config: &Config, days: u32, target_list: Option<String>) {
+    let end = Local::now().date_naive();
+    let start = end - chrono::Duration::days(days as i64 - 1);
+    let Some(lists) = lists_since(config, target_list, start.and_time(chrono::NaiveTime::MIN)) else {
+        return;
+    };
+    let lists: Vec<(String, ListRetro)> = lists.into_iter().map(|(name, _, retro)| (name, retro)).collect();
+    print!("{}", render_markdown(start, end, &lists));
+}
+
+// A bar of up to `width` blocks for `count` out of `max`
+fn bar(count: usize, max: usize, width: usize) -> String {
+    let blocks = (count * width).div_ceil(max.max(1));
+    "█".repeat(blocks)
+}
+
+fn show_insights(config: &Config) {
+    let store = open_storage(config);
+    let names = match store.list_lists() {
+        Ok(names) => names,
+        Err(e) => {
+            eprintln!("Error reading todo directory: {}", e);
+            return;
+        }
+    };
+    let lists: Vec<(String, Vec<TodoItem>)> = names.into_iter().filter_map(|name| Some((name.clone(), store.load(&name).ok()?))).collect();
+    let journal = read_journal().unwrap_or_default();
+    let insights = insights(&lists, &journal, Local::now().date_naive());
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+
+    println!("{}", "=== Insights ===".bold().cyan());
+    println!();
+    let added: usize = insights.added_by_hour.iter().sum();
+    match insights.journal_since {
+        Some(since) if added > 0 => {
+            println!(
+                "{} {}",
+                "When todos get added".bold(),
+                format!("({} since {}, as far back as the journal goes)", plural(added, "todo"), since.format("%Y-%m-%d")).faint()
+            );
+            let max = insights.added_by_hour.iter().copied().max().unwrap_or(0);
+            for (hour, &count) in insights.added_by_hour.iter().enumerate().filter(|(_, &count)| count > 0) {
+                println!("  {:02}:00  {} {}", hour, bar(count, max, 30).cyan(), count);
+            }
+        }
+        _ => println!("{}", "No todos added since the journal began.".faint()),
+    }
+
+    println!();
+    let done: usize = insights.latency.iter().sum();
+    match insights.median_latency {
+        Some(median) => {
+            println!(
+                "{} {}",
+                "From adding to done".bold(),
+                format!("({}, half of them within {})", plural(done, "todo"), plural(median as usize, "day")).faint()
+            );
+            let max = insights.latency.iter().copied().max().unwrap_or(0);
+            for ((label, _), &count) in LATENCY_BUCKETS.iter().zip(&insights.latency) {
+                println!("  {:<10} {} {}", label, bar(count, max, 30).green(), count);
+            }
+        }
+        None => println!("{}", "No completed todos with added and done stamps yet.".faint()),
+    }
+
+    println!();
+    if insights.tags.is_empty() {
+        println!("{}", "Not enough tagged todos to compare tags yet.".faint());
+    } else {
+        println!("{} {}", "Tags that get put off".bold(), "(average days open, until done or today)".faint());
+        let width = insights.tags.iter().take(5).map(|tag| display_width(&tag.tag) + 1).max().unwrap_or(0);
+        for tag in insights.tags.iter().take(5) {
+            println!(
+                "  {}  {:>5.1} days  {}",
+                pad(&format!("#{}", tag.tag), width),
+                tag.average_days,
+                format!("over {}, {} open", plural(tag.todos, "todo"), tag.open).faint()
+            );
+        }
+    }
+}
+
+// Completed todos by list and day, then what's still open
+fn show_report(config: &Config, since: String, target_list: Option<String>) {
+    let Some(since_time) = parse_time(&since) else {
+        eprintln!("Invalid time '{}'. Use e.g. 7d, 2w, yesterday or YYYY-MM-DD", since);
+        return;
+    };
+    let Some(lists) = lists_since(config, target_list, since_time) else {
+        return;
+    };
+
+    println!("{}", format!("=== Report since {} ===", since_time.format("%a %b %-d %Y")).bold().cyan());
+    let mut completed_total = 0;
+    let mut open_total = 0;
+    for (name, items, retro) in &lists {
+        let open: Vec<&TodoItem> = items
+            .iter()
+            .filter(|item| matches!(item.line_type, LineType::Todo) && !item.completed)
+            .collect();
+        if retro.completed.is_empty() && open.is_empty() {
+            continue;
+        }
+        completed_total += retro.completed.len();
+        open_total += open.len();
+
+        let style = config.list_style(name);
+        println!();
+        println!("{}", paint(&style.label(name), &style).bold());
+
+        // Newest day first, and todos whose day isn't known (None) last
+        let mut days: Vec<Option<NaiveDate>> = retro.completed.iter().map(|c| c.when.map(|when| when.date())).collect();
+        days.sort_unstable_by(|a, b| b.cmp(a));
+        days.dedup();
+        for day in days {
+            let label = match day {
+                Some(day) => day.format("%a %b %-d").to_string(),
+                None => "earlier".to_string(),
+            };
+            println!("  {}", label.faint());
+            for completion in retro.completed.iter().filter(|c| c.when.map(|when| when.date()) == day) {
+                println!("    {} {}", "☑".green(), completion.text);
+            }
+        }
+        if !open.is_empty() {
+            println!("  {}", format!("open ({})", open.len()).faint());
+            for item in open {
+                println!("    {} {}", "☐".yellow(), item.text_without_stamps());
+            }
+        }
+    }
+
+    println!();
+    if completed_total == 0 && open_total == 0 {
+        println!("Nothing completed and nothing open.");
+    } else {
+        println!("{} completed, {} still open", completed_total.to_string().green(), open_total.to_string().bright_yellow());
+    }
+}
+
+fn show_stats(config: &Config, target_list: Option<String>) {
+    let store = open_storage(config);
+
+    let names = match target_list {
+        Some(list) => vec![normalize_list_name(&list)],
+        None => match store.list_lists() {
+            Ok(names) => names,
+            Err(e) => {
+                eprintln!("Error reading todo directory: {}", e);
+                return;
+            }
+        },
+    };
+
+    let mut stats = Vec::new();
+    for name in names {
+        let file_name = format!("{}.{}", name, config.todo.list_extension);
+        let items = match store.load(&name) {
+            Ok(items) => items,
+            Err(e) => {
+                eprintln!("Error reading todo list '{}': {}", file_name, e);
+                continue;
+            }
+        };
+        let (list_path, _) = resolve_list(config, Some(name));
+        stats.push(collect_stats(&list_path, file_name, &items));
+    }
+
+    if stats.is_empty() {
+        println!("No todo lists found.");
+        return;
+    }
+
+    let width = stats.iter().map(|s| display_width(&s.name)).max().unwrap_or(0);
+    let percent = |complete: usize, total: usize| (complete * 100).checked_div(total).unwrap_or(0);
+
+    println!("{}", "=== Stats ===".bold().cyan());
+    println!();
+    for list in &stats {
+        let total = list.complete + list.incomplete;
+        println!(
+            "{}  {:>4} total  {} done  {} open  {:>3}%",
+            pad(&list.name, width).bold(),
+            total,
+            format!("{:>4}", list.complete).green(),
+            format!("{:>4}", list.incomplete).bright_yellow(),
+            percent(list.complete, total),
+        );
+        if let Some((text, since)) = &list.oldest {
+            println!(
+                "{:<width$}  {} {} {}",
+                "",
+                "oldest untouched:".faint(),
+                text,
+                format!("(since {})", since.format("%Y-%m-%d")).faint(),
+                width = width
+            );
+        }
+        if let Some(velocity) = &list.velocity {
+            print_forecast(velocity, list.incomplete, width);
+        }
+        let ages: Vec<String> = [
+            list.open_age.map(|days| format!("open todos {:.1} days old", days)),
+            list.time_to_done.map(|days| format!("done {:.1} days after adding", days)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !ages.is_empty() {
+            println!("{:<width$}  {} {}", "", "on average:".faint(), ages.join(", "), width = width);
+        }
+    }
+
+    if stats.len() > 1 {
+        let complete: usize = stats.iter().map(|s| s.complete).sum();
+        let incomplete: usize = stats.iter().map(|s| s.incomplete).sum();
+        println!();
+        println!(
+            "{}  {:>4} total  {} done  {} open  {:>3}%",
+            pad("All lists", width).bold(),
+            complete + incomplete,
+            format!("{:>4}", complete).green(),
+            format!("{:>4}", incomplete).bright_yellow(),
+            percent(complete, complete + incomplete),
+        );
+    }
+}
+
+#[derive(Serialize)]
+struct ExportItem {
+    list: String,
+    index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    // 1-based line in the list file
+    line: usize,
+    // Closest header above the todo
+    section: Option<String>,
+    text: String,
+    completed: bool,
+    tags: Vec<String>,
+    due: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+}
+
+fn export_items(list_name: &str, items: &[TodoItem]) -> Vec<ExportItem> {
+    let mut section = None;
+    let mut exported = Vec::new();
+    for (line, item) in items.iter().enumerate() {
+        match item.line_type {
+            LineType::Header1 | LineType::Header2 | LineType::Header3 => section = Some(item.text.clone()),
+            LineType::Todo => exported.push(ExportItem {
+                list: list_name.to_string(),
+                index: exported.len() + 1,
+                id: item.id().map(str::to_string),
+                line: line + 1,
+                section: section.clone(),
+                text: item.text.clone(),
+                completed: item.completed,
+                tags: item.tags().iter().map(|tag| tag.to_string()).collect(),
+                due: item.due().map(str::to_string),
+                note: note_of(items, line),
+            }),
+            _ => {}
+        }
+    }
+    exported
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn render_export(items: &[ExportItem], format: ExportFormat) -> String {
+    let mut out = String::new();
+    match format {
+        ExportFormat::Json => {
+            out.push_str(&serde_json::to_string_pretty(items).expect("Failed to serialize todos"));
+            out.push('\n');
+        }
+        ExportFormat::Csv => {
+            out.push_str("list,index,section,text,completed,tags,due\n");
+            for item in items {
+                let fields = [
+                    csv_field(&item.list),
+                    item.index.to_string(),
+                    csv_field(item.section.as_deref().unwrap_or("")),
+                    csv_field(&item.text),
+                    item.completed.to_string(),
+                    csv_field(&item.tags.join(" ")),
+                    csv_field(item.due.as_deref().unwrap_or("")),
+                ];
+                out.push_str(&fields.join(","));
+                out.push('\n');
+            }
+        }
+        ExportFormat::Markdown => {
+            let mut list = None;
+            let mut section = None;
+            for item in items {
+                if list != Some(item.list.as_str()) {
+                    if list.is_some() {
+                        out.push('\n');
+                    }
+                    out.push_str(&format!("# {}\n\n", item.list));
+                    list = Some(item.list.as_str());
+                    section = None;
+                }
+                if section != item.section.as_deref() {
+                    if let Some(header) = &item.section {
+                        if !out.ends_with("\n\n") {
+                            out.push('\n');
+                        }
+                        out.push_str(&format!("## {}\n\n", header));
+                    }
+                    section = item.section.as_deref();
+                }
+                let mark = if item.completed { "x" } else { " " };
+                out.push_str(&format!("- [{}] {}\n", mark, item.text));
+            }
+        }
+    }
+    out
+}
+
+fn export_todos(config: &Config, format: ExportFormat, target_list: Option<String>, all: bool, output: Option<PathBuf>) {
+    let store = open_storage(config);
+
+    let names = if all {
+        match store.list_lists() {
+            Ok(names) => names,
+            Err(e) => {
+                eprintln!("Error reading todo directory: {}", e);
+                return;
+            }
+        }
+    } else {
+        vec![target_list_name(config, target_list)]
+    };
+
+    let mut items = Vec::new();
+    for name in names {
+        let file_name = format!("{}.{}", name, config.todo.list_extension);
+        if !store.exists(&name) {
+            eprintln!("List '{}' does not exist", file_name);
+            return;
+        }
+        match store.load(&name) {
+            Ok(todos) => items.extend(export_items(&name, &todos)),
+            Err(e) => {
+                eprintln!("Error reading todo list '{}': {}", file_name, e);
+                return;
+            }
+        }
+    }
+
+    let rendered = render_export(&items, format);
+    match output {
+        Some(path) => match fs::write(&path, rendered) {
+            Ok(()) => println!("Exported {} todos to {}", items.len(), path.display()),
+            Err(e) => eprintln!("Error writing {}: {}", path.display(), e),
+        },
+        None => print!("{}", rendered),
+    }
+}
+
+fn detect_import_format(path: &Path, content: &str) -> ImportFormat {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    match extension.as_str() {
+        "md" | "markdown" => ImportFormat::Markdown,
+        "txt" => ImportFormat::Todotxt,
+        _ if Markdown.detect(content) > 0 => ImportFormat::Markdown,
+        _ => ImportFormat::Todotxt,
+    }
+}
+
+fn import_todos(config: &Config, file: &Path, format: Option<ImportFormat>, target_list: Option<String>) {
+    let content = match fs::read(file).and_then(|bytes| FileFormat::decode(&bytes)) {
+        Ok((content, _)) => content,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", file.display(), e);
+            return;
+        }
+    };
+
+    // Only todos are imported; notes and headers around them are skipped
+    let todos: Vec<TodoItem> = match format.unwrap_or_else(|| detect_import_format(file, &content)) {
+        ImportFormat::Todotxt => content.lines().filter_map(todotxt::parse_line).collect(),
+        ImportFormat::Markdown => content
+            .lines()
+            .map(|line| Markdown.parse(line))
+            .filter(|item| matches!(item.line_type, LineType::Todo))
+            .collect(),
+    };
+    if todos.is_empty() {
+        say!("No todos found in {}", file.display());
+        return;
+    }
+    let todos_count = todos.len();
+
+    let (list_path, list_name) = resolve_list(config, target_list);
+    let recorder = Recorder::new(&[&list_path]);
+    let (today, user) = (Local::now().date_naive(), config.user_name());
+    let result = open_storage(config).update(&list_name, |lines| {
+        let mut items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+        for mut todo in todos {
+            // Ones that don't say when they were added were added now
+            todo.stamp_added(today, user.as_deref());
+            todo.stamp_id(&items);
+            lines.push(format_line(&todo));
+            items.push(todo);
+        }
+        items
+    });
+
+    match result {
+        Ok(items) => {
+            let todos = &items[items.len() - todos_count..];
+            let done = todos.iter().filter(|item| item.completed).count();
+            say!("Imported {} todos ({} completed) into {}", todos.len(), done, list_name);
+            let items = todos.iter().map(|item| item.text.clone()).collect();
+            record(recorder, TodoEvent::ItemsAdded { list: list_name, items });
+        }
+        Err(e) => eprintln!("Error importing into todo list: {}", e),
+    }
+}
+
+// Words of a batch line, split like a shell would: quotes group words and
+// a backslash escapes the next character
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('\'')) => word.get_or_insert_with(String::new).push(c),
+            ('\\', _) => {
+                let escaped = chars.next().ok_or("trailing backslash")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            (c, Some(q)) if c == q => quote = None,
+            (c, Some(_)) => word.get_or_insert_with(String::new).push(c),
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (c, None) if c.is_whitespace() => words.extend(word.take()),
+            (c, None) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("unclosed quote".to_string());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+// The commands of a batch, each with where it came from for errors: one
+// per line, skipping blank lines and # comments, or a JSON array whose
+// entries are lines or arrays of arguments
+fn parse_batch(text: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    if text.trim_start().starts_with('[') {
+        let entries: Vec<serde_json::Value> = serde_json::from_str(text).map_err(|e| format!("invalid JSON: {}", e))?;
+        return entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let label = format!("command {}", i + 1);
+                let args = match entry {
+                    serde_json::Value::String(line) => split_words(line),
+                    serde_json::Value::Array(args) => args
+                        .iter()
+                        .map(|arg| arg.as_str().map(str::to_string).ok_or_else(|| "arguments must be strings".to_string()))
+                        .collect(),
+                    _ => Err("expected a string or an array of strings".to_string()),
+                };
+                args.map(|args| (label.clone(), args)).map_err(|e| format!("{}: {}", label, e))
+            })
+            .collect();
+    }
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            let label = format!("line {}", i + 1);
+            split_words(line).map(|args| (label.clone(), args)).map_err(|e| format!("{}: {}", label, e))
+        })
+        .collect()
+}
+
+// Commands that only touch lists through the store, and don't stop to ask
+// anything, which are the ones a batch can hold the lists for
+fn batchable(command: &Option<Commands>) -> bool {
+    matches!(
+        command,
+        Some(
+            Commands::Add { .. }
+                | Commands::EditItem { .. }
+                | Commands::Depend { .. }
+                | Commands::Ids { .. }
+                | Commands::Sort { .. }
+                | Commands::Star { .. }
+                | Commands::Prioritize { .. }
+                | Commands::Due { .. }
+                | Commands::Reschedule { .. }
+                | Commands::Toggle { .. }
+                | Commands::Get { .. }
+                | Commands::Count { .. }
+                | Commands::Next { .. }
+                | Commands::Move { .. }
+                | Commands::Archive { .. }
+                | Commands::Import { .. }
+        )
+    )
+}
+
+// Every command is parsed before any runs, so a typo on the last line
+// changes nothing. They then run in order against lists held in memory,
+// each locked when first touched and saved once at the end, with one
+// journal entry per list. A command that fails the way it would exit
+// non-zero on its own stops the batch with nothing saved, and nothing the
+// commands before it printed shown.
+fn run_batch(config: &mut Config, input: &Path) {
+    let text = if input == Path::new("-") {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(input)
+    };
+    let text = match text {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", input.display(), e);
+            std::process::exit(1);
+        }
+    };
+    let commands = match parse_batch(&text) {
+        Ok(commands) => commands,
+        Err(e) => {
+            eprintln!("Error in batch, {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut clis = Vec::new();
+    for (label, args) in commands {
+        let args_with_name = std::iter::once("todo".to_string()).chain(args.iter().cloned());
+        let parsed = Cli::command().try_get_matches_from(args_with_name).and_then(|matches| Ok((Cli::from_arg_matches(&matches)?, matches)));
+        let (cli, matches) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                eprintln!("Error in batch, {}: {}", label, e.render().to_string().trim_end());
+                std::process::exit(1);
+            }
+        };
+        let missing = if config.strict || cli.strict { missing_list(config, &named_lists(&matches), false) } else { Ok(()) };
+        if let Err(e) = missing {
+            eprintln!("Error in batch, {}: {}", label, e);
+            std::process::exit(EXIT_NO_LIST);
+        }
+        if !batchable(&cli.command) {
+            let name = args.first().map(String::as_str).unwrap_or("(nothing)");
+            eprintln!("Error in batch, {}: `{}` can't be batched", label, name);
+            std::process::exit(1);
+        }
+        clis.push(cli);
+    }
+
+    let batch = begin_batch(config);
+    *BATCH_EVENTS.lock().unwrap_or_else(|e| e.into_inner()) = Some(Vec::new());
+    *BATCH_OUTPUT.lock().unwrap_or_else(|e| e.into_inner()) = Some(Vec::new());
+    for cli in &clis {
+        run_command(config, cli);
+    }
+
+    // The files still hold what they did before the batch
+    let recorders: Vec<(Recorder, String)> = batch
+        .changed()
+        .into_iter()
+        .map(|path| {
+            let list = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            (Recorder::new(&[&path]), list)
+        })
+        .collect();
+    if let Err(e) = batch.commit() {
+        eprintln!("Error saving todo lists: {}", e);
+        std::process::exit(1);
+    }
+    // Only now is what the commands said true
+    let output = BATCH_OUTPUT.lock().unwrap_or_else(|e| e.into_inner()).take().unwrap_or_default();
+    for line in output {
+        println!("{}", line);
+    }
+    for (recorder, list) in recorders {
+        match recorder.finish(&TodoEvent::ListEdited { list }) {
+            Ok(changes) => log_sync(&changes),
+            Err(e) => eprintln!("Warning: could not write journal: {}", e),
+        }
+    }
+    let events = BATCH_EVENTS.lock().unwrap_or_else(|e| e.into_inner()).take().unwrap_or_default();
+    for event in events {
+        EventBus::global().emit(&event);
+    }
+}
+
+// Plain output only, so prompts can embed it; failures exit non-zero
+#[derive(Serialize)]
+struct Counts<'a> {
+    // The list counted, or the active one for --all-lists
+    list: &'a str,
+    incomplete: usize,
+    complete: usize,
+    total: usize,
+}
+
+fn count_todos(
+    config: &Config,
+    complete: bool,
+    all_lists: bool,
+    format: Option<String>,
+    target_list: Option<String>,
+    json: bool,
+) {
+    let store = open_storage(config);
+
+    let names = if all_lists {
+        match store.list_lists() {
+            Ok(names) => names,
+            Err(e) => {
+                eprintln!("Error reading todo directory: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        vec![target_list_name(config, target_list.clone())]
+    };
+    // Named in the template; across every list, that's the active one
+    let list_name = target_list_name(config, if all_lists { None } else { target_list });
+    let style = config.list_style(&list_name);
+
+    let (mut done, mut open) = (0, 0);
+    for name in names {
+        let items = match store.load(&name) {
+            Ok(items) => items,
+            Err(e) => {
+                eprintln!("Error reading todo list '{}.{}': {}", name, config.todo.list_extension, e);
+                std::process::exit(1);
+            }
+        };
+        for item in items.iter().filter(|item| matches!(item.line_type, LineType::Todo)) {
+            if item.completed {
+                done += 1;
+            } else {
+                open += 1;
+            }
+        }
+    }
+
+    if json {
+        print_json(&Counts { list: &list_name, incomplete: open, complete: done, total: open + done });
+        return;
+    }
+
+    match format {
+        Some(template) => say!(
+            "{}",
+            template
+                .replace("{incomplete}", &open.to_string())
+                .replace("{complete}", &done.to_string())
+                .replace("{total}", &(open + done).to_string())
+                .replace("{list}", &list_name)
+                .replace("{emoji}", style.emoji.as_deref().unwrap_or(""))
+        ),
+        None if complete => say!("{}", done),
+        None => say!("{}", open),
+    }
+}
+
+const TUTORIAL_LIST: &str = "todo-tutorial";
+
+// Ask for a line of input; None when stdin is closed
+fn prompt(message: &str) -> Option<String> {
+    print!("{} ", message);
+    io::stdout().flush().ok()?;
+
+    let mut answer = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim().to_string()),
+    }
+}
+
+// Ask until the answer is non-empty
+fn prompt_required(message: &str) -> Option<String> {
+    loop {
+        let answer = prompt(message)?;
+        if !answer.is_empty() {
+            return Some(answer);
+        }
+    }
+}
+
+fn tutorial_step(number: usize, title: &str, explanation: &[&str]) {
+    println!();
+    println!("{}", format!("--- Step {}: {} ---", number, title).bold().cyan());
+    for line in explanation {
+        println!("{}", line);
+    }
+    println!();
+}
+
+fn run_tutorial(config: &Config) {
+    let mut state = load_state();
+    let store = open_storage(config);
+    let list_name = format!("{}.{}", TUTORIAL_LIST, config.todo.list_extension);
+
+    println!("{}", "=== Welcome to todo ===".bold().cyan());
+    if state.tutorial_completed {
+        println!("{}", "You've done the tutorial before; here it is again.".faint());
+    }
+    println!("This tutorial works on a sandbox list, {}, so your own lists stay untouched.", list_name);
+
+    if store.exists(TUTORIAL_LIST) && !confirm(&format!("{} already exists. Start it over?", list_name)) {
+        return;
+    }
+    if let Err(e) = store.save_list(TUTORIAL_LIST, &["= Tutorial".to_string()]) {
+        eprintln!("Error creating {}: {}", list_name, e);
+        return;
+    }
+
+    let result = (|| -> Option<()> {
+        tutorial_step(1, "Adding todos", &[
+            "Todos are added from the command line and land at the end of a list.",
+        ]);
+        let text = prompt_required("Type something you need to do:")?;
+        store.update(TUTORIAL_LIST, |lines| lines.push(format!("* [ ] {}", text))).ok()?;
+        println!("{} todo add \"{}\" -l {}", "Done! Next time you can run:".green(), text, TUTORIAL_LIST);
+        let text = prompt_required("Add one more:")?;
+        store.update(TUTORIAL_LIST, |lines| lines.push(format!("* [ ] {}", text))).ok()?;
+        println!();
+        display_todo_list(config, Some(TUTORIAL_LIST.to_string()), false, false, &ListFilter::default());
+
+        tutorial_step(2, "Completing todos", &[
+            "Every todo has a number, shown by `todo list`. Commands like toggle,",
+            "move and get use it.",
+        ]);
+        let index = loop {
+            let answer = prompt_required("Which number did you just finish?")?;
+            match answer.parse::<usize>() {
+                Ok(index) if (1..=2).contains(&index) => break index,
+                _ => println!("Pick 1 or 2."),
+            }
+        };
+        store
+            .update(TUTORIAL_LIST, |lines| {
+                if let Some(pos) = todo_line_index(lines, index) {
+                    let mut item = parse_line(&lines[pos]);
+                    item.set_completed(true, Local::now().date_naive(), config.user_name().as_deref());
+                    lines[pos] = format_line(&item);
+                }
+            })
+            .ok()?;
+        println!("{} todo toggle {} -l {}", "Nice! That was:".green(), index, TUTORIAL_LIST);
+
+        tutorial_step(3, "Sections", &[
+            "Lists are plain AsciiDoc files. Lines starting with `==` are section",
+            "headers, which group the todos below them. Open a list in your editor",
+            "with `todo edit` to move things around.",
+        ]);
+        let section = prompt_required("Name a section, e.g. \"This week\":")?;
+        let text = prompt_required(&format!("Something to do in \"{}\":", section))?;
+        store
+            .update(TUTORIAL_LIST, |lines| {
+                lines.push(String::new());
+                lines.push(format!("== {}", section));
+                lines.push(format!("* [ ] {}", text));
+            })
+            .ok()?;
+        println!();
+        display_todo_list(config, Some(TUTORIAL_LIST.to_string()), false, false, &ListFilter::default());
+
+        tutorial_step(4, "Tags", &[
+            "Words starting with # are tags. They are plain text, so they work in",
+            "every command: `todo search \"#home\"` finds all todos tagged home.",
+        ]);
+        let text = prompt_required("Add a todo with a tag, e.g. \"call the bank #errands\":")?;
+        store.update(TUTORIAL_LIST, |lines| lines.push(format!("* [ ] {}", text))).ok()?;
+        let item = TodoItem { text, completed: false, line_type: LineType::Todo, depth: 0 };
+        match item.tags().as_slice() {
+            [] => println!("No tag there, but that's fine; add one whenever you like."),
+            tags => println!("{} {}", "Tagged:".green(), tags.join(", ")),
+        }
+
+        tutorial_step(5, "The interactive view", &[
+            "`todo show` opens a full-screen view of a list:",
+            "  j/k or arrows  move        Space    toggle",
+            "  o/O            add below/above      e/Enter  edit",
+            "  dd / u         delete / undo",
+            "  ?              help        q        save and quit",
+        ]);
+        if confirm("Try it on the tutorial list now?") {
+            if let Err(e) = show_tui(config, Some(TUTORIAL_LIST.to_string()), None, false) {
+                eprintln!("Error running TUI: {}", e);
+            }
+        }
+        Some(())
+    })();
+
+    if result.is_none() {
+        println!();
+        println!("Tutorial stopped. Run `todo tutorial` to start again.");
+        return;
+    }
+
+    state.tutorial_completed = true;
+    if let Err(e) = save_state(&state) {
+        eprintln!("Warning: could not save tutorial progress: {}", e);
+    }
+
+    println!();
+    println!("{}", "That's it! Run `todo --help` to see everything else.".bold().green());
+    if confirm(&format!("Delete {}?", list_name)) {
+        let (list_path, _) = resolve_list(config, Some(TUTORIAL_LIST.to_string()));
+        if let Err(e) = fs::remove_file(&list_path) {
+            eprintln!("Error deleting list: {}", e);
+        }
+    }
+}
+
+// With git.auto_commit on, changes are batched and committed by a background
+// `todo auto-commit` once no new change has arrived for the debounce window
+fn enable_auto_commit(config: &Config) {
+    let todo_path = expand_tilde(&config.todo.path);
+    if !(config.git.auto_commit || config.git.auto_sync) || !git::is_repo(&todo_path) {
+        return;
+    }
+
+    let debounce = config.git.debounce_secs as i64;
+    let background = config.git.background;
+    EventBus::global().subscribe(move |event| {
+        if !git::changes_files(event) {
+            return;
+        }
+        let previous = match git::add_to_batch(event) {
+            Ok(previous) => previous,
+            Err(e) => {
+                eprintln!("Warning: could not queue auto-commit: {}", e);
+                return;
+            }
+        };
+        // Otherwise finish_auto_commit takes care of it on the way out
+        if !background {
+            return;
+        }
+
+        // A batch that should have been committed long ago lost its committer
+        let now = Local::now().naive_local();
+        let running = previous.is_some_and(|last| (now - last).num_seconds() <= 2 * debounce + 5);
+        if running {
+            return;
+        }
+        let spawned = std::env::current_exe().and_then(|exe| {
+            Command::new(exe)
+                .arg("auto-commit")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+        });
+        if let Err(e) = spawned {
+            eprintln!("Warning: could not start auto-commit: {}", e);
+        }
+    });
+}
+
+// Commit a batch, then pull and push if auto_sync is on and there's an
+// upstream to sync with
+fn commit_batch(config: &Config, batch: &PendingCommit) -> Result<(), String> {
+    let todo_path = expand_tilde(&config.todo.path);
+    if batch.last_change.is_none() {
+        return Ok(());
+    }
+    git::commit_all(&todo_path, &batch.summary()).map_err(|e| format!("Error committing todo lists: {}", e))?;
+    if config.git.auto_sync && git::upstream(&todo_path).is_some() {
+        git::pull_and_push(&todo_path).map_err(|e| format!("Error syncing todo lists: {}", e))?;
+    }
+    Ok(())
+}
+
+// Runs in the background, where nobody sees its output, so the outcome is
+// kept in the state file for the next command to report
+fn run_auto_commit(config: &Config) {
+    let result = loop {
+        match git::take_batch(config.git.debounce_secs) {
+            Ok(Ok(batch)) => break commit_batch(config, &batch),
+            Ok(Err(wait)) => thread::sleep(Duration::from_secs(wait)),
+            Err(e) => break Err(format!("Error reading pending auto-commit: {}", e)),
+        }
+    };
+
+    let mut state = load_state();
+    let failure = result.err().map(|error| SyncFailure { time: Local::now().naive_local(), error });
+    if state.sync_failure.is_some() || failure.is_some() {
+        state.sync_failure = failure;
+        let _ = save_state(&state);
+    }
+}
+
+// With git.background off, commit and sync what this command changed
+fn finish_auto_commit(config: &Config) {
+    if config.git.background || !(config.git.auto_commit || config.git.auto_sync) {
+        return;
+    }
+    match git::take_batch(0) {
+        Ok(Ok(batch)) => {
+            if let Err(e) = commit_batch(config, &batch) {
+                eprintln!("{}", e);
+            }
+        }
+        Ok(Err(_)) => {}
+        Err(e) => eprintln!("Error reading pending auto-commit: {}", e),
+    }
+}
+
+// Tell the user, once, about a background auto-commit that failed
+fn report_sync_failure() {
+    let mut state = load_state();
+    let Some(failure) = state.sync_failure.take() else {
+        return;
+    };
+    eprintln!(
+        "Warning: the background auto-commit at {} failed: {}",
+        failure.time.format("%Y-%m-%d %H:%M"),
+        failure.error
+    );
+    eprintln!("Run `todo sync` to retry");
+    let _ = save_state(&state);
+}
+
+// Commit, then rebase onto the upstream and push. Changes queued for
+// auto-commit are committed now, under their summary.
+fn sync_with_git(config: &Config) {
+    let todo_path = expand_tilde(&config.todo.path);
+    if !git::is_repo(&todo_path) {
+        eprintln!("{} is not a git repository; run `git init` there to sync it", todo_path.display());
+        std::process::exit(1);
+    }
+
+    let message = match git::take_batch(0) {
+        Ok(Ok(batch)) if batch.last_change.is_some() => batch.summary(),
+        _ => "Sync todo lists".to_string(),
+    };
+    match git::commit_all(&todo_path, &message) {
+        Ok(true) => println!("Committed: {}", message),
+        Ok(false) => println!("Nothing to commit"),
+        Err(e) => {
+            eprintln!("Error committing todo lists: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let Some(upstream) = git::upstream(&todo_path) else {
+        eprintln!(
+            "No upstream branch to sync with; set one with `git -C {} push -u <remote> <branch>`",
+            todo_path.display()
+        );
+        std::process::exit(1);
+    };
+    if let Err(e) = git::pull_and_push(&todo_path) {
+        eprintln!("Error syncing with {}: {}", upstream, e);
+        std::process::exit(1);
+    }
+    println!("Synced with {}", upstream);
+}
+
+// A completed todo as a line of a commit message, without the metadata
+fn commit_line(text: &str, repo_tag: &str) -> String {
+    let item = TodoItem { text: text.to_string(), completed: true, line_type: LineType::Todo, depth: 0 };
+    let skip_priority = item.priority().is_some() as usize;
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .skip(skip_priority)
+        .filter(|word| *word != STAR && !word.starts_with("due:"))
+        .filter(|word| !word.strip_prefix('#').is_some_and(|tag| tag.eq_ignore_ascii_case(repo_tag)))
+        .collect();
+    words.join(" ")
+}
+
+// The first todo is the subject; with more than one, all of them follow as
+// bullet points
+fn draft_commit_message(config: &Config, since: &str) {
+    let root = std::env::current_dir().ok().and_then(|dir| git::repo_root(&dir));
+    let Some(root) = root else {
+        eprintln!("Not in a git repository");
+        std::process::exit(1);
+    };
+    let revision = if since == "HEAD-push" { "@{push}" } else { since };
+    let Some(since_time) = git::commit_time(&root, revision).or_else(|| parse_time(since)) else {
+        if since == "HEAD-push" {
+            eprintln!("Nothing has been pushed from this branch yet; pass --since, e.g. --since origin/main");
+        } else {
+            eprintln!("'{}' is neither a git revision nor a time such as 2d or 2024-05-01", since);
+        }
+        std::process::exit(1);
+    };
+    let repo_tag = root.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+
+    let Some(lists) = lists_since(config, None, since_time) else {
+        std::process::exit(1);
+    };
+    let mut done = Vec::new();
+    for (name, _, retro) in &lists {
+        for completion in &retro.completed {
+            let item = TodoItem { text: completion.text.clone(), completed: true, line_type: LineType::Todo, depth: 0 };
+            let tagged = item.tags().iter().any(|tag| tag.eq_ignore_ascii_case(&repo_tag));
+            if name == PROJECT_LIST || tagged {
+                done.push(commit_line(&completion.text, &repo_tag));
+            }
+        }
+    }
+
+    let Some(subject) = done.first() else {
+        eprintln!("No todos for {} completed since {}", repo_tag, since_time.format("%Y-%m-%d %H:%M"));
+        std::process::exit(1);
+    };
+    println!("{}", subject);
+    if done.len() > 1 {
+        println!();
+        for line in &done {
+            println!("- {}", line);
+        }
+    }
+}
+
+fn undo(history: bool) {
+    if history {
+        let entries = match read_journal() {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("Error reading journal: {}", e);
+                return;
+            }
+        };
+        if entries.is_empty() {
+            println!("No recent changes.");
+            return;
+        }
+        for entry in entries.iter().rev() {
+            let time = entry.time.format("%Y-%m-%d %H:%M:%S").to_string();
+            if entry.undone {
+                println!("{}  {}", time.faint(), format!("{} (undone)", entry.describe()).faint());
+            } else {
+                println!("{}  {}", time.faint(), entry.describe());
+            }
+        }
+        return;
+    }
+
+    match undo_last() {
+        Ok(Some(entry)) => {
+            println!("Undid {}", entry.describe());
+            // Other devices replay the sync log, so it needs the reversal too
+            let reverted: Vec<FileChange> = entry
+                .changes
+                .into_iter()
+                .map(|change| FileChange { path: change.path, before: change.after, after: change.before })
+                .collect();
+            log_sync(&reverted);
+        }
+        Ok(None) => println!("Nothing to undo."),
+        Err(e) => eprintln!("Cannot undo: {}", e),
+    }
+}
+
+fn show_backups(config: &Config, target_list: Option<String>) {
+    let (list_path, list_name) = resolve_list(config, target_list);
+    let backups = list_backups(&list_path);
+
+    if backups.is_empty() {
+        println!("No backups found for {}.", list_name);
+        return;
+    }
+
+    println!("{}", format!("=== Backups of {} ===", list_name).bold().cyan());
+    println!();
+    for backup in backups.iter().rev() {
+        let todos = read_todos(&backup.path).map(|todos| todos.len()).unwrap_or(0);
+        println!("  {}  {}", backup.taken.format("%Y-%m-%d %H:%M:%S"), format!("{} todos", todos).faint());
+    }
+}
+
+fn restore_backup(config: &Config, target_list: Option<String>, at: Option<String>) {
+    let (list_path, list_name) = resolve_list(config, target_list);
+    let backups = list_backups(&list_path);
+
+    let backup = if let Some(at) = at {
+        let Some(time) = parse_backup_time(&at) else {
+            eprintln!("Invalid time '{}'. Use YYYY-MM-DD, YYYY-MM-DD HH:MM or e.g. 3d", at);
+            return;
+        };
+        backups.iter().rev().find(|b| b.taken <= time)
+    } else {
+        // The newest backup normally matches the current file, so restore
+        // the most recent one that actually differs from it
+        let current = fs::read(&list_path).unwrap_or_default();
+        backups
+            .iter()
+            .rev()
+            .find(|b| fs::read(&b.path).map(|bytes| bytes != current).unwrap_or(false))
+    };
+
+    let Some(backup) = backup else {
+        eprintln!("No matching backup found for {}", list_name);
+        return;
+    };
+
+    let before = fs::read_to_string(&list_path).ok();
+    match fs::copy(&backup.path, &list_path) {
+        Ok(_) => {
+            let after = fs::read_to_string(&list_path).ok();
+            log_sync(&[FileChange { path: list_path.clone(), before, after }]);
+
+            // Snapshot the restored state so the restore itself can be undone
+            if config.backup.enabled {
+                if let Err(e) = snapshot_list(&list_path, Local::now().naive_local(), &config.backup) {
+                    eprintln!("Error backing up todo list: {}", e);
+                }
+            }
+            println!("Restored {} from backup taken {}", list_name, backup.taken.format("%Y-%m-%d %H:%M:%S"));
+        }
+        Err(e) => {
+            eprintln!("Error restoring backup: {}", e);
+        }
+    }
+}
+
+fn backup_directory(config: &Config) {
+    let todo_path = expand_tilde(&config.todo.path);
+    match snapshot_directory(&todo_path, Local::now().naive_local(), &config.backup) {
+        Ok(snapshot) => {
+            let files = snapshot.files().map(|files| files.len()).unwrap_or(0);
+            println!("Saved {} files to {}", files, snapshot.path.display());
+        }
+        Err(e) => {
+            eprintln!("Error taking snapshot: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Where each part of a bundle goes: the config directory, then the
+// workspace's todo directories as the config names them
+fn bundle_roots(config: &Config) -> Vec<(String, PathBuf)> {
+    let config_dir = get_config_path().parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut roots = vec![("config".to_string(), config_dir)];
+    for (name, path) in workspace_roots(config) {
+        let prefix = match name.strip_suffix('/') {
+            Some(name) => format!("workspace/{}", name),
+            None => "todos".to_string(),
+        };
+        roots.push((prefix, path));
+    }
+    roots
+}
+
+fn create_bundle(config: &Config, file: &Path) {
+    let name = file.to_string_lossy();
+    let compression = match Compression::for_name(&name) {
+        Ok(compression) => compression,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut entries = Vec::new();
+    for (prefix, root) in bundle_roots(config) {
+        if let Err(e) = collect_files(&root, &prefix, &mut entries) {
+            eprintln!("Error reading {}: {}", root.display(), e);
+            std::process::exit(1);
+        }
+    }
+    let mtime = Local::now().timestamp().max(0) as u64;
+    let written = if name == "-" {
+        write_bundle(io::stdout().lock(), &entries, mtime, compression)
+    } else {
+        fs::File::create(file).and_then(|out| write_bundle(out, &entries, mtime, compression))
+    };
+    if let Err(e) = written {
+        eprintln!("Error writing bundle: {}", e);
+        std::process::exit(1);
+    }
+    // Stdout may be the bundle itself
+    if name != "-" {
+        println!("Saved {} files to {}", entries.len(), file.display());
+    }
+}
+
+// Unpack a bundle over what's here, into the directories this config names;
+// what's in the bundle never says where its files go. The lists here are
+// snapshotted first, for `todo restore` to go back to.
+fn restore_bundle(config: &Config, file: &Path) {
+    let read = if file.as_os_str() == "-" {
+        read_bundle(io::stdin().lock())
+    } else {
+        fs::File::open(file).and_then(read_bundle)
+    };
+    let entries = match read {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", file.display(), e);
+            std::process::exit(1);
+        }
+    };
+    let roots = bundle_roots(config);
+
+    let todo_path = expand_tilde(&config.todo.path);
+    if list_files(&todo_path).is_ok_and(|files| !files.is_empty()) {
+        if let Err(e) = snapshot_directory(&todo_path, Local::now().naive_local(), &config.backup) {
+            eprintln!("Error taking snapshot: {}", e);
+            std::process::exit(1);
+        }
+        println!("Took a snapshot of the lists here first; `todo restore` goes back to it");
+    }
+
+    let mut restored_files = 0;
+    let mut skipped = 0;
+    for entry in &entries {
+        let target = roots.iter().find_map(|(prefix, root)| {
+            let rest = entry.path.strip_prefix(prefix.as_str())?.strip_prefix('/')?;
+            restore_path(root, rest)
+        });
+        let Some(target) = target else {
+            skipped += 1;
+            continue;
+        };
+        let written = target.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(&target, &entry.data));
+        if let Err(e) = written {
+            eprintln!("Error writing {}: {}", target.display(), e);
+            std::process::exit(1);
+        }
+        restored_files += 1;
+    }
+    println!("Restored {} files from {}", restored_files, file.display());
+    if skipped > 0 {
+        println!("Skipped {} files for directories the config here doesn't name, or that would land outside them", skipped);
+    }
+}
+
+// Snapshots are numbered newest first, as listed
+fn print_snapshots(config: &Config, snapshots: &[Snapshot]) {
+    let extension = Some(config.todo.list_extension.as_str());
+    println!("{}", "=== Snapshots ===".bold().cyan());
+    println!();
+    for (i, snapshot) in snapshots.iter().rev().enumerate() {
+        let files = snapshot.files().unwrap_or_default();
+        let lists = files.iter().filter(|path| path.extension().and_then(|e| e.to_str()) == extension).count();
+        println!(
+            "{}  {}  {}",
+            format!("{:>3}", i + 1).faint(),
+            snapshot.taken.format("%Y-%m-%d %H:%M:%S"),
+            format!("{} lists", lists).faint()
+        );
+    }
+}
+
+// Put the todo directory back as it was in a snapshot. Lists made since go
+// to the trash, and the current state is snapshotted first, so a restore
+// can itself be undone with another.
+fn restore_snapshot(config: &Config, choice: Option<String>) {
+    let todo_path = expand_tilde(&config.todo.path);
+    let snapshots = list_snapshots(&todo_path);
+    if snapshots.is_empty() {
+        println!("No snapshots yet. Take one with `todo backup`.");
+        return;
+    }
+
+    let choice = match choice {
+        Some(choice) => choice,
+        None => {
+            print_snapshots(config, &snapshots);
+            if !io::stdin().is_terminal() {
+                return;
+            }
+            println!();
+            print!("Snapshot to restore (Enter to cancel): ");
+            let mut answer = String::new();
+            if io::stdout().flush().is_err() || io::stdin().read_line(&mut answer).is_err() || answer.trim().is_empty() {
+                return;
+            }
+            answer.trim().to_string()
+        }
+    };
+    let snapshot = match choice.parse::<usize>() {
+        Ok(number) => number.checked_sub(1).and_then(|i| snapshots.iter().rev().nth(i)),
+        Err(_) => match parse_backup_time(&choice) {
+            Some(time) => snapshots.iter().rev().find(|s| s.taken <= time),
+            None => {
+                eprintln!("Invalid snapshot '{}'. Use its number from `todo restore` or a time", choice);
+                std::process::exit(1);
+            }
+        },
+    };
+    let Some(snapshot) = snapshot else {
+        eprintln!("No matching snapshot");
+        std::process::exit(1);
+    };
+
+    // Read it in before the safety snapshot, which may prune this one
+    let taken = snapshot.taken;
+    let read = |file: PathBuf| -> io::Result<(PathBuf, Vec<u8>)> {
+        let name = file.file_name().unwrap_or_default();
+        Ok((todo_path.join(name), fs::read(&file)?))
+    };
+    let restored: io::Result<Vec<_>> = snapshot.files().and_then(|files| files.into_iter().map(read).collect());
+    let restored = match restored {
+        Ok(restored) => restored,
+        Err(e) => {
+            eprintln!("Error reading snapshot: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let saved = match snapshot_directory(&todo_path, Local::now().naive_local(), &config.backup) {
+        Ok(saved) => saved,
+        Err(e) => {
+            eprintln!("Error saving the current state before restoring: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut changes = Vec::new();
+    let extension = Some(config.todo.list_extension.as_str());
+    let current = list_files(&todo_path).unwrap_or_default();
+    let newer = current
+        .iter()
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == extension)
+        .filter(|path| !restored.iter().any(|(restored, _)| restored == *path));
+    for path in newer {
+        let trash_dir = todo_path.join(TRASH_DIR);
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown");
+        let before = fs::read_to_string(path).ok();
+        let moved = fs::create_dir_all(&trash_dir)
+            .and_then(|_| fs::rename(path, trash_path(&trash_dir, name, &config.todo.list_extension)));
+        match moved {
+            Ok(_) => changes.push(FileChange { path: path.clone(), before, after: None }),
+            Err(e) => eprintln!("Error moving {} to the trash: {}", path.display(), e),
+        }
+    }
+    for (path, content) in &restored {
+        let before = fs::read_to_string(path).ok();
+        match fs::write(path, content) {
+            Ok(_) => changes.push(FileChange { path: path.clone(), before, after: String::from_utf8(content.clone()).ok() }),
+            Err(e) => eprintln!("Error restoring {}: {}", path.display(), e),
+        }
+    }
+    changes.retain(|change| change.before != change.after);
+    log_sync(&changes);
+
+    println!(
+        "Restored the snapshot taken {} ({} files changed); the previous state is in the snapshot taken {}",
+        taken.format("%Y-%m-%d %H:%M:%S"),
+        changes.len(),
+        saved.taken.format("%Y-%m-%d %H:%M:%S")
+    );
+}
+
+fn diff_list(config: &Config, target_list: Option<String>, since: String) {
+    let (list_path, list_name) = resolve_list(config, target_list);
+
+    let Some(since_time) = parse_time(&since) else {
+        eprintln!("Invalid time '{}'. Use e.g. yesterday, 3d, 12h or YYYY-MM-DD", since);
+        return;
+    };
+
+    if !list_path.exists() {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    // Compare against the state of the list at the starting point
+    let backups = list_backups(&list_path);
+    let baseline = match backups.iter().rev().find(|b| b.taken <= since_time) {
+        Some(backup) => backup,
+        None => match backups.first() {
+            Some(oldest) => {
+                println!(
+                    "{}",
+                    format!(
+                        "No backup from before {}; comparing against the oldest one ({})",
+                        since_time.format("%Y-%m-%d %H:%M"),
+                        oldest.taken.format("%Y-%m-%d %H:%M")
+                    )
+                    .faint()
+                );
+                oldest
+            }
+            None => {
+                eprintln!("No backups found for {}", list_name);
+                return;
+            }
+        },
+    };
+
+    let (mut before, after) = match (read_todos(&baseline.path), read_todos(&list_path)) {
+        (Ok(before), Ok(after)) => (before, after),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Error reading todo list: {}", e);
+            return;
+        }
+    };
+
+    let mut added = Vec::new();
+    let mut completed = Vec::new();
+    let mut reopened = Vec::new();
+
+    // Match items by text, less the done: stamp; whatever is left in `before`
+    // was removed
+    for item in after {
+        match before.iter().position(|old| old.key() == item.key()) {
+            Some(pos) => {
+                let old = before.remove(pos);
+                if !old.completed && item.completed {
+                    completed.push(item.text);
+                } else if old.completed && !item.completed {
+                    reopened.push(item.text);
+                }
+            }
+            None => added.push(item.text),
+        }
+    }
+    let removed: Vec<String> = before.into_iter().map(|item| item.text).collect();
+
+    println!(
+        "{}",
+        format!("=== {} since {} ===", list_name, baseline.taken.format("%Y-%m-%d %H:%M")).bold().cyan()
+    );
+    println!();
+
+    if added.is_empty() && completed.is_empty() && reopened.is_empty() && removed.is_empty() {
+        println!("{}", "No changes.".faint());
+        return;
+    }
+
+    let sections = [
+        ("Added", "+".bright_yellow(), &added),
+        ("Completed", "☑".green(), &completed),
+        ("Reopened", "☐".bright_yellow(), &reopened),
+        ("Removed", "-".red(), &removed),
+    ];
+    for (title, marker, texts) in sections {
+        if texts.is_empty() {
+            continue;
+        }
+        println!("{} ({})", title.bold(), texts.len());
+        for text in texts {
+            println!("  {} {}", marker, text);
+        }
+        println!();
+    }
+}
+
+// Open todos due today or earlier, with their numbers, most overdue first
+fn urgent_todos(items: &[TodoItem]) -> Vec<(usize, &TodoItem, NaiveDate)> {
+    let today = Local::now().date_naive();
+    let mut urgent: Vec<_> = items
+        .iter()
+        .filter(|item| matches!(item.line_type, LineType::Todo))
+        .enumerate()
+        .filter(|(_, item)| !item.completed)
+        .filter_map(|(i, item)| Some((i + 1, item, item.due_date().filter(|due| *due <= today)?)))
+        .collect();
+    urgent.sort_by_key(|(_, _, due)| *due);
+    urgent
+}
+
+// What `todo list` shows
+#[derive(Default)]
+struct ListFilter {
+    completed: Option<bool>,
+    section: Option<String>,
+    // Lowercase
+    contains: Option<String>,
+    tag: Option<String>,
+    // Who added or completed the todo
+    by: Option<String>,
+    // Open and not waiting for another todo
+    actionable: bool,
+    // Not a filter, but how the todos that pass are ordered
+    sort: Option<SortKey>,
+    // A plugin's filter, by name
+    plugin: Option<String>,
+}
+
+fn header_level(item: &TodoItem) -> Option<usize> {
+    match item.line_type {
+        LineType::Header1 => Some(1),
+        LineType::Header2 => Some(2),
+        LineType::Header3 => Some(3),
+        _ => None,
+    }
+}
+
+impl ListFilter {
+    fn is_active(&self) -> bool {
+        self.section.is_some() || self.hides_others()
+    }
+
+    // Whether only matching todos are shown, rather than whole sections
+    fn hides_others(&self) -> bool {
+        self.completed.is_some() || self.contains.is_some() || self.tag.is_some() || self.by.is_some() || self.actionable || self.plugin.is_some()
+    }
+
+    fn keeps_todo(&self, item: &TodoItem, items: &[TodoItem]) -> bool {
+        self.completed.is_none_or(|completed| item.completed == completed)
+            && (!self.actionable || !item.completed && !is_blocked(item, items))
+            && self.contains.as_ref().is_none_or(|text| item.text.to_lowercase().contains(text))
+            && self.tag.as_ref().is_none_or(|tag| item.has_tag(tag))
+            && self.by.as_ref().is_none_or(|by| {
+                [item.added_by(), item.done_by()].into_iter().flatten().any(|name| name.eq_ignore_ascii_case(by))
+            })
+            && self.plugin.as_ref().is_none_or(|name| match plugins().filter(name, item) {
+                Ok(keep) => keep,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            })
+    }
+
+    // Which lines to show. Filtering by state, text, tag or person hides everything but
+    // the matching todos and the headers above them.
+    fn visible_lines(&self, items: &[TodoItem]) -> Vec<bool> {
+        let mut in_section = self.section.is_none();
+        let mut section_level = 0;
+        let mut visible: Vec<bool> = items
+            .iter()
+            .map(|item| {
+                if let (Some(section), Some(level)) = (&self.section, header_level(item)) {
+                    if item.text.eq_ignore_ascii_case(section.trim()) {
+                        (in_section, section_level) = (true, level);
+                    } else if level <= section_level {
+                        in_section = false;
+                    }
+                }
+                in_section
+                    && match item.line_type {
+                        LineType::Todo => self.keeps_todo(item, items),
+                        _ => !self.hides_others(),
+                    }
+            })
+            .collect();
+
+        // Notes: shown with their todo. Todos: shown when a subtask is, to
+        // show what it's part of.
+        for i in 0..items.len() {
+            if let Some(owner) = note_owner(items, i) {
+                visible[i] = visible[owner];
+            }
+        }
+        for i in (0..items.len()).rev() {
+            if visible[i] && matches!(items[i].line_type, LineType::Todo) {
+                if let Some(parent) = parent_of(items, i) {
+                    visible[parent] = true;
+                }
+            }
+        }
+
+        if self.hides_others() {
+            // Headers: shown when a todo below them is, up to the next
+            // header of the same or a higher level
+            for i in 0..items.len() {
+                let Some(level) = header_level(&items[i]) else {
+                    continue;
+                };
+                visible[i] = (i + 1..items.len())
+                    .take_while(|&j| header_level(&items[j]).is_none_or(|other| other > level))
+                    .any(|j| visible[j] && matches!(items[j].line_type, LineType::Todo));
+            }
+        }
+        visible
+    }
+}
+
+fn display_todo_list(config: &Config, target_list: Option<String>, read_only: bool, json: bool, filter: &ListFilter) {
+    // Determine which list to display
+    let (list_path, list_name) = resolve_list(config, target_list);
+
+    // Check if the list exists
+    let store = open_storage(config);
+    if !store.exists(&list_name) {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    // A list that can't be decoded is shown with the bad bytes replaced
+    let read = || if read_only { read_list_lossy(&list_path) } else { store.load_list(&list_name) };
+    if json {
+        match read() {
+            Ok(lines) => {
+                let items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+                let visible = filter.visible_lines(&items);
+                let mut exported = export_items(&normalize_list_name(&list_name), &items);
+                exported.retain(|item| visible[item.line - 1]);
+                print_json(&exported);
+            }
+            Err(e) => eprintln!("Error reading todo list: {}", e),
+        }
+        return;
+    }
+
+    // Display header
+    let style = config.list_style(&list_name);
+    let header = format!("=== {} ===", style.label(&list_name));
+    if style.color.is_some() {
+        println!("{}", paint(&header, &style).bold());
+    } else {
+        println!("{}", header.bold().cyan());
+    }
+    println!();
+
+    match read() {
+        Ok(lines) => {
+            let mut incomplete_count = 0;
+            let mut complete_count = 0;
+            let mut has_todos = false;
+
+            let items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+            let visible = filter.visible_lines(&items);
+            // Numbers of the todos shown
+            let shown: Vec<usize> = items
+                .iter()
+                .zip(&visible)
+                .filter(|(item, _)| matches!(item.line_type, LineType::Todo))
+                .enumerate()
+                .filter(|(_, (_, visible))| **visible)
+                .map(|(i, _)| i + 1)
+                .collect();
+
+            let links = config.output.hyperlinks.enabled();
+            let show = |text: &str| if links { linkify(text) } else { text.to_string() };
+            let tagged = |text: &str| show(&paint_words(text));
+
+            let mut urgent = if config.output.due_first { urgent_todos(&items) } else { Vec::new() };
+            urgent.retain(|(index, _, _)| shown.contains(index));
+            let today = Local::now().date_naive();
+            if !urgent.is_empty() {
+                println!("{}", "Due".bold().red());
+                for (index, item, due) in &urgent {
+                    let when = if *due < today { "overdue".red() } else { "today".yellow() };
+                    println!("{} {} {} {}", format!("{:>3}", index).faint(), "☐".bright_yellow(), tagged(&item.text_without_stamps()), when);
+                }
+                println!();
+            }
+
+            // Numbers stay those of the file when the todos are shown sorted
+            let numbers: Vec<usize> = items
+                .iter()
+                .scan(0, |number, item| {
+                    *number += matches!(item.line_type, LineType::Todo) as usize;
+                    Some(*number)
+                })
+                .collect();
+            let order = match filter.sort {
+                Some(by) => sorted_order(&items, by, false),
+                None => (0..items.len()).collect(),
+            };
+            for i in order {
+                let (item, number) = (&items[i], numbers[i]);
+                if !visible[i] {
+                    continue;
+                }
+                match item.line_type {
+                    LineType::Todo => {
+                        has_todos = true;
+                        // Number todos so index-based commands can refer to them,
+                        // with subtasks indented under their todo
+                        let index = format!("{}{}", format!("{:>3}", number).faint(), "  ".repeat(item.depth));
+                        let children = subtasks(&items, i);
+                        let done = children.iter().filter(|&&child| items[child].completed).count();
+                        let mut progress = if children.is_empty() { String::new() } else { format!(" {}/{}", done, children.len()).faint().to_string() };
+                        let decoration = plugins().decorate(item);
+                        if !decoration.is_empty() {
+                            progress = format!("{} {}", progress, decoration.magenta());
+                        }
+                        // Shown without the stamps; `todo get` prints them
+                        let item = &TodoItem { text: item.text_without_stamps(), ..item.clone() };
+                        if urgent.iter().any(|(urgent_index, _, _)| *urgent_index == number) {
+                            // Already shown at the top
+                            incomplete_count += 1;
+                        } else if item.completed {
+                            complete_count += 1;
+                            println!("{} {} {}{}", index, "☑".green(), show(&item.text).strikethrough().faint(), progress);
+                        } else if item.due_date().is_some_and(|due| due < today) {
+                            incomplete_count += 1;
+                            println!("{} {} {}{}", index, "☐".bright_yellow(), show(&item.text).red(), progress);
+                        } else if let Some(priority) = item.priority() {
+                            incomplete_count += 1;
+                            let marker = item.text.split_whitespace().next().unwrap_or_default();
+                            let rest = tagged(item.text_without_priority());
+                            println!("{} {} {} {}{}", index, "☐".bright_yellow(), paint_priority(marker, priority), rest, progress);
+                        } else {
+                            incomplete_count += 1;
+                            println!("{} {} {}{}", index, "☐".bright_yellow(), tagged(&item.text), progress);
+                        }
+                    }
+                    LineType::Header1 => println!("{}", item.text.bold().bright_cyan()),
+                    LineType::Header2 => println!("{}", item.text.bold().cyan()),
+                    LineType::Header3 => println!("{}", if high_contrast() { item.text.bold().bright_blue() } else { item.text.bold().blue() }),
+                    LineType::Bullet => println!("  {} {}", "•".bright_white(), show(&item.text)),
+                    LineType::Text => println!("{}", show(&item.text)),
+                    LineType::Note => {
+                        let depth = note_owner(&items, i).map_or(0, |owner| items[owner].depth);
+                        println!("      {}{}", "  ".repeat(depth), show(&item.text).faint());
+                    }
+                    LineType::Empty => println!(),
+                }
+            }
+
+            if !has_todos && filter.is_active() {
+                println!("{}", "No matching todos.".faint());
+            } else if !has_todos {
+                println!("{}", "No todos found.".faint());
+            } else {
+                println!();
+                println!(
+                    "{} {} incomplete, {} complete",
+                    "Summary:".bold(),
+                    incomplete_count.to_string().bright_yellow(),
+                    complete_count.to_string().green()
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!("Error reading todo list: {}", e);
+        }
+    }
+}
+
+fn show_next(config: &Config, count: usize, target_list: Option<String>) {
+    let store = open_storage(config);
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+
+    if !store.exists(&list) {
+        eprintln!("List '{}' does not exist", list_name);
+        return;
+    }
+
+    let items = match store.load(&list) {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("Error reading todo list '{}': {}", list_name, e);
+            return;
+        }
+    };
+
+    // Keep the numbers `todo list` shows, so they work with index commands
+    let open: Vec<(usize, TodoItem)> = items
+        .into_iter()
+        .filter(|item| matches!(item.line_type, LineType::Todo))
+        .enumerate()
+        .filter(|(_, item)| !item.completed)
+        .take(count)
+        .collect();
+
+    if open.is_empty() {
+        say!("{}", "Nothing left to do.".faint());
+        return;
+    }
+    for (i, item) in open {
+        say!("{} {} {}", format!("{:>3}", i + 1).faint(), "☐".bright_yellow(), item.text_without_stamps());
+    }
+}
+
+// Ask to pick one of several single-letter options; None when stdin is closed
+fn choose(options: &[(char, &str)]) -> Option<char> {
+    loop {
+        for (key, label) in options {
+            println!("  [{}] {}", key, label);
+        }
+        let answer = prompt(">")?.to_lowercase();
+        if let Some(key) = answer.chars().next().filter(|c| options.iter().any(|(key, _)| key == c)) {
+            return Some(key);
+        }
+    }
+}
+
+fn open_in_editor(editor: &str, path: &Path) {
+    match Command::new(editor).arg(path).status() {
+        Ok(status) if !status.success() => eprintln!("Editor exited with status: {}", status),
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to open editor '{}': {}", editor, e),
+    }
+}
+
+// `todo <name>`, for a name that isn't one of todo's commands, runs the
+// executable `name` in ~/.config/todo/scripts with the rest of the
+// arguments, the way git runs git-<name>. Scripts find the lists through
+// TODO_DIR, and the active one through TODO_LIST (its path) and
+// TODO_LIST_NAME. A script that fails makes todo fail with its status.
+fn run_script(config: &Config, args: &[String]) {
+    let Some((name, args)) = args.split_first() else {
+        return;
+    };
+    if let Some(command) = plugins().command(name) {
+        let list = target_list_name(config, None);
+        let items = open_storage(config).load(&list).unwrap_or_default();
+        match plugins().run_command(command, args, &items) {
+            Ok(todos) if todos.is_empty() => {}
+            Ok(todos) => add_todos(config, todos, None, AddPosition::End),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let scripts = scripts_dir();
+    let path = scripts.join(name);
+    if name.starts_with('.') || name.contains(std::path::MAIN_SEPARATOR) || !path.is_file() {
+        eprintln!("Unknown command '{}', and no script of that name in {}", name, scripts.display());
+        eprintln!("Use --help to see available commands");
+        std::process::exit(2);
+    }
+
+    let (list_path, list_name) = resolve_list(config, None);
+    let status = Command::new(&path)
+        .args(args)
+        .env("TODO_DIR", expand_tilde(&config.todo.path))
+        .env("TODO_LIST", &list_path)
+        .env("TODO_LIST_NAME", &list_name)
+        .status();
+    match status {
+        Ok(status) if !status.success() => std::process::exit(status.code().unwrap_or(1)),
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Error running script {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Safe mode for a config file that can't be loaded. The editor setting is
+// part of the broken file, so the editor comes from the environment.
+fn recover_config(mut error: String) -> Config {
+    let path = get_config_path();
+    loop {
+        eprintln!("{}", format!("Can't load {}:", path.display()).bold().red());
+        eprintln!("{}", error.trim_end());
+        if !io::stdin().is_terminal() {
+            std::process::exit(1);
+        }
+
+        let broken = path.with_extension("toml.broken");
+        let reset = format!("reset to defaults (keeping the current file as {})", broken.display());
+        match choose(&[('e', "edit the config file"), ('r', &reset), ('q', "quit")]) {
+            Some('e') => {
+                let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string());
+                open_in_editor(&editor, &path);
+            }
+            Some('r') => {
+                if let Err(e) = fs::rename(&path, &broken) {
+                    eprintln!("Error moving config file aside: {}", e);
+                    continue;
+                }
+            }
+            _ => std::process::exit(1),
+        }
+
+        match ensure_config_exists() {
+            Ok(config) => return config,
+            Err(e) => {
+                eprintln!();
+                error = e;
+            }
+        }
+    }
+}
+
+// Through the desktop's notification service, or remind.command if set. A
+// desktop without one is an error, not a reminder that silently never shows.
+fn notify(config: &RemindConfig, title: &str, message: &str) -> io::Result<()> {
+    if config.command.is_empty() {
+        let shown = notify_rust::Notification::new().appname("todo").summary(title).body(message).show();
+        return shown.map(|_| ()).map_err(|e| {
+            io::Error::other(format!("no notification service to show reminders ({}); set remind.command to a program that can", e))
+        });
+    }
+    let mut command = Command::new(&config.command);
+    if config.command == "osascript" {
+        let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        command.arg("-e").arg(format!("display notification {} with title {}", quote(message), quote(title)));
+    } else {
+        command.arg(title).arg(message);
+    }
+    let status = command.stdout(Stdio::null()).stderr(Stdio::null()).status().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => io::Error::other(format!("'{}' not found; set remind.command", config.command)),
+        _ => e,
+    })?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{} exited with {}", config.command, status)));
+    }
+    Ok(())
+}
+
+// Notify about the open todos due by `remind.days_ahead` days from now that
+// haven't come up yet today. A handful get a notification each; more than
+// that are summed up in one.
+fn check_reminders(config: &Config) -> io::Result<()> {
+    let store = open_storage(config);
+    let mut lists = store.list_lists()?;
+    if config.project_list.is_some() {
+        lists.insert(0, PROJECT_LIST.to_string());
+    }
+
+    let today = Local::now().date_naive();
+    let horizon = today + chrono::Duration::days(config.remind.days_ahead as i64);
+    let mut state = load_state();
+    state.reminded.retain(|_, day| *day == today);
+
+    let mut due = Vec::new();
+    for list in lists {
+        let Ok(items) = store.load(&list) else {
+            continue;
+        };
+        for item in items.iter().filter(|item| matches!(item.line_type, LineType::Todo) && !item.completed) {
+            let Some(date) = item.due_date().filter(|date| *date <= horizon) else {
+                continue;
+            };
+            let key = format!("{}\t{}", list, item.text);
+            if !state.reminded.contains_key(&key) {
+                due.push((list.clone(), item.text_without_stamps(), date, key));
+            }
+        }
+    }
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    let when = |date: NaiveDate| match (date - today).num_days() {
+        days if days < 0 => "Overdue".to_string(),
+        0 => "Due today".to_string(),
+        1 => "Due tomorrow".to_string(),
+        _ => format!("Due {}", date.format("%a %b %-d")),
+    };
+    due.sort_by_key(|(_, _, date, _)| *date);
+    if due.len() <= 3 {
+        for (list, text, date, _) in &due {
+            notify(&config.remind, &when(*date), &format!("{} ({})", text, list))?;
+        }
+    } else {
+        let overdue = due.iter().filter(|(_, _, date, _)| *date < today).count();
+        let title = format!("{} todos due soon, {} overdue", due.len(), overdue);
+        let lines: Vec<String> = due
+            .iter()
+            .take(5)
+            .map(|(list, text, date, _)| format!("{}: {} ({})", when(*date), text, list))
+            .collect();
+        notify(&config.remind, &title, &lines.join("\n"))?;
+    }
+
+    for (_, _, _, key) in due {
+        state.reminded.insert(key, today);
+    }
+    save_state(&state)
+}
+
+fn remind(config: &Config, daemon: bool) {
+    loop {
+        if let Err(e) = check_reminders(config) {
+            eprintln!("Error sending reminders: {}", e);
+            if !daemon {
+                std::process::exit(1);
+            }
+        }
+        if !daemon {
+            return;
+        }
+        thread::sleep(Duration::from_secs(config.remind.interval_mins.max(1) * 60));
+    }
+}
+
+// Redraws go through the storage's change notifications; the list is read
+// leniently, as an editor may be halfway through saving it
+fn watch_list(config: &Config, target_list: Option<String>) {
+    let store = open_storage(config);
+    let list_name = target_list_name(config, target_list);
+    let changes = match store.watch(&list_name) {
+        Ok(changes) => changes,
+        Err(e) => {
+            eprintln!("Error watching todo list: {}", e);
+            return;
+        }
+    };
+
+    loop {
+        let _ = execute!(io::stdout(), Clear(ClearType::All), cursor::MoveTo(0, 0));
+        display_todo_list(config, Some(list_name.clone()), true, false, &ListFilter::default());
+        println!();
+        println!("{}", "Watching for changes, Ctrl-C to stop".faint());
+        if changes.recv().is_err() {
+            break;
+        }
+        // A burst of writes redraws once
+        while changes.try_recv().is_ok() {}
+    }
+}
+
+// Safe mode for a list that can't be decoded: show where it breaks and offer
+// to fix it, restore it or look at it read-only. Returns whether to continue
+// read-only; quitting exits.
+fn recover_list(config: &Config, target_list: Option<String>) -> bool {
+    let (list_path, list_name) = resolve_list(config, target_list);
+    loop {
+        let problem = match diagnose_list(&list_path) {
+            Ok(None) => return false,
+            Ok(Some(problem)) => problem,
+            Err(e) => {
+                eprintln!("Error reading todo list '{}': {}", list_name, e);
+                std::process::exit(1);
+            }
+        };
+
+        eprintln!("{}", format!("Can't read {}: {}", list_name, problem.message).bold().red());
+        eprintln!("{} {}", format!("{:>5} |", problem.line).faint(), problem.context);
+        if !io::stdin().is_terminal() {
+            std::process::exit(1);
+        }
+
+        // The newest backup that reads fine
+        let backup = list_backups(&list_path)
+            .into_iter()
+            .rev()
+            .find(|backup| matches!(diagnose_list(&backup.path), Ok(None)));
+        let restore = backup
+            .as_ref()
+            .map(|backup| format!("restore the backup from {}", backup.taken.format("%Y-%m-%d %H:%M:%S")));
+
+        let mut options = vec![('e', "open it in the editor")];
+        if let Some(restore) = &restore {
+            options.push(('r', restore));
+        }
+        options.extend([('v', "continue read-only"), ('q', "quit")]);
+
+        match choose(&options) {
+            Some('e') => open_in_editor(&config.editor.command, &list_path),
+            Some('r') => {
+                let Some(backup) = &backup else { continue };
+                // Keep the broken file among the backups in case it matters
+                if let Err(e) = snapshot_list(&list_path, Local::now().naive_local(), &config.backup) {
+                    eprintln!("Error backing up todo list: {}", e);
+                    continue;
+                }
+                match fs::copy(&backup.path, &list_path) {
+                    Ok(_) => println!("Restored {} from backup taken {}", list_name, backup.taken.format("%Y-%m-%d %H:%M:%S")),
+                    Err(e) => eprintln!("Error restoring backup: {}", e),
+                }
+            }
+            Some('v') => return true,
+            _ => std::process::exit(1),
+        }
+    }
+}
+
+// The active list's file is missing. On first use that just means creating
+// it, but if it had backups or journaled changes it was deleted or renamed
+// behind todo's back, and quietly starting over with an empty list would
+// look like lost todos. Asks what to do instead; without a terminal to ask
+// on, it warns and creates the list.
+fn check_active_list(config: &mut Config, list_path: &Path) {
+    if list_path.exists() || config.project_list.is_some() {
+        ensure_active_list_exists(list_path);
+        return;
+    }
+    let backup = list_backups(list_path).pop();
+    let journaled = read_journal()
+        .unwrap_or_default()
+        .iter()
+        .any(|entry| entry.changes.iter().any(|change| change.path == list_path));
+    if backup.is_none() && !journaled {
+        ensure_active_list_exists(list_path);
+        return;
+    }
+
+    let list_name = format!("{}.{}", config.todo.active_list, config.todo.list_extension);
+    if !io::stdin().is_terminal() {
+        eprintln!("Warning: the active list {} was missing; created an empty one", list_name);
+        if backup.is_some() {
+            eprintln!("Its backups are still there: `todo backups list` shows them");
+        }
+        ensure_active_list_exists(list_path);
+        return;
+    }
+
+    // A list holding exactly what the last backup did is most likely the
+    // same list under a new name
+    let store = open_storage(config);
+    let lists = store.list_lists().unwrap_or_default();
+    let backup_content = backup.as_ref().and_then(|backup| fs::read_to_string(&backup.path).ok());
+    let renamed = backup_content.as_ref().and_then(|content| {
+        lists.iter().find(|name| fs::read_to_string(store_path(config, name)).is_ok_and(|other| other == *content))
+    });
+
+    eprintln!("{}", format!("The active list {} is gone: it was deleted or renamed outside todo.", list_name).bold().red());
+    let switch = renamed.map(|name| format!("switch to {}, which has the same todos", name));
+    let restore = backup
+        .as_ref()
+        .map(|backup| format!("restore the backup from {}", backup.taken.format("%Y-%m-%d %H:%M:%S")));
+    let mut options = Vec::new();
+    if let Some(switch) = &switch {
+        options.push(('s', switch.as_str()));
+    }
+    if let Some(restore) = &restore {
+        options.push(('r', restore.as_str()));
+    }
+    options.extend([('p', "pick another list"), ('n', "start a new, empty list"), ('q', "quit")]);
+
+    loop {
+        match choose(&options) {
+            Some('s') => {
+                if let Some(name) = renamed {
+                    use_list(config, name.clone());
+                }
+                return;
+            }
+            Some('r') => {
+                let Some(backup) = &backup else { continue };
+                match fs::copy(&backup.path, list_path) {
+                    Ok(_) => println!("Restored {} from backup taken {}", list_name, backup.taken.format("%Y-%m-%d %H:%M:%S")),
+                    Err(e) => {
+                        eprintln!("Error restoring backup: {}", e);
+                        continue;
+                    }
+                }
+                return;
+            }
+            Some('p') => {
+                if lists.is_empty() {
+                    println!("There are no other lists.");
+                    continue;
+                }
+                for (i, name) in lists.iter().enumerate() {
+                    println!("  {:>3} {}", i + 1, name);
+                }
+                let Some(answer) = prompt("List number or name:") else { continue };
+                let picked = answer.parse::<usize>().ok().and_then(|i| lists.get(i.wrapping_sub(1))).cloned();
+                match picked.or_else(|| lists.iter().find(|name| **name == normalize_list_name(&answer)).cloned()) {
+                    Some(name) => {
+                        use_list(config, name);
+                        return;
+                    }
+                    None => println!("No list '{}'.", answer),
+                }
+            }
+            Some('n') => {
+                ensure_active_list_exists(list_path);
+                return;
+            }
+            _ => std::process::exit(1),
+        }
+    }
+}
+
+// Where a list of the workspace lives
+fn store_path(config: &Config, list_name: &str) -> PathBuf {
+    get_list_path(config, &expand_tilde(&config.todo.path), list_name)
+}
+
+// Fireworks, or with `animate` off just the message
+fn show_fireworks(animate: bool) -> io::Result<()> {
+    let mut stdout = io::stdout();
+
+    // Clear screen
+    execute!(stdout, Clear(ClearType::All), cursor::Hide)?;
+
+    // Fireworks explosion patterns
+    let explosion_frames = [
+        vec!["        *        ", "       ***       ", "      *****      ", "     *******     ", "    *********    "],
+        vec!["    *       *    ", "   **       **   ", "  ***       ***  ", " ****       **** ", "*****       *****"],
+        vec!["  *           *  ", " * *         * * ", "*   *       *   *", " *   *     *   * ", "  *   *   *   *  "],
+        vec![" *             * ", "*               *", "                 ", "*               *", " *             * "],
+    ];
+
+    let colors = [
+        "\x1b[91m", // Bright red
+        "\x1b[93m", // Bright yellow
+        "\x1b[92m", // Bright green
+        "\x1b[96m", // Bright cyan
+        "\x1b[95m", // Bright magenta
+    ];
+    let reset = "\x1b[0m";
+
+    // Show celebration message
+    execute!(stdout, cursor::MoveTo(0, 0))?;
+    let msg = "🎉  ALL TODOS COMPLETE!  🎉";
+    let padding = (80_u16.saturating_sub(msg.len() as u16)) / 2;
+    execute!(stdout, cursor::MoveTo(padding, 2))?;
+    write!(stdout, "{}", msg.bright_green().bold())?;
+    stdout.flush()?;
+
+    // Animate fireworks at different positions
+    let positions = vec![(10, 8), (50, 6), (30, 10), (60, 12), (20, 14)];
+
+    for round in (0..3).filter(|_| animate) {
+        for (frame_idx, frame) in explosion_frames.iter().enumerate() {
+            execute!(stdout, cursor::MoveTo(0, 4))?;
+
+            // Draw all fireworks
+            for (pos_idx, &(x, y)) in positions.iter().enumerate() {
+                let color = colors[pos_idx % colors.len()];
+
+                // Only show this firework if it's time
+                if (round * positions.len() + pos_idx) / 2 <= frame_idx {
+                    for (i, line) in frame.iter().enumerate() {
+                        execute!(stdout, cursor::MoveTo(x, y + i as u16))?;
+                        write!(stdout, "{}{}{}", color, line, reset)?;
+                    }
+                }
+            }
+
+            stdout.flush()?;
+            thread::sleep(Duration::from_millis(150));
+
+            // Clear fireworks for next frame
+            for &(x, y) in &positions {
+                for i in 0..5 {
+                    execute!(stdout, cursor::MoveTo(x, y + i))?;
+                    write!(stdout, "                 ")?;
+                }
+            }
+        }
+    }
+
+    // Final message
+    execute!(stdout, cursor::MoveTo(0, 20))?;
+    let final_msg = "Press any key to continue...";
+    let padding = (80_u16.saturating_sub(final_msg.len() as u16)) / 2;
+    execute!(stdout, cursor::MoveTo(padding, 20))?;
+    write!(stdout, "{}", final_msg.faint())?;
+    stdout.flush()?;
+
+    // Wait for key press
+    loop {
+        if let Event::Key(_) = event::read()? {
+            break;
+        }
+    }
+
+    execute!(stdout, Clear(ClearType::All), cursor::Show)?;
+    Ok(())
+}
+
+// A todo's text with misspelled words underlined in red and, with `tags`,
+// its tags colored
+fn word_spans(text: String, misspellings: &[Misspelling], style: Style, tags: bool) -> Vec<Span<'static>> {
+    if misspellings.is_empty() && !(tags && text.contains('#')) {
+        return vec![Span::styled(text, style)];
+    }
+    let misspelled = |word: &str| {
+        let word = word.trim();
+        let bare = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'');
+        spell::is_checked(word) && misspellings.iter().any(|m| m.word == bare)
+    };
+    text.split_inclusive(' ')
+        .flat_map(|word| {
+            let bare = word.trim_end();
+            if misspelled(word) {
+                vec![Span::styled(word.to_string(), style.fg(Color::Red).add_modifier(Modifier::UNDERLINED))]
+            } else if tags && tag_of(bare).is_some() {
+                match tag_style(bare) {
+                    // A chip, which the space after it isn't part of
+                    Some(tag) => vec![
+                        Span::styled(bare.to_string(), style.fg(Color::Black).bg(tui_color(tag, Color::Magenta))),
+                        Span::styled(word[bare.len()..].to_string(), style),
+                    ],
+                    None => vec![Span::styled(word.to_string(), style.fg(Color::Magenta))],
+                }
+            } else {
+                vec![Span::styled(word.to_string(), style)]
+            }
+        })
+        .collect()
+}
+
+// The spans with what matches the search in their text, taken together as
+// it reads, picked out
+fn highlight_spans<'a>(spans: Vec<Span<'a>>, pattern: &Regex) -> Vec<Span<'a>> {
+    let text: String = spans.iter().map(|span| span.content.as_ref()).collect();
+    let ranges: Vec<(usize, usize)> = pattern.find_iter(&text).map(|found| (found.start(), found.end())).collect();
+    if ranges.is_empty() {
+        return spans;
+    }
+    let found = Style::default().fg(Color::Black).bg(Color::Yellow);
+    let mut highlighted = Vec::new();
+    let mut start = 0;
+    for span in spans {
+        let end = start + span.content.len();
+        let mut at = start;
+        for &(from, to) in ranges.iter().filter(|&&(from, to)| from < end && to > start) {
+            let (from, to) = (from.max(start), to.min(end));
+            if from > at {
+                highlighted.push(Span::styled(text[at..from].to_string(), span.style));
+            }
+            highlighted.push(Span::styled(text[from..to].to_string(), span.style.patch(found)));
+            at = to;
+        }
+        if at < end {
+            highlighted.push(Span::styled(text[at..end].to_string(), span.style));
+        }
+        start = end;
+    }
+    highlighted
+}
+
+// A line as its characters, each with its style
+fn line_cells(line: Line) -> Vec<(char, Style)> {
+    let base = line.style;
+    line.spans.iter().flat_map(|span| span.content.chars().map(move |c| (c, base.patch(span.style)))).collect()
+}
+
+// Characters back into spans, one for each run of a style
+fn cells_line(cells: &[(char, Style)]) -> Line<'static> {
+    let mut spans: Vec<Span> = Vec::new();
+    for &(c, style) in cells {
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push(c),
+            _ => spans.push(Span::styled(c.to_string(), style)),
+        }
+    }
+    Line::from(spans)
+}
+
+// The line without its first `columns` columns
+fn scroll_line(line: Line, columns: usize) -> Line {
+    if columns == 0 {
+        return line;
+    }
+    let mut skipped = 0;
+    let cells: Vec<(char, Style)> = line_cells(line)
+        .into_iter()
+        .skip_while(|&(c, _)| {
+            skipped += c.width().unwrap_or(0);
+            skipped <= columns
+        })
+        .collect();
+    cells_line(&cells)
+}
+
+// The line broken into rows of at most `width` columns, after the last
+// space that fits where there is one, with the rows after the first
+// indented by `hang`
+fn wrap_line(line: Line, width: usize, hang: usize) -> Vec<Line<'static>> {
+    let cells = line_cells(line);
+    let hang = if hang * 2 < width { hang } else { 0 };
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while start < cells.len() {
+        let indent = if rows.is_empty() { 0 } else { hang };
+        let (mut end, mut used) = (start, indent);
+        while end < cells.len() && used + cells[end].0.width().unwrap_or(0) <= width.max(indent + 1) {
+            used += cells[end].0.width().unwrap_or(0);
+            end += 1;
+        }
+        if end < cells.len() {
+            if let Some(space) = (start + 1..end).rev().find(|&i| cells[i].0 == ' ') {
+                end = space + 1;
+            }
+        }
+        // A character wider than the view still gets a row
+        end = end.max(start + 1);
+        let mut row = vec![(' ', Style::default()); indent];
+        row.extend_from_slice(&cells[start..end]);
+        rows.push(cells_line(&row));
+        start = end;
+        // Continuation rows don't start with the space they broke at
+        while start < cells.len() && cells[start].0 == ' ' {
+            start += 1;
+        }
+    }
+    if rows.is_empty() {
+        rows.push(Line::from(""));
+    }
+    rows
+}
+
+const DETAILS_WIDTH: u16 = 36;
+
+// What a key does in the list view
+#[derive(Clone, Copy, PartialEq)]
+enum Action {
+    Down,
+    Up,
+    Top,
+    Bottom,
+    HalfPageDown,
+    HalfPageUp,
+    NextColumn,
+    ScrollRight,
+    ScrollLeft,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    NewList,
+    Edit,
+    Append,
+    InsertBelow,
+    InsertAbove,
+    MoveDown,
+    MoveUp,
+    Nest,
+    Unnest,
+    DueToday,
+    DueTomorrow,
+    DueNextWeek,
+    DuePrompt,
+    Delete,
+    Undo,
+    Redo,
+    Paste,
+    Visual,
+    Toggle,
+    Details,
+    HideCompleted,
+    Wrap,
+    Escape,
+    Quit,
+    Help,
+    // In edit mode
+    Save,
+    Cancel,
+    Cursor,
+    DeleteBefore,
+    DeleteUnder,
+}
+
+impl Action {
+    // Whether the key does this now; if not, a later binding of the same
+    // key may
+    fn applies(self, app: &App) -> bool {
+        match self {
+            Action::ScrollRight => !app.wrap,
+            Action::NextMatch | Action::PreviousMatch => app.search.is_some(),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum KeyMode {
+    Normal,
+    Edit,
+}
+
+// A line of the help, and the keys behind it. A key with Ctrl only matches
+// with Ctrl held; other modifiers don't matter.
+struct Binding {
+    label: &'static str,
+    help: &'static str,
+    keys: &'static [(KeyCode, bool, Action)],
+}
+
+struct KeySection {
+    title: &'static str,
+    mode: KeyMode,
+    bindings: &'static [Binding],
+}
+
+const fn key(code: KeyCode, action: Action) -> (KeyCode, bool, Action) {
+    (code, false, action)
+}
+
+const fn char_key(c: char, action: Action) -> (KeyCode, bool, Action) {
+    (KeyCode::Char(c), false, action)
+}
+
+const fn ctrl_key(c: char, action: Action) -> (KeyCode, bool, Action) {
+    (KeyCode::Char(c), true, action)
+}
+
+// Every key of the list view and what it does, by section: handle_key
+// dispatches from it, and the help overlay is drawn from it
+const KEYMAP: &[KeySection] = &[
+    KeySection {
+        title: "Navigation",
+        mode: KeyMode::Normal,
+        bindings: &[
+            Binding {
+                label: "j / ↓",
+                help: "Move down to next todo or header",
+                keys: &[char_key('j', Action::Down), key(KeyCode::Down, Action::Down)],
+            },
+            Binding {
+                label: "k / ↑",
+                help: "Move up to previous todo or header",
+                keys: &[char_key('k', Action::Up), key(KeyCode::Up, Action::Up)],
+            },
+            Binding { label: "g", help: "Go to first todo", keys: &[char_key('g', Action::Top)] },
+            Binding { label: "G", help: "Go to last todo", keys: &[char_key('G', Action::Bottom)] },
+            Binding {
+                label: "Ctrl+d / u",
+                help: "Scroll down / up half a page",
+                keys: &[ctrl_key('d', Action::HalfPageDown), ctrl_key('u', Action::HalfPageUp)],
+            },
+            Binding { label: "Mouse", help: "Click to select, click the box to toggle; wheel scrolls", keys: &[] },
+            Binding { label: "Ctrl+w", help: "Next column (when tui.columns > 1)", keys: &[ctrl_key('w', Action::NextColumn)] },
+            Binding {
+                label: "H / L",
+                help: "Scroll long todos sideways (← / → too), when not wrapped",
+                keys: &[
+                    char_key('H', Action::ScrollLeft),
+                    key(KeyCode::Left, Action::ScrollLeft),
+                    char_key('L', Action::ScrollRight),
+                    key(KeyCode::Right, Action::ScrollRight),
+                ],
+            },
+            Binding {
+                label: "/",
+                help: "Search; then n / N for the next / previous match",
+                keys: &[char_key('/', Action::Search), char_key('n', Action::NextMatch), char_key('N', Action::PreviousMatch)],
+            },
+            Binding { label: "N", help: "Create a list and switch to it", keys: &[char_key('N', Action::NewList)] },
+        ],
+    },
+    KeySection {
+        title: "Editing",
+        mode: KeyMode::Normal,
+        bindings: &[
+            Binding {
+                label: "e / Enter",
+                help: "Edit current todo",
+                keys: &[char_key('e', Action::Edit), key(KeyCode::Enter, Action::Edit)],
+            },
+            Binding { label: "a", help: "Add a new todo at the end of the list", keys: &[char_key('a', Action::Append)] },
+            // The digit is read as the new todo's first character
+            Binding { label: "a1, a2...", help: "Add a new todo to the first, second... section", keys: &[] },
+            Binding { label: "o", help: "Insert new todo below current line", keys: &[char_key('o', Action::InsertBelow)] },
+            Binding { label: "O", help: "Insert new todo above current line", keys: &[char_key('O', Action::InsertAbove)] },
+            Binding {
+                label: "J / K",
+                help: "Move todo down / up, into other sections too",
+                keys: &[char_key('J', Action::MoveDown), char_key('K', Action::MoveUp)],
+            },
+            Binding { label: "Tab", help: "Make todo a subtask of the one above", keys: &[key(KeyCode::Tab, Action::Nest)] },
+            Binding { label: "Shift+Tab", help: "Take subtask a level out", keys: &[key(KeyCode::BackTab, Action::Unnest)] },
+            Binding {
+                label: "t / m / w",
+                help: "Make todo due today, tomorrow or next week",
+                keys: &[char_key('t', Action::DueToday), char_key('m', Action::DueTomorrow), char_key('w', Action::DueNextWeek)],
+            },
+            Binding {
+                label: "D",
+                help: "Type a due date (\"fri\", \"in 3 days\", \"jun 1\")",
+                keys: &[char_key('D', Action::DuePrompt)],
+            },
+            Binding { label: "dd", help: "Delete current todo (held in memory until quit)", keys: &[char_key('d', Action::Delete)] },
+            Binding { label: "u", help: "Undo the last change", keys: &[char_key('u', Action::Undo)] },
+            Binding { label: "Ctrl+r", help: "Redo what was undone", keys: &[ctrl_key('r', Action::Redo)] },
+            Binding { label: "p", help: "Paste the last deleted todo below the current one", keys: &[char_key('p', Action::Paste)] },
+            Binding {
+                label: "v",
+                help: "Select todos from here with j / k, then Space, d or J / K",
+                keys: &[char_key('v', Action::Visual)],
+            },
+        ],
+    },
+    KeySection {
+        title: "In Edit Mode",
+        mode: KeyMode::Edit,
+        bindings: &[
+            Binding { label: "Enter", help: "Save changes", keys: &[key(KeyCode::Enter, Action::Save)] },
+            Binding { label: "ESC", help: "Cancel (discard new todo or restore original)", keys: &[key(KeyCode::Esc, Action::Cancel)] },
+            Binding {
+                label: "← / →",
+                help: "Move the cursor (Home/End: to the start/end)",
+                keys: &[
+                    key(KeyCode::Left, Action::Cursor),
+                    key(KeyCode::Right, Action::Cursor),
+                    key(KeyCode::Home, Action::Cursor),
+                    key(KeyCode::End, Action::Cursor),
+                ],
+            },
+            Binding {
+                label: "Backspace",
+                help: "Delete character before the cursor (Del: under it)",
+                keys: &[key(KeyCode::Backspace, Action::DeleteBefore), key(KeyCode::Delete, Action::DeleteUnder)],
+            },
+        ],
+    },
+    KeySection {
+        title: "Actions",
+        mode: KeyMode::Normal,
+        bindings: &[
+            Binding {
+                label: "Space",
+                help: "Toggle todo completion; on a header, its whole section",
+                keys: &[char_key(' ', Action::Toggle)],
+            },
+            Binding { label: "i", help: "Show or hide the selected todo's details", keys: &[char_key('i', Action::Details)] },
+            Binding { label: "h", help: "Hide or show completed todos", keys: &[char_key('h', Action::HideCompleted)] },
+            Binding { label: "z", help: "Wrap long todos, or cut them off at the edge", keys: &[char_key('z', Action::Wrap)] },
+            Binding {
+                label: "Esc",
+                help: "Dismiss the hint in the status bar, or clear the search",
+                keys: &[key(KeyCode::Esc, Action::Escape)],
+            },
+            Binding { label: "q", help: "Save and quit", keys: &[char_key('q', Action::Quit)] },
+            Binding { label: "?", help: "Toggle this help", keys: &[char_key('?', Action::Help)] },
+        ],
+    },
+];
+
+// What a key does in `mode`: the first of its bindings that applies now
+fn key_action(app: &App, mode: KeyMode, key: KeyEvent) -> Option<Action> {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    KEYMAP
+        .iter()
+        .filter(|section| section.mode == mode)
+        .flat_map(|section| section.bindings)
+        .flat_map(|binding| binding.keys)
+        .find(|&&(code, with_ctrl, action)| code == key.code && with_ctrl == ctrl && action.applies(app))
+        .map(|&(_, _, action)| action)
+}
+
+// The keymap in a centered popup, its sections spread over as many
+// columns as it takes to fit the screen's height, if its width allows
+fn help_ui(f: &mut Frame) {
+    let keymap: Vec<(&str, Vec<(&str, &str)>)> = KEYMAP
+        .iter()
+        .map(|section| (section.title, section.bindings.iter().map(|binding| (binding.label, binding.help)).collect()))
+        .collect();
+    // Keys plugins added come last, under their own title, leaving out
+    // those the TUI keeps for itself
+    let bound = |name: &str| {
+        let normal = KEYMAP.iter().filter(|section| section.mode == KeyMode::Normal);
+        let mut keys = normal.flat_map(|section| section.bindings).flat_map(|binding| binding.keys);
+        keys.any(|&(code, ctrl, _)| !ctrl && matches!(code, KeyCode::Char(c) if name.chars().eq([c])))
+    };
+    let plugin_keys: Vec<(&str, &str)> = plugins()
+        .keys
+        .iter()
+        .filter(|key| !bound(&key.name))
+        .map(|key| (key.name.as_str(), key.help.as_str()))
+        .collect();
+    let plugin_section = [("Plugins", plugin_keys)];
+    let key_width = keymap.iter().flat_map(|(_, keys)| keys.iter()).map(|(key, _)| display_width(key)).max().unwrap_or(0);
+    let sections: Vec<Vec<Line>> = keymap
+        .into_iter()
+        .chain(plugin_section.into_iter().filter(|(_, keys)| !keys.is_empty()))
+        .map(|(title, keys)| {
+            let mut lines = vec![Line::styled(title, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))];
+            lines.extend(keys.iter().map(|(key, action)| Line::from(format!("  {:<width$}  {}", key, action, width = key_width))));
+            lines
+        })
+        .collect();
+    let column_width = sections.iter().flatten().map(Line::width).max().unwrap_or(0) as u16;
+
+    // Whole sections to a column, in order, with the longest column as
+    // short as it can be
+    let split = |columns: usize| -> Vec<Vec<Line>> {
+        let pack = |most: usize| {
+            let mut split: Vec<Vec<Line>> = vec![Vec::new()];
+            for section in &sections {
+                let column = split.last_mut().expect("at least one column");
+                if !column.is_empty() && column.len() + 1 + section.len() > most {
+                    split.push(Vec::new());
+                } else if !column.is_empty() {
+                    column.push(Line::from(""));
+                }
+                split.last_mut().expect("at least one column").extend(section.iter().cloned());
+            }
+            split
+        };
+        let total = sections.iter().map(|section| section.len() + 1).sum::<usize>();
+        (1..=total).map(pack).find(|split| split.len() <= columns).unwrap_or_else(|| pack(total))
+    };
+    let area = f.area();
+    let fits_width = |columns: u16| columns * column_width + 2 * (columns - 1) + 4 <= area.width.saturating_sub(4);
+    let fits_height = |split: &[Vec<Line>]| split.iter().map(Vec::len).max().unwrap_or(0) as u16 + 2 <= area.height.saturating_sub(4);
+    let mut columns = 1;
+    while !fits_height(&split(columns)) && columns < KEYMAP.len() && fits_width(columns as u16 + 1) {
+        columns += 1;
+    }
+    let split = split(columns);
+    let columns = split.len();
+    let rows = split.iter().map(Vec::len).max().unwrap_or(0) as u16;
+
+    let popup_width = (column_width * columns as u16 + 2 * (columns as u16 - 1) + 4).min(area.width.saturating_sub(4));
+    let popup_height = (rows + 2).min(area.height.saturating_sub(4));
+    let popup_area = Rect {
+        x: (area.width.saturating_sub(popup_width)) / 2,
+        y: (area.height.saturating_sub(popup_height)) / 2,
+        width: popup_width,
+        height: popup_height,
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .title(" Help - Press any key to close ")
+        .title_alignment(Alignment::Center);
+    let inner = block.inner(popup_area).inner(Margin { horizontal: 1, vertical: 0 });
+    f.render_widget(widgets::Clear, popup_area);
+    f.render_widget(block, popup_area);
+
+    let areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+        .spacing(2)
+        .split(inner);
+    for (lines, area) in split.into_iter().zip(areas.iter()) {
+        f.render_widget(Paragraph::new(lines).style(Style::default().fg(Color::White)), *area);
+    }
+}
+
+// What the TUI's detail pane shows of a todo: its stamps and metadata, then
+// its note
+fn detail_lines(item: &TodoItem, note: Option<String>) -> Vec<Line<'static>> {
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<10}", name), Style::default().fg(Color::DarkGray)),
+            Span::raw(value),
+        ])
+    };
+    let stamp = |date: Option<NaiveDate>, by: Option<&str>| match (date, by) {
+        (Some(date), Some(by)) => Some(format!("{} by {}", date, by)),
+        (Some(date), None) => Some(date.to_string()),
+        (None, Some(by)) => Some(format!("by {}", by)),
+        (None, None) => None,
+    };
+
+    let mut lines = vec![Line::styled(item.text_without_stamps(), Style::default().add_modifier(Modifier::BOLD)), Line::from("")];
+    if let Some(added) = stamp(item.added(), item.added_by()) {
+        lines.push(field("Added", added));
+    }
+    if let Some(id) = item.id() {
+        lines.push(field("Id", id.to_string()));
+    }
+    if item.completed {
+        lines.push(field("Done", stamp(item.done(), item.done_by()).unwrap_or_else(|| "yes".to_string())));
+    }
+    if let Some(due) = item.due_date() {
+        lines.push(field("Due", due.to_string()));
+    }
+    if let Some(priority) = item.priority() {
+        lines.push(field("Priority", priority.to_string()));
+    }
+    let tags = item.tags();
+    if !tags.is_empty() {
+        lines.push(field("Tags", tags.iter().map(|tag| format!("#{}", tag)).collect::<Vec<_>>().join(" ")));
+    }
+    if let Some(estimate) = item.estimate() {
+        lines.push(field("Estimate", estimate.to_string()));
+    }
+    if let Some(every) = item.every() {
+        lines.push(field("Repeats", format!("every {}", every)));
+    }
+    if let Some(note) = note {
+        lines.push(Line::from(""));
+        lines.extend(note.lines().map(|line| Line::from(line.to_string())));
+    }
+    lines
+}
+
+fn ui(f: &mut Frame, app: &App) {
+    let compact = app.density == Density::Compact;
+    let today = Local::now().date_naive();
+    // Compact mode drops borders and padding, leaving one line per bar
+    let (bar, padding, borders) = if compact { (1, "", Borders::NONE) } else { (3, "  ", Borders::ALL) };
+    let chunks = if app.edit_mode {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(bar),  // Title
+                Constraint::Min(0),       // Content
+                Constraint::Length(bar),  // Input field
+                Constraint::Length(bar),  // Status bar
+            ])
+            .split(f.area())
+    } else {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(bar),  // Title
+                Constraint::Min(0),       // Content
+                Constraint::Length(bar),  // Status bar
+            ])
+            .split(f.area())
+    };
+
+    // Title
+    let read_only = if app.read_only { " (read-only)" } else { "" };
+    let hidden = match app.count_todos() {
+        (_, complete) if app.hide_completed && complete > 0 => format!(" · {} done hidden", complete),
+        _ => String::new(),
+    };
+    let accent = tui_color(&app.style, Color::Cyan);
+    let title = Paragraph::new(format!("{}{}{}{} ", padding, app.style.label(&app.list_name), read_only, hidden))
+        .style(
+            Style::default()
+                .fg(accent)
+                .add_modifier(Modifier::BOLD)
+        )
+        .block(
+            Block::default()
+                .borders(borders)
+                .border_style(Style::default().fg(accent))
+        );
+    f.render_widget(title, chunks[0]);
+
+    // Todo list
+    let visible = app.visible_rows();
+    let items: Vec<(Line, Style, usize)> = visible
+        .iter()
+        .map(|&i| (i, &app.items[i]))
+        .map(|(i, todo_item)| {
+            let is_pending_delete = app.is_deleted(i);
+
+            let mut content = match todo_item.line_type {
+                LineType::Todo => {
+                    // The star is drawn after the checkbox rather than where it is in
+                    // the text, and the stamps are left to the details pane
+                    let starred = todo_item.starred();
+                    let text = todo_item.text_without_stamps();
+                    let text = if starred { text.replace(STAR, "").trim_end().to_string() } else { text };
+                    let star = Span::styled(if starred { "★ " } else { "" }, Style::default().fg(Color::Yellow));
+                    if is_pending_delete {
+                        Line::from(vec![
+                            Span::styled("✗ ", Style::default().fg(Color::Red)),
+                            star,
+                            Span::styled(
+                                text,
+                                Style::default()
+                                    .fg(Color::DarkGray)
+                                    .add_modifier(Modifier::CROSSED_OUT | Modifier::DIM),
                             ),
                         ])
-                    } else {
+                    } else if todo_item.completed {
                         Line::from(vec![
-                            Span::styled("☐ ", Style::default().fg(Color::Yellow)),
-                            Span::styled(&todo_item.text, Style::default().fg(Color::White)),
+                            Span::styled("☑ ", Style::default().fg(Color::Green)),
+                            star,
+                            Span::styled(
+                                text,
+                                Style::default()
+                                    .fg(Color::DarkGray)
+                                    .add_modifier(Modifier::CROSSED_OUT),
+                            ),
                         ])
+                    } else {
+                        let mut spans = vec![Span::styled("☐ ", Style::default().fg(Color::Yellow)), star];
+                        // Waiting on todos that aren't done yet
+                        if is_blocked(todo_item, &app.items) {
+                            spans.push(Span::styled("🔒 ", Style::default().fg(Color::DarkGray)));
+                        }
+                        let misspellings = app.misspellings.get(&todo_item.text).map(Vec::as_slice).unwrap_or(&[]);
+                        let overdue = todo_item.due_date().is_some_and(|due| due < today);
+                        let color = if overdue { Color::Red } else { Color::White };
+                        let text = match todo_item.priority() {
+                            Some(priority) => {
+                                let marker = text.split_whitespace().next().unwrap_or_default().to_string();
+                                let style = Style::default().fg(priority_color(priority)).add_modifier(Modifier::BOLD);
+                                spans.push(Span::styled(format!("{} ", marker), style));
+                                text.trim_start()[marker.len()..].trim_start().to_string()
+                            }
+                            None => text,
+                        };
+                        spans.extend(word_spans(text, misspellings, Style::default().fg(color), !overdue));
+                        Line::from(spans)
                     }
                 }
-                LineType::Header1 => {
-                    Line::from(Span::styled(
-                        &todo_item.text,
-                        Style::default()
-                            .fg(Color::Cyan)
-                            .add_modifier(Modifier::BOLD),
-                    ))
+                LineType::Header1 => {
+                    Line::from(Span::styled(
+                        &todo_item.text,
+                        Style::default()
+                            .fg(Color::Cyan)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                }
+                LineType::Header2 => {
+                    Line::from(Span::styled(
+                        &todo_item.text,
+                        Style::default()
+                            .fg(Color::Blue)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                }
+                LineType::Header3 => {
+                    Line::from(Span::styled(
+                        &todo_item.text,
+                        Style::default()
+                            .fg(Color::Magenta)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                }
+                LineType::Bullet => {
+                    Line::from(vec![
+                        Span::raw("  • "),
+                        Span::styled(&todo_item.text, Style::default().fg(Color::White)),
+                    ])
+                }
+                LineType::Text => {
+                    Line::from(Span::styled(&todo_item.text, Style::default().fg(Color::Gray)))
+                }
+                LineType::Note => {
+                    // Lined up with the todo's text, and going with it when it's deleted
+                    let deleted = app.is_deleted(i);
+                    let style = Style::default().fg(Color::DarkGray);
+                    let style = if deleted { style.add_modifier(Modifier::CROSSED_OUT | Modifier::DIM) } else { style };
+                    Line::from(vec![Span::raw("  "), Span::styled(&todo_item.text, style)])
+                }
+                LineType::Empty => Line::from(""),
+            };
+            // Subtasks are indented under their todo, which shows how many of
+            // them are done
+            let depth = match todo_item.line_type {
+                LineType::Todo => todo_item.depth,
+                LineType::Note => note_owner(&app.items, i).map_or(0, |owner| app.items[owner].depth),
+                _ => 0,
+            };
+            if depth > 0 {
+                content.spans.insert(0, Span::raw("  ".repeat(depth)));
+            }
+            let children = subtasks(&app.items, i);
+            if matches!(todo_item.line_type, LineType::Todo) && !children.is_empty() {
+                let done = children.iter().filter(|&&child| app.items[child].completed).count();
+                content.spans.push(Span::styled(format!(" {}/{}", done, children.len()), Style::default().fg(Color::DarkGray)));
+            }
+            let decoration = if matches!(todo_item.line_type, LineType::Todo) { plugins().decorate(todo_item) } else { String::new() };
+            if !decoration.is_empty() {
+                content.spans.push(Span::styled(format!(" {}", decoration), Style::default().fg(Color::Magenta)));
+            }
+
+            if let Some(pattern) = app.search.as_ref().filter(|_| matches!(todo_item.line_type, LineType::Todo)) {
+                content.spans = highlight_spans(content.spans, pattern);
+            }
+
+            let style = if i == app.selected {
+                Style::default()
+                    .bg(Color::Rgb(60, 60, 80))
+                    .add_modifier(Modifier::BOLD)
+            } else if app.visual_range().is_some_and(|range| range.contains(&i)) {
+                Style::default().bg(Color::Rgb(45, 45, 65))
+            } else {
+                Style::default()
+            };
+
+            // Wrapped rows line up under the text, past the indent and checkbox
+            let hang = match todo_item.line_type {
+                LineType::Todo | LineType::Note => 2 * depth + 2,
+                LineType::Bullet => 4,
+                _ => 0,
+            };
+            (content, style, hang)
+        })
+        .collect();
+
+    // The detail pane takes the right of the content area, when there's room
+    // left for the list
+    let (content, details) = if app.show_details && chunks[1].width >= DETAILS_WIDTH * 2 {
+        let split = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(DETAILS_WIDTH)])
+            .split(chunks[1]);
+        (split[0], Some(split[1]))
+    } else {
+        (chunks[1], None)
+    };
+    if let Some(area) = details {
+        let lines = match app.items.get(app.selected) {
+            Some(item) if matches!(item.line_type, LineType::Todo) => detail_lines(item, note_of(&app.items, app.selected)),
+            _ => vec![Line::styled("No todo selected", Style::default().fg(Color::DarkGray))],
+        };
+        let pane = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(borders)
+                    .border_style(Style::default().fg(Color::White))
+                    .title(if compact { "" } else { " Details " })
+            );
+        f.render_widget(pane, area);
+    }
+
+    let block = Block::default()
+        .borders(borders)
+        .border_style(Style::default().fg(Color::White));
+    let inner = block.inner(content);
+    f.render_widget(block, content);
+
+    // On wide terminals, fill each column top to bottom before the next
+    let columns = app.columns_for(inner.width);
+    let rows = inner.height as usize;
+    app.layout.set((columns, rows));
+    let areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+        .spacing(2)
+        .split(inner);
+    // Each item is a row, cut off at the edge and scrolled sideways
+    // together, or as many rows as its text wraps onto
+    let width = areas.first().map_or(0, |area| area.width as usize);
+    let items: Vec<(Vec<Line>, Style)> = items
+        .into_iter()
+        .map(|(line, style, hang)| match app.wrap {
+            true => (wrap_line(line, width, hang), style),
+            false => (vec![scroll_line(line, app.hscroll)], style),
+        })
+        .collect();
+    // Lists longer than the view scroll with the selection
+    let heights: Vec<usize> = items.iter().map(|(lines, _)| lines.len()).collect();
+    let offset = app.scroll_to_selection(&visible, &heights, columns, rows.max(1));
+    let mut items = visible.iter().zip(items).skip(offset).peekable();
+    let mut drawn_rows = Vec::new();
+    for area in areas.iter() {
+        let (mut column, mut column_rows) = (Vec::new(), Vec::new());
+        while let Some((&i, (lines, style))) = items.next_if(|(_, (lines, _))| column_rows.is_empty() || column_rows.len() + lines.len() <= rows) {
+            column_rows.extend((0..lines.len()).map(|row| (i, row == 0)));
+            column.push(ListItem::new(lines).style(style));
+        }
+        f.render_widget(List::new(column), *area);
+        drawn_rows.push((*area, column_rows));
+    }
+    let drawn = drawn_rows.first().map_or(0, |(_, rows)| rows.len());
+    *app.drawn_rows.borrow_mut() = drawn_rows;
+
+    // An empty list gets pointers to getting started instead of a blank box,
+    // below whatever headers it has
+    if !app.edit_mode && app.items.iter().all(|item| !matches!(item.line_type, LineType::Todo)) {
+        let key = |key: &str, what: &str| {
+            Line::from(vec![Span::styled(format!("{:<8}", key), Style::default().fg(accent)), Span::raw(what.to_string())])
+        };
+        let lines = vec![
+            Line::styled("Nothing to do here yet", Style::default().add_modifier(Modifier::BOLD)),
+            Line::from(""),
+            key("a", "add a todo"),
+            key("?", "see every key"),
+            key("q", "quit"),
+            Line::from(""),
+            Line::styled("Or from the shell: todo add \"Buy milk\"", Style::default().fg(Color::DarkGray)),
+        ];
+        let height = lines.len() as u16;
+        let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+        let below = inner.height.saturating_sub(drawn as u16);
+        if below >= height && inner.width >= width {
+            let area = Rect {
+                x: inner.x + (inner.width - width) / 2,
+                y: inner.y + drawn as u16 + (below - height) / 2,
+                width,
+                height,
+            };
+            f.render_widget(Paragraph::new(lines), area);
+        }
+    }
+
+    // Input field (only shown in edit mode)
+    if app.edit_mode {
+        let section = app.edit_section.map(|header| format!(" New Todo in {} ", app.items[header].section_name()));
+        let title = if app.edit_due {
+            " Due Date "
+        } else if app.edit_search {
+            " Search "
+        } else if app.edit_new_list {
+            " New List "
+        } else if app.edit_existing_index.is_some() {
+            " Edit Todo "
+        } else if let Some(section) = &section {
+            section
+        } else {
+            " New Todo "
+        };
+        // Without a border to carry the title, it leads the line
+        let prefix = if compact { format!("{}: ", title.trim()) } else { "  ".to_string() };
+        let area = if compact { chunks[2] } else { chunks[2].inner(Margin { horizontal: 1, vertical: 1 }) };
+        // Scrolled sideways as far as it takes to keep the cursor in view
+        let cursor = display_width(&prefix) + display_width(&app.edit_text[..app.edit_cursor]);
+        let scroll = (cursor + 1).saturating_sub(area.width as usize);
+        f.set_cursor_position(Position::new(area.x + (cursor - scroll) as u16, area.y));
+        let input = Paragraph::new(format!("{}{}", prefix, app.edit_text))
+            .scroll((0, scroll as u16))
+            .style(Style::default().fg(Color::Yellow))
+            .block(
+                Block::default()
+                    .borders(borders)
+                    .border_style(Style::default().fg(Color::Yellow))
+                    .title(if compact { "" } else { title })
+            );
+        f.render_widget(input, chunks[2]);
+    }
+
+    // Status bar
+    let (incomplete, complete) = app.count_todos();
+    let status_text = if app.edit_due {
+        let prompt = if app.edit_due_invalid { "Can't read that date" } else { "Type a date like \"fri\" or \"in 3 days\"" };
+        format!(" {}  │  [Enter] set (empty clears)  [ESC] cancel ", prompt)
+    } else if app.edit_new_list {
+        let prompt = app.new_list_error.as_deref().unwrap_or("Type a name, then optionally a list to copy the todos of");
+        format!(" {}  │  [Enter] create  [ESC] cancel ", prompt)
+    } else if app.edit_search || app.search.is_some() {
+        let found = match app.search_matches() {
+            _ if app.search.is_none() => "Type to search the todos".to_string(),
+            0 => "No todo matches".to_string(),
+            1 => "1 match".to_string(),
+            n => format!("{} matches", n),
+        };
+        let keys = if app.edit_search { "[Enter] keep  [ESC] cancel" } else { "[n/N] next/previous  [Esc] clear search" };
+        format!(" {}  │  {} ", found, keys)
+    } else if app.edit_mode {
+        let sections = app.section_headers().len().min(9);
+        let what = if app.edit_append && app.edit_text.is_empty() && app.edit_section.is_none() && sections > 0 {
+            format!("Type todo text, or 1-{} for a section", sections)
+        } else {
+            "Type todo text".to_string()
+        };
+        format!(" {} incomplete  {} complete  │  {}  │  [Enter] save  [ESC] cancel ", incomplete, complete, what)
+    } else if let Some(error) = &app.plugin_error {
+        format!(" {} ", error)
+    } else if let Some(range) = app.visual_range() {
+        let selected = range.filter(|&i| app.selectable(i)).count();
+        format!(" VISUAL  {} selected  │  [j/k] extend  [Space] toggle  [d] delete  [J/K] move  [v/Esc] cancel ", selected)
+    } else if app.pending_key == Some('d') {
+        format!(" {} incomplete  {} complete  │  [d] delete this todo  [any other key] keep it ", incomplete, complete)
+    } else if let Some(misspellings) = app
+        .items
+        .get(app.selected)
+        .and_then(|item| app.misspellings.get(&item.text))
+        .filter(|misspellings| !misspellings.is_empty())
+    {
+        let found: Vec<String> = misspellings
+            .iter()
+            .map(|m| match m.suggestions.as_slice() {
+                [] => format!("{} → ?", m.word),
+                suggestions => format!("{} → {}", m.word, suggestions.iter().take(3).cloned().collect::<Vec<_>>().join(", ")),
+            })
+            .collect();
+        format!(" {} incomplete  {} complete  │  Spelling: {} ", incomplete, complete, found.join("; "))
+    } else if let Some(hint) = app.current_hint() {
+        format!(" {} incomplete  {} complete  │  {}  │  [Esc] dismiss ", incomplete, complete, hint.text)
+    } else {
+        format!(
+            " {} incomplete  {} complete  │  [j/k] move  [Space] toggle  [e/Enter] edit  [dd] delete  [u] undo  [p] paste  [a/o/O] add  [?] help  [q] quit ",
+            incomplete, complete
+        )
+    };
+
+    let status = Paragraph::new(status_text)
+        .style(Style::default().fg(Color::White).bg(Color::Rgb(40, 40, 60)))
+        .block(Block::default());
+
+    let status_idx = if app.edit_mode { 3 } else { 2 };
+    f.render_widget(status, chunks[status_idx]);
+
+    // Help dialog overlay
+    if app.show_help {
+        help_ui(f);
+    }
+
+    if app.high_contrast {
+        raise_contrast(f.buffer_mut());
+    }
+}
+
+// Recolor a rendered screen for high contrast: nothing dimmed, foregrounds
+// in bright colors or white, and anything with a background of its own (the
+// selection, the status bar) black on white
+fn raise_contrast(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        cell.modifier.remove(Modifier::DIM);
+        if cell.bg != Color::Reset {
+            cell.bg = Color::White;
+            cell.fg = Color::Black;
+            continue;
+        }
+        cell.fg = match cell.fg {
+            Color::Red => Color::LightRed,
+            Color::Green => Color::LightGreen,
+            Color::Yellow => Color::LightYellow,
+            Color::Blue => Color::LightBlue,
+            Color::Magenta => Color::LightMagenta,
+            Color::Cyan => Color::LightCyan,
+            Color::Black | Color::DarkGray | Color::Gray | Color::Rgb(..) | Color::Indexed(_) => Color::White,
+            color => color,
+        };
+    }
+}
+
+// What the TUI should do after a key press
+enum KeyOutcome {
+    Continue,
+    Quit,
+    // The last open todo was just completed (and the list saved)
+    Celebrate,
+    // Enter in the new list prompt, with what was typed; the prompt stays
+    // open until the list is created
+    NewList(String),
+}
+
+// Columns the view moves sideways for each H or L
+const HSCROLL_COLUMNS: usize = 8;
+
+// Rows the view moves for a turn of the mouse wheel
+const WHEEL_ROWS: isize = 3;
+
+// Clicking a todo selects it, clicking its checkbox toggles it as Space
+// would, and the wheel scrolls. The mouse is ignored while a prompt or the
+// help is open, and isn't part of recorded sessions.
+fn handle_mouse(app: &mut App, mouse: MouseEvent, celebrate: &AtomicBool) -> io::Result<KeyOutcome> {
+    if app.edit_mode || app.show_help {
+        return Ok(KeyOutcome::Continue);
+    }
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            app.pending_key = None;
+            let Some((i, checkbox)) = app.item_at(mouse.column, mouse.row).filter(|&(i, _)| app.navigable(i)) else {
+                return Ok(KeyOutcome::Continue);
+            };
+            app.selected = i;
+            if checkbox {
+                return handle_key(app, KeyEvent::from(KeyCode::Char(' ')), celebrate);
+            }
+        }
+        MouseEventKind::ScrollDown => app.scroll_by(WHEEL_ROWS),
+        MouseEventKind::ScrollUp => app.scroll_by(-WHEEL_ROWS),
+        _ => {}
+    }
+    Ok(KeyOutcome::Continue)
+}
+
+// Completing the last todo of the list earns fireworks
+fn celebrations() -> Arc<AtomicBool> {
+    let celebrate = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&celebrate);
+    EventBus::global().subscribe(move |event| {
+        if matches!(event, TodoEvent::ListCompleted { .. }) {
+            flag.store(true, Ordering::SeqCst);
+        }
+    });
+    celebrate
+}
+
+fn handle_key(app: &mut App, key: KeyEvent, celebrate: &AtomicBool) -> io::Result<KeyOutcome> {
+    if key.kind != KeyEventKind::Press {
+        return Ok(KeyOutcome::Continue);
+    }
+    // Whatever the key changes is recorded for `u` to take back
+    let before = app.undo_state();
+
+    // If help is showing, any key closes it
+    if app.show_help {
+        app.toggle_help();
+    } else if app.edit_mode {
+        // Handle keys in edit mode; the rest are typed
+        match (key_action(app, KeyMode::Edit, key), key.code) {
+            (Some(Action::Save), _) if app.edit_new_list => return Ok(KeyOutcome::NewList(app.edit_text.trim().to_string())),
+            (Some(Action::Save), _) => {
+                let adding = app.edit_insert_position.is_some();
+                app.finish_edit();
+                // New todos are written to the list as soon as they're entered
+                if adding && !app.edit_mode {
+                    app.save_keeping_deletes()?;
+                }
+            }
+            (Some(Action::Cancel), _) => app.cancel_edit(),
+            (Some(Action::Cursor), code) => app.move_cursor(code),
+            (Some(Action::DeleteBefore), _) => app.handle_backspace(),
+            (Some(Action::DeleteUnder), _) => app.handle_delete(),
+            (_, KeyCode::Char(c)) => app.handle_char_input(c),
+            _ => {}
+        }
+        if app.edit_search && matches!(key.code, KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Delete) {
+            app.update_search();
+        }
+    } else if app.visual_anchor.is_some() {
+        // Keys in visual mode act on everything selected; the rest do nothing
+        match key_action(app, KeyMode::Normal, key) {
+            Some(Action::Escape | Action::Visual) => app.visual_anchor = None,
+            Some(Action::Down) => app.next(),
+            Some(Action::Up) => app.previous(),
+            Some(Action::Top) => app.goto_top(),
+            Some(Action::Bottom) => app.goto_bottom(),
+            Some(Action::HalfPageDown) => app.scroll_half_page(true),
+            Some(Action::HalfPageUp) => app.scroll_half_page(false),
+            Some(Action::MoveDown) => app.move_current(true),
+            Some(Action::MoveUp) => app.move_current(false),
+            Some(Action::Delete) => app.delete_selection(),
+            Some(Action::Toggle) => {
+                app.toggle_selection();
+                if celebrate.swap(false, Ordering::SeqCst) {
+                    app.record_change(before);
+                    app.save_todos()?;
+                    return Ok(KeyOutcome::Celebrate);
                 }
-                LineType::Header2 => {
-                    Line::from(Span::styled(
-                        &todo_item.text,
-                        Style::default()
-                            .fg(Color::Blue)
-                            .add_modifier(Modifier::BOLD),
-                    ))
+            }
+            _ => {}
+        }
+    } else {
+        // Handle keys in normal mode. Any key but the second of a two-key
+        // command cancels the first.
+        let pending = app.pending_key.take();
+        app.plugin_error = None;
+        let Some(action) = key_action(app, KeyMode::Normal, key) else {
+            // Keys of the TUI's own stay its own; plugins get what's left
+            if let KeyCode::Char(c) = key.code {
+                if plugins().key(c).is_some() {
+                    app.run_plugin_key(c);
                 }
-                LineType::Header3 => {
-                    Line::from(Span::styled(
-                        &todo_item.text,
-                        Style::default()
-                            .fg(Color::Magenta)
-                            .add_modifier(Modifier::BOLD),
-                    ))
+            }
+            app.record_change(before);
+            return Ok(KeyOutcome::Continue);
+        };
+        match action {
+            Action::Help => app.toggle_help(),
+            Action::Details => app.toggle_details(),
+            Action::HideCompleted => app.set_hide_completed(!app.hide_completed),
+            Action::Wrap => {
+                app.wrap = !app.wrap;
+                app.hscroll = 0;
+            }
+            Action::ScrollRight => app.hscroll += HSCROLL_COLUMNS,
+            Action::ScrollLeft => app.hscroll = app.hscroll.saturating_sub(HSCROLL_COLUMNS),
+            Action::Escape if pending.is_some() => {}
+            Action::Escape if app.search.is_some() => app.search = None,
+            Action::Escape => {
+                if let Some(hint) = app.current_hint() {
+                    app.dismiss_hint(hint.id);
                 }
-                LineType::Bullet => {
-                    Line::from(vec![
-                        Span::raw("  • "),
-                        Span::styled(&todo_item.text, Style::default().fg(Color::White)),
-                    ])
+            }
+            Action::Quit => {
+                app.save_todos()?;
+                return Ok(KeyOutcome::Quit);
+            }
+            Action::NextColumn => app.next_column(),
+            Action::Down => app.next(),
+            Action::Up => app.previous(),
+            Action::Top => app.goto_top(),
+            Action::Bottom => app.goto_bottom(),
+            Action::MoveDown => app.move_current(true),
+            Action::MoveUp => app.move_current(false),
+            Action::Nest => app.nest_current(true),
+            Action::Unnest => app.nest_current(false),
+            Action::HalfPageDown => app.scroll_half_page(true),
+            Action::HalfPageUp => app.scroll_half_page(false),
+            Action::Delete if pending == Some('d') => app.delete_current(),
+            Action::Delete => app.pending_key = Some('d'),
+            Action::Undo => {
+                app.undo();
+                return Ok(KeyOutcome::Continue);
+            }
+            Action::Redo => {
+                app.redo();
+                return Ok(KeyOutcome::Continue);
+            }
+            Action::Paste => app.paste_register(),
+            Action::Visual => app.toggle_visual(),
+            Action::Append => app.start_append(),
+            Action::InsertAbove => app.start_insert_above(),
+            Action::InsertBelow => app.start_insert_below(),
+            Action::Edit => app.start_edit_current(),
+            Action::DueToday => app.reschedule_current(Some(Local::now().date_naive())),
+            Action::DueTomorrow => app.reschedule_current(Local::now().date_naive().succ_opt()),
+            Action::DueNextWeek => app.reschedule_current(parse_date("next week", Local::now().date_naive())),
+            Action::DuePrompt => app.start_due_prompt(),
+            Action::Search => app.start_search(),
+            Action::NextMatch => app.next_match(true),
+            Action::PreviousMatch => app.next_match(false),
+            Action::NewList => app.start_new_list(),
+            Action::Toggle => {
+                if app.items.get(app.selected).is_some_and(|item| item.header_level().is_some()) {
+                    app.toggle_section();
+                } else {
+                    app.toggle_current();
                 }
-                LineType::Text => {
-                    Line::from(Span::styled(&todo_item.text, Style::default().fg(Color::Gray)))
+                if celebrate.swap(false, Ordering::SeqCst) {
+                    app.record_change(before);
+                    app.save_todos()?;
+                    return Ok(KeyOutcome::Celebrate);
                 }
-                LineType::Empty => Line::from(""),
+            }
+            Action::Save | Action::Cancel | Action::Cursor | Action::DeleteBefore | Action::DeleteUnder => {}
+        }
+    }
+    app.record_change(before);
+    Ok(KeyOutcome::Continue)
+}
+
+fn run_app<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    config: &Config,
+    mut app: App,
+    mut recording: Option<&mut Session>,
+) -> io::Result<()> {
+    let celebrate = celebrations();
+    let started = Instant::now();
+    // The caller journals the list it opened; lists created with `N` are
+    // journaled here as they're left
+    let mut created: Option<(Recorder, String)> = None;
+
+    loop {
+        let frame = terminal.draw(|f| ui(f, &app))?;
+        if app.hyperlinks {
+            write_links(frame.buffer, app.items.iter().map(|item| item.text.as_str()))?;
+        }
+
+        let outcome = match event::read()? {
+            Event::Key(key) => {
+                if let Some(session) = recording.as_deref_mut() {
+                    let at_ms = started.elapsed().as_millis() as u64;
+                    session.keys.push(RecordedKey { at_ms, key });
+                }
+                handle_key(&mut app, key, &celebrate)?
+            }
+            Event::Mouse(mouse) => handle_mouse(&mut app, mouse, &celebrate)?,
+            _ => continue,
+        };
+        match outcome {
+            KeyOutcome::Continue => {}
+            KeyOutcome::Quit => break,
+            KeyOutcome::NewList(input) => match create_list(config, &input) {
+                Ok((path, name)) => {
+                    app.save_todos()?;
+                    if let Some((recorder, name)) = created.take() {
+                        record(recorder, TodoEvent::ListEdited { list: name });
+                    }
+                    created = Some((Recorder::new(&[&path]), name.clone()));
+                    app = open_app(config, path, &name, false)?;
+                    // A recording replays against the list it started on
+                    recording = None;
+                }
+                Err(e) => app.new_list_error = Some(e),
+            },
+            KeyOutcome::Celebrate => {
+                // Temporarily exit the TUI
+                disable_raw_mode()?;
+                let mut stdout = io::stdout();
+                execute!(
+                    stdout,
+                    LeaveAlternateScreen,
+                    DisableMouseCapture
+                )?;
+
+                // Show fireworks
+                show_fireworks(!app.reduced_motion)?;
+
+                // Re-enter the TUI
+                enable_raw_mode()?;
+                execute!(
+                    stdout,
+                    EnterAlternateScreen,
+                    EnableMouseCapture
+                )?;
+            }
+        }
+    }
+    if let Some((recorder, name)) = created {
+        record(recorder, TodoEvent::ListEdited { list: name });
+    }
+    Ok(())
+}
+
+// The list for the TUI's `N`, from its name and optionally another list to
+// start from: that list's headers and todos, the todos all open and stamped
+// as new. Returns its path and file name, or why it couldn't be made.
+fn create_list(config: &Config, input: &str) -> Result<(PathBuf, String), String> {
+    let mut words = input.split_whitespace();
+    let Some(name) = words.next() else {
+        return Err("Type a name for the list".to_string());
+    };
+    let template = words.next();
+    if words.next().is_some() {
+        return Err("A list name can't have spaces".to_string());
+    }
+    let name = normalize_list_name(name);
+    check_list_name(config, &name)?;
+    let store = open_storage(config);
+    let (path, list_name) = resolve_list(config, Some(name.clone()));
+    if store.exists(&name) {
+        return Err(format!("There's already a list named {}", list_name));
+    }
+
+    let mut lines = Vec::new();
+    if let Some(template) = template {
+        let mut items = store.load(template).map_err(|_| format!("No list named {} to copy", template))?;
+        let today = Local::now().date_naive();
+        let user = config.user_name();
+        for i in 0..items.len() {
+            if matches!(items[i].line_type, LineType::Todo) {
+                let mut todo = items[i].clone();
+                // Ids are new, so what it waited on is gone
+                todo.text = todo.text_without_stamps();
+                todo.clear_blockers();
+                todo.completed = false;
+                todo.stamp_added(today, user.as_deref());
+                todo.stamp_id(&items);
+                items[i] = todo;
+            }
+        }
+        lines = items.iter().map(format_line).collect();
+    }
+
+    let recorder = Recorder::new(&[&path]);
+    store.save_list(&name, &lines).map_err(|e| format!("Couldn't create {}: {}", list_name, e))?;
+    record(recorder, TodoEvent::ListEdited { list: list_name.clone() });
+    Ok((path, list_name))
+}
+
+// The single-list TUI for a list, set up as the config says
+fn open_app(config: &Config, list_path: PathBuf, list_name: &str, read_only: bool) -> io::Result<App> {
+    let storage = open_storage(config);
+    let mut app = if read_only {
+        App::new_read_only(storage, &list_path, list_name.to_string())?
+    } else {
+        App::new(storage, list_name.to_string())?
+    };
+    app.style = config.list_style(list_name);
+    app.density = config.tui.density;
+    app.columns = config.tui.columns;
+    app.column_width = config.tui.column_width;
+    app.emoji = config.emoji.clone();
+    app.spell = config.spell.clone();
+    app.hyperlinks = config.output.hyperlinks.enabled();
+    app.reduced_motion = config.reduced_motion;
+    app.high_contrast = config.high_contrast;
+    app.user = config.user_name();
+    app.complete_parents = config.subtasks.complete_parent;
+    app.set_hide_completed(config.tui.hide_completed);
+    app.wrap = config.tui.wrap;
+    app.hints = true;
+    app.dismissed_hints = load_state().dismissed_hints;
+    app.check_spelling();
+    Ok(app)
+}
+
+// A list as shown on the dashboard
+struct Pane {
+    name: String,
+    path: PathBuf,
+    style: ListStyle,
+    items: Vec<TodoItem>,
+}
+
+impl Pane {
+    fn counts(&self) -> (usize, usize) {
+        let todos = self.items.iter().filter(|item| matches!(item.line_type, LineType::Todo));
+        todos.fold((0, 0), |(open, total), item| (open + !item.completed as usize, total + 1))
+    }
+}
+
+// Every list side by side, for `todo dashboard`
+struct Dashboard {
+    panes: Vec<Pane>,
+    focus: usize,
+    high_contrast: bool,
+    filters: Vec<QuickFilter>,
+}
+
+// A saved filter bound to a key, which shows only the todos that pass it
+// while it's on; with several on, todos have to pass them all
+struct QuickFilter {
+    key: char,
+    name: String,
+    conditions: Vec<Condition>,
+    active: bool,
+}
+
+// The saved filters that have a key, in key order
+fn quick_filters(config: &Config) -> Result<Vec<QuickFilter>, String> {
+    let today = Local::now().date_naive();
+    let mut filters = Vec::new();
+    for (name, filter) in &config.filters {
+        let Some(key) = filter.key else {
+            continue;
+        };
+        if !('1'..='9').contains(&key) {
+            return Err(format!("Filter '{}' has key '{}': expected a digit from 1 to 9", name, key));
+        }
+        if let Some(other) = filters.iter().find(|other: &&QuickFilter| other.key == key) {
+            return Err(format!("Filters '{}' and '{}' both have key '{}'", other.name, name, key));
+        }
+        let conditions = filter
+            .conditions
+            .iter()
+            .map(|condition| parse_condition(condition, today))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Filter '{}': {}", name, e))?;
+        filters.push(QuickFilter { key, name: name.clone(), conditions, active: false });
+    }
+    filters.sort_by_key(|filter| filter.key);
+    Ok(filters)
+}
+
+impl Dashboard {
+    // Lists are read leniently, so one that can't be decoded still shows
+    fn load(config: &Config) -> io::Result<Self> {
+        let store = open_storage(config);
+        let mut names = store.list_lists()?;
+        if config.project_list.is_some() {
+            names.insert(0, PROJECT_LIST.to_string());
+        }
+
+        let panes = names
+            .into_iter()
+            .map(|name| {
+                let (path, name) = resolve_list(config, Some(name));
+                let items = load_leniently(store.as_ref(), &name, &path).unwrap_or_default();
+                Pane { style: config.list_style(&name), name, path, items }
+            })
+            .collect();
+        Ok(Dashboard { panes, focus: 0, high_contrast: config.high_contrast, filters: Vec::new() })
+    }
+
+    fn toggle_filter(&mut self, key: char) {
+        if let Some(filter) = self.filters.iter_mut().find(|filter| filter.key == key) {
+            filter.active = !filter.active;
+        }
+    }
+
+    fn filtering(&self) -> bool {
+        self.filters.iter().any(|filter| filter.active)
+    }
+
+    // Whether an open todo passes the filters that are on
+    fn shows(&self, item: &TodoItem) -> bool {
+        let active = self.filters.iter().filter(|filter| filter.active);
+        active.flat_map(|filter| &filter.conditions).all(|condition| condition.matches(item))
+    }
+
+    fn next(&mut self) {
+        if self.focus + 1 < self.panes.len() {
+            self.focus += 1;
+        }
+    }
+
+    fn previous(&mut self) {
+        self.focus = self.focus.saturating_sub(1);
+    }
+}
+
+// Narrower than this, panes scroll sideways with the focus instead
+const PANE_MIN_WIDTH: u16 = 28;
+
+fn dashboard_ui(f: &mut Frame, dashboard: &Dashboard) {
+    let today = Local::now().date_naive();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.area());
+
+    let fits = (chunks[0].width / PANE_MIN_WIDTH).max(1) as usize;
+    let shown = dashboard.panes.len().clamp(1, fits);
+    let first = (dashboard.focus + 1).saturating_sub(shown);
+    let areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, shown as u32); shown])
+        .split(chunks[0]);
+
+    for (area, (i, pane)) in areas.iter().zip(dashboard.panes.iter().enumerate().skip(first)) {
+        let (open, total) = pane.counts();
+        let focused = i == dashboard.focus;
+        let accent = if focused { tui_color(&pane.style, Color::Cyan) } else { Color::DarkGray };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(accent))
+            .title(Span::styled(
+                format!(" {} {}/{} ", pane.style.label(&pane.name), open, total),
+                Style::default().fg(tui_color(&pane.style, Color::White)).add_modifier(Modifier::BOLD),
+            ));
+
+        // Open todos first, under their headers; completed ones are only
+        // counted. While filtering, headers are only shown over todos that
+        // passed.
+        let mut lines: Vec<ListItem> = Vec::new();
+        let mut header = None;
+        for item in &pane.items {
+            match item.line_type {
+                LineType::Todo if !item.completed && dashboard.shows(item) => {
+                    if let Some(header) = header.take() {
+                        lines.push(header);
+                    }
+                    let overdue = item.due_date().is_some_and(|due| due < today);
+                    let style = if overdue { Style::default().fg(Color::Red) } else { Style::default() };
+                    lines.push(ListItem::new(Line::from(vec![
+                        Span::styled("☐ ", Style::default().fg(Color::Yellow)),
+                        Span::styled(item.text_without_stamps(), style),
+                    ])));
+                }
+                LineType::Header1 | LineType::Header2 | LineType::Header3 => {
+                    let line = ListItem::new(Span::styled(
+                        item.text.clone(),
+                        Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD),
+                    ));
+                    if dashboard.filtering() {
+                        header = Some(line);
+                    } else {
+                        lines.push(line);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if dashboard.filtering() {
+            if lines.is_empty() {
+                lines.push(ListItem::new(Span::styled("Nothing here passes", Style::default().fg(Color::DarkGray))));
+            }
+        } else if total > open {
+            lines.push(ListItem::new(Span::styled(
+                format!("+ {} done", total - open),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        f.render_widget(List::new(lines).block(block), *area);
+    }
+
+    if dashboard.panes.is_empty() {
+        let empty = Paragraph::new("No todo lists yet. Add one with `todo add -l <list> <todo>`.")
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(empty, chunks[0]);
+    }
+
+    let (open, total) = dashboard.panes.iter().map(Pane::counts).fold((0, 0), |(o, t), (po, pt)| (o + po, t + pt));
+    let counts = if dashboard.filtering() {
+        let passing = dashboard
+            .panes
+            .iter()
+            .flat_map(|pane| &pane.items)
+            .filter(|item| matches!(item.line_type, LineType::Todo) && !item.completed && dashboard.shows(item))
+            .count();
+        let names: Vec<&str> = dashboard.filters.iter().filter(|filter| filter.active).map(|filter| filter.name.as_str()).collect();
+        format!("{} open showing {}", passing, names.join(" + "))
+    } else {
+        format!("{} open of {}", open, total)
+    };
+    let keys = match (dashboard.filters.first(), dashboard.filters.last()) {
+        (Some(first), Some(last)) if first.key == last.key => format!("[{}] {}  ", first.key, first.name),
+        (Some(first), Some(last)) => format!("[{}-{}] filter  ", first.key, last.key),
+        _ => String::new(),
+    };
+    let status = Paragraph::new(format!(
+        " {} lists  {}  │  [h/l] switch list  [Enter] open list  {}[q] quit ",
+        dashboard.panes.len(),
+        counts,
+        keys
+    ))
+    .style(Style::default().fg(Color::White).bg(Color::Rgb(40, 40, 60)));
+    f.render_widget(status, chunks[1]);
+
+    if dashboard.high_contrast {
+        raise_contrast(f.buffer_mut());
+    }
+}
+
+fn show_dashboard(config: &Config) -> io::Result<()> {
+    let filters = match quick_filters(config) {
+        Ok(filters) => filters,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut dashboard = Dashboard::load(config)?;
+    dashboard.filters = filters;
+    let hyperlinks = config.output.hyperlinks.enabled();
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let res = (|| -> io::Result<()> {
+        loop {
+            let frame = terminal.draw(|f| dashboard_ui(f, &dashboard))?;
+            if hyperlinks {
+                let texts = dashboard.panes.iter().flat_map(|pane| pane.items.iter().map(|item| item.text.as_str()));
+                write_links(frame.buffer, texts)?;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('l') | KeyCode::Right | KeyCode::Tab => dashboard.next(),
+                KeyCode::Char('h') | KeyCode::Left | KeyCode::BackTab => dashboard.previous(),
+                KeyCode::Char(c @ '1'..='9') => dashboard.toggle_filter(c),
+                KeyCode::Enter => {
+                    let Some(pane) = dashboard.panes.get(dashboard.focus) else {
+                        continue;
+                    };
+                    // Drill into the list, then come back to the dashboard
+                    let read_only = !matches!(diagnose_list(&pane.path), Ok(None));
+                    let recorder = Recorder::new(&[&pane.path]);
+                    let app = open_app(config, pane.path.clone(), &pane.name, read_only)?;
+                    let name = pane.name.clone();
+                    run_app(&mut terminal, config, app, None)?;
+                    record(recorder, TodoEvent::ListEdited { list: name });
+
+                    let focus = dashboard.focus;
+                    let filters = std::mem::take(&mut dashboard.filters);
+                    dashboard = Dashboard::load(config)?;
+                    dashboard.focus = focus.min(dashboard.panes.len().saturating_sub(1));
+                    dashboard.filters = filters;
+                    terminal.clear()?;
+                }
+                _ => {}
+            }
+        }
+    })();
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    terminal.show_cursor()?;
+    res
+}
+
+// `todo serve`: one list that anyone who can reach it may read,
+// and holders of the token may also check off. Requests are handled one at
+// a time, which is plenty for a household.
+fn serve_list(config: &Config, target_list: Option<String>, token: Option<&str>, bind: &str) {
+    let list = target_list_name(config, target_list);
+    let list_name = format!("{}.{}", list, config.todo.list_extension);
+    if !open_storage(config).exists(&list) {
+        eprintln!("List '{}' does not exist", list_name);
+        std::process::exit(1);
+    }
+    if token.is_some_and(str::is_empty) {
+        eprintln!("The token can't be empty");
+        std::process::exit(1);
+    }
+
+    let listener = match TcpListener::bind(bind) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Error listening on {}: {}", bind, e);
+            std::process::exit(1);
+        }
+    };
+    let address = listener.local_addr().map(|address| address.to_string()).unwrap_or_else(|_| bind.to_string());
+    println!("Sharing {} read-only at http://{}/", list_name, address);
+    if let Some(token) = token {
+        println!("To check todos off, open http://{}/?token={}", address, encode(token));
+    }
+    println!("{}", "Press Ctrl-C to stop.".faint());
+
+    // Each connection on a thread of its own, so one that's slow to send its
+    // request, or a browser's idle preconnect, doesn't keep the others waiting
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
             };
+            let list = &list;
+            scope.spawn(move || {
+                if let Err(e) = handle_share_request(config, list, token, &stream) {
+                    eprintln!("Error serving request: {}", e);
+                }
+            });
+        }
+    });
+}
 
-            let style = if i == app.selected {
-                Style::default()
-                    .bg(Color::Rgb(60, 60, 80))
-                    .add_modifier(Modifier::BOLD)
-            } else {
-                Style::default()
+fn handle_share_request(config: &Config, list: &str, token: Option<&str>, stream: &TcpStream) -> io::Result<()> {
+    let request = share::read_request(stream)?;
+    let authorized = token.filter(|token| token_matches(request.param("token"), token));
+    let (list_path, list_name) = resolve_list(config, Some(list.to_string()));
+    let store = open_storage(config);
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => {
+            let items = load_leniently(store.as_ref(), &list_name, &list_path)?;
+            share::respond(stream, "200 OK", "text/html; charset=utf-8", &share::render_page(list, &items, authorized))
+        }
+        ("GET", "/list.json") => {
+            let items = load_leniently(store.as_ref(), &list_name, &list_path)?;
+            let json = serde_json::to_string_pretty(&export_items(list, &items)).map_err(io::Error::other)?;
+            share::respond(stream, "200 OK", "application/json", &json)
+        }
+        ("POST", "/toggle") => {
+            let Some(token) = authorized else {
+                return share::respond(stream, "403 Forbidden", "text/plain; charset=utf-8", "Checking todos off needs the token.\n");
             };
+            // The page may be out of date, in which case the todo is left
+            // alone and the page reloaded
+            if let Some(index) = request.param("n").and_then(|n| n.parse().ok()) {
+                set_done(config, list, &index, None, request.param("text"), None)?;
+            }
+            share::redirect(stream, &format!("/?token={}", encode(token)))
+        }
+        _ => share::respond(stream, "404 Not Found", "text/plain; charset=utf-8", "Not found.\n"),
+    }
+}
+
+// Days `todo plan` looks ahead, and the planner shows
+const PLAN_DAYS: usize = 7;
+
+// The open todos due over the coming week, from every list
+fn load_plan(config: &Config) -> io::Result<Vec<Day>> {
+    let mut files = workspace_files(config)?;
+    if let Some(project) = &config.project_list {
+        files.insert(0, (format!("{}.{}", PROJECT_LIST, config.todo.list_extension), project.clone()));
+    }
+    let store = open_storage(config);
+    let lists: Vec<(String, Vec<TodoItem>)> = files
+        .into_iter()
+        .filter_map(|(file, _)| Some((normalize_list_name(&file), store.load_todos(&file).ok()?)))
+        .collect();
+    let today = Local::now().date_naive();
+    Ok(plan::plan(&lists, today, PLAN_DAYS, config.plan.default_estimate_mins))
+}
+
+// "Wed Oct 14", with today and tomorrow named
+fn plan_day_label(date: NaiveDate, today: NaiveDate) -> String {
+    match (date - today).num_days() {
+        0 => format!("{} (today)", date.format("%a %b %-d")),
+        1 => format!("{} (tomorrow)", date.format("%a %b %-d")),
+        _ => date.format("%a %b %-d").to_string(),
+    }
+}
+
+// An estimate as shown: the default one with a '~'
+fn plan_minutes(todo: &plan::Planned) -> String {
+    format!("{}{}", if todo.estimated { "" } else { "~" }, format_minutes(todo.minutes))
+}
+
+#[derive(Serialize)]
+struct PlanReport<'a> {
+    capacity_mins: u32,
+    days: &'a [Day],
+    moves: &'a [Move],
+}
+
+fn print_plan(config: &Config, week: bool, json: bool) {
+    let plan = match load_plan(config) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("Error reading todo directory: {}", e);
+            return;
+        }
+    };
+    let capacity = config.plan.capacity_mins;
+    // Moves are worked out over the week even when only today is shown, so
+    // an overloaded today still gets somewhere to move todos to
+    let mut moves = suggest_moves(&plan, capacity);
+    let days = if week { &plan[..] } else { &plan[..1] };
+    moves.retain(|m| days.iter().any(|day| day.date == m.from));
+    if json {
+        print_json(&PlanReport { capacity_mins: capacity, days, moves: &moves });
+        return;
+    }
+
+    let today = Local::now().date_naive();
+    for (i, day) in days.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let load = day.load();
+        let summary = format!("{} of {}", format_minutes(load), format_minutes(capacity));
+        let summary = if load > capacity {
+            format!("{}, over by {}", summary, format_minutes(load - capacity)).red().bold()
+        } else {
+            summary.green()
+        };
+        println!("{}  {}", plan_day_label(day.date, today).bold(), summary);
+        if day.todos.is_empty() {
+            println!("  {}", "Nothing due".faint());
+        }
+        for todo in &day.todos {
+            let text = without_stamps(&todo.text);
+            let text = if todo.due < today { text.red().to_string() } else { paint_words(&text) };
+            let place = format!("{} #{}", todo.list, todo.index);
+            println!("  {:>6}  {}  {}", plan_minutes(todo), text, place.faint());
+        }
+    }
+
+    if !moves.is_empty() {
+        println!();
+        println!("{}", "To make room, move:".bold());
+        for m in &moves {
+            let command = format!("todo due {} {} -l {}", m.index, m.to.format("%Y-%m-%d"), m.list);
+            println!("  {} from {} to {}  {}", without_stamps(&m.text), m.from.format("%a"), m.to.format("%a"), command.faint());
+        }
+    } else if days.iter().any(|day| day.load() > capacity) {
+        println!();
+        println!("{}", "The coming days have no room to move todos to.".faint());
+    }
+}
 
-            ListItem::new(content).style(style)
-        })
-        .collect();
+// Set the due date of a list's todo number `index`, as long as it's still
+// the todo with `text`. False when the list has changed since.
+fn reschedule(config: &Config, list: &str, index: usize, text: &str, due: NaiveDate) -> io::Result<bool> {
+    let store = open_storage(config);
+    let (list_path, list_name) = resolve_list(config, Some(list.to_string()));
+    let recorder = Recorder::new(&[&list_path]);
+    let result = store.update(list, |lines| {
+        let pos = todo_line_index(lines, index).filter(|&pos| parse_line(&lines[pos]).text == text)?;
+        let mut item = parse_line(&lines[pos]);
+        item.set_due(Some(due));
+        lines[pos] = format_line(&item);
+        Some(item.text)
+    })?;
+    match result {
+        Some(text) => {
+            record(recorder, TodoEvent::ItemDueSet { list: list_name, text, due: Some(due) });
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
 
-    let list = List::new(items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::White))
-        );
+// The week of `todo plan --interactive`, a column per day
+struct Planner {
+    plan: Vec<Day>,
+    capacity: u32,
+    focus: usize,
+    selected: usize,
+    // Todo picked up with the mouse: its day and position
+    dragging: Option<(usize, usize)>,
+    message: Option<String>,
+    high_contrast: bool,
+}
 
-    f.render_widget(list, chunks[1]);
+impl Planner {
+    fn selected_todo(&self) -> Option<&plan::Planned> {
+        self.plan.get(self.focus)?.todos.get(self.selected)
+    }
 
-    // Input field (only shown in edit mode)
-    if app.edit_mode {
-        let title = if app.edit_existing_index.is_some() {
-            " Edit Todo "
-        } else {
-            " New Todo "
-        };
-        let input = Paragraph::new(format!("  {}▋", app.edit_text))
-            .style(Style::default().fg(Color::Yellow))
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Yellow))
-                    .title(title)
-            );
-        f.render_widget(input, chunks[2]);
+    fn select_day(&mut self, day: usize) {
+        self.focus = day.min(self.plan.len().saturating_sub(1));
+        let count = self.plan[self.focus].todos.len();
+        self.selected = self.selected.min(count.saturating_sub(1));
     }
 
-    // Status bar
-    let (incomplete, complete) = app.count_todos();
-    let status_text = if app.edit_mode {
-        format!(
-            " {} incomplete  {} complete  │  Type todo text  │  [Enter] save  [ESC] cancel ",
-            incomplete, complete
-        )
-    } else {
-        format!(
-            " {} incomplete  {} complete  │  [j/k] move  [Space] toggle  [e/Enter] edit  [d] delete  [u] undo  [o/O] insert  [?] help  [q] quit ",
-            incomplete, complete
-        )
-    };
+    // Move a todo to another day, then follow it there
+    fn move_todo(&mut self, config: &Config, day: usize, position: usize, to: usize) -> io::Result<()> {
+        let (Some(todo), Some(target)) = (self.plan[day].todos.get(position).cloned(), self.plan.get(to)) else {
+            return Ok(());
+        };
+        let date = target.date;
+        if !reschedule(config, &todo.list, todo.index, &todo.text, date)? {
+            self.message = Some(format!("{} changed since the plan was loaded", todo.list));
+        }
+        self.reload(config)?;
+        self.focus = to;
+        self.selected = self.plan[to]
+            .todos
+            .iter()
+            .position(|other| other.list == todo.list && other.index == todo.index)
+            .unwrap_or(0);
+        Ok(())
+    }
 
-    let status = Paragraph::new(status_text)
-        .style(Style::default().fg(Color::White).bg(Color::Rgb(40, 40, 60)))
-        .block(Block::default());
+    fn reload(&mut self, config: &Config) -> io::Result<()> {
+        self.plan = load_plan(config)?;
+        self.select_day(self.focus);
+        Ok(())
+    }
+}
 
-    let status_idx = if app.edit_mode { 3 } else { 2 };
-    f.render_widget(status, chunks[status_idx]);
+// Narrower than this, day columns scroll sideways with the focus instead
+const DAY_MIN_WIDTH: u16 = 24;
+
+// Which days are shown, and where
+fn planner_columns(area: Rect, planner: &Planner) -> (usize, Vec<Rect>) {
+    let area = Rect { height: area.height.saturating_sub(1), ..area };
+    let shown = (area.width / DAY_MIN_WIDTH).clamp(1, PLAN_DAYS as u16) as usize;
+    let first = (planner.focus + 1).saturating_sub(shown);
+    let areas = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, shown as u32); shown])
+        .split(area);
+    (first, areas.to_vec())
+}
 
-    // Help dialog overlay
-    if app.show_help {
-        let help_text = vec![
-            Line::from(vec![
-                Span::styled("Todo TUI Help", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
-            ]),
-            Line::from(""),
-            Line::from(vec![
-                Span::styled("Navigation", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-            ]),
-            Line::from("  j / ↓      Move down to next todo"),
-            Line::from("  k / ↑      Move up to previous todo"),
-            Line::from("  g          Go to first todo"),
-            Line::from("  G          Go to last todo"),
-            Line::from(""),
-            Line::from(vec![
-                Span::styled("Editing", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-            ]),
-            Line::from("  e / Enter  Edit current todo"),
-            Line::from("  o          Insert new todo below current line"),
-            Line::from("  O          Insert new todo above current line"),
-            Line::from("  d          Delete current todo (held in memory)"),
-            Line::from("  u          Undo last delete"),
-            Line::from(""),
-            Line::from(vec![
-                Span::styled("In Edit Mode", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-            ]),
-            Line::from("  Enter      Save changes"),
-            Line::from("  ESC        Cancel (discard new todo or restore original)"),
-            Line::from("  Backspace  Delete character"),
-            Line::from(""),
-            Line::from(vec![
-                Span::styled("Actions", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-            ]),
-            Line::from("  Space      Toggle todo completion"),
-            Line::from("  q          Save and quit"),
-            Line::from("  ?          Toggle this help"),
-            Line::from(""),
-            Line::from(vec![
-                Span::styled("Examples", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-            ]),
-            Line::from("  • Press 'o' to add a new todo below"),
-            Line::from("  • Press 'd' to mark for deletion (not saved until quit)"),
-            Line::from("  • Press 'e' to edit, type new text, then Enter to save"),
-            Line::from("  • Press Space to mark a todo as complete"),
-            Line::from(""),
-            Line::from(vec![
-                Span::styled("Press any key to close", Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)),
-            ]),
-        ];
+// The first todo shown in a day's column, scrolled to keep the selection in view
+fn planner_offset(area: Rect, planner: &Planner, day: usize) -> usize {
+    let rows = area.height.saturating_sub(2) as usize;
+    if day == planner.focus { (planner.selected + 1).saturating_sub(rows) } else { 0 }
+}
 
-        // Create a centered popup
-        let area = f.area();
-        let popup_width = 70.min(area.width.saturating_sub(4));
-        let popup_height = 36.min(area.height.saturating_sub(4));
+// The day and todo under a screen position
+fn planner_hit(area: Rect, planner: &Planner, column: u16, row: u16) -> Option<(usize, Option<usize>)> {
+    let (first, areas) = planner_columns(area, planner);
+    let (i, rect) = areas.iter().enumerate().find(|(_, rect)| rect.x <= column && column < rect.x + rect.width)?;
+    let day = first + i;
+    if day >= planner.plan.len() || row >= rect.y + rect.height {
+        return None;
+    }
+    let todo = (row > rect.y)
+        .then(|| (row - rect.y - 1) as usize + planner_offset(*rect, planner, day))
+        .filter(|&todo| todo < planner.plan[day].todos.len());
+    Some((day, todo))
+}
 
-        let popup_area = Rect {
-            x: (area.width.saturating_sub(popup_width)) / 2,
-            y: (area.height.saturating_sub(popup_height)) / 2,
-            width: popup_width,
-            height: popup_height,
+fn planner_ui(f: &mut Frame, planner: &Planner) {
+    let today = Local::now().date_naive();
+    let area = f.area();
+    let (first, areas) = planner_columns(area, planner);
+    let moves = suggest_moves(&planner.plan, planner.capacity);
+
+    for (area, (i, day)) in areas.iter().zip(planner.plan.iter().enumerate().skip(first)) {
+        let load = day.load();
+        let over = load > planner.capacity;
+        let accent = match (i == planner.focus, over) {
+            (_, true) => Color::Red,
+            (true, false) => Color::Cyan,
+            (false, false) => Color::DarkGray,
         };
+        let title = format!(" {} {}/{} ", day.date.format("%a %-d"), format_minutes(load), format_minutes(planner.capacity));
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(accent))
+            .title(Span::styled(title, Style::default().fg(if over { Color::Red } else { Color::White }).add_modifier(Modifier::BOLD)));
+
+        let offset = planner_offset(*area, planner, i);
+        let lines: Vec<ListItem> = day
+            .todos
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .map(|(j, todo)| {
+                let mut style = if todo.due < today { Style::default().fg(Color::Red) } else { Style::default() };
+                if i == planner.focus && j == planner.selected {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                if planner.dragging == Some((i, j)) {
+                    style = style.add_modifier(Modifier::DIM);
+                }
+                let mut spans = vec![
+                    Span::styled(format!("{:>6} ", plan_minutes(todo)), Style::default().fg(Color::DarkGray)),
+                    Span::styled(todo.text.clone(), style),
+                ];
+                if let Some(m) = moves.iter().find(|m| m.list == todo.list && m.index == todo.index) {
+                    spans.push(Span::styled(format!(" → {}", m.to.format("%a")), Style::default().fg(Color::Yellow)));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        f.render_widget(List::new(lines).block(block), *area);
+    }
 
-        let help_paragraph = Paragraph::new(help_text)
-            .style(Style::default().fg(Color::White))
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan))
-                    .title(" Help - Press any key to close ")
-                    .title_alignment(Alignment::Center)
-            )
-            .wrap(Wrap { trim: false });
+    let overloaded = planner.plan.iter().filter(|day| day.load() > planner.capacity).count();
+    let status = match &planner.message {
+        Some(message) => format!(" {} ", message),
+        None => format!(
+            " {} overloaded  │  [h/l] day  [j/k] todo  [H/L] or drag: move todo  [s] make room ({} moves)  [q] quit ",
+            match overloaded {
+                1 => "1 day".to_string(),
+                n => format!("{} days", n),
+            },
+            moves.len()
+        ),
+    };
+    let status_area = Rect { y: area.y + area.height.saturating_sub(1), height: 1.min(area.height), ..area };
+    f.render_widget(Paragraph::new(status).style(Style::default().fg(Color::White).bg(Color::Rgb(40, 40, 60))), status_area);
 
-        f.render_widget(help_paragraph, popup_area);
+    if planner.high_contrast {
+        raise_contrast(f.buffer_mut());
     }
 }
 
-fn run_app<B: ratatui::backend::Backend>(
-    terminal: &mut Terminal<B>,
-    mut app: App,
-) -> io::Result<()> {
-    loop {
-        terminal.draw(|f| ui(f, &app))?;
+fn show_planner(config: &Config) -> io::Result<()> {
+    let mut planner = Planner {
+        plan: load_plan(config)?,
+        capacity: config.plan.capacity_mins,
+        focus: 0,
+        selected: 0,
+        dragging: None,
+        message: None,
+        high_contrast: config.high_contrast,
+    };
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press {
-                // If help is showing, any key closes it
-                if app.show_help {
-                    app.toggle_help();
-                } else if app.edit_mode {
-                    // Handle keys in edit mode
-                    match key.code {
-                        KeyCode::Enter => app.finish_edit(),
-                        KeyCode::Esc => app.cancel_edit(),
-                        KeyCode::Char(c) => app.handle_char_input(c),
-                        KeyCode::Backspace => app.handle_backspace(),
-                        _ => {}
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let res = (|| -> io::Result<()> {
+        loop {
+            terminal.draw(|f| planner_ui(f, &planner))?;
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    planner.message = None;
+                    // H/L or Shift with an arrow carries the selected todo to the next day
+                    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+                    let carry = match key.code {
+                        KeyCode::Char('L') => Some(planner.focus + 1),
+                        KeyCode::Right if shift => Some(planner.focus + 1),
+                        KeyCode::Char('H') => planner.focus.checked_sub(1),
+                        KeyCode::Left if shift => planner.focus.checked_sub(1),
+                        _ => None,
+                    };
+                    if let Some(to) = carry.filter(|&to| to < planner.plan.len()) {
+                        if planner.selected_todo().is_some() {
+                            planner.move_todo(config, planner.focus, planner.selected, to)?;
+                        }
+                        continue;
                     }
-                } else {
-                    // Handle keys in normal mode
                     match key.code {
-                        KeyCode::Char('?') => app.toggle_help(),
-                        KeyCode::Char('q') => {
-                            app.save_todos()?;
-                            return Ok(());
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Char('l') | KeyCode::Right | KeyCode::Tab => planner.select_day(planner.focus + 1),
+                        KeyCode::Char('h') | KeyCode::Left | KeyCode::BackTab => {
+                            planner.select_day(planner.focus.saturating_sub(1))
+                        }
+                        KeyCode::Char('j') | KeyCode::Down
+                            if planner.selected + 1 < planner.plan[planner.focus].todos.len() =>
+                        {
+                            planner.selected += 1
                         }
-                        KeyCode::Char('j') | KeyCode::Down => app.next(),
-                        KeyCode::Char('k') | KeyCode::Up => app.previous(),
-                        KeyCode::Char('g') => app.goto_top(),
-                        KeyCode::Char('G') => app.goto_bottom(),
-                        KeyCode::Char('d') => app.delete_current(),
-                        KeyCode::Char('u') => app.undo_delete(),
-                        KeyCode::Char('O') => app.start_insert_above(),
-                        KeyCode::Char('o') => app.start_insert_below(),
-                        KeyCode::Char('e') | KeyCode::Enter => app.start_edit_current(),
-                        KeyCode::Char(' ') => {
-                        // Check if we had incomplete todos before toggle
-                        let had_incomplete = app.items.iter()
-                            .any(|item| matches!(item.line_type, LineType::Todo) && !item.completed);
-
-                        app.toggle_current();
-
-                        // Check if all todos are now complete
-                        let all_complete = app.items.iter()
-                            .filter(|item| matches!(item.line_type, LineType::Todo))
-                            .all(|item| item.completed);
-
-                        // If we just completed the last todo, show fireworks!
-                        if had_incomplete && all_complete && app.items.iter().any(|item| matches!(item.line_type, LineType::Todo)) {
-                            app.save_todos()?;
-
-                            // Temporarily exit the TUI
-                            disable_raw_mode()?;
-                            let mut stdout = io::stdout();
-                            execute!(
-                                stdout,
-                                LeaveAlternateScreen,
-                                DisableMouseCapture
-                            )?;
-
-                            // Show fireworks
-                            show_fireworks()?;
-
-                            // Re-enter the TUI
-                            enable_raw_mode()?;
-                            execute!(
-                                stdout,
-                                EnterAlternateScreen,
-                                EnableMouseCapture
-                            )?;
+                        KeyCode::Char('k') | KeyCode::Up => planner.selected = planner.selected.saturating_sub(1),
+                        KeyCode::Char('s') => {
+                            let moves = suggest_moves(&planner.plan, planner.capacity);
+                            let mut moved = 0;
+                            for m in &moves {
+                                moved += reschedule(config, &m.list, m.index, &m.text, m.to)? as usize;
+                            }
+                            planner.reload(config)?;
+                            planner.message = Some(match moved {
+                                0 => "Nothing to move".to_string(),
+                                1 => "Moved 1 todo".to_string(),
+                                n => format!("Moved {} todos", n),
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+                Event::Mouse(mouse) => {
+                    let area = terminal.get_frame().area();
+                    let hit = planner_hit(area, &planner, mouse.column, mouse.row);
+                    match mouse.kind {
+                        MouseEventKind::Down(MouseButton::Left) => {
+                            if let Some((day, todo)) = hit {
+                                planner.focus = day;
+                                planner.selected = todo.unwrap_or(0);
+                                planner.dragging = todo.map(|todo| (day, todo));
+                            }
                         }
+                        MouseEventKind::Up(MouseButton::Left) => {
+                            if let (Some((from, todo)), Some((to, _))) = (planner.dragging.take(), hit) {
+                                if from != to {
+                                    planner.move_todo(config, from, todo, to)?;
+                                }
+                            }
                         }
                         _ => {}
                     }
                 }
+                _ => {}
             }
         }
-    }
-}
+    })();
 
-fn show_tui(config: &Config, target_list: Option<String>) -> io::Result<()> {
-    let todo_path = expand_tilde(&config.todo.path);
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    terminal.show_cursor()?;
+    res
+}
 
+fn show_tui(config: &Config, target_list: Option<String>, record_to: Option<PathBuf>, read_only: bool) -> io::Result<()> {
     // Determine which list to display
-    let (list_path, list_name) = if let Some(list_name) = target_list {
-        let list_name = if list_name.contains('.') {
-            list_name.split('.').next().unwrap().to_string()
-        } else {
-            list_name
-        };
-        let file_name = format!("{}.{}", list_name, config.todo.list_extension);
-        let path = todo_path.join(&file_name);
-        (path, file_name)
-    } else {
-        let path = get_active_list_path(config, &todo_path);
-        let file_name = format!("{}.{}", config.todo.active_list, config.todo.list_extension);
-        (path, file_name)
-    };
+    let (list_path, list_name) = resolve_list(config, target_list);
 
     // Ensure the list exists
     ensure_active_list_exists(&list_path);
@@ -1215,9 +8256,19 @@ fn show_tui(config: &Config, target_list: Option<String>) -> io::Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
+    let mut session = match &record_to {
+        Some(_) if !read_only => {
+            let size = terminal.size()?;
+            let lines = open_storage(config).load_list(&list_name)?;
+            Some(Session::new(list_name.clone(), size.width, size.height, lines))
+        }
+        _ => None,
+    };
+
     // Create app and run
-    let app = App::new(list_path, list_name)?;
-    let res = run_app(&mut terminal, app);
+    let recorder = Recorder::new(&[&list_path]);
+    let app = open_app(config, list_path, &list_name, read_only)?;
+    let res = run_app(&mut terminal, config, app, session.as_mut());
 
     // Restore terminal
     disable_raw_mode()?;
@@ -1232,65 +8283,640 @@ fn show_tui(config: &Config, target_list: Option<String>) -> io::Result<()> {
         eprintln!("Error: {}", err);
     }
 
+    if let (Some(session), Some(path)) = (session, record_to) {
+        match session.save(&path) {
+            Ok(()) => println!("Recorded {} key presses to {}", session.keys.len(), path.display()),
+            Err(e) => eprintln!("Error saving recording: {}", e),
+        }
+    }
+
+    record(recorder, TodoEvent::ListEdited { list: list_name });
+    Ok(())
+}
+
+// Play a session against an off-screen terminal and a copy of its list kept
+// in memory, passing each rendered frame and the time it was shown to
+// `on_frame`. The user's own lists are never touched.
+fn play_session(session: &Session, mut on_frame: impl FnMut(u64, &Buffer)) -> io::Result<()> {
+    let storage = MemoryStorage::new();
+    storage.save_list(&session.list, &session.lines)?;
+
+    let mut app = App::new(Box::new(storage), session.list.clone())?;
+    let mut terminal = Terminal::new(TestBackend::new(session.width, session.height))?;
+    let celebrate = celebrations();
+
+    terminal.draw(|f| ui(f, &app))?;
+    on_frame(0, terminal.backend().buffer());
+    for recorded in &session.keys {
+        let outcome = handle_key(&mut app, recorded.key, &celebrate)?;
+        if matches!(outcome, KeyOutcome::Quit) {
+            break;
+        }
+        terminal.draw(|f| ui(f, &app))?;
+        on_frame(recorded.at_ms, terminal.backend().buffer());
+    }
+    Ok(())
+}
+
+// Replay a recorded session and print the final screen
+fn replay_session(path: &Path) -> io::Result<()> {
+    let session = Session::load(path)?;
+    let mut last = String::new();
+    play_session(&session, |_, buffer| last = render_buffer(buffer))?;
+    print!("{}", last);
+    Ok(())
+}
+
+// Symbols of a row of a rendered screen, leaving out the cells hidden under
+// the second half of wide characters such as emoji
+fn row_cells(buffer: &Buffer, y: u16) -> impl Iterator<Item = (u16, &ratatui::buffer::Cell)> {
+    let mut hidden = 0;
+    (0..buffer.area.width).map(move |x| (x, &buffer[(x, y)])).filter(move |(_, cell)| {
+        if hidden > 0 {
+            hidden -= 1;
+            return false;
+        }
+        hidden = display_width(cell.symbol()).saturating_sub(1);
+        true
+    })
+}
+
+// Text of a rendered screen, one line per row
+fn render_buffer(buffer: &Buffer) -> String {
+    let mut out = String::new();
+    for y in 0..buffer.area.height {
+        let row: String = row_cells(buffer, y).map(|(_, cell)| cell.symbol()).collect();
+        out.push_str(row.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn ansi_color(color: Color, background: bool) -> String {
+    let offset = if background { 10 } else { 0 };
+    let code = match color {
+        Color::Reset => 39,
+        Color::Black => 30,
+        Color::Red => 31,
+        Color::Green => 32,
+        Color::Yellow => 33,
+        Color::Blue => 34,
+        Color::Magenta => 35,
+        Color::Cyan => 36,
+        Color::Gray => 37,
+        Color::DarkGray => 90,
+        Color::LightRed => 91,
+        Color::LightGreen => 92,
+        Color::LightYellow => 93,
+        Color::LightBlue => 94,
+        Color::LightMagenta => 95,
+        Color::LightCyan => 96,
+        Color::White => 97,
+        Color::Rgb(r, g, b) => return format!("{};2;{};{};{}", 38 + offset, r, g, b),
+        Color::Indexed(i) => return format!("{};5;{}", 38 + offset, i),
+    };
+    (code + offset).to_string()
+}
+
+// A rendered screen as terminal output, redrawing from the top-left corner
+// Escape sequence setting a cell's colors and modifiers
+fn ansi_style(cell: &ratatui::buffer::Cell) -> String {
+    let modifiers = [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+        (Modifier::CROSSED_OUT, "9"),
+    ];
+    let mut codes = vec!["0".to_string(), ansi_color(cell.fg, false), ansi_color(cell.bg, true)];
+    codes.extend(
+        modifiers
+            .iter()
+            .filter(|(modifier, _)| cell.modifier.contains(*modifier))
+            .map(|(_, code)| code.to_string()),
+    );
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+fn ansi_frame(buffer: &Buffer) -> String {
+    let mut out = String::from("\x1b[H\x1b[2J");
+    for y in 0..buffer.area.height {
+        let mut current = None;
+        for (_, cell) in row_cells(buffer, y) {
+            let style = (cell.fg, cell.bg, cell.modifier);
+            if current != Some(style) {
+                out.push_str(&ansi_style(cell));
+                current = Some(style);
+            }
+            out.push_str(cell.symbol());
+        }
+        out.push_str("\x1b[0m");
+        if y + 1 < buffer.area.height {
+            out.push_str("\r\n");
+        }
+    }
+    out
+}
+
+// Make the links in todos on screen clickable. Ratatui works out widths from
+// cell contents, so OSC 8 sequences can't go in the cells themselves;
+// instead, once a frame is drawn, the cells showing each link are written
+// again wrapped in one. Links cut off at the edge of a pane are left alone.
+fn write_links<'a>(buffer: &Buffer, texts: impl Iterator<Item = &'a str>) -> io::Result<()> {
+    let mut links: Vec<(String, String)> = Vec::new();
+    for text in texts {
+        for link in find_links(text) {
+            let shown = text[link.start..link.end].to_string();
+            if !links.iter().any(|(other, _)| *other == shown) {
+                links.push((shown, link.target));
+            }
+        }
+    }
+    if links.is_empty() {
+        return Ok(());
+    }
+
+    let mut out = String::from("\x1b7");
+    for y in 0..buffer.area.height {
+        let mut row = String::new();
+        let mut cells = Vec::new();
+        for (x, cell) in row_cells(buffer, y) {
+            cells.push((row.len(), x, cell));
+            row.push_str(cell.symbol());
+        }
+        for (shown, target) in &links {
+            for (start, _) in row.match_indices(shown.as_str()) {
+                let end = start + shown.len();
+                let mut linked = cells.iter().filter(|(offset, _, _)| (start..end).contains(offset)).peekable();
+                let Some((_, x, _)) = linked.peek() else {
+                    continue;
+                };
+                out.push_str(&format!("\x1b[{};{}H", y + 1, x + 1));
+                let mut text = String::new();
+                let mut current = None;
+                for (_, _, cell) in linked {
+                    let style = ansi_style(cell);
+                    if current.as_ref() != Some(&style) {
+                        text.push_str(&style);
+                        current = Some(style);
+                    }
+                    text.push_str(cell.symbol());
+                }
+                out.push_str(&hyperlink(target, &text));
+            }
+        }
+    }
+    out.push_str("\x1b[0m\x1b8");
+
+    let mut stdout = io::stdout();
+    stdout.write_all(out.as_bytes())?;
+    stdout.flush()
+}
+
+// Scripted tour of the TUI used by `todo demo`
+fn demo_session() -> Session {
+    let lines = [
+        "= Launch checklist",
+        "",
+        "== This week",
+        "* [x] Draft the announcement #writing",
+        "* [ ] Review open pull requests",
+        "* [ ] Update the changelog due:2024-06-01",
+        "",
+        "== Later",
+        "* [ ] Plan the next milestone",
+    ];
+
+    let mut keys = Vec::new();
+    let mut at_ms = 0;
+    let mut press = |code: KeyCode, pause: u64| {
+        at_ms += pause;
+        keys.push(RecordedKey { at_ms, key: KeyEvent::from(code) });
+    };
+
+    press(KeyCode::Char('j'), 1200);
+    press(KeyCode::Char(' '), 800);
+    press(KeyCode::Char('j'), 800);
+    press(KeyCode::Char('o'), 800);
+    for c in "Write the release notes".chars() {
+        press(KeyCode::Char(c), 90);
+    }
+    press(KeyCode::Enter, 600);
+    press(KeyCode::Char('G'), 1000);
+    press(KeyCode::Char('d'), 300);
+    press(KeyCode::Char('d'), 800);
+    press(KeyCode::Char('u'), 1000);
+    press(KeyCode::Char('?'), 1000);
+    press(KeyCode::Esc, 2500);
+    press(KeyCode::Char('g'), 800);
+
+    Session {
+        list: "launch.adoc".to_string(),
+        width: 80,
+        height: 24,
+        lines: lines.iter().map(|line| line.to_string()).collect(),
+        keys,
+    }
+}
+
+// Write a session as an asciinema (v2) recording
+fn write_demo(cast: &Path, recording: Option<&Path>) -> io::Result<()> {
+    let session = match recording {
+        Some(path) => Session::load(path)?,
+        None => demo_session(),
+    };
+
+    let header = serde_json::json!({
+        "version": 2,
+        "width": session.width,
+        "height": session.height,
+        "title": format!("todo: {}", session.list),
+    });
+    let mut out = format!("{}\n", header);
+    let mut end_ms = 0;
+    play_session(&session, |at_ms, buffer| {
+        let frame = serde_json::json!([at_ms as f64 / 1000.0, "o", ansi_frame(buffer)]);
+        out.push_str(&format!("{}\n", frame));
+        end_ms = at_ms;
+    })?;
+    // Hold the last frame for a moment before the recording ends
+    out.push_str(&format!("{}\n", serde_json::json!([(end_ms + 2000) as f64 / 1000.0, "o", ""])));
+
+    fs::write(cast, out)?;
+    println!("Wrote {} ({} key presses)", cast.display(), session.keys.len());
     Ok(())
 }
 
 fn main() {
+    // Answers the completion scripts' calls back into todo
+    CompleteEnv::with_factory(Cli::command).complete();
+
+    // Plugin commands are listed in the help with the built-in ones
+    let mut command = Cli::command();
+    if !plugins().commands.is_empty() {
+        let listed: Vec<String> = plugins().commands.iter().map(|command| format!("  {:<12} {}", command.name, command.help)).collect();
+        command = command.after_help(format!("Plugin commands:\n{}", listed.join("\n")));
+    }
+    let matches = command.get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // Works even when the config can't be loaded, to find it and fix it
+    if let Some(Commands::Config { command: ConfigCommand::Path }) = &cli.command {
+        println!("{}", get_config_path().display());
+        return;
+    }
+
+    if cli.strict && !get_config_path().exists() {
+        eprintln!("Error: no config file at {} (--strict doesn't create one)", get_config_path().display());
+        std::process::exit(EXIT_NO_CONFIG);
+    }
     // Ensure config exists and load it
-    let mut config = ensure_config_exists();
+    let mut config = ensure_config_exists().unwrap_or_else(recover_config);
+    config.strict |= cli.strict;
+    if let Ok(dir) = std::env::current_dir() {
+        config.project_list = find_project_list(&config, &dir);
+    }
 
+    // Make sure the active list exists, asking first if it went missing.
+    // Commands that manage lists and backups don't need it.
+    let needs_active = !matches!(
+        cli.command,
+        Some(
+            Commands::Use { .. }
+                | Commands::Backups { .. }
+                | Commands::Restore { .. }
+                | Commands::Backup { command: Some(BackupCommand::Restore { .. }) }
+                | Commands::Completions { .. }
+                | Commands::Config { .. }
+        )
+    );
+    if config.strict {
+        check_strict(&config, &named_lists(&matches), needs_active && !matches!(cli.command, Some(Commands::Init)));
+    }
     // Ensure todo directory exists
     let todo_path = ensure_todo_directory_exists(&config);
 
-    // Get active list path
-    let active_list_path = get_active_list_path(&config, &todo_path);
-
-    // Ensure active list file exists
-    ensure_active_list_exists(&active_list_path);
-
-    // Parse CLI arguments first to check if user provided any commands
-    let cli = Cli::parse();
+    if needs_active && !config.strict {
+        let active_list_path = get_active_list_path(&config, &todo_path);
+        check_active_list(&mut config, &active_list_path);
+    }
 
     // Check if there's piped input AND no subcommand was provided
     let stdin = io::stdin();
     if cli.command.is_none() && !stdin.is_terminal() {
         // Read from stdin
         let reader = BufReader::new(stdin);
-        for line in reader.lines() {
-            if let Ok(todo_text) = line {
-                let trimmed = todo_text.trim();
-                if !trimmed.is_empty() {
-                    add_todo(&config, trimmed.to_string(), None);
-                }
-            }
+        let todos: Vec<String> = reader
+            .lines()
+            .map_while(Result::ok)
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        if !todos.is_empty() {
+            add_todos(&config, todos, None, AddPosition::End);
         }
         return;
     }
 
+    HIGH_CONTRAST.store(config.high_contrast, Ordering::Relaxed);
+    let tag_styles = config.tag_colors.keys().filter_map(|tag| Some((tag.to_lowercase(), config.tag_style(tag)?)));
+    let _ = TAG_STYLES.set(tag_styles.collect());
+    if !matches!(cli.command, Some(Commands::AutoCommit)) {
+        report_sync_failure();
+    }
+    enable_sync(&config);
+    enable_auto_commit(&config);
+    enable_badges(&config);
+    enable_nudges(&config, &cli.command);
+
+    run_command(&mut config, &cli);
+    if let Some(nudge) = NUDGE.get().filter(|nudge| !nudge.is_empty()) {
+        println!("{}", nudge.dimmed());
+    }
+    finish_auto_commit(&config);
+}
+
+// Exit codes under `--strict`, telling scripts which thing was missing
+const EXIT_NO_CONFIG: i32 = 3;
+const EXIT_NO_TODO_DIR: i32 = 4;
+const EXIT_NO_LIST: i32 = 5;
+
+// Where lists are named other than by `--list`, which any command can have
+const LIST_ARGS: &[(&str, &str)] = &[("use", "list_name"), ("move", "to"), ("merge", "source"), ("merge", "target")];
+
+// The lists a command names, which it would create if they were missing
+fn named_lists(matches: &ArgMatches) -> Vec<String> {
+    let mut lists = Vec::new();
+    let mut matches = matches;
+    while let Some((command, sub)) = matches.subcommand() {
+        let ids = LIST_ARGS.iter().filter(|(name, _)| *name == command).map(|(_, id)| *id);
+        for id in std::iter::once("list").chain(ids) {
+            if let Ok(Some(list)) = sub.try_get_one::<String>(id) {
+                lists.push(list.clone());
+            }
+        }
+        matches = sub;
+    }
+    lists
+}
+
+// Under `--strict`, a missing todo directory or list is an error rather
+// than something to create, so a typo in a script doesn't go unnoticed
+fn check_strict(config: &Config, named: &[String], needs_active: bool) {
+    let todo_path = expand_tilde(&config.todo.path);
+    if !todo_path.is_dir() {
+        eprintln!("Error: no todo directory at {} (--strict doesn't create one)", todo_path.display());
+        std::process::exit(EXIT_NO_TODO_DIR);
+    }
+    if let Err(e) = missing_list(config, named, needs_active) {
+        eprintln!("Error: {}", e);
+        std::process::exit(EXIT_NO_LIST);
+    }
+}
+
+// The first of the lists, or the active list when none is named and it's
+// needed, that doesn't exist
+fn missing_list(config: &Config, named: &[String], needs_active: bool) -> Result<(), String> {
+    if named.is_empty() && needs_active {
+        let active = get_active_list_path(config, &expand_tilde(&config.todo.path));
+        return match active.exists() {
+            true => Ok(()),
+            false => Err(format!("the active list {} doesn't exist (--strict doesn't create lists)", active.display())),
+        };
+    }
+    for list in named {
+        let (path, name) = resolve_list(config, Some(list.clone()));
+        if !path.exists() {
+            return Err(format!("no list named {} (--strict doesn't create lists)", name));
+        }
+    }
+    Ok(())
+}
+
+fn run_command(config: &mut Config, cli: &Cli) {
     match &cli.command {
         Some(Commands::Lists) => {
-            list_todos(&config);
+            list_todos(config, cli.json);
+        }
+        Some(Commands::List { list, pending, done, section, contains, tag, by, actionable, sort, filter }) => {
+            if let Some(name) = filter.as_ref().filter(|name| !plugins().has_filter(name)) {
+                eprintln!("No plugin filter named '{}'", name);
+                std::process::exit(1);
+            }
+            let read_only = recover_list(config, list.clone());
+            let filter = ListFilter {
+                completed: if *pending { Some(false) } else if *done { Some(true) } else { None },
+                section: section.clone(),
+                contains: contains.as_deref().map(str::to_lowercase),
+                tag: tag.clone(),
+                by: by.clone(),
+                actionable: *actionable,
+                sort: *sort,
+                plugin: filter.clone(),
+            };
+            display_todo_list(config, list.clone(), read_only, cli.json, &filter);
         }
-        Some(Commands::List { list }) => {
-            display_todo_list(&config, list.clone());
+        Some(Commands::Remind { daemon, once: _ }) => {
+            remind(config, *daemon);
         }
-        Some(Commands::Show { list }) => {
-            if let Err(e) = show_tui(&config, list.clone()) {
+        Some(Commands::Watch { list }) => {
+            watch_list(config, list.clone());
+        }
+        Some(Commands::Show { list, record, replay }) => {
+            let result = match replay {
+                Some(path) => replay_session(path),
+                None => {
+                    let read_only = recover_list(config, list.clone());
+                    show_tui(config, list.clone(), record.clone(), read_only)
+                }
+            };
+            if let Err(e) = result {
                 eprintln!("Error running TUI: {}", e);
             }
         }
+        Some(Commands::Dashboard) => {
+            if let Err(e) = show_dashboard(config) {
+                eprintln!("Error running dashboard: {}", e);
+            }
+        }
+        Some(Commands::Serve { public, list, token, bind }) => {
+            let bind = bind.as_deref().unwrap_or(if *public { "0.0.0.0:8080" } else { "127.0.0.1:8080" });
+            serve_list(config, list.clone(), token.as_deref(), bind);
+        }
+        Some(Commands::Plan { week, interactive }) => {
+            if *interactive {
+                if let Err(e) = show_planner(config) {
+                    eprintln!("Error running planner: {}", e);
+                }
+            } else {
+                print_plan(config, *week, cli.json);
+            }
+        }
         Some(Commands::Use { list_name }) => {
-            use_list(&mut config, list_name.clone());
+            use_list(config, list_name.clone());
         }
-        Some(Commands::Add { todo, list }) => {
-            add_todo(&config, todo.clone(), list.clone());
+        Some(Commands::Add { todo, message, top, after, under, section, due, list }) => {
+            let mut todos: Vec<String> = todo.iter().chain(message).cloned().collect();
+            if let Some(due) = due {
+                let Some(date) = parse_date(due, Local::now().date_naive()) else {
+                    eprintln!("Invalid date '{}': expected YYYY-MM-DD or words like \"next friday\"", due);
+                    return;
+                };
+                for text in &mut todos {
+                    let mut item = TodoItem { text: text.clone(), completed: false, line_type: LineType::Todo, depth: 0 };
+                    item.set_due(Some(date));
+                    *text = item.text;
+                }
+            }
+            let position = match (top, after, under, section) {
+                (true, _, _, _) => AddPosition::Top,
+                (_, Some(index), _, _) => AddPosition::After(index.clone()),
+                (_, _, Some(index), _) => AddPosition::Under(index.clone()),
+                (_, _, _, Some(header)) => AddPosition::Section(header.clone()),
+                _ => AddPosition::End,
+            };
+            add_todos(config, todos, list.clone(), position);
         }
         Some(Commands::Edit) => {
-            edit_list(&config);
+            edit_list(config);
+        }
+        Some(Commands::Rename { old, new }) => {
+            rename_list(config, old.clone(), new.clone());
+        }
+        Some(Commands::DeleteList { name, force, trash }) => {
+            delete_list(config, name.clone(), *force, *trash);
+        }
+        Some(Commands::Diff { list, since }) => {
+            diff_list(config, list.clone(), since.clone());
+        }
+        Some(Commands::Next { count, list }) => {
+            show_next(config, *count, list.clone());
+        }
+        Some(Commands::EditItem { index, text, list }) => {
+            edit_item(config, index, text.clone(), list.clone());
         }
+        Some(Commands::Note { index, list }) => {
+            edit_note(config, index, list.clone());
+        }
+        Some(Commands::Depend { index, on, clear, list }) => {
+            depend_todo(config, index, on, *clear, list.clone());
+        }
+        Some(Commands::Ids { list }) => {
+            assign_ids(config, list.clone());
+        }
+        Some(Commands::Sort { by, completed_last, list }) => {
+            sort_list(config, *by, *completed_last, list.clone());
+        }
+        Some(Commands::Merge { source, target, section, delete_source }) => {
+            merge_lists(config, source.clone(), target.clone(), *section, *delete_source);
+        }
+        Some(Commands::Move { index, to, list }) => {
+            move_todo(config, index, to.clone(), list.clone());
+        }
+        Some(Commands::Star { index, remove, list }) => {
+            star_todo(config, index, !*remove, list.clone());
+        }
+        Some(Commands::Prioritize { index, level, list }) => {
+            prioritize_todo(config, index, level, list.clone());
+        }
+        Some(Commands::Due { index, date, clear: _, list }) => {
+            let date = if date.is_empty() { None } else { Some(date.join(" ")) };
+            set_due(config, index, date.as_deref(), list.clone());
+        }
+        Some(Commands::Reschedule { conditions, to, dry_run, list }) => {
+            reschedule_todos(config, conditions, to, *dry_run, list.clone());
+        }
+        Some(Commands::Starred) => show_starred(config),
+        Some(Commands::Search { query, regex, tag }) => {
+            search_lists(config, query.clone(), *regex, tag.as_deref(), cli.json);
+        }
+        Some(Commands::Tags { list }) => {
+            list_tags(config, list.clone(), cli.json);
+        }
+        Some(Commands::Get { index, field, list }) => {
+            get_todo(config, index, *field, list.clone());
+        }
+        Some(Commands::Archive { list }) => {
+            archive_list(config, list.clone());
+        }
+        Some(Commands::Toggle { index, done, open, list }) => {
+            // --done/--open pin the state, which keeps keybindings idempotent
+            let state = match (done, open) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            };
+            toggle_todo(config, index, state, list.clone());
+        }
+        Some(Commands::Stats { list }) => {
+            show_stats(config, list.clone());
+        }
+        Some(Commands::Report { since, list }) => {
+            show_report(config, since.clone(), list.clone());
+        }
+        Some(Commands::Retro { week: _, days, list }) => {
+            show_retro(config, days.unwrap_or(7), list.clone());
+        }
+        Some(Commands::Insights) => show_insights(config),
+        Some(Commands::Count { complete, all_lists, format, list }) => {
+            count_todos(config, *complete, *all_lists, format.clone(), list.clone(), cli.json);
+        }
+        Some(Commands::Demo { cast, session }) => {
+            if let Err(e) = write_demo(cast, session.as_deref()) {
+                eprintln!("Error generating demo: {}", e);
+            }
+        }
+        Some(Commands::Tutorial) => {
+            run_tutorial(config);
+        }
+        Some(Commands::Sync) => {
+            sync_with_git(config);
+        }
+        Some(Commands::CommitMsg { since }) => {
+            draft_commit_message(config, since);
+        }
+        Some(Commands::AutoCommit) => {
+            run_auto_commit(config);
+        }
+        Some(Commands::Undo { history }) => {
+            undo(*history);
+        }
+        Some(Commands::Export { format, list, all, output }) => {
+            export_todos(config, *format, list.clone(), *all, output.clone());
+        }
+        Some(Commands::Import { file, format, list }) => {
+            import_todos(config, file, *format, list.clone());
+        }
+        Some(Commands::Batch { input }) => run_batch(config, input),
+        Some(Commands::Backup { command: None }) => backup_directory(config),
+        Some(Commands::Backup { command: Some(BackupCommand::Create { file }) }) => create_bundle(config, file),
+        Some(Commands::Backup { command: Some(BackupCommand::Restore { file }) }) => restore_bundle(config, file),
+        Some(Commands::Restore { snapshot }) => restore_snapshot(config, snapshot.clone()),
+        Some(Commands::Backups { command }) => match command {
+            BackupsCommand::List { list } => show_backups(config, list.clone()),
+            BackupsCommand::Restore { list, at } => restore_backup(config, list.clone(), at.clone()),
+        },
+        Some(Commands::Badge { list, svg, label, keep_updated }) => {
+            write_badge_command(config, list.clone(), svg, label, *keep_updated);
+        }
+        Some(Commands::Init) => init_project_list(config),
+        Some(Commands::Completions { shell }) => print_completions(*shell),
+        Some(Commands::Config { command }) => match command {
+            ConfigCommand::Get { key } => show_config_value(config, key),
+            ConfigCommand::Set { key, value } => set_config(config, key, value),
+            ConfigCommand::Path => println!("{}", get_config_path().display()),
+        },
+        Some(Commands::Script(args)) => run_script(config, args),
         None => {
-            println!("Active list: {}.{}", config.todo.active_list, config.todo.list_extension);
+            match &config.project_list {
+                Some(project) => println!("Active list: {} (project)", project.display()),
+                None => println!("Active list: {}.{}", config.todo.active_list, config.todo.list_extension),
+            }
             println!("Use --help to see available commands");
+            if !load_state().tutorial_completed {
+                println!("New here? Run `todo tutorial` for a quick walkthrough");
+            }
         }
     }
 }
@@ -0,0 +1,38 @@
+use crate::config::Nudge;
+use crate::item::{LineType, TodoItem};
+use crate::retro::streaks;
+use chrono::NaiveDate;
+use std::collections::BTreeSet;
+
+// The short lines `todo add` and `todo toggle` can finish with, as
+// `[output] nudges` picks: what's left on the list, the todo that's been
+// waiting longest, and how many days in a row something got done.
+
+// `list` and its `items` as they are after the change; `done_days` are the
+// days anything on any list was completed
+pub fn nudge(kind: Nudge, list: &str, items: &[TodoItem], done_days: &BTreeSet<NaiveDate>, today: NaiveDate) -> Option<String> {
+    let open: Vec<&TodoItem> = items.iter().filter(|item| matches!(item.line_type, LineType::Todo) && !item.completed).collect();
+    match kind {
+        Nudge::Remaining => Some(match open.len() {
+            0 => format!("Nothing left in {}", list),
+            1 => format!("1 todo left in {}", list),
+            n => format!("{} todos left in {}", n, list),
+        }),
+        Nudge::Oldest => {
+            let (added, item) = open.iter().filter_map(|item| Some((item.added()?, item))).min_by_key(|(added, _)| *added)?;
+            let days = (today - added).num_days();
+            let age = match days {
+                0 => "today".to_string(),
+                1 => "1 day ago".to_string(),
+                n => format!("{} days ago", n),
+            };
+            Some(format!("Oldest open: {} (added {})", item.text_without_stamps(), age))
+        }
+        Nudge::Streak => Some(match streaks(done_days, today) {
+            (0, _) => "Get one done today to start a streak".to_string(),
+            (1, _) => "Something done 1 day running".to_string(),
+            (current, longest) if current >= longest => format!("Something done {} days running, your longest yet", current),
+            (current, _) => format!("Something done {} days running", current),
+        }),
+    }
+}
@@ -0,0 +1,139 @@
+use crate::item::{LineType, TodoItem};
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+// Capacity planning: the open todos due over the next days, each weighed by
+// its `est:`, against how much work fits in a day. Overdue todos count
+// against the first day, as that's the earliest they can still be done.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Planned {
+    pub list: String,
+    // Number of the todo in its list, as shown by `todo list`
+    pub index: usize,
+    pub text: String,
+    pub minutes: u32,
+    // False when the todo has no `est:` and the default was used
+    pub estimated: bool,
+    pub priority: Option<char>,
+    pub due: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub todos: Vec<Planned>,
+}
+
+impl Day {
+    pub fn load(&self) -> u32 {
+        self.todos.iter().map(|todo| todo.minutes).sum()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Move {
+    pub list: String,
+    pub index: usize,
+    pub text: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+// Minutes in an estimate like "45m", "2h", "1h30m" or "1.5h"; a bare
+// number is minutes, and a day is eight hours
+pub fn parse_estimate(input: &str) -> Option<u32> {
+    let input = input.trim().to_lowercase();
+    if let Ok(minutes) = input.parse() {
+        return Some(minutes);
+    }
+    let mut minutes = 0.0;
+    let mut rest = input.as_str();
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let (amount, tail) = rest.split_at(split);
+        let amount: f64 = amount.parse().ok()?;
+        let unit_end = tail.find(|c: char| c.is_ascii_digit()).unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_end);
+        minutes += amount
+            * match unit {
+                // The minutes of "1h30"
+                "" | "m" | "min" | "mins" => 1.0,
+                "h" | "hr" | "hrs" => 60.0,
+                "d" | "day" | "days" => 8.0 * 60.0,
+                _ => return None,
+            };
+        rest = tail;
+    }
+    Some(minutes.round() as u32)
+}
+
+// "1h30m", "2h", "45m"
+pub fn format_minutes(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h{}m", h, m),
+    }
+}
+
+// The open todos due in the `days` days from `start`, day by day, in the
+// order of their lists
+pub fn plan(lists: &[(String, Vec<TodoItem>)], start: NaiveDate, days: usize, default_estimate: u32) -> Vec<Day> {
+    let mut plan: Vec<Day> = (0..days).map(|i| Day { date: start + Duration::days(i as i64), todos: Vec::new() }).collect();
+    for (list, items) in lists {
+        let todos = items.iter().filter(|item| matches!(item.line_type, LineType::Todo));
+        for (i, item) in todos.enumerate() {
+            let Some(due) = item.due_date().filter(|_| !item.completed) else {
+                continue;
+            };
+            let day = (due - start).num_days().max(0) as usize;
+            let Some(day) = plan.get_mut(day) else {
+                continue;
+            };
+            let estimate = item.estimate().and_then(parse_estimate);
+            day.todos.push(Planned {
+                list: list.clone(),
+                index: i + 1,
+                text: item.text.clone(),
+                minutes: estimate.unwrap_or(default_estimate),
+                estimated: estimate.is_some(),
+                priority: item.priority(),
+                due,
+            });
+        }
+    }
+    plan
+}
+
+// Todos to move to later days so no day holds more than `capacity`
+// minutes. The least important todos of an overloaded day go first, each
+// to the first later day with room for it; ones that fit nowhere stay.
+pub fn suggest_moves(plan: &[Day], capacity: u32) -> Vec<Move> {
+    let mut loads: Vec<u32> = plan.iter().map(Day::load).collect();
+    let mut moves = Vec::new();
+    for (i, day) in plan.iter().enumerate() {
+        // Unprioritized todos, then C before B before A; later ones in the
+        // list before earlier ones
+        let mut candidates: Vec<&Planned> = day.todos.iter().rev().collect();
+        candidates.sort_by_key(|todo| std::cmp::Reverse(todo.priority.unwrap_or('~')));
+        for todo in candidates {
+            if loads[i] <= capacity {
+                break;
+            }
+            let Some(to) = (i + 1..plan.len()).find(|&j| loads[j] + todo.minutes <= capacity) else {
+                continue;
+            };
+            loads[i] -= todo.minutes;
+            loads[to] += todo.minutes;
+            moves.push(Move {
+                list: todo.list.clone(),
+                index: todo.index,
+                text: todo.text.clone(),
+                from: day.date,
+                to: plan[to].date,
+            });
+        }
+    }
+    moves
+}
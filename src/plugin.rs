@@ -0,0 +1,208 @@
+use crate::item::{LineType, TodoItem};
+use mlua::{Function, Lua, Table, Value};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// Lua plugins: the `.lua` files of the scripts directory, run in name order
+// when todo starts. A plugin adds to todo through the `todo` table:
+//
+//   todo.command(name, help, function(args, items) ... end)
+//     `todo <name> args...`, given the active list's todos. A string or a
+//     table of strings it returns is added to the list.
+//   todo.filter(name, function(item) ... end)
+//     `todo list --filter <name>` shows the todos it returns true for.
+//   todo.decorate(function(item) ... end)
+//     A string it returns is shown after the todo, in `todo list` and the TUI.
+//   todo.key(key, help, function(item) ... end)
+//     A TUI key for the selected todo, if the TUI has no use for the key
+//     itself. A string it returns becomes the todo's text; the stamps
+//     are kept.
+//
+// Items are tables with `text`, `completed`, `depth`, `tags`, `due`,
+// `priority` and `id`.
+
+pub const EXTENSION: &str = "lua";
+
+pub struct Registered {
+    pub name: String,
+    pub help: String,
+    // The file it came from, for errors
+    pub plugin: String,
+    function: Function,
+}
+
+#[derive(Default)]
+struct Registry {
+    commands: Vec<Registered>,
+    filters: Vec<Registered>,
+    decorations: Vec<Registered>,
+    keys: Vec<Registered>,
+}
+
+pub struct Plugins {
+    lua: Lua,
+    pub commands: Vec<Registered>,
+    pub filters: Vec<Registered>,
+    pub decorations: Vec<Registered>,
+    pub keys: Vec<Registered>,
+}
+
+impl Default for Plugins {
+    fn default() -> Self {
+        Plugins { lua: Lua::new(), commands: Vec::new(), filters: Vec::new(), decorations: Vec::new(), keys: Vec::new() }
+    }
+}
+
+// Run every plugin in `dir`. One that fails to load is left out, with why in
+// the errors; what it registered before failing is kept.
+pub fn load(dir: &Path) -> (Plugins, Vec<String>) {
+    let mut errors = Vec::new();
+    let mut files: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(Result::ok).map(|entry| entry.path()).collect(),
+        Err(_) => return (Plugins::default(), errors),
+    };
+    files.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == EXTENSION));
+    files.sort();
+
+    let lua = Lua::new();
+    let registry = Arc::new(Mutex::new(Registry::default()));
+    let current = Arc::new(Mutex::new(String::new()));
+    if let Err(e) = install_api(&lua, &registry, &current) {
+        errors.push(format!("Couldn't set up plugins: {}", e));
+        return (Plugins::default(), errors);
+    }
+    for path in files {
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        *current.lock().unwrap_or_else(|e| e.into_inner()) = name.clone();
+        let loaded = fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|source| {
+            lua.load(source).set_name(format!("@{}", name)).exec().map_err(|e| e.to_string())
+        });
+        if let Err(e) = loaded {
+            errors.push(format!("Error in plugin {}: {}", name, e));
+        }
+    }
+
+    let registry = std::mem::take(&mut *registry.lock().unwrap_or_else(|e| e.into_inner()));
+    let plugins = Plugins {
+        lua,
+        commands: registry.commands,
+        filters: registry.filters,
+        decorations: registry.decorations,
+        keys: registry.keys,
+    };
+    (plugins, errors)
+}
+
+fn install_api(lua: &Lua, registry: &Arc<Mutex<Registry>>, current: &Arc<Mutex<String>>) -> mlua::Result<()> {
+    let api = lua.create_table()?;
+    // Each registering function files what it's given under one kind
+    let register = |kind: fn(&mut Registry) -> &mut Vec<Registered>, named: bool| {
+        let (registry, current) = (Arc::clone(registry), Arc::clone(current));
+        lua.create_function(move |_, args: mlua::Variadic<Value>| {
+            // The name and help text are the strings, in that order; the
+            // help can be left out
+            let mut texts = args.iter().filter_map(|arg| match arg {
+                Value::String(text) => Some(text.to_string_lossy()),
+                _ => None,
+            });
+            let function = args.iter().find_map(|arg| match arg {
+                Value::Function(function) => Some(function.clone()),
+                _ => None,
+            });
+            let function = function.ok_or_else(|| mlua::Error::runtime("expected a function"))?;
+            let name = match named {
+                true => texts.next().ok_or_else(|| mlua::Error::runtime("expected a name"))?,
+                false => String::new(),
+            };
+            let help = texts.next().unwrap_or_default();
+            let plugin = current.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            let mut registry = registry.lock().unwrap_or_else(|e| e.into_inner());
+            let list = kind(&mut registry);
+            // A later plugin takes over a name an earlier one had
+            list.retain(|other| !named || other.name != name);
+            list.push(Registered { name, help, plugin, function });
+            Ok(())
+        })
+    };
+    api.set("command", register(|registry| &mut registry.commands, true)?)?;
+    api.set("filter", register(|registry| &mut registry.filters, true)?)?;
+    api.set("decorate", register(|registry| &mut registry.decorations, false)?)?;
+    api.set("key", register(|registry| &mut registry.keys, true)?)?;
+    lua.globals().set("todo", api)
+}
+
+fn item_table(lua: &Lua, item: &TodoItem) -> mlua::Result<Table> {
+    let table = lua.create_table()?;
+    // The stamps stay todo's to keep
+    table.set("text", item.text_without_stamps())?;
+    table.set("completed", item.completed)?;
+    table.set("depth", item.depth)?;
+    table.set("tags", lua.create_sequence_from(item.tags())?)?;
+    table.set("due", item.due())?;
+    table.set("priority", item.priority().map(String::from))?;
+    table.set("id", item.id())?;
+    Ok(table)
+}
+
+fn failed(registered: &Registered, e: mlua::Error) -> String {
+    let what = if registered.name.is_empty() { String::new() } else { format!(" '{}'", registered.name) };
+    // Without the traceback, which doesn't fit a status bar
+    let e = e.to_string();
+    format!("Error in plugin {}{}: {}", registered.plugin, what, e.lines().next().unwrap_or_default())
+}
+
+impl Plugins {
+    pub fn command(&self, name: &str) -> Option<&Registered> {
+        self.commands.iter().find(|command| command.name == name)
+    }
+
+    pub fn has_filter(&self, name: &str) -> bool {
+        self.filters.iter().any(|filter| filter.name == name)
+    }
+
+    pub fn key(&self, key: char) -> Option<&Registered> {
+        self.keys.iter().find(|registered| registered.name.chars().eq([key]))
+    }
+
+    // The todos a command gave back to add
+    pub fn run_command(&self, command: &Registered, args: &[String], items: &[TodoItem]) -> Result<Vec<String>, String> {
+        let call = || {
+            let todos = self.lua.create_table()?;
+            for item in items.iter().filter(|item| matches!(item.line_type, LineType::Todo)) {
+                todos.raw_push(item_table(&self.lua, item)?)?;
+            }
+            let returned: Value = command.function.call((args.to_vec(), todos))?;
+            match returned {
+                Value::Nil => Ok(Vec::new()),
+                Value::String(text) => Ok(vec![text.to_string_lossy()]),
+                Value::Table(texts) => texts.sequence_values::<String>().collect(),
+                _ => Err(mlua::Error::runtime("a command returns a string, a table of strings or nothing")),
+            }
+        };
+        call().map_err(|e| failed(command, e))
+    }
+
+    // Whether the named filter keeps the todo; Err if it failed
+    pub fn filter(&self, name: &str, item: &TodoItem) -> Result<bool, String> {
+        let Some(filter) = self.filters.iter().find(|filter| filter.name == name) else {
+            return Ok(true);
+        };
+        item_table(&self.lua, item).and_then(|table| filter.function.call(table)).map_err(|e| failed(filter, e))
+    }
+
+    // What the decorations put after the todo, or nothing. A decoration that
+    // fails is left out, as there's nowhere to say so mid-list.
+    pub fn decorate(&self, item: &TodoItem) -> String {
+        let decorations = self.decorations.iter().filter_map(|decoration| {
+            let text: Option<String> = item_table(&self.lua, item).and_then(|table| decoration.function.call(table)).ok()?;
+            text.filter(|text| !text.is_empty())
+        });
+        decorations.collect::<Vec<_>>().join(" ")
+    }
+
+    // The todo's new text from a TUI key, if it gives one
+    pub fn run_key(&self, key: &Registered, item: &TodoItem) -> Result<Option<String>, String> {
+        item_table(&self.lua, item).and_then(|table| key.function.call(table)).map_err(|e| failed(key, e))
+    }
+}
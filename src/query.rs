@@ -0,0 +1,121 @@
+use crate::item::TodoItem;
+use crate::time::parse_date;
+use chrono::NaiveDate;
+
+// Conditions picking todos out of a list, as in `todo reschedule --where
+// "due<today"`: a field, a comparison and a value. `due` and `added` take
+// dates, in words too, with `none` for todos without one; `tag` and
+// `priority` can only be compared with = and !=.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn holds<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+    Due,
+    Added,
+    Tag,
+    Priority,
+}
+
+#[derive(Debug, Clone)]
+pub struct Condition {
+    field: Field,
+    comparison: Comparison,
+    // A date, tag or priority letter; None for `none`
+    value: Option<String>,
+}
+
+pub fn parse_condition(input: &str, today: NaiveDate) -> Result<Condition, String> {
+    // The two-character operators first, so "<=" isn't read as "<"
+    const OPERATORS: [(&str, Comparison); 6] = [
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("!=", Comparison::NotEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+        ("=", Comparison::Equal),
+    ];
+    let (at, operator, comparison) = OPERATORS
+        .iter()
+        .filter_map(|&(operator, comparison)| Some((input.find(operator)?, operator, comparison)))
+        .min_by_key(|&(at, _, _)| at)
+        .ok_or_else(|| format!("'{}' has no comparison: expected something like \"due<today\"", input))?;
+    let (field, value) = (input[..at].trim().to_lowercase(), input[at + operator.len()..].trim());
+
+    let field = match field.as_str() {
+        "due" => Field::Due,
+        "added" => Field::Added,
+        "tag" => Field::Tag,
+        "priority" => Field::Priority,
+        _ => return Err(format!("Unknown field '{}': expected due, added, tag or priority", field)),
+    };
+    let value = if value.eq_ignore_ascii_case("none") {
+        if !matches!(comparison, Comparison::Equal | Comparison::NotEqual) {
+            return Err(format!("'{}': none can only be compared with = and !=", input));
+        }
+        None
+    } else {
+        Some(match field {
+            Field::Due | Field::Added => parse_date(value, today)
+                .ok_or_else(|| format!("Invalid date '{}': expected YYYY-MM-DD or words like \"next friday\"", value))?
+                .format("%Y-%m-%d")
+                .to_string(),
+            Field::Tag => value.trim_start_matches('#').to_string(),
+            Field::Priority => value.to_uppercase(),
+        })
+    };
+    if matches!(field, Field::Tag | Field::Priority) && !matches!(comparison, Comparison::Equal | Comparison::NotEqual) {
+        return Err(format!("'{}': tags and priorities can only be compared with = and !=", input));
+    }
+    Ok(Condition { field, comparison, value })
+}
+
+impl Condition {
+    pub fn matches(&self, item: &TodoItem) -> bool {
+        let date = |date: Option<NaiveDate>| date.map(|date| date.format("%Y-%m-%d").to_string());
+        let actual = match self.field {
+            Field::Due => date(item.due_date()),
+            Field::Added => date(item.added()),
+            Field::Priority => item.priority().map(String::from),
+            Field::Tag => return self.matches_tag(item),
+        };
+        match (&actual, &self.value) {
+            // YYYY-MM-DD dates sort as strings do
+            (Some(actual), Some(value)) => self.comparison.holds(actual, value),
+            (None, None) => self.comparison == Comparison::Equal,
+            // A todo without the field is only unequal to a value, and one
+            // with it to none
+            (None, Some(_)) | (Some(_), None) => self.comparison == Comparison::NotEqual,
+        }
+    }
+
+    // `tag=none` is the todos without tags
+    fn matches_tag(&self, item: &TodoItem) -> bool {
+        let found = match &self.value {
+            Some(tag) => item.tags().iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            None => item.tags().is_empty(),
+        };
+        found == (self.comparison == Comparison::Equal)
+    }
+}
@@ -0,0 +1,175 @@
+use crate::item::{LineType, TodoItem};
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+// Recurring todos. A todo with an `every:` attribute (`every:3d`,
+// `every:monday`, `every:month`) comes back when it's completed: an open
+// copy due on the next date goes in right after the completed one.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interval {
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+    Weekday(Weekday),
+}
+
+// "daily", "day", "3d", "2w", "month", "6m", "yearly", "monday", "fri"...
+pub fn parse_interval(input: &str) -> Option<Interval> {
+    let input = input.to_lowercase();
+    match input.as_str() {
+        "day" | "daily" => return Some(Interval::Days(1)),
+        "week" | "weekly" => return Some(Interval::Weeks(1)),
+        "month" | "monthly" => return Some(Interval::Months(1)),
+        "year" | "yearly" | "annually" => return Some(Interval::Years(1)),
+        _ => {}
+    }
+    if let Ok(weekday) = input.parse::<Weekday>() {
+        return Some(Interval::Weekday(weekday));
+    }
+
+    let (amount, unit) = input.split_at(input.find(|c: char| !c.is_ascii_digit())?);
+    let amount: u32 = amount.parse().ok().filter(|amount| *amount > 0)?;
+    match unit {
+        "d" | "day" | "days" => Some(Interval::Days(amount)),
+        "w" | "week" | "weeks" => Some(Interval::Weeks(amount)),
+        "m" | "month" | "months" => Some(Interval::Months(amount)),
+        "y" | "year" | "years" => Some(Interval::Years(amount)),
+        _ => None,
+    }
+}
+
+fn last_day_of_month(date: NaiveDate) -> NaiveDate {
+    let first = NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap_or(date);
+    first.checked_add_months(Months::new(1)).and_then(|next| next.pred_opt()).unwrap_or(date)
+}
+
+// The first date of the interval after `from`. Months keep the day of the
+// month where it exists, and otherwise land on the month's last day (Jan 31
+// is followed by Feb 28); a todo due on a month's last day stays on the last
+// day, so it doesn't drift to the 28th once it's been through February.
+pub fn next_date(interval: Interval, from: NaiveDate) -> Option<NaiveDate> {
+    let add_months = |months: u32| {
+        let next = from.checked_add_months(Months::new(months))?;
+        Some(if from == last_day_of_month(from) { last_day_of_month(next) } else { next })
+    };
+    match interval {
+        Interval::Days(days) => from.checked_add_days(Days::new(days.into())),
+        Interval::Weeks(weeks) => from.checked_add_days(Days::new(u64::from(weeks) * 7)),
+        Interval::Months(months) => add_months(months),
+        Interval::Years(years) => add_months(years.checked_mul(12)?),
+        Interval::Weekday(weekday) => {
+            let ahead = (7 + weekday.num_days_from_monday() - from.weekday().num_days_from_monday()) % 7;
+            from.checked_add_days(Days::new(if ahead == 0 { 7 } else { ahead.into() }))
+        }
+    }
+}
+
+// The open copy a completed recurring todo leaves behind, due on the next
+// date after its due date (or today, if it had none) that isn't already
+// past. The copy waits on the todo it came from with `after:`, which is how
+// next_copy finds it again. Todos without a readable `every:` don't recur.
+pub fn next_occurrence(item: &TodoItem, today: NaiveDate) -> Option<TodoItem> {
+    let interval = parse_interval(item.every()?)?;
+    let mut due = next_date(interval, item.due_date().unwrap_or(today))?;
+    while due <= today {
+        due = next_date(interval, due)?;
+    }
+
+    let mut copy = TodoItem { text: item.text_without_stamps(), completed: false, line_type: LineType::Todo, depth: item.depth };
+    copy.stamp_added(today, item.added_by());
+    // The star marks today's focus, not the next round's
+    copy.set_starred(false);
+    copy.set_due(Some(due));
+    // Only on the round before it, or the `after:` would grow every round
+    copy.clear_blockers();
+    if let Some(id) = item.id() {
+        copy.add_blocker(id);
+    }
+    Some(copy)
+}
+
+// Position of the open copy completing `item` left in `items`, if it's
+// still there: so completing it again doesn't leave a second copy, and
+// reopening it can take the copy back
+pub fn next_copy(items: &[TodoItem], item: &TodoItem) -> Option<usize> {
+    let (id, every) = (item.id()?, item.every()?);
+    items.iter().position(|other| {
+        matches!(other.line_type, LineType::Todo) && !other.completed && other.every() == Some(every) && other.blockers().contains(&id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn todo(text: &str) -> TodoItem {
+        TodoItem { text: text.to_string(), completed: false, line_type: LineType::Todo, depth: 0 }
+    }
+
+    #[test]
+    fn month_after_jan_31_is_the_end_of_february() {
+        assert_eq!(next_date(Interval::Months(1), date(2026, 1, 31)), Some(date(2026, 2, 28)));
+        assert_eq!(next_date(Interval::Months(1), date(2024, 1, 31)), Some(date(2024, 2, 29)));
+    }
+
+    #[test]
+    fn last_day_of_the_month_stays_the_last_day() {
+        assert_eq!(next_date(Interval::Months(1), date(2026, 2, 28)), Some(date(2026, 3, 31)));
+        assert_eq!(next_date(Interval::Months(1), date(2026, 4, 30)), Some(date(2026, 5, 31)));
+        assert_eq!(next_date(Interval::Months(1), date(2026, 3, 31)), Some(date(2026, 4, 30)));
+    }
+
+    #[test]
+    fn day_of_the_month_is_kept_where_it_exists() {
+        assert_eq!(next_date(Interval::Months(1), date(2026, 3, 30)), Some(date(2026, 4, 30)));
+        assert_eq!(next_date(Interval::Months(1), date(2026, 1, 30)), Some(date(2026, 2, 28)));
+        assert_eq!(next_date(Interval::Months(1), date(2026, 5, 15)), Some(date(2026, 6, 15)));
+    }
+
+    #[test]
+    fn feb_29_yearly_lands_on_the_last_day_of_february() {
+        assert_eq!(next_date(Interval::Years(1), date(2024, 2, 29)), Some(date(2025, 2, 28)));
+        assert_eq!(next_date(Interval::Years(4), date(2024, 2, 29)), Some(date(2028, 2, 29)));
+    }
+
+    #[test]
+    fn days_roll_over_month_ends() {
+        assert_eq!(next_date(Interval::Days(1), date(2026, 4, 30)), Some(date(2026, 5, 1)));
+        assert_eq!(next_date(Interval::Days(3), date(2026, 1, 30)), Some(date(2026, 2, 2)));
+        assert_eq!(next_date(Interval::Weeks(1), date(2026, 12, 28)), Some(date(2027, 1, 4)));
+    }
+
+    #[test]
+    fn next_occurrence_skips_dates_already_past() {
+        let item = todo("rent every:month due:2026-01-31 id:abcd");
+        let next = next_occurrence(&item, date(2026, 3, 5)).unwrap();
+        assert_eq!(next.due_date(), Some(date(2026, 3, 31)));
+        assert!(!next.completed);
+    }
+
+    #[test]
+    fn next_occurrence_waits_on_the_todo_it_came_from() {
+        let item = todo("water plants every:3d after:#zzzz id:abcd");
+        let next = next_occurrence(&item, date(2026, 10, 14)).unwrap();
+        assert_eq!(next.blockers(), vec!["abcd"]);
+        assert_eq!(next.id(), None);
+        assert!(next_occurrence(&todo("no interval"), date(2026, 10, 14)).is_none());
+    }
+
+    #[test]
+    fn next_copy_finds_only_the_open_copy() {
+        let mut item = todo("water plants every:3d id:abcd");
+        item.completed = true;
+        let mut next = next_occurrence(&item, date(2026, 10, 14)).unwrap();
+        next.set_id("efgh");
+        let mut items = vec![item.clone(), next];
+        assert_eq!(next_copy(&items, &item), Some(1));
+        items[1].completed = true;
+        assert_eq!(next_copy(&items, &item), None);
+    }
+}
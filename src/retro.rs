@@ -0,0 +1,192 @@
+use crate::backup::list_backups;
+use crate::item::{parse_line, LineType, TodoItem};
+use crate::journal::JournalEntry;
+use crate::sync::{diff_ops, Op};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+// Retrospectives: what got done over a stretch of days, pieced together from
+// the journal and backups, as Markdown to paste into a team retro or journal
+
+#[derive(Debug, Clone)]
+pub struct Completion {
+    // Without the `added:` and `done:` stamps, as for the other texts here
+    pub text: String,
+    // When it was completed, if the journal still has it or, failing that,
+    // the start of the day its `done:` stamp gives; todos done in changes
+    // the journal has since dropped, or made outside todo, only show up
+    // against the backups
+    pub when: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Default)]
+pub struct ListRetro {
+    pub completed: Vec<Completion>,
+    // Todos that were already open when the period started and still are
+    pub carried: Vec<String>,
+}
+
+fn todos(content: &str) -> Vec<TodoItem> {
+    content
+        .lines()
+        .map(parse_line)
+        .filter(|item| matches!(item.line_type, LineType::Todo))
+        .collect()
+}
+
+// The list as it was at `since`: the last backup from before then or, failing
+// that, the earliest copy from after. A list nothing has changed since keeps
+// the items it has now.
+fn baseline(list_path: &Path, journal: &[JournalEntry], since: NaiveDateTime) -> Option<Vec<TodoItem>> {
+    let backups = list_backups(list_path);
+    if let Some(backup) = backups.iter().rev().find(|backup| backup.taken <= since) {
+        return fs::read_to_string(&backup.path).ok().map(|content| todos(&content));
+    }
+
+    let journaled = journal
+        .iter()
+        .filter(|entry| entry.time >= since)
+        .flat_map(|entry| entry.changes.iter().map(move |change| (entry.time, change)))
+        .find(|(_, change)| change.path == list_path)
+        .map(|(time, change)| (time, todos(change.before.as_deref().unwrap_or(""))));
+    let backed_up = backups
+        .first()
+        .and_then(|backup| Some((backup.taken, todos(&fs::read_to_string(&backup.path).ok()?))));
+
+    match (journaled, backed_up) {
+        (Some(a), Some(b)) => Some(if a.0 <= b.0 { a.1 } else { b.1 }),
+        (a, b) => a.or(b).map(|(_, items)| items),
+    }
+}
+
+pub fn list_retro(list_path: &Path, items: &[TodoItem], journal: &[JournalEntry], since: NaiveDateTime) -> ListRetro {
+    let now: Vec<&TodoItem> = items.iter().filter(|item| matches!(item.line_type, LineType::Todo)).collect();
+
+    // Completion times from the journal, the latest one winning for todos
+    // that were reopened and completed again
+    let mut dated: BTreeMap<String, NaiveDateTime> = BTreeMap::new();
+    for entry in journal.iter().filter(|entry| entry.time >= since && !entry.undone) {
+        for change in entry.changes.iter().filter(|change| change.path == list_path) {
+            for op in diff_ops(change.before.as_deref(), change.after.as_deref()) {
+                if let Op::SetDone { text, completed: true } = op {
+                    let item = TodoItem { text, completed: true, line_type: LineType::Todo, depth: 0 };
+                    dated.insert(item.key(), entry.time);
+                }
+            }
+        }
+    }
+
+    let baseline = baseline(list_path, journal, since).unwrap_or_else(|| now.iter().map(|&item| item.clone()).collect());
+    let open_before = |key: &str| baseline.iter().any(|item| !item.completed && item.key() == key);
+
+    let mut retro = ListRetro::default();
+    for item in &now {
+        let key = item.key();
+        if item.completed {
+            let stamped = item.done().map(|day| day.and_time(NaiveTime::MIN)).filter(|&when| when >= since);
+            let when = dated.get(&key).copied().or(stamped);
+            if when.is_some() || open_before(&key) {
+                retro.completed.push(Completion { text: item.text_without_stamps(), when });
+            }
+        } else if open_before(&key) {
+            retro.carried.push(item.text_without_stamps());
+        }
+    }
+    retro
+}
+
+// Consecutive days with something completed: the run ending on `end` (or
+// the day before, as today may not be over yet) and the longest run
+pub fn streaks(days: &BTreeSet<NaiveDate>, end: NaiveDate) -> (usize, usize) {
+    let run_ending = |day: NaiveDate| (0..).take_while(|&i| days.contains(&(day - Duration::days(i)))).count();
+    let current = match run_ending(end) {
+        0 => run_ending(end - Duration::days(1)),
+        run => run,
+    };
+    let longest = days.iter().map(|&day| run_ending(day)).max().unwrap_or(0);
+    (current, longest)
+}
+
+pub fn render_markdown(start: NaiveDate, end: NaiveDate, lists: &[(String, ListRetro)]) -> String {
+    let mut out = String::new();
+    let completed: Vec<&Completion> = lists.iter().flat_map(|(_, retro)| &retro.completed).collect();
+    let active_lists = lists.iter().filter(|(_, retro)| !retro.completed.is_empty()).count();
+    let days: BTreeSet<NaiveDate> = completed.iter().filter_map(|c| c.when).map(|when| when.date()).collect();
+    let period_days = (end - start).num_days() + 1;
+
+    let _ = writeln!(out, "# Retro: {} to {}", start.format("%Y-%m-%d"), end.format("%Y-%m-%d"));
+    let _ = writeln!(out);
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    let _ = writeln!(out, "- **Completed:** {} in {}", plural(completed.len(), "todo"), plural(active_lists, "list"));
+    if !days.is_empty() {
+        let (current, longest) = streaks(&days, end);
+        let _ = writeln!(
+            out,
+            "- **Active days:** {} of {} (current streak: {}, longest: {})",
+            days.len(),
+            period_days,
+            plural(current, "day"),
+            plural(longest, "day")
+        );
+    }
+    let carried: usize = lists.iter().map(|(_, retro)| retro.carried.len()).sum();
+    let _ = writeln!(out, "- **Carried over:** {}", plural(carried, "open todo"));
+
+    if completed.is_empty() {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "Nothing completed in this period.");
+    } else {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "## Completed by list");
+        for (name, retro) in lists.iter().filter(|(_, retro)| !retro.completed.is_empty()) {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "### {} ({})", name, retro.completed.len());
+            let _ = writeln!(out);
+            for completion in &retro.completed {
+                match completion.when {
+                    Some(when) => {
+                        let _ = writeln!(out, "- {} _({})_", completion.text, when.format("%a %b %-d"));
+                    }
+                    None => {
+                        let _ = writeln!(out, "- {}", completion.text);
+                    }
+                }
+            }
+        }
+
+        let mut by_tag: BTreeMap<String, usize> = BTreeMap::new();
+        for completion in &completed {
+            let item = TodoItem { text: completion.text.clone(), completed: true, line_type: LineType::Todo, depth: 0 };
+            let tags = item.tags();
+            if tags.is_empty() {
+                *by_tag.entry("untagged".to_string()).or_default() += 1;
+            }
+            for tag in tags {
+                *by_tag.entry(format!("#{}", tag)).or_default() += 1;
+            }
+        }
+        let _ = writeln!(out);
+        let _ = writeln!(out, "## Completed by tag");
+        let _ = writeln!(out);
+        for (tag, count) in &by_tag {
+            let _ = writeln!(out, "- {}: {}", tag, count);
+        }
+    }
+
+    if carried > 0 {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "## Carried over");
+        for (name, retro) in lists.iter().filter(|(_, retro)| !retro.carried.is_empty()) {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "### {} ({})", name, retro.carried.len());
+            let _ = writeln!(out);
+            for text in &retro.carried {
+                let _ = writeln!(out, "- [ ] {}", text);
+            }
+        }
+    }
+    out
+}
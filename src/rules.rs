@@ -0,0 +1,44 @@
+use crate::config::Rule;
+use crate::item::{LineType, TodoItem};
+use regex::Regex;
+
+// The text of a new todo with what the rules it matches add to it. Rules see
+// the text as it was typed, so one rule's additions don't set off another,
+// and nothing the todo already has is added again.
+pub fn apply_rules(rules: &[Rule], text: &str) -> Result<String, String> {
+    let mut result = text.to_string();
+    for rule in rules {
+        let regex =
+            Regex::new(&rule.pattern).map_err(|e| format!("Invalid rule pattern '{}': {}", rule.pattern, e))?;
+        let Some(captures) = regex.captures(text) else {
+            continue;
+        };
+
+        if let Some(priority) = rule.priority {
+            if !priority.is_ascii_uppercase() {
+                return Err(format!("Invalid rule priority '{}': expected a letter from A to Z", priority));
+            }
+            if item(&result).priority().is_none() {
+                result = format!("({}) {}", priority, result);
+            }
+        }
+        for tag in &rule.tags {
+            let tag = tag.trim_start_matches('#');
+            if !tag.is_empty() && !item(&result).tags().contains(&tag) {
+                result = format!("{} #{}", result, tag);
+            }
+        }
+        if let Some(template) = &rule.append {
+            let mut extra = String::new();
+            captures.expand(template, &mut extra);
+            if !extra.is_empty() && !result.contains(&extra) {
+                result = format!("{} {}", result, extra);
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn item(text: &str) -> TodoItem {
+    TodoItem { text: text.to_string(), completed: false, line_type: LineType::Todo, depth: 0 }
+}
@@ -0,0 +1,38 @@
+use crossterm::event::KeyEvent;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// A recorded TUI session: the list as it was when the session started, the
+// terminal size and every key pressed. Replaying it needs nothing else, so a
+// recording attached to a bug report reproduces the bug on any machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub list: String,
+    pub width: u16,
+    pub height: u16,
+    pub lines: Vec<String>,
+    pub keys: Vec<RecordedKey>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedKey {
+    // Milliseconds since the session started
+    pub at_ms: u64,
+    pub key: KeyEvent,
+}
+
+impl Session {
+    pub fn new(list: String, width: u16, height: u16, lines: Vec<String>) -> Self {
+        Session { list, width, height, lines, keys: Vec::new() }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self).map_err(io::Error::other)? + "\n")
+    }
+}
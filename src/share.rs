@@ -0,0 +1,251 @@
+use crate::badge::escape;
+use crate::item::{LineType, TodoItem};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+// Sharing one list over HTTP, for `todo serve`: a page anyone who can reach
+// it can read, and where whoever has the token can check todos
+// off. Just enough HTTP/1.1 for browsers is spoken here; every response
+// closes the connection.
+
+// Requests bigger than this are refused rather than read
+const MAX_BODY: usize = 64 * 1024;
+const MAX_LINE: usize = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+// The longest a client gets to send its whole request, however slowly the
+// bytes come
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    // The query string's parameters and, for forms, the body's
+    pub params: HashMap<String, String>,
+}
+
+impl Request {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+}
+
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = |byte: u8| (byte as char).to_digit(16);
+                match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    (Some(high), Some(low)) => {
+                        out.push((high * 16 + low) as u8);
+                        i += 2;
+                    }
+                    _ => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+pub fn encode(text: &str) -> String {
+    let mut out = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "%{:02X}", byte);
+            }
+        }
+    }
+    out
+}
+
+fn parse_params(text: &str, params: &mut HashMap<String, String>) {
+    for pair in text.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        params.insert(decode(name), decode(value));
+    }
+}
+
+// The stream until a deadline, each read waiting only as long as is left
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    until: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let too_long = || io::Error::new(io::ErrorKind::TimedOut, "request took too long");
+        let left = self.until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(too_long());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => too_long(),
+            _ => e,
+        })
+    }
+}
+
+// One line of the request, refusing one longer than MAX_LINE
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> io::Result<usize> {
+    let read = reader.take(MAX_LINE as u64 + 1).read_line(line)?;
+    if read > MAX_LINE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request line too long"));
+    }
+    Ok(read)
+}
+
+pub fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let mut reader = BufReader::new(Deadline { stream, until: Instant::now() + READ_TIMEOUT });
+    let mut line = String::new();
+    read_line(&mut reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request { method: method.to_string(), path: decode(path), params: HashMap::new() };
+    parse_params(query, &mut request.params);
+
+    let mut length = 0;
+    for count in 0.. {
+        let mut header = String::new();
+        if read_line(&mut reader, &mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "too many headers"));
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    parse_params(&String::from_utf8_lossy(&body), &mut request.params);
+    Ok(request)
+}
+
+pub fn respond(mut stream: &TcpStream, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+pub fn redirect(mut stream: &TcpStream, location: &str) -> io::Result<()> {
+    write!(stream, "HTTP/1.1 303 See Other\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", location)?;
+    stream.flush()
+}
+
+// Compares every byte, so how long a wrong guess took doesn't tell how much
+// of it was right
+pub fn token_matches(given: Option<&str>, token: &str) -> bool {
+    let Some(given) = given else {
+        return false;
+    };
+    let (a, b) = (given.as_bytes(), token.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// The list as a page. With the token, todos are buttons that check them off
+// or open them again; without it, the page is only for reading.
+pub fn render_page(name: &str, items: &[TodoItem], token: Option<&str>) -> String {
+    let mut body = String::new();
+    let mut index = 0;
+    for item in items {
+        let text = escape(&item.text);
+        match item.line_type {
+            LineType::Header1 => {
+                let _ = writeln!(body, "<h2>{}</h2>", text);
+            }
+            LineType::Header2 | LineType::Header3 => {
+                let _ = writeln!(body, "<h3>{}</h3>", text);
+            }
+            LineType::Todo => {
+                index += 1;
+                let (mark, class) = if item.completed { ("☑", "todo done") } else { ("☐", "todo") };
+                // The form sends the whole text back to find the todo by;
+                // the button shows it without the stamps
+                let shown = escape(&item.text_without_stamps());
+                match token {
+                    Some(token) => {
+                        let _ = writeln!(
+                            body,
+                            r#"<form method="post" action="/toggle?token={}"><input type="hidden" name="n" value="{}"><input type="hidden" name="text" value="{}"><button class="{}">{} {}</button></form>"#,
+                            encode(token),
+                            index,
+                            text,
+                            class,
+                            mark,
+                            shown
+                        );
+                    }
+                    None => {
+                        let _ = writeln!(body, r#"<p class="{}">{} {}</p>"#, class, mark, shown);
+                    }
+                }
+            }
+            LineType::Bullet => {
+                let _ = writeln!(body, "<p>• {}</p>", text);
+            }
+            LineType::Text => {
+                let _ = writeln!(body, "<p>{}</p>", text);
+            }
+            LineType::Note => {
+                let _ = writeln!(body, r#"<p class="note">{}</p>"#, text);
+            }
+            LineType::Empty => {}
+        }
+    }
+    if index == 0 {
+        body.push_str("<p class=\"empty\">Nothing on this list.</p>\n");
+    }
+
+    let name = escape(name);
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{name}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; max-width: 40em; margin: 1em auto; padding: 0 1em; line-height: 1.4; }}
+form {{ margin: 0; }}
+.todo {{ display: block; width: 100%; text-align: left; font: inherit; background: none; border: 0; padding: .4em 0; margin: 0; }}
+button.todo {{ cursor: pointer; }}
+.done {{ color: #888; text-decoration: line-through; }}
+.note {{ color: #555; margin: 0 0 .2em 1.6em; }}
+.empty {{ color: #888; }}
+</style>
+</head>
+<body>
+<h1>{name}</h1>
+{body}</body>
+</html>
+"#
+    )
+}
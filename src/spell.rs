@@ -0,0 +1,87 @@
+use crate::config::SpellConfig;
+use crate::item::STAR;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Misspelling {
+    pub word: String,
+    pub suggestions: Vec<String>,
+}
+
+// Whether a word of a todo is worth checking: tags, attributes such as
+// `due:`, links, the priority marker and the star are left out
+pub fn is_checked(word: &str) -> bool {
+    !(word.starts_with('#')
+        || word.contains(':')
+        || word.contains('/')
+        || word == STAR
+        || (word.len() == 3 && word.starts_with('(') && word.ends_with(')')))
+}
+
+fn checkable(text: &str) -> String {
+    text.split_whitespace().filter(|word| is_checked(word)).collect::<Vec<_>>().join(" ")
+}
+
+// Check several texts with one run of the checker, which can be anything
+// speaking the ispell pipe protocol (`hunspell -a`, `aspell -a`)
+pub fn check(config: &SpellConfig, texts: &[&str]) -> io::Result<Vec<Vec<Misspelling>>> {
+    let mut command = Command::new(&config.command);
+    command.arg("-a");
+    if let Some(dictionary) = &config.dictionary {
+        command.args(["-d", dictionary]);
+    }
+    let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::null()).spawn()?;
+
+    // A leading ^ keeps a line from being read as a checker command.
+    // Writing from another thread keeps a long list from filling both pipes.
+    let input: String = texts.iter().map(|text| format!("^{}\n", checkable(text))).collect();
+    let mut stdin = child.stdin.take().ok_or_else(|| io::Error::other("checker has no stdin"))?;
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let stdout = child.stdout.take().ok_or_else(|| io::Error::other("checker has no stdout"))?;
+    let mut lines = BufReader::new(stdout).lines();
+    // The banner, e.g. "@(#) International Ispell Version 3.2.06"
+    lines.next().transpose()?;
+
+    let mut results = Vec::new();
+    let mut current = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.is_empty() {
+            results.push(std::mem::take(&mut current));
+            if results.len() == texts.len() {
+                break;
+            }
+            continue;
+        }
+        if let Some(misspelling) = parse_result(&line) {
+            if !config.ignore.iter().any(|word| word.eq_ignore_ascii_case(&misspelling.word)) {
+                current.push(misspelling);
+            }
+        }
+    }
+    writer.join().map_err(|_| io::Error::other("checker input thread panicked"))??;
+    child.wait()?;
+
+    if results.len() != texts.len() {
+        return Err(io::Error::other(format!("{} stopped before checking everything", config.command)));
+    }
+    Ok(results)
+}
+
+// `& word count offset: one, two` (with suggestions) or `# word offset`
+fn parse_result(line: &str) -> Option<Misspelling> {
+    let mut parts = line.splitn(2, ": ");
+    let head = parts.next()?;
+    let mut fields = head.split(' ');
+    match fields.next()? {
+        "&" => Some(Misspelling {
+            word: fields.next()?.to_string(),
+            suggestions: parts.next()?.split(", ").map(str::to_string).collect(),
+        }),
+        "#" => Some(Misspelling { word: fields.next()?.to_string(), suggestions: Vec::new() }),
+        _ => None,
+    }
+}
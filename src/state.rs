@@ -0,0 +1,66 @@
+use crate::config::get_config_path;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Things todo remembers about the user between runs that aren't settings,
+// kept next to the config file so config.toml stays hand-editable
+const STATE_FILE: &str = "state.toml";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub tutorial_completed: bool,
+    // Identifies this machine in the sync logs
+    pub device_id: Option<String>,
+    // TUI hints the user has dismissed, by id
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dismissed_hints: Vec<String>,
+    // Todos `todo remind` has notified about, by list and text, and the day
+    // it last did, so each one comes up once a day
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub reminded: BTreeMap<String, NaiveDate>,
+    // The last background auto-commit or sync that failed, for the next
+    // command to report
+    pub sync_failure: Option<SyncFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncFailure {
+    pub time: NaiveDateTime,
+    pub error: String,
+}
+
+pub fn state_path() -> PathBuf {
+    get_config_path().with_file_name(STATE_FILE)
+}
+
+// A missing or unreadable state file just means a fresh state
+pub fn load_state() -> State {
+    fs::read_to_string(state_path())
+        .ok()
+        .and_then(|content| toml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// This machine's id, made up and saved on first use
+pub fn device_id() -> io::Result<String> {
+    let mut state = load_state();
+    if let Some(id) = &state.device_id {
+        return Ok(id.clone());
+    }
+
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let id = format!("{:08x}", (nanos as u64 ^ ((std::process::id() as u64) << 32)) as u32);
+    state.device_id = Some(id.clone());
+    save_state(&state)?;
+    Ok(id)
+}
+
+pub fn save_state(state: &State) -> io::Result<()> {
+    fs::write(state_path(), toml::to_string(state).map_err(io::Error::other)?)
+}
@@ -0,0 +1,455 @@
+use crate::config::{expand_tilde, normalize_list_name, BackupConfig, Config, PROJECT_LIST};
+use crate::file::{list_files, lock_list, lock_list_shared, read_list_lines, write_list_file, FileFormat};
+use crate::item::{parse_line, LineType, TodoItem};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+// Where lists live. Commands talk to this trait rather than the filesystem
+// so other backends (SQLite, SSH, in-memory for tests) can be dropped in.
+// Lists are exchanged as raw lines, leaving parsing to the caller.
+pub trait Storage: Send + Sync {
+    // Names of all lists, sorted
+    fn list_lists(&self) -> io::Result<Vec<String>>;
+
+    fn exists(&self, list_name: &str) -> bool;
+
+    fn load_list(&self, list_name: &str) -> io::Result<Vec<String>>;
+
+    fn save_list(&self, list_name: &str, lines: &[String]) -> io::Result<()>;
+
+    // Atomic read-modify-write; backends must keep other writers out while
+    // the closure runs
+    fn update_list(&self, list_name: &str, f: &mut dyn FnMut(&mut Vec<String>)) -> io::Result<()>;
+
+    // Receives a message whenever the list changes, until the receiver is
+    // dropped
+    fn watch(&self, list_name: &str) -> io::Result<Receiver<()>>;
+}
+
+impl dyn Storage + '_ {
+    pub fn load(&self, list_name: &str) -> io::Result<Vec<TodoItem>> {
+        Ok(self.load_list(list_name)?.iter().map(|line| parse_line(line)).collect())
+    }
+
+    // Only the todos, without headers and notes
+    pub fn load_todos(&self, list_name: &str) -> io::Result<Vec<TodoItem>> {
+        Ok(self.load(list_name)?.into_iter().filter(|item| matches!(item.line_type, LineType::Todo)).collect())
+    }
+
+    // update_list with a closure that can return a value
+    pub fn update<T>(&self, list_name: &str, f: impl FnOnce(&mut Vec<String>) -> T) -> io::Result<T> {
+        let mut f = Some(f);
+        let mut result = None;
+        self.update_list(list_name, &mut |lines| {
+            if let Some(f) = f.take() {
+                result = Some(f(lines));
+            }
+        })?;
+        Ok(result.expect("update_list did not run the closure"))
+    }
+}
+
+// The todo directory's files, or the batch's copies of them while one runs
+pub fn open_storage(config: &Config) -> Box<dyn Storage> {
+    match BATCH.get() {
+        Some(batch) => Box::new(batch.clone()),
+        None => Box::new(ListStore::from_config(config)),
+    }
+}
+
+static BATCH: OnceLock<BatchStore> = OnceLock::new();
+
+// Route every open_storage from here on through one BatchStore, for `todo
+// batch`. Nothing is written until its commit.
+pub fn begin_batch(config: &Config) -> BatchStore {
+    BATCH.get_or_init(|| BatchStore::new(ListStore::from_config(config))).clone()
+}
+
+// Filesystem storage: one file per list in the todo directory. The handle
+// can be cloned and shared between threads. Every operation goes through the
+// list's file lock, so separate handles in this or other processes never see
+// each other's partial writes.
+//
+// The methods block on file I/O; async callers should run them with their
+// runtime's blocking-task facility (e.g. tokio's spawn_blocking), which is
+// why the handle is cheap to clone and 'static.
+#[derive(Debug, Clone)]
+pub struct ListStore {
+    root: PathBuf,
+    extension: String,
+    backup: BackupConfig,
+    // Where the project-local list is, if there is one
+    project: Option<PathBuf>,
+    // The workspace's other directories, by the prefix of their lists' names
+    workspace: BTreeMap<String, PathBuf>,
+}
+
+impl ListStore {
+    pub fn new(root: PathBuf, extension: String, backup: BackupConfig) -> Self {
+        ListStore { root, extension, backup, project: None, workspace: BTreeMap::new() }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let mut store = ListStore::new(
+            expand_tilde(&config.todo.path),
+            config.todo.list_extension.clone(),
+            config.backup.clone(),
+        );
+        store.project = config.project_list.clone();
+        store.workspace = config.workspace.iter().map(|(name, path)| (name.clone(), expand_tilde(path))).collect();
+        store
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn list_path(&self, list_name: &str) -> PathBuf {
+        let list_name = normalize_list_name(list_name);
+        let workspace = list_name.split_once('/').and_then(|(root, name)| Some((self.workspace.get(root)?, name)));
+        if let Some((root, name)) = workspace {
+            return root.join(format!("{}.{}", name, self.extension));
+        }
+        match &self.project {
+            Some(path) if list_name == PROJECT_LIST => path.clone(),
+            // The project list's archive is kept next to it
+            Some(path) if list_name.strip_suffix("-archive") == Some(PROJECT_LIST) => {
+                path.with_file_name(format!("{}.{}", list_name, self.extension))
+            }
+            _ => self.root.join(format!("{}.{}", list_name, self.extension)),
+        }
+    }
+}
+
+impl Storage for ListStore {
+    // Lists in the workspace's other directories come after the main one's,
+    // as `root/list`; a directory that can't be read is skipped
+    fn list_lists(&self) -> io::Result<Vec<String>> {
+        let names = |root: &Path, prefix: &str| -> io::Result<Vec<String>> {
+            Ok(list_files(root)?
+                .iter()
+                .filter(|path| path.extension().and_then(|e| e.to_str()) == Some(self.extension.as_str()))
+                .filter_map(|path| Some(format!("{}{}", prefix, path.file_stem()?.to_str()?)))
+                .collect())
+        };
+        let mut lists = names(&self.root, "")?;
+        for (name, root) in &self.workspace {
+            lists.extend(names(root, &format!("{}/", name)).unwrap_or_default());
+        }
+        Ok(lists)
+    }
+
+    fn exists(&self, list_name: &str) -> bool {
+        self.list_path(list_name).exists()
+    }
+
+    fn load_list(&self, list_name: &str) -> io::Result<Vec<String>> {
+        let path = self.list_path(list_name);
+        let _lock = lock_list_shared(&path)?;
+        Ok(read_list_lines(&path)?.0)
+    }
+
+    fn save_list(&self, list_name: &str, lines: &[String]) -> io::Result<()> {
+        self.update_list(list_name, &mut |current| *current = lines.to_vec())
+    }
+
+    // The file is only rewritten when the closure changed something
+    fn update_list(&self, list_name: &str, f: &mut dyn FnMut(&mut Vec<String>)) -> io::Result<()> {
+        let path = self.list_path(list_name);
+        let _lock = lock_list(&path)?;
+        let (mut lines, format) = read_list_lines(&path)?;
+
+        let original = lines.clone();
+        f(&mut lines);
+        if lines != original {
+            write_list_file(&path, &lines, &format, &self.backup)?;
+        }
+        Ok(())
+    }
+
+    // Watches the file's directory rather than the file, as saving it, by
+    // todo or most editors, replaces it with a new one
+    fn watch(&self, list_name: &str) -> io::Result<Receiver<()>> {
+        let path = self.list_path(list_name);
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")).to_path_buf();
+        let (events, received) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(events).map_err(io::Error::other)?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive).map_err(io::Error::other)?;
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _watcher = watcher;
+            for event in received {
+                let Ok(event) = event else {
+                    continue;
+                };
+                let changed = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_));
+                let ours = event.paths.iter().any(|changed| changed.file_name() == path.file_name());
+                if changed && ours && sender.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+// Filesystem storage for a run of commands in one process: each list is
+// locked and read the first time it's touched and stays locked, with the
+// commands working on the copy in memory, until commit writes the lists that
+// changed, once each. Don't lock a held list's file any other way before
+// then; flock doesn't nest across file handles.
+#[derive(Debug, Clone)]
+pub struct BatchStore {
+    store: ListStore,
+    held: Arc<Mutex<BTreeMap<PathBuf, HeldList>>>,
+}
+
+#[derive(Debug)]
+struct HeldList {
+    _lock: fs::File,
+    format: FileFormat,
+    original: Vec<String>,
+    lines: Vec<String>,
+}
+
+impl BatchStore {
+    pub fn new(store: ListStore) -> Self {
+        BatchStore { store, held: Arc::default() }
+    }
+
+    // The closure runs with the list taken out of the map, so that it can
+    // update another list, as moving a todo does
+    fn with_list<T>(&self, list_name: &str, f: impl FnOnce(&mut Vec<String>) -> T) -> io::Result<T> {
+        let path = self.store.list_path(list_name);
+        let mut lines = {
+            let mut held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+            let list = match held.entry(path.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let lock = lock_list(entry.key())?;
+                    let (lines, format) = read_list_lines(entry.key())?;
+                    entry.insert(HeldList { _lock: lock, format, original: lines.clone(), lines })
+                }
+            };
+            std::mem::take(&mut list.lines)
+        };
+        let result = f(&mut lines);
+        let mut held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(list) = held.get_mut(&path) {
+            list.lines = lines;
+        }
+        Ok(result)
+    }
+
+    // Files of the lists that would be written by commit
+    pub fn changed(&self) -> Vec<PathBuf> {
+        let held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+        held.iter().filter(|(_, list)| list.lines != list.original).map(|(path, _)| path.clone()).collect()
+    }
+
+    // Write the lists that changed and release them all. A list that fails
+    // to write stops the rest.
+    pub fn commit(&self) -> io::Result<()> {
+        let held = std::mem::take(&mut *self.held.lock().unwrap_or_else(|e| e.into_inner()));
+        for (path, list) in held {
+            if list.lines != list.original {
+                write_list_file(&path, &list.lines, &list.format, &self.store.backup)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Storage for BatchStore {
+    fn list_lists(&self) -> io::Result<Vec<String>> {
+        self.store.list_lists()
+    }
+
+    fn exists(&self, list_name: &str) -> bool {
+        let held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+        held.contains_key(&self.store.list_path(list_name)) || self.store.exists(list_name)
+    }
+
+    // Reading a list doesn't create it
+    fn load_list(&self, list_name: &str) -> io::Result<Vec<String>> {
+        if !self.exists(list_name) {
+            return self.store.load_list(list_name);
+        }
+        self.with_list(list_name, |lines| lines.clone())
+    }
+
+    fn save_list(&self, list_name: &str, lines: &[String]) -> io::Result<()> {
+        self.with_list(list_name, |current| *current = lines.to_vec())
+    }
+
+    fn update_list(&self, list_name: &str, f: &mut dyn FnMut(&mut Vec<String>)) -> io::Result<()> {
+        self.with_list(list_name, f)
+    }
+
+    fn watch(&self, list_name: &str) -> io::Result<Receiver<()>> {
+        self.store.watch(list_name)
+    }
+}
+
+// Storage that only lives in memory, for tests and previews
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    lists: Mutex<HashMap<String, Vec<String>>>,
+    watchers: Mutex<HashMap<String, Vec<Sender<()>>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        MemoryStorage::default()
+    }
+
+    fn notify(&self, list_name: &str) {
+        let mut watchers = self.watchers.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(senders) = watchers.get_mut(list_name) {
+            senders.retain(|sender| sender.send(()).is_ok());
+        }
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn list_lists(&self) -> io::Result<Vec<String>> {
+        let lists = self.lists.lock().unwrap_or_else(|e| e.into_inner());
+        let mut names: Vec<String> = lists.keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn exists(&self, list_name: &str) -> bool {
+        let lists = self.lists.lock().unwrap_or_else(|e| e.into_inner());
+        lists.contains_key(&normalize_list_name(list_name))
+    }
+
+    fn load_list(&self, list_name: &str) -> io::Result<Vec<String>> {
+        let lists = self.lists.lock().unwrap_or_else(|e| e.into_inner());
+        lists
+            .get(&normalize_list_name(list_name))
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no list named '{}'", list_name)))
+    }
+
+    fn save_list(&self, list_name: &str, lines: &[String]) -> io::Result<()> {
+        self.update_list(list_name, &mut |current| *current = lines.to_vec())
+    }
+
+    fn update_list(&self, list_name: &str, f: &mut dyn FnMut(&mut Vec<String>)) -> io::Result<()> {
+        let name = normalize_list_name(list_name);
+        let changed = {
+            let mut lists = self.lists.lock().unwrap_or_else(|e| e.into_inner());
+            let lines = lists.entry(name.clone()).or_default();
+            let original = lines.clone();
+            f(lines);
+            *lines != original
+        };
+        if changed {
+            self.notify(&name);
+        }
+        Ok(())
+    }
+
+    fn watch(&self, list_name: &str) -> io::Result<Receiver<()>> {
+        let (sender, receiver) = mpsc::channel();
+        let mut watchers = self.watchers.lock().unwrap_or_else(|e| e.into_inner());
+        watchers.entry(normalize_list_name(list_name)).or_default().push(sender);
+        Ok(receiver)
+    }
+}
+
+// Compile-time guarantee that the handles can be shared across threads
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync + 'static>() {}
+    assert_send_sync::<ListStore>();
+    assert_send_sync::<MemoryStorage>();
+    assert_send_sync::<BatchStore>();
+    assert_send_sync::<Arc<dyn Storage>>();
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn list(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn update_returns_the_closures_value() {
+        let store: Box<dyn Storage> = Box::new(MemoryStorage::new());
+        store.save_list("work", &list(&["* [ ] one"])).unwrap();
+        let count = store.update("work.adoc", |lines| {
+            lines.push("* [ ] two".to_string());
+            lines.len()
+        });
+        assert_eq!(count.unwrap(), 2);
+        assert_eq!(store.load_list("work").unwrap(), list(&["* [ ] one", "* [ ] two"]));
+    }
+
+    #[test]
+    fn load_todos_leaves_out_headers_and_notes() {
+        let store: Box<dyn Storage> = Box::new(MemoryStorage::new());
+        store.save_list("work", &list(&["== Today", "* [ ] one", "  a note", "* [x] two"])).unwrap();
+        let todos = store.load_todos("work").unwrap();
+        assert_eq!(todos.iter().map(|item| item.text.as_str()).collect::<Vec<_>>(), ["one", "two"]);
+        assert!(store.load("missing").is_err());
+    }
+
+    #[test]
+    fn watch_hears_only_changes() {
+        let store = MemoryStorage::new();
+        let changes = store.watch("work").unwrap();
+        store.save_list("work", &list(&["* [ ] one"])).unwrap();
+        store.save_list("work", &list(&["* [ ] one"])).unwrap();
+        store.save_list("other", &list(&["* [ ] two"])).unwrap();
+        assert_eq!(changes.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn list_store_watch_hears_its_list_saved() {
+        let root = std::env::temp_dir().join(format!("todo-watch-test-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let no_backups = BackupConfig { enabled: false, ..BackupConfig::default() };
+        let store = ListStore::new(root.clone(), "adoc".to_string(), no_backups);
+        let changes = store.watch("work").unwrap();
+        store.save_list("other", &list(&["* [ ] two"])).unwrap();
+        assert!(changes.recv_timeout(Duration::from_millis(300)).is_err());
+        store.save_list("work", &list(&["* [ ] one"])).unwrap();
+        let heard = changes.recv_timeout(Duration::from_secs(5));
+        fs::remove_dir_all(&root).unwrap();
+        assert!(heard.is_ok());
+    }
+
+    #[test]
+    fn batch_updates_one_list_inside_another() {
+        let root = std::env::temp_dir().join(format!("todo-batch-test-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let no_backups = BackupConfig { enabled: false, ..BackupConfig::default() };
+        let batch = BatchStore::new(ListStore::new(root.clone(), "adoc".to_string(), no_backups));
+        let store: Box<dyn Storage> = Box::new(batch.clone());
+        store.save_list("from", &list(&["* [ ] one"])).unwrap();
+        store
+            .update("from", |from| {
+                let moved = from.remove(0);
+                store.update("to", |to| to.push(moved)).unwrap();
+            })
+            .unwrap();
+        // Nothing is written before the commit
+        assert!(fs::read_to_string(root.join("to.adoc")).unwrap_or_default().is_empty());
+        batch.commit().unwrap();
+        let written = fs::read_to_string(root.join("to.adoc")).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(written.lines().collect::<Vec<_>>(), ["* [ ] one"]);
+    }
+}
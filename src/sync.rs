@@ -0,0 +1,354 @@
+use crate::config::BackupConfig;
+use crate::file::{find_header, list_files, lock_list, note_end, read_list_lines, section_end, todo_end, write_list_file};
+use crate::item::{derived_id, format_line, parse_line, LineType, TodoItem};
+use crate::journal::FileChange;
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// Merging for lists synced by file-sync tools (Dropbox, Syncthing) rather
+// than git. Every change todo makes to a list is also written as per-item
+// operations to a log owned by this device, .sync/<list>/<device>.jsonl.
+// Each device only ever writes its own log, so the logs themselves never
+// conflict. A merge starts from the last state the devices had in common,
+// where this device last merged, and replays onto the list only the other
+// devices' operations logged since; what this device did since is already in
+// its file. Operations are idempotent (add if missing, remove if present, set
+// state), so replaying ones the file already reflects is harmless.
+//
+// Todos are identified by their text, less the `done:` stamp. Headers,
+// notes, and changes made to the file without todo, aren't in the logs,
+// though a removed todo takes its note and subtasks along.
+pub const SYNC_DIR: &str = ".sync";
+const CURSOR_SUFFIX: &str = ".cursor";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Op {
+    // `after` is the todo it followed and `section` the header above it,
+    // used to put it back in roughly the same place
+    Add {
+        text: String,
+        completed: bool,
+        after: Option<String>,
+        section: Option<String>,
+        // How deep it's nested as a subtask
+        #[serde(default, skip_serializing_if = "is_top_level")]
+        depth: usize,
+    },
+    Remove { text: String },
+    SetDone { text: String, completed: bool },
+}
+
+fn is_top_level(depth: &usize) -> bool {
+    *depth == 0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedOp {
+    pub time: NaiveDateTime,
+    pub device: String,
+    #[serde(flatten)]
+    pub op: Op,
+}
+
+struct Logger {
+    root: PathBuf,
+    device: String,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+// Start logging operations for lists in `root` as `device`
+pub fn enable(root: PathBuf, device: String) {
+    let _ = LOGGER.set(Logger { root, device });
+}
+
+fn log_dir(root: &Path, list: &str) -> PathBuf {
+    root.join(SYNC_DIR).join(list)
+}
+
+fn todos(content: Option<&str>) -> Vec<(Option<String>, TodoItem)> {
+    let mut section = None;
+    let mut todos = Vec::new();
+    for item in content.unwrap_or("").lines().map(parse_line) {
+        match item.line_type {
+            LineType::Header1 | LineType::Header2 | LineType::Header3 => section = Some(item.text.clone()),
+            LineType::Todo => todos.push((section.clone(), item)),
+            _ => {}
+        }
+    }
+    todos
+}
+
+// Per-item operations that turn `before` into `after`
+pub fn diff_ops(before: Option<&str>, after: Option<&str>) -> Vec<Op> {
+    let (before, after) = (todos(before), todos(after));
+    let mut ops = Vec::new();
+
+    let after_keys: HashSet<String> = after.iter().map(|(_, item)| item.key()).collect();
+    for (_, item) in &before {
+        if !after_keys.contains(&item.key()) {
+            ops.push(Op::Remove { text: item.text.clone() });
+        }
+    }
+
+    for (i, (section, item)) in after.iter().enumerate() {
+        match before.iter().find(|(_, old)| old.key() == item.key()) {
+            Some((_, old)) if old.completed != item.completed => {
+                ops.push(Op::SetDone { text: item.text.clone(), completed: item.completed })
+            }
+            Some(_) => {}
+            None => ops.push(Op::Add {
+                text: item.text.clone(),
+                completed: item.completed,
+                after: i.checked_sub(1).map(|prev| after[prev].1.text.clone()),
+                section: section.clone(),
+                depth: item.depth,
+            }),
+        }
+    }
+    ops
+}
+
+// Log the operations behind journaled file changes, if logging is enabled.
+// Only files directly in the todo directory are lists.
+pub fn log_changes(changes: &[FileChange]) -> io::Result<()> {
+    let Some(logger) = LOGGER.get() else {
+        return Ok(());
+    };
+
+    let time = Local::now().naive_local();
+    for change in changes {
+        if change.path.parent() != Some(logger.root.as_path()) {
+            continue;
+        }
+        let Some(list) = change.path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let ops = diff_ops(change.before.as_deref(), change.after.as_deref());
+        append_ops(&logger.root, list, &logger.device, time, ops)?;
+    }
+    Ok(())
+}
+
+fn append_ops(root: &Path, list: &str, device: &str, time: NaiveDateTime, ops: Vec<Op>) -> io::Result<()> {
+    if ops.is_empty() {
+        return Ok(());
+    }
+    let dir = log_dir(root, list);
+    fs::create_dir_all(&dir)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(dir.join(format!("{}.jsonl", device)))?;
+    for op in ops {
+        let logged = LoggedOp { time, device: device.to_string(), op };
+        writeln!(file, "{}", serde_json::to_string(&logged).map_err(io::Error::other)?)?;
+    }
+    Ok(())
+}
+
+// Todos added on different devices can end up with the same id. The one
+// whose text sorts first keeps it; the others get an id worked out from
+// their text, which every device merging the same todos comes up with.
+fn separate_ids(lines: &mut [String]) {
+    let mut items: Vec<TodoItem> = lines.iter().map(|line| parse_line(line)).collect();
+    let with_id: Vec<usize> =
+        (0..items.len()).filter(|&i| matches!(items[i].line_type, LineType::Todo) && items[i].id().is_some()).collect();
+    for &i in &with_id {
+        let id = items[i].id().map(str::to_string);
+        let keeper = with_id.iter().copied().filter(|&j| items[j].id() == id.as_deref()).min_by_key(|&j| items[j].key());
+        if keeper != Some(i) {
+            let new = derived_id(&items, &items[i].key());
+            items[i].set_id(&new);
+            lines[i] = format_line(&items[i]);
+        }
+    }
+}
+
+// Apply an operation to a list's lines
+pub fn apply_op(lines: &mut Vec<String>, op: &Op) {
+    let find = |lines: &[String], text: &str| {
+        let key = TodoItem { text: text.to_string(), completed: false, line_type: LineType::Todo, depth: 0 }.key();
+        lines.iter().position(|line| {
+            let item = parse_line(line);
+            matches!(item.line_type, LineType::Todo) && item.key() == key
+        })
+    };
+
+    match op {
+        Op::Add { text, completed, after, section, depth } => {
+            if find(lines, text).is_some() {
+                return;
+            }
+            let line = format_line(&TodoItem { text: text.clone(), completed: *completed, line_type: LineType::Todo, depth: *depth });
+            let after_pos = after.as_deref().and_then(|after| find(lines, after));
+            // Otherwise at the end of its section, or of the list
+            let section_end = || Some(section_end(lines, find_header(lines, section.as_deref()?)?));
+            match after_pos.map(|pos| note_end(lines, pos)).or_else(section_end) {
+                Some(pos) => lines.insert(pos, line),
+                None => lines.push(line),
+            }
+        }
+        Op::Remove { text } => {
+            if let Some(pos) = find(lines, text) {
+                lines.drain(pos..todo_end(lines, pos));
+            }
+        }
+        Op::SetDone { text, completed } => {
+            // The text brings the `done:` stamp along
+            if let Some(pos) = find(lines, text) {
+                let depth = parse_line(&lines[pos]).depth;
+                let item = TodoItem { text: text.clone(), completed: *completed, line_type: LineType::Todo, depth };
+                lines[pos] = format_line(&item);
+            }
+        }
+    }
+}
+
+// Each device's operations on a list, in the order it logged them
+fn read_logs(root: &Path, list: &str) -> io::Result<BTreeMap<String, Vec<LoggedOp>>> {
+    let dir = log_dir(root, list);
+    let mut logs = BTreeMap::new();
+    if !dir.exists() {
+        return Ok(logs);
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(device) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+            continue;
+        }
+        // A log the sync tool hasn't finished copying may end mid-line
+        let ops = fs::read_to_string(&path)?
+            .lines()
+            .map_while(|line| serde_json::from_str::<LoggedOp>(line).ok())
+            .collect();
+        logs.insert(device.to_string(), ops);
+    }
+    Ok(logs)
+}
+
+// Copies of a list that a sync tool saved when two devices changed it at
+// once: Syncthing's `<list>.sync-conflict-...` and Dropbox's
+// `<list> (... conflicted copy ...)`
+pub fn conflict_copies(list_path: &Path) -> io::Result<Vec<PathBuf>> {
+    let (Some(root), Some(stem)) = (list_path.parent(), list_path.file_stem().and_then(|s| s.to_str())) else {
+        return Ok(Vec::new());
+    };
+    let extension = list_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    Ok(list_files(root)?
+        .into_iter()
+        .filter(|path| {
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                return false;
+            };
+            path.extension().and_then(|e| e.to_str()) == Some(extension)
+                && (name.starts_with(&format!("{}.sync-conflict-", stem))
+                    || (name.starts_with(&format!("{} (", stem)) && name.contains("conflicted copy")))
+        })
+        .collect())
+}
+
+#[derive(Debug, Default)]
+pub struct MergeResult {
+    pub applied: usize,
+    // Conflict copies the sync tool left next to the list. They stay there,
+    // as they may hold changes made without todo, which aren't in the logs.
+    pub conflicts: Vec<PathBuf>,
+}
+
+// Bring other devices' changes into a list: the operations they logged since
+// this device last merged, in time order. A todo removed here, by todo or by
+// editing the file, stays removed unless another device adds it again.
+pub fn merge_list(list_path: &Path, device: &str, backup: &BackupConfig) -> io::Result<MergeResult> {
+    let (Some(root), Some(list)) = (list_path.parent(), list_path.file_name().and_then(|n| n.to_str())) else {
+        return Ok(MergeResult::default());
+    };
+    if !list_path.exists() {
+        return Ok(MergeResult::default());
+    }
+
+    let _lock = lock_list(list_path)?;
+    let logs = read_logs(root, list)?;
+    let mut result = MergeResult { conflicts: conflict_copies(list_path)?, ..MergeResult::default() };
+
+    // How many of each device's operations this device has merged
+    let cursor_path = log_dir(root, list).join(format!("{}{}", device, CURSOR_SUFFIX));
+    let cursor: BTreeMap<String, usize> = fs::read_to_string(&cursor_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let mut ops: Vec<&LoggedOp> = logs
+        .iter()
+        .filter(|(other, _)| *other != device)
+        .flat_map(|(other, ops)| ops.iter().skip(cursor.get(other).copied().unwrap_or(0)))
+        .collect();
+    if ops.is_empty() {
+        return Ok(result);
+    }
+    ops.sort_by(|a, b| a.time.cmp(&b.time).then_with(|| a.device.cmp(&b.device)));
+
+    let (mut lines, format) = read_list_lines(list_path)?;
+    for logged in ops {
+        let before = lines.clone();
+        apply_op(&mut lines, &logged.op);
+        if lines != before {
+            result.applied += 1;
+        }
+    }
+    // The new ids go in the log like any change, so replaying other
+    // devices' adds doesn't bring the clashing ids back
+    let replayed = lines.clone();
+    separate_ids(&mut lines);
+    if lines != replayed {
+        let (before, after) = (replayed.join("\n"), lines.join("\n"));
+        append_ops(root, list, device, Local::now().naive_local(), diff_ops(Some(&before), Some(&after)))?;
+        result.applied += 1;
+    }
+    if result.applied > 0 {
+        write_list_file(list_path, &lines, &format, backup)?;
+    }
+
+    let cursor: BTreeMap<&String, usize> = logs.iter().map(|(device, ops)| (device, ops.len())).collect();
+    fs::create_dir_all(log_dir(root, list))?;
+    fs::write(&cursor_path, serde_json::to_string(&cursor).map_err(io::Error::other)?)?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(text: &str) -> Op {
+        Op::Add { text: text.to_string(), completed: false, after: None, section: None, depth: 0 }
+    }
+
+    #[test]
+    fn merge_keeps_what_was_removed_since() {
+        let root = std::env::temp_dir().join(format!("todo-sync-test-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let list = root.join("work.adoc");
+        let backup = BackupConfig { enabled: false, ..BackupConfig::default() };
+        let time = Local::now().naive_local();
+        fs::write(&list, "").unwrap();
+
+        append_ops(&root, "work.adoc", "other", time, vec![add("one"), add("two")]).unwrap();
+        assert_eq!(merge_list(&list, "here", &backup).unwrap().applied, 2);
+
+        // Edited out by hand here, then another todo added over there
+        fs::write(&list, "* [ ] two\n").unwrap();
+        append_ops(&root, "work.adoc", "other", time, vec![add("three")]).unwrap();
+        let merged = merge_list(&list, "here", &backup).unwrap();
+        let lines = fs::read_to_string(&list).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(merged.applied, 1);
+        assert_eq!(lines.lines().collect::<Vec<_>>(), ["* [ ] two", "* [ ] three"]);
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::{Datelike, Days, Local, Month, Months, NaiveDate, NaiveDateTime, Weekday};
+
+// Accepts a date and time, a date (meaning the start of that day), "now",
+// "today", "yesterday", or a relative offset like "3d", "12h" or "2w" ago
+pub fn parse_time(input: &str) -> Option<NaiveDateTime> {
+    let input = input.trim().to_lowercase();
+    let now = Local::now().naive_local();
+    let today = now.date();
+
+    match input.as_str() {
+        "now" => return Some(now),
+        "today" => return today.and_hms_opt(0, 0, 0),
+        "yesterday" => return today.pred_opt()?.and_hms_opt(0, 0, 0),
+        _ => {}
+    }
+
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(&input, format) {
+            return Some(time);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+
+    let split = input.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = input.split_at(split);
+    let amount: i64 = amount.parse().ok()?;
+    let offset = match unit.trim() {
+        "m" | "min" | "mins" | "minutes" => chrono::Duration::minutes(amount),
+        "h" | "hours" => chrono::Duration::hours(amount),
+        "d" | "days" => chrono::Duration::days(amount),
+        "w" | "weeks" => chrono::Duration::weeks(amount),
+        _ => return None,
+    };
+    Some(now - offset)
+}
+
+// Like parse_time, but a bare date means the end of that day
+pub fn parse_backup_time(input: &str) -> Option<NaiveDateTime> {
+    if let Ok(date) = NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d") {
+        return date.and_hms_opt(23, 59, 59);
+    }
+    parse_time(input)
+}
+
+// A day given in words, for due dates: a date (YYYY-MM-DD), "today",
+// "tomorrow", "yesterday", a weekday ("friday", "next fri"), "next week" or
+// "next month" (their first day), "jun 1" or "1 june", or an offset like
+// "in 3 days", "2 weeks" or "3d". Words can also be joined with '-', as in
+// `due:next-friday`. Weekdays and month days mean the next one after today.
+pub fn parse_date(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let input = input.replace(['-', '_'], " ");
+    let words: Vec<&str> = input.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["today"] => return Some(today),
+        ["tomorrow"] => return today.succ_opt(),
+        ["yesterday"] => return today.pred_opt(),
+        ["next", "week"] => return Some(next_weekday(today, Weekday::Mon)),
+        ["next", "month"] => return NaiveDate::from_ymd_opt(today.year(), today.month(), 1)?.checked_add_months(Months::new(1)),
+        ["next", "year"] => return NaiveDate::from_ymd_opt(today.year() + 1, 1, 1),
+        [day] | ["next" | "this", day] if day.parse::<Weekday>().is_ok() => {
+            return Some(next_weekday(today, day.parse().ok()?));
+        }
+        _ => {}
+    }
+
+    if let Some(date) = month_day(&words, today) {
+        return Some(date);
+    }
+
+    // Offsets: "in 3 days", "3 days", "3d"
+    let words = words.strip_prefix(&["in"]).unwrap_or(&words);
+    let (amount, unit) = match words {
+        [amount, unit] => (*amount, *unit),
+        [word] => word.split_at(word.find(|c: char| !c.is_ascii_digit())?),
+        _ => return None,
+    };
+    let amount: u32 = match amount {
+        "a" | "an" | "one" => 1,
+        amount => amount.parse().ok()?,
+    };
+    match unit {
+        "d" | "day" | "days" => today.checked_add_days(Days::new(amount.into())),
+        "w" | "week" | "weeks" => today.checked_add_days(Days::new(u64::from(amount) * 7)),
+        "m" | "month" | "months" => today.checked_add_months(Months::new(amount)),
+        "y" | "year" | "years" => today.checked_add_months(Months::new(amount * 12)),
+        _ => None,
+    }
+}
+
+// `date` moved by an offset like "+1d", "-2w", "+3m" or "+1y"
+pub fn shift_date(input: &str, date: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    let (forward, offset) = match input.split_at_checked(1)? {
+        ("+", offset) => (true, offset),
+        ("-", offset) => (false, offset),
+        _ => return None,
+    };
+    let (amount, unit) = offset.split_at(offset.find(|c: char| !c.is_ascii_digit())?);
+    let amount: u32 = amount.parse().ok()?;
+    let (days, months) = match unit.trim() {
+        "d" | "day" | "days" => (u64::from(amount), 0),
+        "w" | "week" | "weeks" => (u64::from(amount) * 7, 0),
+        "m" | "month" | "months" => (0, amount),
+        "y" | "year" | "years" => (0, amount * 12),
+        _ => return None,
+    };
+    if forward {
+        date.checked_add_days(Days::new(days))?.checked_add_months(Months::new(months))
+    } else {
+        date.checked_sub_days(Days::new(days))?.checked_sub_months(Months::new(months))
+    }
+}
+
+// The first `weekday` after `today`
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    today + chrono::Duration::days(if days == 0 { 7 } else { days.into() })
+}
+
+// "jun 1", "june 1st" or "1 june": this year's, unless that's already past
+fn month_day(words: &[&str], today: NaiveDate) -> Option<NaiveDate> {
+    let (month, day) = match words {
+        [first, second] => match first.parse::<Month>() {
+            Ok(month) => (month, *second),
+            Err(_) => (second.parse::<Month>().ok()?, *first),
+        },
+        _ => return None,
+    };
+    let day: u32 = day.trim_end_matches(|c: char| c.is_ascii_alphabetic()).parse().ok()?;
+    let date = NaiveDate::from_ymd_opt(today.year(), month.number_from_month(), day)?;
+    if date >= today {
+        Some(date)
+    } else {
+        NaiveDate::from_ymd_opt(today.year() + 1, month.number_from_month(), day)
+    }
+}
@@ -0,0 +1,110 @@
+use crate::item::{LineType, TodoItem};
+use chrono::NaiveDate;
+
+// todo.txt lines, for `todo import`. A line is `x` if done, an optional
+// `(A)` priority, then the completion date if done and the creation date,
+// then the text. The priority becomes the todo's `(A)` marker and the dates
+// its `done:` and `added:` stamps. `+project`s become tags; contexts and
+// `key:value` pairs are kept as they are, except a done todo's `pri:A`,
+// which is where some apps keep the priority once it's done.
+pub fn parse_line(line: &str) -> Option<TodoItem> {
+    let date = |word: &&str| NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok();
+    let mut words = line.split_whitespace().peekable();
+    let completed = words.next_if_eq(&"x").is_some();
+    let mut priority = words.next_if(|word| marker(word).is_some()).and_then(marker);
+    let first = words.next_if(date);
+    let second = words.next_if(date);
+    let (done, added) = if completed { (first, second) } else { (None, first) };
+
+    let mut text = Vec::new();
+    for word in words {
+        match (word.strip_prefix('+'), word.strip_prefix("pri:")) {
+            (Some(project), _) if !project.is_empty() => text.push(format!("#{}", project)),
+            (_, Some(letter)) if completed && marker(&format!("({})", letter)).is_some() => {
+                priority = priority.or(letter.chars().next());
+            }
+            _ => text.push(word.to_string()),
+        }
+    }
+    if text.is_empty() {
+        return None;
+    }
+
+    let mut item = TodoItem { text: text.join(" "), completed: false, line_type: LineType::Todo, depth: 0 };
+    if priority.is_some() {
+        item.set_priority(priority);
+    }
+    let parse = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+    if let Some(added) = added.and_then(parse) {
+        item.stamp_added(added, None);
+    }
+    match done.and_then(parse) {
+        Some(done) => item.set_completed(true, done, None),
+        None => item.completed = completed,
+    }
+    Some(item)
+}
+
+// The letter of a `(A)`..`(Z)` priority
+fn marker(word: &str) -> Option<char> {
+    match word.as_bytes() {
+        [b'(', letter, b')'] if letter.is_ascii_uppercase() => Some(*letter as char),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::{format_line, parse_line as parse_list_line};
+
+    const SAMPLE: &str = "\
+(A) 2026-10-01 Call the bank +finance @phone
+x 2026-10-12 2026-10-02 Pay rent +home
+x (B) 2026-10-13 2026-10-03 Send the invoice
+x 2026-10-11 Water the plants pri:C
+2026-10-04 Book flights due:2026-11-01
+Read a book
+";
+
+    #[test]
+    fn sample_survives_the_list() {
+        let imported: Vec<TodoItem> = SAMPLE.lines().filter_map(parse_line).collect();
+        let lines: Vec<String> = imported.iter().map(format_line).collect();
+        assert_eq!(
+            lines,
+            [
+                "* [ ] (A) Call the bank #finance @phone added:2026-10-01",
+                "* [x] Pay rent #home added:2026-10-02 done:2026-10-12",
+                "* [x] (B) Send the invoice added:2026-10-03 done:2026-10-13",
+                "* [x] (C) Water the plants done:2026-10-11",
+                "* [ ] Book flights due:2026-11-01 added:2026-10-04",
+                "* [ ] Read a book",
+            ]
+        );
+
+        // And read back from the list, it's what todo.txt said
+        let read: Vec<TodoItem> = lines.iter().map(|line| parse_list_line(line)).collect();
+        let date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+        let summary: Vec<_> = read.iter().map(|item| (item.completed, item.priority(), item.added(), item.done())).collect();
+        assert_eq!(
+            summary,
+            [
+                (false, Some('A'), date("2026-10-01"), None),
+                (true, None, date("2026-10-02"), date("2026-10-12")),
+                (true, Some('B'), date("2026-10-03"), date("2026-10-13")),
+                (true, Some('C'), None, date("2026-10-11")),
+                (false, None, date("2026-10-04"), None),
+                (false, None, None, None),
+            ]
+        );
+        assert_eq!(read[0].text_without_priority(), "Call the bank #finance @phone added:2026-10-01");
+    }
+
+    #[test]
+    fn blank_lines_and_bare_markers_are_skipped() {
+        assert_eq!(parse_line(""), None);
+        assert_eq!(parse_line("x 2026-10-12"), None);
+        assert_eq!(parse_line("(A)"), None);
+    }
+}